@@ -0,0 +1,81 @@
+use chess::bench::bench_boards;
+use chess::movelist::MoveList;
+use chess::nnue;
+use chess::see::see;
+use chess::tt::{Bound, SharedTransTable};
+use criterion::{Criterion, black_box, criterion_group, criterion_main};
+
+fn movegen(c: &mut Criterion) {
+    let mut boards = bench_boards();
+    c.bench_function("generate_legal_moves", |bench| {
+        let mut list = MoveList::new();
+        bench.iter(|| {
+            for b in boards.iter_mut() {
+                list.clear();
+                b.generate_legal_moves(&mut list);
+                black_box(&list);
+            }
+        })
+    });
+
+    c.bench_function("make_move/unmake_move", |bench| {
+        let mut list = MoveList::new();
+        bench.iter(|| {
+            for b in boards.iter_mut() {
+                list.clear();
+                b.generate_legal_moves(&mut list);
+                for &m in &list {
+                    let undo = b.make_move(m);
+                    b.unmake_move(black_box(m), undo);
+                }
+            }
+        })
+    });
+}
+
+fn eval(c: &mut Criterion) {
+    let _ = nnue::init();
+    let mut boards = bench_boards();
+    c.bench_function("nnue::evaluate", |bench| {
+        bench.iter(|| {
+            for b in &boards {
+                black_box(nnue::evaluate(b));
+            }
+        })
+    });
+
+    let captures: Vec<_> = boards
+        .iter_mut()
+        .map(|b| {
+            let mut list = MoveList::new();
+            b.generate_legal_moves(&mut list);
+            list.retain(|m| m.is_capture());
+            list
+        })
+        .collect();
+    c.bench_function("see", |bench| {
+        bench.iter(|| {
+            for (b, list) in boards.iter().zip(&captures) {
+                for &m in list {
+                    black_box(see(b, m));
+                }
+            }
+        })
+    });
+}
+
+fn tt(c: &mut Criterion) {
+    let boards = bench_boards();
+    let tt = SharedTransTable::new(16);
+    c.bench_function("tt store/probe", |bench| {
+        bench.iter(|| {
+            for (i, b) in boards.iter().enumerate() {
+                tt.store(b.zobrist, i as i16, 0, Bound::Exact, None);
+                black_box(tt.probe(b.zobrist));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, movegen, eval, tt);
+criterion_main!(benches);
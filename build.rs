@@ -1,7 +1,7 @@
 use std::env;
 use std::fs::File;
 use std::io::{BufWriter, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 type Bitboard = u64;
 
@@ -77,6 +77,8 @@ fn slider_attacks(sq: usize, blockers: Bitboard, is_rook: bool) -> Bitboard {
     attacks
 }
 
+/// Finds a magic for `sq` by trial and error and returns it with the
+/// square's attack sets in magic order.
 fn find_magic_for_sq(sq: usize, is_rook: bool, rng: &mut Rng) -> (u64, Vec<u64>) {
     let mask = slider_mask(sq, is_rook);
     let bits = mask.count_ones();
@@ -124,8 +126,48 @@ fn find_magic_for_sq(sq: usize, is_rook: bool, rng: &mut Rng) -> (u64, Vec<u64>)
     }
 }
 
+/// Software `pdep`: deposits the low bits of `index` on the set bits of
+/// `mask`, lowest first.
+fn deposit(index: usize, mut mask: Bitboard) -> Bitboard {
+    let mut result = 0;
+    let mut bit = 0;
+    while mask != 0 {
+        let sq = mask & mask.wrapping_neg();
+        if index & (1 << bit) != 0 {
+            result |= sq;
+        }
+        mask ^= sq;
+        bit += 1;
+    }
+    result
+}
+
+/// Slider attacks indexed by `pext(occupied, mask)`, each square's block at
+/// the same offset as in the magic table since both are `1 << bits` long.
+fn pext_table(is_rook: bool) -> Vec<Bitboard> {
+    let mut table = Vec::new();
+    for sq in 0..64 {
+        let mask = slider_mask(sq, is_rook);
+        for index in 0..1usize << mask.count_ones() {
+            table.push(slider_attacks(sq, deposit(index, mask), is_rook));
+        }
+    }
+    table
+}
+
+fn write_slider_table(f: &mut impl Write, name: &str, table: &[Bitboard]) {
+    writeln!(f, "\nstatic {}: [Bitboard; {}] = [", name, table.len()).unwrap();
+    for (i, &attack) in table.iter().enumerate() {
+        write!(f, "0x{:016X},", attack).unwrap();
+        if (i + 1) % 8 == 0 {
+            writeln!(f).unwrap();
+        }
+    }
+    writeln!(f, "];").unwrap();
+}
+
 /// Generates all attack tables and writes them to a single .rs file.
-fn generate_attacks_source(out_dir: &PathBuf) {
+fn generate_attacks_source(out_dir: &Path) {
     let dest_path = out_dir.join("generated_attacks.rs");
     let mut f = BufWriter::new(File::create(&dest_path).unwrap());
 
@@ -196,48 +238,36 @@ fn generate_attacks_source(out_dir: &PathBuf) {
     }
 
     let mut rng = Rng::new();
-    let mut rook_attack_table = Vec::new();
-    let mut bishop_attack_table = Vec::new();
-
-    println!("cargo:warning=Generating Rook magic bitboard tables...");
-    for sq in 0..64 {
-        let (_, mut table) = find_magic_for_sq(sq, true, &mut rng);
-        rook_attack_table.append(&mut table);
-    }
-
-    println!("cargo:warning=Generating Bishop magic bitboard tables...");
-    for sq in 0..64 {
-        let (_, mut table) = find_magic_for_sq(sq, false, &mut rng);
-        bishop_attack_table.append(&mut table);
-    }
-
-    writeln!(
-        f,
-        "\npub const ROOK_ATTACKS: [Bitboard; {}] = [",
-        rook_attack_table.len()
-    )
-    .unwrap();
-    for (i, &attack) in rook_attack_table.iter().enumerate() {
-        write!(f, "0x{:016X},", attack).unwrap();
-        if (i + 1) % 8 == 0 {
-            writeln!(f).unwrap();
+    let pext = env::var_os("CARGO_FEATURE_PEXT").is_some();
+    for (name, label, is_rook) in [("ROOK", "Rook", true), ("BISHOP", "Bishop", false)] {
+        println!("cargo:warning=Generating {label} magic bitboard tables...");
+        let mut magics = Vec::new();
+        let mut attacks = Vec::new();
+        for sq in 0..64 {
+            let mask = slider_mask(sq, is_rook);
+            let (magic, mut table) = find_magic_for_sq(sq, is_rook, &mut rng);
+            magics.push((mask, magic, attacks.len()));
+            attacks.append(&mut table);
         }
-    }
-    writeln!(f, "];").unwrap();
 
-    writeln!(
-        f,
-        "\npub const BISHOP_ATTACKS: [Bitboard; {}] = [",
-        bishop_attack_table.len()
-    )
-    .unwrap();
-    for (i, &attack) in bishop_attack_table.iter().enumerate() {
-        write!(f, "0x{:016X},", attack).unwrap();
-        if (i + 1) % 8 == 0 {
-            writeln!(f).unwrap();
+        writeln!(f, "\nstatic {name}_MAGICS: [Magic; 64] = [").unwrap();
+        for (mask, magic, offset) in magics {
+            writeln!(
+                f,
+                "Magic {{ mask: 0x{mask:X}, magic: 0x{magic:X}, shift: {}, offset: {offset} }},",
+                64 - mask.count_ones()
+            )
+            .unwrap();
         }
+        writeln!(f, "];").unwrap();
+        write_slider_table(&mut f, &format!("{name}_ATTACKS"), &attacks);
+        let pext_attacks = if pext {
+            pext_table(is_rook)
+        } else {
+            Vec::new()
+        };
+        write_slider_table(&mut f, &format!("{name}_PEXT_ATTACKS"), &pext_attacks);
     }
-    writeln!(f, "];").unwrap();
 
     println!(
         "cargo:warning=Wrote all attack tables to {}",
@@ -246,7 +276,7 @@ fn generate_attacks_source(out_dir: &PathBuf) {
 }
 
 /// Downloads the NNUE file
-fn download_nnue(out_dir: &PathBuf) {
+fn download_nnue(out_dir: &Path) {
     const NNUE_URL: &str = "https://tests.stockfishchess.org/api/nn/nn-9931db908a9b.nnue";
     let dest_path = out_dir.join("nn-9931db908a9b.nnue");
 
@@ -266,7 +296,7 @@ fn download_nnue(out_dir: &PathBuf) {
         .expect("Failed to write to NNUE file");
 }
 
-fn generate_zobrist_keys(out_dir: &PathBuf) {
+fn generate_zobrist_keys(out_dir: &Path) {
     let dest_path = out_dir.join("generated_zobrist.rs");
     let mut f = BufWriter::new(File::create(&dest_path).unwrap());
 
@@ -0,0 +1,46 @@
+/*
+ * C interface to the chess engine, matching src/ffi.rs.
+ *
+ * Link against the `chess` library built by `cargo build --release`
+ * (target/release/libchess.so or libchess.a). All functions are blocking and
+ * a handle must not be used from two threads at once.
+ */
+#ifndef CHESS_H
+#define CHESS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct ChessEngine ChessEngine;
+
+/* Creates an engine with a `hash_mb` megabyte hash table at the start
+ * position. Returns NULL if the network cannot be loaded or the table
+ * allocated. */
+ChessEngine *engine_create(uint32_t hash_mb);
+
+/* Sets the position from a FEN. Returns 0 on success, -1 if the FEN is
+ * invalid. */
+int engine_set_fen(ChessEngine *engine, const char *fen);
+
+/* Searches for `movetime_ms` milliseconds. Returns the score in centipawns
+ * from the side to move's view (mate in n plies is 30000 - n), or INT_MIN on
+ * error. */
+int engine_go_movetime(ChessEngine *engine, uint64_t movetime_ms);
+
+/* Writes the last search's best move in UCI notation into `buf`. Returns its
+ * length, 0 if there is no move, or -1 if `buf` is too small (6 bytes always
+ * suffice). */
+int engine_best_move(const ChessEngine *engine, char *buf, size_t len);
+
+/* Frees an engine. NULL is ignored. */
+void engine_destroy(ChessEngine *engine);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* CHESS_H */
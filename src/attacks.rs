@@ -0,0 +1,265 @@
+//! Attack sets for every piece. Leaper attacks come from fixed 64-entry
+//! tables; slider attacks from a choice of backends sharing one table
+//! layout, all generated by `build.rs`:
+//!
+//! - [`Backend::Magic`]: magic multiplication, on every CPU.
+//! - [`Backend::Pext`]: BMI2 `pext`, faster where the CPU has it.
+//! - [`Backend::Table`]: the `pext` table indexed in software; slow, but it
+//!   needs neither magics nor BMI2, so it is the reference for the others.
+//!
+//! Without the `pext` feature only magics are built in. With it, the best
+//! backend the CPU supports is picked at runtime and [`set_backend`] can
+//! override the choice.
+
+use crate::types::{Bitboard, Color};
+#[cfg(feature = "pext")]
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// One square's slider lookup: the relevant occupancy bits, the magic that
+/// hashes them, and where the square's `1 << bits` attack sets start.
+struct Magic {
+    mask: Bitboard,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+// The leaper tables, ROOK/BISHOP_MAGICS with their ROOK/BISHOP_ATTACKS, and
+// the same sets in pext order as ROOK/BISHOP_PEXT_ATTACKS (empty without the
+// `pext` feature).
+include!(concat!(env!("OUT_DIR"), "/generated_attacks.rs"));
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Magic,
+    Pext,
+    Table,
+}
+
+impl Backend {
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Magic => "magic",
+            Backend::Pext => "pext",
+            Backend::Table => "table",
+        }
+    }
+
+    /// The backends this build and CPU can run.
+    pub fn available() -> Vec<Backend> {
+        [Backend::Magic, Backend::Pext, Backend::Table]
+            .into_iter()
+            .filter(|&b| b.is_supported())
+            .collect()
+    }
+
+    fn is_supported(self) -> bool {
+        match self {
+            Backend::Magic => true,
+            Backend::Pext => cfg!(feature = "pext") && has_bmi2(),
+            Backend::Table => cfg!(feature = "pext"),
+        }
+    }
+}
+
+fn has_bmi2() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        is_x86_feature_detected!("bmi2")
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    false
+}
+
+/// `Backend as u8 + 1`; 0 until the first lookup picks the best one.
+#[cfg(feature = "pext")]
+static BACKEND: AtomicU8 = AtomicU8::new(0);
+
+/// The backend slider lookups use.
+#[inline(always)]
+pub fn backend() -> Backend {
+    #[cfg(feature = "pext")]
+    {
+        match BACKEND.load(Ordering::Relaxed) {
+            1 => Backend::Magic,
+            2 => Backend::Pext,
+            3 => Backend::Table,
+            _ => detect_backend(),
+        }
+    }
+    #[cfg(not(feature = "pext"))]
+    Backend::Magic
+}
+
+#[cfg(feature = "pext")]
+#[cold]
+fn detect_backend() -> Backend {
+    let best = if Backend::Pext.is_supported() {
+        Backend::Pext
+    } else {
+        Backend::Magic
+    };
+    BACKEND.store(best as u8 + 1, Ordering::Relaxed);
+    best
+}
+
+/// Switches the slider backend, e.g. to compare them.
+pub fn set_backend(backend: Backend) -> Result<(), String> {
+    if !backend.is_supported() {
+        return Err(format!(
+            "the {} backend is not available in this build or on this CPU",
+            backend.name()
+        ));
+    }
+    #[cfg(feature = "pext")]
+    BACKEND.store(backend as u8 + 1, Ordering::Relaxed);
+    Ok(())
+}
+
+const fn ray_tables() -> ([[Bitboard; 64]; 64], [[Bitboard; 64]; 64]) {
+    let mut between = [[0; 64]; 64];
+    let mut line = [[0; 64]; 64];
+    const DIRS: [(i32, i32); 8] = [
+        (1, 0),
+        (-1, 0),
+        (0, 1),
+        (0, -1),
+        (1, 1),
+        (1, -1),
+        (-1, 1),
+        (-1, -1),
+    ];
+    let mut a = 0;
+    while a < 64 {
+        let mut d = 0;
+        while d < 8 {
+            let (df, dr) = DIRS[d];
+            // The full line through `a` in this direction, both ways.
+            let mut full: Bitboard = 1 << a;
+            let mut sign = -1;
+            while sign <= 1 {
+                let (mut f, mut r) = ((a % 8) as i32 + sign * df, (a / 8) as i32 + sign * dr);
+                while f >= 0 && f < 8 && r >= 0 && r < 8 {
+                    full |= 1 << (r * 8 + f);
+                    f += sign * df;
+                    r += sign * dr;
+                }
+                sign += 2;
+            }
+            let (mut f, mut r) = ((a % 8) as i32 + df, (a / 8) as i32 + dr);
+            let mut ray: Bitboard = 0;
+            while f >= 0 && f < 8 && r >= 0 && r < 8 {
+                let b = (r * 8 + f) as usize;
+                between[a][b] = ray;
+                line[a][b] = full;
+                ray |= 1 << b;
+                f += df;
+                r += dr;
+            }
+            d += 1;
+        }
+        a += 1;
+    }
+    (between, line)
+}
+
+static RAY_TABLES: ([[Bitboard; 64]; 64], [[Bitboard; 64]; 64]) = ray_tables();
+
+/// Squares strictly between two aligned squares; empty if they don't share a
+/// rank, file or diagonal.
+pub static BETWEEN: &[[Bitboard; 64]; 64] = &RAY_TABLES.0;
+
+/// The whole rank, file or diagonal through two aligned squares, else empty.
+pub static LINE: &[[Bitboard; 64]; 64] = &RAY_TABLES.1;
+
+#[inline(always)]
+fn magic_index(m: &Magic, occupied: Bitboard) -> usize {
+    ((occupied & m.mask).wrapping_mul(m.magic) >> m.shift) as usize
+}
+
+/// # Safety
+/// The CPU must support BMI2.
+#[cfg(all(feature = "pext", target_arch = "x86_64"))]
+#[target_feature(enable = "bmi2")]
+#[inline]
+unsafe fn pext_index(m: &Magic, occupied: Bitboard) -> usize {
+    std::arch::x86_64::_pext_u64(occupied, m.mask) as usize
+}
+
+/// Software `pext`: the occupancy's mask bits packed down, lowest first.
+#[cfg(feature = "pext")]
+#[inline(never)]
+fn table_index(m: &Magic, occupied: Bitboard) -> usize {
+    let (mut mask, mut index, mut bit) = (m.mask, 0, 1);
+    while mask != 0 {
+        let lowest = mask & mask.wrapping_neg();
+        if occupied & lowest != 0 {
+            index |= bit;
+        }
+        mask ^= lowest;
+        bit <<= 1;
+    }
+    index
+}
+
+#[inline(always)]
+#[cfg_attr(not(feature = "pext"), allow(unused_variables))]
+fn slider(
+    m: &Magic,
+    magic_table: &[Bitboard],
+    pext_table: &[Bitboard],
+    occupied: Bitboard,
+) -> Bitboard {
+    match backend() {
+        #[cfg(all(feature = "pext", target_arch = "x86_64"))]
+        // SAFETY: the backend is only `Pext` on BMI2 CPUs.
+        Backend::Pext => pext_table[m.offset + unsafe { pext_index(m, occupied) }],
+        #[cfg(feature = "pext")]
+        Backend::Table => pext_table[m.offset + table_index(m, occupied)],
+        _ => magic_table[m.offset + magic_index(m, occupied)],
+    }
+}
+
+#[inline(always)]
+pub fn rook(sq: usize, occupied: Bitboard) -> Bitboard {
+    slider(
+        &ROOK_MAGICS[sq],
+        &ROOK_ATTACKS,
+        &ROOK_PEXT_ATTACKS,
+        occupied,
+    )
+}
+
+#[inline(always)]
+pub fn bishop(sq: usize, occupied: Bitboard) -> Bitboard {
+    slider(
+        &BISHOP_MAGICS[sq],
+        &BISHOP_ATTACKS,
+        &BISHOP_PEXT_ATTACKS,
+        occupied,
+    )
+}
+
+#[inline(always)]
+pub fn queen(sq: usize, occupied: Bitboard) -> Bitboard {
+    rook(sq, occupied) | bishop(sq, occupied)
+}
+
+#[inline(always)]
+pub fn knight(sq: usize) -> Bitboard {
+    KNIGHT_ATTACKS[sq]
+}
+
+#[inline(always)]
+pub fn king(sq: usize) -> Bitboard {
+    KING_ATTACKS[sq]
+}
+
+/// The squares a `color` pawn on `sq` attacks.
+#[inline(always)]
+pub fn pawn(color: Color, sq: usize) -> Bitboard {
+    match color {
+        Color::White => WHITE_PAWN_ATTACKS[sq],
+        Color::Black => BLACK_PAWN_ATTACKS[sq],
+    }
+}
@@ -0,0 +1,164 @@
+use crate::attacks::{self, Backend};
+use crate::board::Board;
+use crate::movelist::MoveList;
+use crate::nnue;
+use crate::search::best_move_timed;
+use crate::see::see;
+use crate::tt::{Bound, SharedTransTable};
+use crate::uci_io;
+use std::hint::black_box;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::time::Instant;
+
+pub const BENCH_DEPTH: usize = 12;
+const BENCH_HASH_MB: usize = 16;
+
+/// Positions shared by the micro benchmarks and the criterion suite.
+pub const BENCH_FENS: &[&str] = &[
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "2r3k1/pp3ppp/4p3/3pP3/1q1P4/1P3N2/P4PPP/2RQ2K1 b - - 0 22",
+    "8/8/4k3/8/2p5/8/B2K4/8 w - - 0 1",
+];
+
+pub fn bench_boards() -> Vec<Board> {
+    BENCH_FENS
+        .iter()
+        .map(|fen| Board::from_fen(fen).expect("bench FENs are valid"))
+        .collect()
+}
+
+pub struct MicroResult {
+    pub name: &'static str,
+    pub ops: u64,
+    pub ns_per_op: f64,
+}
+
+fn measure<F: FnMut() -> u64>(name: &'static str, iterations: u32, mut f: F) -> MicroResult {
+    let start = Instant::now();
+    let mut ops = 0;
+    for _ in 0..iterations {
+        ops += f();
+    }
+    let elapsed = start.elapsed().as_nanos() as f64;
+    MicroResult {
+        name,
+        ops,
+        ns_per_op: elapsed / ops.max(1) as f64,
+    }
+}
+
+/// Times the engine's hot primitives over [`BENCH_FENS`]. Each iteration
+/// sweeps every position once. `nnue::init` must have been called.
+pub fn micro_bench(iterations: u32) -> Vec<MicroResult> {
+    let mut boards = bench_boards();
+    let moves: Vec<MoveList> = boards
+        .iter_mut()
+        .map(|b| {
+            let mut list = MoveList::new();
+            b.generate_legal_moves(&mut list);
+            list
+        })
+        .collect();
+    let tt = SharedTransTable::new(16);
+
+    let mut results = Vec::new();
+
+    results.push(measure("generate_legal_moves", iterations, || {
+        let mut list = MoveList::new();
+        for b in boards.iter_mut() {
+            list.clear();
+            b.generate_legal_moves(&mut list);
+            black_box(&list);
+        }
+        boards.len() as u64
+    }));
+
+    results.push(measure("make_move/unmake_move", iterations, || {
+        let mut ops = 0;
+        for (b, list) in boards.iter_mut().zip(&moves) {
+            for &m in list {
+                let undo = b.make_move(m);
+                b.unmake_move(black_box(m), undo);
+            }
+            ops += list.len() as u64;
+        }
+        ops
+    }));
+
+    let default_backend = attacks::backend();
+    for backend in Backend::available() {
+        let name = match backend {
+            Backend::Magic => "slider attacks (magic)",
+            Backend::Pext => "slider attacks (pext)",
+            Backend::Table => "slider attacks (table)",
+        };
+        let _ = attacks::set_backend(backend);
+        results.push(measure(name, iterations, || {
+            for b in &boards {
+                let occ = black_box(b.all_pieces);
+                for sq in 0..64 {
+                    black_box(attacks::queen(sq, occ));
+                }
+            }
+            boards.len() as u64 * 64
+        }));
+    }
+    let _ = attacks::set_backend(default_backend);
+
+    results.push(measure("nnue::evaluate", iterations, || {
+        for b in &boards {
+            black_box(nnue::evaluate(b));
+        }
+        boards.len() as u64
+    }));
+
+    results.push(measure("see", iterations, || {
+        let mut ops = 0;
+        for (b, list) in boards.iter().zip(&moves) {
+            for &m in list.iter().filter(|m| m.is_capture()) {
+                black_box(see(b, m));
+                ops += 1;
+            }
+        }
+        ops
+    }));
+
+    results.push(measure("tt store/probe", iterations, || {
+        for (i, b) in boards.iter().enumerate() {
+            let m = moves[i].first().copied();
+            tt.store(b.zobrist, i as i16, 0, Bound::Exact, m);
+            black_box(tt.probe(b.zobrist));
+        }
+        boards.len() as u64
+    }));
+
+    results
+}
+
+/// Searches every bench position to `depth` with a fresh hash table and
+/// returns the total node count and elapsed time in milliseconds.
+pub fn run_bench(depth: usize) -> (u64, u64) {
+    let start = Instant::now();
+    let mut nodes = 0;
+    for b in bench_boards() {
+        let mut tt = SharedTransTable::new(BENCH_HASH_MB);
+        let stop = Arc::new(AtomicBool::new(false));
+        let (_, _, n) =
+            best_move_timed(&b, &mut tt, (u64::MAX / 4, u64::MAX / 4), depth, stop, true);
+        nodes += n;
+    }
+    (nodes, start.elapsed().as_millis() as u64)
+}
+
+/// Runs [`run_bench`] and prints the summary line OpenBench parses.
+pub fn print_bench(depth: usize) {
+    let (nodes, ms) = run_bench(depth);
+    let nps = nodes * 1000 / ms.max(1);
+    uci_io::send(format!("Bench: {nodes} nodes {nps} nps"));
+}
@@ -0,0 +1,284 @@
+//! Win/draw bitbases for king and pawn, rook or queen against a bare king,
+//! solved by retrograde analysis the first time they are needed.
+//!
+//! Each table has one bit per position with the extra piece White's, set
+//! when White wins: index `white_to_move << 18 | wk << 12 | bk << 6 | piece`.
+//! Positions with the piece Black's are probed colour-flipped.
+
+use crate::attacks;
+use crate::board::Board;
+use crate::types::{Bitboard, Color, PieceKind};
+use std::sync::OnceLock;
+
+const TABLE_SIZE: usize = 1 << 19;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Win(Color),
+    Draw,
+}
+
+struct Tables {
+    kpk: Vec<u64>,
+    krk: Vec<u64>,
+    kqk: Vec<u64>,
+}
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+/// Solves the tables now rather than on the first probe.
+pub fn init() {
+    tables();
+}
+
+fn tables() -> &'static Tables {
+    TABLES.get_or_init(|| {
+        let krk = solve(PieceKind::Rook, &[]);
+        let kqk = solve(PieceKind::Queen, &[]);
+        let kpk = solve(PieceKind::Pawn, &[&kqk, &krk]);
+        Tables { kpk, krk, kqk }
+    })
+}
+
+#[inline]
+fn index(white_to_move: bool, wk: usize, bk: usize, piece: usize) -> usize {
+    (white_to_move as usize) << 18 | wk << 12 | bk << 6 | piece
+}
+
+#[inline]
+fn bit(table: &[u64], idx: usize) -> bool {
+    table[idx / 64] & (1 << (idx % 64)) != 0
+}
+
+#[inline]
+fn bb(sq: usize) -> Bitboard {
+    1 << sq
+}
+
+/// Squares White's `kind` on `sq` attacks with occupancy `occ`.
+fn piece_attacks(kind: PieceKind, sq: usize, occ: Bitboard) -> Bitboard {
+    match kind {
+        PieceKind::Pawn => attacks::WHITE_PAWN_ATTACKS[sq],
+        PieceKind::Rook => attacks::rook(sq, occ),
+        _ => attacks::queen(sq, occ),
+    }
+}
+
+/// The White-wins bits of the K + `kind` v K table. `promotions` are the
+/// solved tables a pawn promotes into.
+fn solve(kind: PieceKind, promotions: &[&[u64]]) -> Vec<u64> {
+    let mut valid = vec![false; TABLE_SIZE];
+    for (idx, v) in valid.iter_mut().enumerate() {
+        let (white_to_move, wk, bk, piece) =
+            (idx >> 18 == 1, idx >> 12 & 63, idx >> 6 & 63, idx & 63);
+        *v = wk != bk
+            && piece != wk
+            && piece != bk
+            && attacks::king(wk) & bb(bk) == 0
+            && (kind != PieceKind::Pawn || (8..56).contains(&piece))
+            // White to move must not be giving check already.
+            && !(white_to_move && piece_attacks(kind, piece, bb(wk) | bb(bk)) & bb(bk) != 0);
+    }
+
+    let mut win = vec![false; TABLE_SIZE];
+    loop {
+        let mut changed = false;
+        for idx in 0..TABLE_SIZE {
+            if !valid[idx] || win[idx] {
+                continue;
+            }
+            let (wk, bk, piece) = (idx >> 12 & 63, idx >> 6 & 63, idx & 63);
+            let won = if idx >> 18 == 1 {
+                white_wins(kind, wk, bk, piece, &win, promotions)
+            } else {
+                black_loses(kind, wk, bk, piece, &win)
+            };
+            if won {
+                win[idx] = true;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut bits = vec![0u64; TABLE_SIZE / 64];
+    for (idx, _) in win.iter().enumerate().filter(|(_, w)| **w) {
+        bits[idx / 64] |= 1 << (idx % 64);
+    }
+    bits
+}
+
+/// Whether White to move has a move into a won position.
+fn white_wins(
+    kind: PieceKind,
+    wk: usize,
+    bk: usize,
+    piece: usize,
+    win: &[bool],
+    promotions: &[&[u64]],
+) -> bool {
+    let kings = bb(wk) | bb(bk);
+    let mut king_moves = attacks::king(wk) & !attacks::king(bk) & !bb(piece) & !bb(bk);
+    while king_moves != 0 {
+        let to = king_moves.trailing_zeros() as usize;
+        king_moves &= king_moves - 1;
+        if win[index(false, to, bk, piece)] {
+            return true;
+        }
+    }
+
+    if kind != PieceKind::Pawn {
+        let mut moves = piece_attacks(kind, piece, kings) & !kings;
+        while moves != 0 {
+            let to = moves.trailing_zeros() as usize;
+            moves &= moves - 1;
+            if win[index(false, wk, bk, to)] {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    let push = piece + 8;
+    if kings & bb(push) != 0 {
+        return false;
+    }
+    if push >= 56 {
+        return promotions
+            .iter()
+            .any(|table| bit(table, index(false, wk, bk, push)));
+    }
+    win[index(false, wk, bk, push)]
+        || (piece < 16 && kings & bb(push + 8) == 0 && win[index(false, wk, bk, push + 8)])
+}
+
+/// Whether Black to move is checkmated or every move leaves a won position.
+fn black_loses(kind: PieceKind, wk: usize, bk: usize, piece: usize, win: &[bool]) -> bool {
+    // The black king no longer blocks the piece once it steps away.
+    let attacked = attacks::king(wk) | piece_attacks(kind, piece, bb(wk) | bb(piece));
+    let in_check = piece_attacks(kind, piece, bb(wk) | bb(bk)) & bb(bk) != 0;
+    let mut moves = attacks::king(bk) & !attacked;
+    if moves == 0 {
+        return in_check;
+    }
+    while moves != 0 {
+        let to = moves.trailing_zeros() as usize;
+        moves &= moves - 1;
+        // Taking the undefended piece leaves two bare kings.
+        if to == piece || !win[index(true, wk, to, piece)] {
+            return false;
+        }
+    }
+    true
+}
+
+/// The exact result of a king and pawn, rook or queen against king
+/// position, or `None` for any other material.
+pub fn probe(b: &Board) -> Option<Verdict> {
+    if b.all_pieces.count_ones() != 3 {
+        return None;
+    }
+    let strong = if b.w_pieces.count_ones() == 2 {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let strong_king = b.king_square(strong)?.index();
+    let weak_king = b.king_square(strong.other())?.index();
+    let own = if strong == Color::White {
+        b.w_pieces
+    } else {
+        b.b_pieces
+    };
+    let piece = (own & !bb(strong_king)).trailing_zeros() as usize;
+
+    let t = tables();
+    let table = match b.piece_on[piece].kind()? {
+        PieceKind::Pawn => &t.kpk,
+        PieceKind::Rook => &t.krk,
+        PieceKind::Queen => &t.kqk,
+        _ => return None,
+    };
+    let flip = |sq: usize| if strong == Color::White { sq } else { sq ^ 56 };
+    let idx = index(
+        b.turn == strong,
+        flip(strong_king),
+        flip(weak_king),
+        flip(piece),
+    );
+    Some(if bit(table, idx) {
+        Verdict::Win(strong)
+    } else {
+        Verdict::Draw
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verdict(fen: &str) -> Option<Verdict> {
+        probe(&Board::from_fen(fen).unwrap())
+    }
+
+    #[test]
+    fn kpk() {
+        // King in front of its pawn on the sixth wins whoever moves.
+        assert_eq!(
+            verdict("4k3/8/4K3/4P3/8/8/8/8 w - - 0 1"),
+            Some(Verdict::Win(Color::White))
+        );
+        assert_eq!(
+            verdict("4k3/8/4K3/4P3/8/8/8/8 b - - 0 1"),
+            Some(Verdict::Win(Color::White))
+        );
+        // The defending king holds the opposition in front of the pawn.
+        assert_eq!(
+            verdict("8/8/8/8/8/4k3/4P3/4K3 w - - 0 1"),
+            Some(Verdict::Draw)
+        );
+        // A rook pawn can't drive the king out of the corner.
+        assert_eq!(
+            verdict("k7/8/1K6/P7/8/8/8/8 w - - 0 1"),
+            Some(Verdict::Draw)
+        );
+    }
+
+    #[test]
+    fn black_pawn_is_probed_flipped() {
+        assert_eq!(
+            verdict("8/8/8/8/4p3/4k3/8/4K3 b - - 0 1"),
+            Some(Verdict::Win(Color::Black))
+        );
+        assert_eq!(
+            verdict("4k3/4p3/4K3/8/8/8/8/8 b - - 0 1"),
+            Some(Verdict::Draw)
+        );
+    }
+
+    #[test]
+    fn krk_and_kqk() {
+        assert_eq!(
+            verdict("8/8/8/8/8/8/1k6/R6K w - - 0 1"),
+            Some(Verdict::Win(Color::White))
+        );
+        // The undefended rook is taken.
+        assert_eq!(
+            verdict("8/8/8/8/8/8/1k6/R6K b - - 0 1"),
+            Some(Verdict::Draw)
+        );
+        // Stalemate.
+        assert_eq!(
+            verdict("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1"),
+            Some(Verdict::Draw)
+        );
+    }
+
+    #[test]
+    fn other_material_is_not_covered() {
+        assert_eq!(verdict("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1"), None);
+        assert_eq!(verdict("4k3/8/8/8/8/8/4P3/3RK3 w - - 0 1"), None);
+    }
+}
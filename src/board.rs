@@ -1,7 +1,28 @@
+use crate::attacks;
+use crate::error::ChessError;
 use crate::fen;
-use crate::magics;
+use crate::movegen::MoveGen;
+use crate::movelist::MoveList;
+use crate::square::{File, Rank, Square, Squares};
 use crate::types::*;
+use crate::uci_io::format_uci;
 use crate::zobrist;
+use std::error::Error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// A move that isn't legal in the position it was offered to, from
+/// [`Board::try_make_move`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IllegalMove(pub Move);
+
+impl fmt::Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "illegal move {}", format_uci(self.0))
+    }
+}
+
+impl Error for IllegalMove {}
 
 #[derive(Clone)]
 pub struct Board {
@@ -17,6 +38,76 @@ pub struct Board {
     pub fullmove_number: i32,
     pub history: Vec<ZKey>,
     pub zobrist: ZKey,
+    /// Zobrist key of the pawns alone, for the pawn structure cache.
+    pub pawn_key: ZKey,
+}
+
+/// Boards are equal when they hold the same position: pieces, side to move,
+/// castling rights and en passant square, the fields the Zobrist key covers.
+/// The move counters and the game history are ignored, so one position
+/// reached by different move orders compares equal.
+impl PartialEq for Board {
+    fn eq(&self, other: &Self) -> bool {
+        self.piece_on == other.piece_on
+            && self.turn == other.turn
+            && self.castle == other.castle
+            && self.en_passant_sq == other.en_passant_sq
+    }
+}
+
+impl Eq for Board {}
+
+impl Hash for Board {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.zobrist.hash(state);
+    }
+}
+
+/// A board is stored as its FEN and the Zobrist keys of the game so far,
+/// which is what repetition detection needs after loading it back.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SavedBoard {
+    fen: String,
+    history: Vec<ZKey>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Board {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SavedBoard {
+            fen: self.to_fen(),
+            history: self.history.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Board {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error as _;
+        let saved = SavedBoard::deserialize(deserializer)?;
+        let mut b = Board::from_fen(&saved.fen).map_err(D::Error::custom)?;
+        if !saved.history.is_empty() {
+            if saved.history.last() != Some(&b.zobrist) {
+                return Err(D::Error::custom("history does not end at the position"));
+            }
+            b.history = saved.history;
+        }
+        Ok(b)
+    }
+}
+
+/// A knight, bishop, rook, queen or king move.
+#[inline(always)]
+fn piece_move(from: usize, to: usize, capture: bool) -> Move {
+    let (from, to) = (Square::from_index(from), Square::from_index(to));
+    if capture {
+        Move::capture(from, to)
+    } else {
+        Move::quiet(from, to)
+    }
 }
 
 impl Board {
@@ -34,23 +125,62 @@ impl Board {
             fullmove_number: 1,
             history: Vec::with_capacity(128),
             zobrist: 0,
+            pawn_key: 0,
         }
     }
 
+    /// Where `c`'s king stands; `None` on kingless test positions.
+    #[inline(always)]
+    pub fn king_square(&self, c: Color) -> Option<Square> {
+        Square::first(self.piece_bb[Piece::from_kind(PieceKind::King, c).index()])
+    }
+
+    /// The king's square as an index, 64 when there is no king.
     #[inline(always)]
-    pub fn king_square(&self, c: Color) -> u32 {
+    pub(crate) fn king_index(&self, c: Color) -> usize {
         let king_piece = Piece::from_kind(PieceKind::King, c);
-        self.piece_bb[king_piece.index()].trailing_zeros()
+        self.piece_bb[king_piece.index()].trailing_zeros() as usize
+    }
+
+    /// The en passant target square, if the last move was a double push.
+    #[inline]
+    pub fn en_passant(&self) -> Option<Square> {
+        Square::try_from(self.en_passant_sq).ok()
     }
 
     #[inline]
-    pub fn from_fen(fen_str: &str) -> Result<Self, String> {
-        fen::parse_fen(fen_str)
+    pub fn from_fen(fen_str: &str) -> Result<Self, ChessError> {
+        Ok(fen::parse_fen(fen_str)?)
+    }
+
+    /// The same position with colours swapped and the board flipped vertically.
+    pub fn mirrored(&self) -> Self {
+        let mut b = Board::empty();
+        for sq in Square::all() {
+            let p = self.piece_on[sq.index()];
+            if let (Some(kind), Some(c)) = (p.kind(), p.color()) {
+                b.place_piece(Piece::from_kind(kind, c.other()), sq.flip());
+            }
+        }
+        b.turn = self.turn.other();
+        b.castle = ((self.castle & (WK_CASTLE | WQ_CASTLE)) << 2)
+            | ((self.castle & (BK_CASTLE | BQ_CASTLE)) >> 2);
+        b.en_passant_sq = if self.en_passant_sq == NO_SQ {
+            NO_SQ
+        } else {
+            self.en_passant_sq ^ 56
+        };
+        b.halfmove_clock = self.halfmove_clock;
+        b.fullmove_number = self.fullmove_number;
+        b.rebuild_derived();
+        b.recompute_zobrist();
+        b.history.push(b.zobrist);
+        b
     }
 
     #[inline]
-    pub fn place_piece(&mut self, p: Piece, sq: usize) {
-        self.piece_on[sq] = p;
+    pub fn place_piece(&mut self, p: Piece, sq: Square) {
+        self.piece_on[sq.index()] = p;
     }
 
     #[inline]
@@ -77,13 +207,18 @@ impl Board {
     #[inline]
     pub fn recompute_zobrist(&mut self) {
         let mut h = 0u64;
+        let mut pawns = 0u64;
 
         for sq in 0..64 {
             let p = self.piece_on[sq];
             if !p.is_empty() {
                 h ^= zobrist::ZOB.piece_key(p, sq);
+                if matches!(p, Piece::WP | Piece::BP) {
+                    pawns ^= zobrist::ZOB.piece_key(p, sq);
+                }
             }
         }
+        self.pawn_key = pawns;
 
         h ^= zobrist::ZOB.castle[(self.castle & 0xF) as usize];
 
@@ -98,34 +233,212 @@ impl Board {
         self.zobrist = h;
     }
 
+    /// Number of earlier occurrences of the current position since the last
+    /// irreversible move. Only positions with the same side to move are compared.
     #[inline]
     pub fn count_repetitions(&self) -> usize {
         let current_key = self.zobrist;
-        let mut count = 0;
 
-        for &key in self
-            .history
+        self.history
             .iter()
             .rev()
-            .take(self.halfmove_clock as usize)
-            .skip(1)
-        {
-            if key == current_key {
-                count += 1;
+            .take(self.halfmove_clock as usize + 1)
+            .skip(2)
+            .step_by(2)
+            .filter(|&&key| key == current_key)
+            .count()
+    }
+
+    #[inline]
+    pub fn is_draw_by_repetition(&self) -> bool {
+        self.count_repetitions() >= 2
+    }
+
+    /// Repetition test for the search. `root_len` is the history length at the
+    /// root: a position that already occurred after the root is a draw, since
+    /// either side could repeat it again, while positions from the game before
+    /// the root still need the full threefold.
+    pub fn is_search_repetition(&self, root_len: usize) -> bool {
+        let len = self.history.len();
+        let window = (self.halfmove_clock as usize + 1).min(len);
+        let mut before_root = 0;
+        for back in (2..window).step_by(2) {
+            let i = len - 1 - back;
+            if self.history[i] == self.zobrist {
+                if i >= root_len {
+                    return true;
+                }
+                before_root += 1;
+                if before_root >= 2 {
+                    return true;
+                }
             }
         }
+        false
+    }
 
-        count
+    /// How many times the current position has occurred in the game, including now.
+    #[inline]
+    pub fn repetition_count(&self) -> usize {
+        self.count_repetitions() + 1
     }
 
+    /// True when the side to move may claim a draw by threefold repetition or
+    /// the fifty-move rule.
     #[inline]
-    pub fn is_draw_by_repetition(&self) -> bool {
-        self.count_repetitions() >= 2
+    pub fn can_claim_draw(&self) -> bool {
+        self.repetition_count() >= 3 || self.halfmove_clock >= 100
+    }
+
+    /// True when the side to move is in check.
+    #[inline]
+    pub fn in_check(&self) -> bool {
+        self.checkers() != 0
+    }
+
+    /// Enemy pieces giving check to the side to move.
+    #[inline]
+    pub fn checkers(&self) -> Bitboard {
+        let Some(ksq) = self.king_square(self.turn) else {
+            return 0;
+        };
+        let enemy = if self.turn == Color::White {
+            self.b_pieces
+        } else {
+            self.w_pieces
+        };
+        self.attackers_to(ksq, self.all_pieces) & enemy
     }
 
+    /// Pieces of the side to move that cannot leave the line between their
+    /// king and an enemy slider without exposing the king.
     #[inline]
-    pub fn is_square_attacked(&self, square: i32, by: Color) -> bool {
-        let sq = square as usize;
+    pub fn pinned(&self) -> Bitboard {
+        let Some(ksq) = self.king_square(self.turn) else {
+            return 0;
+        };
+        let friendly = if self.turn == Color::White {
+            self.w_pieces
+        } else {
+            self.b_pieces
+        };
+        self.blockers(ksq.index(), self.turn.other(), friendly)
+    }
+
+    /// True when the legal move `m` checks the opponent, found without
+    /// making it: an attack from the destination, a slider uncovered by the
+    /// piece leaving its line, or the rook of a castle.
+    pub fn gives_check(&self, m: Move) -> bool {
+        let us = self.turn;
+        let Some(ksq) = self.king_square(us.other()) else {
+            return false;
+        };
+        let (k, from, to) = (ksq.index(), m.from().index(), m.to().index());
+        let occ = (self.all_pieces ^ m.from().bb()) | m.to().bb();
+        let direct = match m.promotion().or(self.piece_on[from].kind()) {
+            Some(PieceKind::Pawn) => attacks::pawn(us, to),
+            Some(PieceKind::Knight) => attacks::knight(to),
+            Some(PieceKind::Bishop) => attacks::bishop(to, occ),
+            Some(PieceKind::Rook) => attacks::rook(to, occ),
+            Some(PieceKind::Queen) => attacks::queen(to, occ),
+            _ => 0,
+        };
+        if direct & ksq.bb() != 0 {
+            return true;
+        }
+
+        let friendly = if us == Color::White {
+            self.w_pieces
+        } else {
+            self.b_pieces
+        };
+        if self.blockers(k, us, friendly) & m.from().bb() != 0
+            && attacks::LINE[k][from] & m.to().bb() == 0
+        {
+            return true;
+        }
+
+        if m.is_en_passant() {
+            // The captured pawn may have been the one screening a slider.
+            let occ = occ ^ (1u64 << (to ^ 8));
+            let (diagonal, orthogonal) = self.sliders(us);
+            return attacks::bishop(k, occ) & diagonal != 0
+                || attacks::rook(k, occ) & orthogonal != 0;
+        }
+        if m.is_castle() {
+            let (rook_from, rook_to) = if to > from {
+                (to + 1, to - 1)
+            } else {
+                (to - 2, to + 1)
+            };
+            let occ = (occ ^ (1u64 << rook_from)) | (1u64 << rook_to);
+            return attacks::rook(rook_to, occ) & ksq.bb() != 0;
+        }
+        false
+    }
+
+    /// True when neither side has mating material: bare kings, a single minor
+    /// piece, or only bishops that all stand on the same square colour.
+    pub fn is_insufficient_material(&self) -> bool {
+        let heavy_or_pawns = self.piece_bb[Piece::WP.index()]
+            | self.piece_bb[Piece::BP.index()]
+            | self.piece_bb[Piece::WR.index()]
+            | self.piece_bb[Piece::BR.index()]
+            | self.piece_bb[Piece::WQ.index()]
+            | self.piece_bb[Piece::BQ.index()];
+        if heavy_or_pawns != 0 {
+            return false;
+        }
+
+        let knights = self.piece_bb[Piece::WN.index()] | self.piece_bb[Piece::BN.index()];
+        let bishops = self.piece_bb[Piece::WB.index()] | self.piece_bb[Piece::BB.index()];
+        if (knights | bishops).count_ones() <= 1 {
+            return true;
+        }
+
+        const LIGHT_SQUARES: Bitboard = 0x55AA_55AA_55AA_55AA;
+        knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0)
+    }
+
+    /// Classifies the position. Checkmate and stalemate take precedence over
+    /// the fifty-move rule and repetition.
+    pub fn status(&self) -> GameStatus {
+        let mut moves = MoveList::new();
+        self.generate_legal_moves(&mut moves);
+
+        if moves.is_empty() {
+            return if self.in_check() {
+                GameStatus::Checkmate
+            } else {
+                GameStatus::Stalemate
+            };
+        }
+        if self.halfmove_clock >= 100 {
+            return GameStatus::FiftyMoveRule;
+        }
+        if self.is_draw_by_repetition() {
+            return GameStatus::Repetition;
+        }
+        GameStatus::Ongoing
+    }
+
+    /// The outcome of the game at this position, with dead positions
+    /// (insufficient material) counted as draws as well.
+    pub fn game_result(&self) -> GameResult {
+        match self.status() {
+            GameStatus::Checkmate if self.turn == Color::White => GameResult::BlackWins,
+            GameStatus::Checkmate => GameResult::WhiteWins,
+            GameStatus::Stalemate => GameResult::DrawStalemate,
+            GameStatus::FiftyMoveRule => GameResult::DrawFiftyMove,
+            GameStatus::Repetition => GameResult::DrawRepetition,
+            GameStatus::Ongoing if self.is_insufficient_material() => GameResult::DrawMaterial,
+            GameStatus::Ongoing => GameResult::Ongoing,
+        }
+    }
+
+    #[inline]
+    pub fn is_square_attacked(&self, square: Square, by: Color) -> bool {
+        let sq = square.index();
         let (pawn, knight, king, bishop_like, rook_like) = if by == Color::White {
             (
                 Piece::WP,
@@ -145,24 +458,24 @@ impl Board {
         };
 
         let pawn_attacks = if by == Color::White {
-            magics::BLACK_PAWN_ATTACKS[sq]
+            attacks::BLACK_PAWN_ATTACKS[sq]
         } else {
-            magics::WHITE_PAWN_ATTACKS[sq]
+            attacks::WHITE_PAWN_ATTACKS[sq]
         };
 
         if (pawn_attacks & self.piece_bb[pawn.index()]) != 0 {
             return true;
         }
-        if (magics::knight_attacks_from(sq) & self.piece_bb[knight.index()]) != 0 {
+        if (attacks::knight(sq) & self.piece_bb[knight.index()]) != 0 {
             return true;
         }
-        if (magics::king_attacks_from(sq) & self.piece_bb[king.index()]) != 0 {
+        if (attacks::king(sq) & self.piece_bb[king.index()]) != 0 {
             return true;
         }
-        if (magics::get_bishop_attacks(sq, self.all_pieces) & bishop_like) != 0 {
+        if (attacks::bishop(sq, self.all_pieces) & bishop_like) != 0 {
             return true;
         }
-        if (magics::get_rook_attacks(sq, self.all_pieces) & rook_like) != 0 {
+        if (attacks::rook(sq, self.all_pieces) & rook_like) != 0 {
             return true;
         }
 
@@ -170,287 +483,362 @@ impl Board {
     }
 
     #[inline]
-    pub fn generate_pseudo_legal_moves(&self, out: &mut Vec<Move>) {
+    pub fn generate_pseudo_legal_moves(&self, out: &mut MoveList) {
         out.clear();
-        self.gen_pawns(out);
-        self.gen_leapers(out);
-        self.gen_sliders(out);
+        self.gen_pawns(out, !0, GenKind::All);
+        self.gen_knights(out, !0);
+        self.gen_king(out);
+        self.gen_sliders(out, !0);
     }
 
+    /// Pieces of either colour attacking `sq` given the occupancy `occ`.
     #[inline]
-    pub fn generate_legal_moves(&mut self, out: &mut Vec<Move>) {
-        let mut pseudo = Vec::with_capacity(128);
-        self.generate_pseudo_legal_moves(&mut pseudo);
+    pub fn attackers_to(&self, sq: Square, occ: Bitboard) -> Bitboard {
+        let sq = sq.index();
+        let bb = &self.piece_bb;
+        let diagonal = bb[Piece::WB.index()]
+            | bb[Piece::BB.index()]
+            | bb[Piece::WQ.index()]
+            | bb[Piece::BQ.index()];
+        let orthogonal = bb[Piece::WR.index()]
+            | bb[Piece::BR.index()]
+            | bb[Piece::WQ.index()]
+            | bb[Piece::BQ.index()];
+        (attacks::BLACK_PAWN_ATTACKS[sq] & bb[Piece::WP.index()])
+            | (attacks::WHITE_PAWN_ATTACKS[sq] & bb[Piece::BP.index()])
+            | (attacks::knight(sq) & (bb[Piece::WN.index()] | bb[Piece::BN.index()]))
+            | (attacks::king(sq) & (bb[Piece::WK.index()] | bb[Piece::BK.index()]))
+            | (attacks::bishop(sq, occ) & diagonal)
+            | (attacks::rook(sq, occ) & orthogonal)
+    }
 
-        out.clear();
+    /// Bishops/queens and rooks/queens of `c`.
+    #[inline(always)]
+    fn sliders(&self, c: Color) -> (Bitboard, Bitboard) {
+        let q = self.piece_bb[Piece::from_kind(PieceKind::Queen, c).index()];
+        (
+            self.piece_bb[Piece::from_kind(PieceKind::Bishop, c).index()] | q,
+            self.piece_bb[Piece::from_kind(PieceKind::Rook, c).index()] | q,
+        )
+    }
 
-        for m in pseudo {
-            let u = self.make_move(m);
-            let us = self.turn.other();
+    /// Destination squares for non-pawn moves of `kind`.
+    #[inline(always)]
+    fn kind_mask(&self, kind: GenKind) -> Bitboard {
+        let enemy = if self.turn == Color::White {
+            self.b_pieces
+        } else {
+            self.w_pieces
+        };
+        match kind {
+            GenKind::All => !0,
+            GenKind::Noisy => enemy,
+            GenKind::Quiet => !self.all_pieces,
+        }
+    }
 
-            let our_king_bb = self.piece_bb[Piece::from_kind(PieceKind::King, us).index()];
-            if our_king_bb == 0 {
-                self.unmake_move(m, u);
-                continue;
+    /// Pieces in `mask` standing alone between a king on `ksq` and a slider
+    /// of `by`: pins when `mask` is the king's side, discovered-check
+    /// candidates when it is `by`'s.
+    fn blockers(&self, ksq: usize, by: Color, mask: Bitboard) -> Bitboard {
+        let (diagonal, orthogonal) = self.sliders(by);
+        let mut snipers =
+            (attacks::bishop(ksq, 0) & diagonal) | (attacks::rook(ksq, 0) & orthogonal);
+        let mut found = 0;
+        while snipers != 0 {
+            let s = snipers.trailing_zeros() as usize;
+            snipers &= snipers - 1;
+            let between = attacks::BETWEEN[ksq][s] & self.all_pieces;
+            if between & (between.wrapping_sub(1)) == 0 {
+                found |= between & mask;
             }
-            let king_sq = our_king_bb.trailing_zeros() as i32;
+        }
+        found
+    }
+
+    /// En passant can expose the king along the rank both pawns leave, which
+    /// the pin mask doesn't see, so it is checked on the resulting occupancy.
+    fn en_passant_is_legal(&self, m: Move, ksq: usize) -> bool {
+        // The captured pawn stands beside `from`, on the file of `to`.
+        let captured = 1u64 << (m.to().index() ^ 8);
+        let occ = self.all_pieces ^ m.from().bb() ^ m.to().bb() ^ captured;
+        let (diagonal, orthogonal) = self.sliders(self.turn.other());
+        attacks::bishop(ksq, occ) & diagonal == 0 && attacks::rook(ksq, occ) & orthogonal == 0
+    }
+
+    #[inline]
+    pub fn generate_legal_moves(&self, out: &mut MoveList) {
+        self.generate_legal(out, GenKind::All);
+    }
+
+    /// Legal moves as an iterator, for callers that don't keep a buffer.
+    pub fn legal_moves(&self) -> MoveGen {
+        MoveGen::new(self, GenKind::All)
+    }
+
+    /// Legal captures, en passant included.
+    pub fn captures(&self) -> MoveGen {
+        self.legal_moves().captures()
+    }
+
+    /// Legal moves that neither capture nor promote.
+    pub fn quiets(&self) -> MoveGen {
+        MoveGen::new(self, GenKind::Quiet).quiets()
+    }
+
+    /// Every legal move when in check, otherwise nothing.
+    pub fn evasions(&self) -> MoveGen {
+        self.legal_moves().evasions()
+    }
 
-            if !self.is_square_attacked(king_sq, self.turn) {
-                out.push(m);
+    /// Whether `m` (e.g. from the hash table or a killer slot) is legal here.
+    pub fn is_legal(&self, m: Move) -> bool {
+        let mut dest = m.to().bb();
+        if m.is_en_passant() {
+            // The captured pawn may be the piece giving check.
+            dest |= 1u64 << (m.to().index() ^ 8);
+        }
+        let mut moves = MoveList::new();
+        self.gen_legal(&mut moves, GenKind::All, dest);
+        moves.contains(&m)
+    }
+
+    /// Generates only legal moves of the given kind.
+    #[inline]
+    pub fn generate_legal(&self, out: &mut MoveList, kind: GenKind) {
+        self.gen_legal(out, kind, !0);
+    }
+
+    /// Legal moves of `kind` landing on `dest`. King moves are tested against
+    /// the enemy's attacks with the king lifted off the board; other moves
+    /// must resolve any check and stay on their pin ray.
+    fn gen_legal(&self, out: &mut MoveList, kind: GenKind, dest: Bitboard) {
+        out.clear();
+        let us = self.turn;
+        let ksq = self.king_index(us);
+        if ksq >= 64 {
+            // Kingless test positions: nothing to keep safe.
+            self.gen_pawns(out, dest, kind);
+            self.gen_knights(out, dest & self.kind_mask(kind));
+            self.gen_sliders(out, dest & self.kind_mask(kind));
+            return;
+        }
+
+        let (friendly, enemy) = if us == Color::White {
+            (self.w_pieces, self.b_pieces)
+        } else {
+            (self.b_pieces, self.w_pieces)
+        };
+        let checkers = self.checkers();
+        let kind_mask = self.kind_mask(kind);
+
+        let occ_without_king = self.all_pieces ^ (1u64 << ksq);
+        let steps = attacks::king(ksq) & !friendly & kind_mask & dest;
+        let safe = Squares(steps)
+            .filter(|&to| self.attackers_to(to, occ_without_king) & enemy == 0)
+            .fold(0, |bb, to| bb | to.bb());
+        self.gen_leapers(out, 1u64 << ksq, &attacks::KING_ATTACKS, safe);
+
+        if checkers & checkers.wrapping_sub(1) != 0 {
+            // Double check: only the king can move.
+            return;
+        }
+        let targets = if checkers == 0 {
+            if kind != GenKind::Noisy {
+                self.gen_castles(out, Square::from_index(ksq));
             }
+            !friendly & dest
+        } else {
+            (checkers | attacks::BETWEEN[ksq][checkers.trailing_zeros() as usize]) & dest
+        };
 
-            self.unmake_move(m, u);
+        let first = out.len();
+        self.gen_pawns(out, targets, kind);
+        self.gen_knights(out, targets & kind_mask);
+        self.gen_sliders(out, targets & kind_mask);
+
+        let pinned = self.blockers(ksq, us.other(), friendly);
+        if pinned == 0 && self.en_passant_sq == NO_SQ {
+            return;
+        }
+        let mut i = first;
+        while i < out.len() {
+            let m = out[i];
+            let legal = if m.is_en_passant() {
+                self.en_passant_is_legal(m, ksq)
+            } else {
+                pinned & m.from().bb() == 0
+                    || attacks::LINE[ksq][m.from().index()] & m.to().bb() != 0
+            };
+            if legal {
+                i += 1;
+            } else {
+                out.swap_remove(i);
+            }
         }
     }
 
-    fn gen_pawns(&self, out: &mut Vec<Move>) {
+    /// Pawn moves of `kind` landing on `targets`; en passant also qualifies
+    /// when the captured pawn is a target. Each kind of pawn move is made
+    /// for all pawns at once by shifting the whole pawn set.
+    fn gen_pawns(&self, out: &mut MoveList, targets: Bitboard, kind: GenKind) {
+        let (quiets, noisy) = (kind != GenKind::Noisy, kind != GenKind::Quiet);
         let white = self.turn == Color::White;
         let pawn = if white { Piece::WP } else { Piece::BP };
         let pawns = self.piece_bb[pawn.index()];
         let enemy = if white { self.b_pieces } else { self.w_pieces };
-        let dir = if white { 8 } else { -8 };
-        let start_rank = if white { 1 } else { 6 };
-        let promo_rank = if white { 6 } else { 1 };
-        let mut bb = pawns;
-
-        while bb != 0 {
-            let from = bb.trailing_zeros() as i32;
-            bb &= bb - 1;
-            let r = rank_of(from);
-            let f = file_of(from);
-
-            let to = from + dir;
-            if in_board(to) && (self.all_pieces & (1u64 << to)) == 0 {
-                if r == promo_rank {
-                    for pk in [
-                        PieceKind::Queen,
-                        PieceKind::Rook,
-                        PieceKind::Bishop,
-                        PieceKind::Knight,
-                    ] {
-                        out.push(Move {
-                            from: from as u8,
-                            to: to as u8,
-                            capture: false,
-                            en_passant: false,
-                            double_push: false,
-                            castle: false,
-                            promotion: Some(pk),
-                        });
-                    }
-                } else {
-                    out.push(Move::quiet(from as u8, to as u8));
-                    if r == start_rank {
-                        let to2 = from + 2 * dir;
-                        if (self.all_pieces & (1u64 << to2)) == 0 {
-                            out.push(Move {
-                                from: from as u8,
-                                to: to2 as u8,
-                                capture: false,
-                                en_passant: false,
-                                double_push: true,
-                                castle: false,
-                                promotion: None,
-                            });
-                        }
-                    }
-                }
+        let empty = !self.all_pieces;
+        // Board deltas of a push and of captures towards the a- and h-files.
+        let (push, left, right) = if white { (8, 7, 9) } else { (-8, -9, -7) };
+        let (third, seventh) = if white {
+            (Rank::THIRD, Rank::SEVENTH)
+        } else {
+            (Rank::SIXTH, Rank::SECOND)
+        };
+        let shift = |bb: Bitboard, by: i32| if by > 0 { bb << by } else { bb >> -by };
+        let origin = |to: Square, by: i32| Square::from_index((to.index() as i32 - by) as usize);
+        let promoting = pawns & seventh.bb();
+        let rest = pawns & !seventh.bb();
+
+        if quiets {
+            let single = shift(rest, push) & empty;
+            let double = shift(single & third.bb(), push) & empty & targets;
+            for to in Squares(single & targets) {
+                out.push(Move::quiet(origin(to, push), to));
             }
+            for to in Squares(double) {
+                out.push(Move::double_push(origin(to, 2 * push), to));
+            }
+        }
+        if !noisy {
+            return;
+        }
 
-            for df in [-1, 1] {
-                let cap = from + dir + df;
-                if (df == -1 && f == 0) || (df == 1 && f == 7) {
-                    continue;
-                }
-
-                if !in_board(cap) {
-                    continue;
-                }
-
-                let cap_bb = 1u64 << cap;
-                if (enemy & cap_bb) != 0 {
-                    if r == promo_rank {
-                        for pk in [
-                            PieceKind::Queen,
-                            PieceKind::Rook,
-                            PieceKind::Bishop,
-                            PieceKind::Knight,
-                        ] {
-                            out.push(Move {
-                                from: from as u8,
-                                to: cap as u8,
-                                capture: true,
-                                en_passant: false,
-                                double_push: false,
-                                castle: false,
-                                promotion: Some(pk),
-                            });
-                        }
-                    } else {
-                        out.push(Move {
-                            from: from as u8,
-                            to: cap as u8,
-                            capture: true,
-                            en_passant: false,
-                            double_push: false,
-                            castle: false,
-                            promotion: None,
-                        });
-                    }
-                }
-
-                if self.en_passant_sq == cap {
-                    out.push(Move {
-                        from: from as u8,
-                        to: cap as u8,
-                        capture: true,
-                        en_passant: true,
-                        double_push: false,
-                        castle: false,
-                        promotion: None,
-                    });
-                }
+        let promote = |out: &mut MoveList, from: Square, to: Square, capture: bool| {
+            for pk in [
+                PieceKind::Queen,
+                PieceKind::Rook,
+                PieceKind::Bishop,
+                PieceKind::Knight,
+            ] {
+                out.push(Move::promote(from, to, pk, capture));
+            }
+        };
+        for to in Squares(shift(promoting, push) & empty & targets) {
+            promote(out, origin(to, push), to, false);
+        }
+        for (by, edge) in [(left, File::A), (right, File::H)] {
+            let (promoting, rest) = (promoting & !edge.bb(), rest & !edge.bb());
+            for to in Squares(shift(promoting, by) & enemy & targets) {
+                promote(out, origin(to, by), to, true);
+            }
+            for to in Squares(shift(rest, by) & enemy & targets) {
+                out.push(Move::capture(origin(to, by), to));
+            }
+            if let Some(ep) = self.en_passant()
+                && shift(rest, by) & ep.bb() != 0
+                && targets & (ep.bb() | shift(ep.bb(), -push)) != 0
+            {
+                out.push(Move::en_passant(origin(ep, by), ep));
             }
         }
     }
 
+    /// Knight or king steps from each of `pieces`, looked up in `table`.
     #[inline(always)]
-    fn first_sq(bb: u64) -> Option<i32> {
-        if bb == 0 {
-            None
+    fn gen_leapers(
+        &self,
+        out: &mut MoveList,
+        pieces: Bitboard,
+        table: &[Bitboard; 64],
+        targets: Bitboard,
+    ) {
+        let (friendly, enemy) = if self.turn == Color::White {
+            (self.w_pieces, self.b_pieces)
         } else {
-            Some(bb.trailing_zeros() as i32)
+            (self.b_pieces, self.w_pieces)
+        };
+        for from in Squares(pieces) {
+            let att = table[from.index()] & !friendly & targets;
+            for to in Squares(att & enemy) {
+                out.push(Move::capture(from, to));
+            }
+            for to in Squares(att & !enemy) {
+                out.push(Move::quiet(from, to));
+            }
         }
     }
 
     #[inline]
-    fn gen_leapers(&self, out: &mut Vec<Move>) {
-        let white = self.turn == Color::White;
-        let friendly = if white { self.w_pieces } else { self.b_pieces };
-
-        let kn = if white { Piece::WN } else { Piece::BN };
-        let mut bb = self.piece_bb[kn.index()];
-        while bb != 0 {
-            let from = bb.trailing_zeros() as usize;
-            bb &= bb - 1;
-
-            let mut att = magics::knight_attacks_from(from) & !friendly;
-            while att != 0 {
-                let to = att.trailing_zeros() as usize;
-                att &= att - 1;
-                let capture = (self.all_pieces & (1u64 << to)) != 0;
-                out.push(Move {
-                    from: from as u8,
-                    to: to as u8,
-                    capture,
-                    en_passant: false,
-                    double_push: false,
-                    castle: false,
-                    promotion: None,
-                });
-            }
-        }
+    fn gen_knights(&self, out: &mut MoveList, targets: Bitboard) {
+        let kn = if self.turn == Color::White {
+            Piece::WN
+        } else {
+            Piece::BN
+        };
+        let knights = self.piece_bb[kn.index()];
+        self.gen_leapers(out, knights, &attacks::KNIGHT_ATTACKS, targets);
+    }
 
-        let king = if white { Piece::WK } else { Piece::BK };
+    /// Pseudo-legal king steps and castling.
+    #[inline]
+    fn gen_king(&self, out: &mut MoveList) {
+        let king = Piece::from_kind(PieceKind::King, self.turn);
         let king_bb = self.piece_bb[king.index()];
-
-        let Some(from) = Self::first_sq(king_bb) else {
+        let Some(from) = Square::first(king_bb) else {
             return;
         };
+        self.gen_leapers(out, king_bb, &attacks::KING_ATTACKS, !0);
+        self.gen_castles(out, from);
+    }
 
-        let mut att = magics::king_attacks_from(from as usize) & !friendly;
-        while att != 0 {
-            let to = att.trailing_zeros() as usize;
-            att &= att - 1;
-            let capture = (self.all_pieces & (1u64 << to)) != 0;
-            out.push(Move {
-                from: from as u8,
-                to: to as u8,
-                capture,
-                en_passant: false,
-                double_push: false,
-                castle: false,
-                promotion: None,
-            });
-        }
-
-        if self.is_square_attacked(from, self.turn.other()) {
+    /// Castling with the king on `from`; only legal castles are produced.
+    fn gen_castles(&self, out: &mut MoveList, from: Square) {
+        if self.castle == 0 || self.is_square_attacked(from, self.turn.other()) {
             return;
         }
 
-        if white {
+        if self.turn == Color::White {
             if (self.castle & WK_CASTLE) != 0
                 && (self.all_pieces & ((1u64 << 5) | (1u64 << 6))) == 0
                 && self.piece_on[7] == Piece::WR
-                && !self.is_square_attacked(5, Color::Black)
-                && !self.is_square_attacked(6, Color::Black)
+                && !self.is_square_attacked(Square::F1, Color::Black)
+                && !self.is_square_attacked(Square::G1, Color::Black)
             {
-                out.push(Move {
-                    from: 4,
-                    to: 6,
-                    capture: false,
-                    en_passant: false,
-                    double_push: false,
-                    castle: true,
-                    promotion: None,
-                });
+                out.push(Move::castle(Square::E1, Square::G1));
             }
 
             if (self.castle & WQ_CASTLE) != 0
                 && (self.all_pieces & ((1u64 << 1) | (1u64 << 2) | (1u64 << 3))) == 0
                 && self.piece_on[0] == Piece::WR
-                && !self.is_square_attacked(3, Color::Black)
-                && !self.is_square_attacked(2, Color::Black)
+                && !self.is_square_attacked(Square::D1, Color::Black)
+                && !self.is_square_attacked(Square::C1, Color::Black)
             {
-                out.push(Move {
-                    from: 4,
-                    to: 2,
-                    capture: false,
-                    en_passant: false,
-                    double_push: false,
-                    castle: true,
-                    promotion: None,
-                });
+                out.push(Move::castle(Square::E1, Square::C1));
             }
         } else {
             if (self.castle & BK_CASTLE) != 0
                 && (self.all_pieces & ((1u64 << 61) | (1u64 << 62))) == 0
                 && self.piece_on[63] == Piece::BR
-                && !self.is_square_attacked(61, Color::White)
-                && !self.is_square_attacked(62, Color::White)
+                && !self.is_square_attacked(Square::F8, Color::White)
+                && !self.is_square_attacked(Square::G8, Color::White)
             {
-                out.push(Move {
-                    from: 60,
-                    to: 62,
-                    capture: false,
-                    en_passant: false,
-                    double_push: false,
-                    castle: true,
-                    promotion: None,
-                });
+                out.push(Move::castle(Square::E8, Square::G8));
             }
 
             if (self.castle & BQ_CASTLE) != 0
                 && (self.all_pieces & ((1u64 << 57) | (1u64 << 58) | (1u64 << 59))) == 0
                 && self.piece_on[56] == Piece::BR
-                && !self.is_square_attacked(59, Color::White)
-                && !self.is_square_attacked(58, Color::White)
+                && !self.is_square_attacked(Square::D8, Color::White)
+                && !self.is_square_attacked(Square::C8, Color::White)
             {
-                out.push(Move {
-                    from: 60,
-                    to: 58,
-                    capture: false,
-                    en_passant: false,
-                    double_push: false,
-                    castle: true,
-                    promotion: None,
-                });
+                out.push(Move::castle(Square::E8, Square::C8));
             }
         }
     }
 
     #[inline]
-    fn gen_sliders(&self, out: &mut Vec<Move>) {
+    fn gen_sliders(&self, out: &mut MoveList, targets: Bitboard) {
         let white = self.turn == Color::White;
         let friendly = if white { self.w_pieces } else { self.b_pieces };
         let enemy = if white { self.b_pieces } else { self.w_pieces };
@@ -462,21 +850,13 @@ impl Board {
             let from = bb.trailing_zeros() as usize;
             bb &= bb - 1;
 
-            let mut att = magics::get_bishop_attacks(from, occ) & !friendly;
+            let mut att = attacks::bishop(from, occ) & !friendly & targets;
             while att != 0 {
                 let to = att.trailing_zeros() as usize;
                 att &= att - 1;
                 let capture = (enemy & (1u64 << to)) != 0;
 
-                out.push(Move {
-                    from: from as u8,
-                    to: to as u8,
-                    capture,
-                    en_passant: false,
-                    double_push: false,
-                    castle: false,
-                    promotion: None,
-                });
+                out.push(piece_move(from, to, capture));
             }
         }
 
@@ -486,22 +866,14 @@ impl Board {
         while rb != 0 {
             let from = rb.trailing_zeros() as usize;
             rb &= rb - 1;
-            let mut att = magics::get_rook_attacks(from, occ) & !friendly;
+            let mut att = attacks::rook(from, occ) & !friendly & targets;
 
             while att != 0 {
                 let to = att.trailing_zeros() as usize;
                 att &= att - 1;
                 let capture = (enemy & (1u64 << to)) != 0;
 
-                out.push(Move {
-                    from: from as u8,
-                    to: to as u8,
-                    capture,
-                    en_passant: false,
-                    double_push: false,
-                    castle: false,
-                    promotion: None,
-                });
+                out.push(piece_move(from, to, capture));
             }
         }
 
@@ -512,27 +884,29 @@ impl Board {
             let from = qb.trailing_zeros() as usize;
             qb &= qb - 1;
 
-            let mut att = (magics::get_rook_attacks(from, occ)
-                | magics::get_bishop_attacks(from, occ))
-                & !friendly;
+            let mut att =
+                (attacks::rook(from, occ) | attacks::bishop(from, occ)) & !friendly & targets;
 
             while att != 0 {
                 let to = att.trailing_zeros() as usize;
                 att &= att - 1;
                 let capture = (enemy & (1u64 << to)) != 0;
-                out.push(Move {
-                    from: from as u8,
-                    to: to as u8,
-                    capture,
-                    en_passant: false,
-                    double_push: false,
-                    castle: false,
-                    promotion: None,
-                });
+                out.push(piece_move(from, to, capture));
             }
         }
     }
 
+    /// Plays `m` if it is legal here, flags included, and leaves the board
+    /// untouched if not. For moves from outside the engine; the search
+    /// uses the unchecked [`Board::make_move`].
+    pub fn try_make_move(&mut self, m: Move) -> Result<Undo, IllegalMove> {
+        if !self.is_legal(m) {
+            return Err(IllegalMove(m));
+        }
+        Ok(self.make_move(m))
+    }
+
+    /// Plays `m` without checking it; an illegal move corrupts the board.
     #[inline]
     pub fn make_move(&mut self, m: Move) -> Undo {
         let mut undo = Undo {
@@ -540,6 +914,7 @@ impl Board {
             old_castle: self.castle,
             old_en_passant_sq: self.en_passant_sq,
             old_halfmove_clock: self.halfmove_clock,
+            old_pawn_key: self.pawn_key,
         };
 
         if self.en_passant_sq != NO_SQ {
@@ -547,11 +922,15 @@ impl Board {
         }
         self.en_passant_sq = NO_SQ;
 
-        let from = m.from as usize;
-        let to = m.to as usize;
+        let from = m.from().index();
+        let to = m.to().index();
         let moving = self.piece_on[from];
 
+        let moving_pawn = matches!(moving, Piece::WP | Piece::BP);
         self.zobrist ^= zobrist::ZOB.piece_key(moving, from);
+        if moving_pawn {
+            self.pawn_key ^= zobrist::ZOB.piece_key(moving, from);
+        }
         self.piece_on[from] = Piece::Empty;
         self.piece_bb[moving.index()] ^= 1u64 << from;
 
@@ -561,8 +940,8 @@ impl Board {
             _ => {}
         }
 
-        if m.capture {
-            let cap_sq = if m.en_passant {
+        if m.is_capture() {
+            let cap_sq = if m.is_en_passant() {
                 if self.turn == Color::White {
                     to - 8
                 } else {
@@ -577,6 +956,9 @@ impl Board {
 
             if !captured.is_empty() {
                 self.zobrist ^= zobrist::ZOB.piece_key(captured, cap_sq);
+                if matches!(captured, Piece::WP | Piece::BP) {
+                    self.pawn_key ^= zobrist::ZOB.piece_key(captured, cap_sq);
+                }
                 self.piece_on[cap_sq] = Piece::Empty;
                 self.piece_bb[captured.index()] ^= 1u64 << cap_sq;
                 match captured.color() {
@@ -587,7 +969,7 @@ impl Board {
             }
         }
 
-        if let Some(pk) = m.promotion {
+        if let Some(pk) = m.promotion() {
             let promoted_piece = Piece::from_kind(pk, self.turn);
             self.piece_on[to] = promoted_piece;
             self.piece_bb[promoted_piece.index()] |= 1u64 << to;
@@ -596,6 +978,9 @@ impl Board {
             self.piece_on[to] = moving;
             self.piece_bb[moving.index()] |= 1u64 << to;
             self.zobrist ^= zobrist::ZOB.piece_key(moving, to);
+            if moving_pawn {
+                self.pawn_key ^= zobrist::ZOB.piece_key(moving, to);
+            }
         }
 
         match moving.color() {
@@ -604,7 +989,7 @@ impl Board {
             _ => {}
         }
 
-        if m.castle {
+        if m.is_castle() {
             let (rook_from, rook_to) = if to > from {
                 (to + 1, to - 1)
             } else {
@@ -626,7 +1011,7 @@ impl Board {
             }
         }
 
-        if m.double_push {
+        if m.is_double_push() {
             let ep = if self.turn == Color::White {
                 from + 8
             } else {
@@ -636,7 +1021,7 @@ impl Board {
             self.zobrist ^= zobrist::ZOB.ep_file[ep % 8];
         }
 
-        if matches!(moving.kind(), Some(PieceKind::Pawn)) || m.capture {
+        if matches!(moving.kind(), Some(PieceKind::Pawn)) || m.is_capture() {
             self.halfmove_clock = 0;
         } else {
             self.halfmove_clock += 1;
@@ -657,7 +1042,7 @@ impl Board {
             _ => {}
         }
 
-        if m.capture {
+        if m.is_capture() {
             match to {
                 0 => self.castle &= !WQ_CASTLE,
                 7 => self.castle &= !WK_CASTLE,
@@ -680,6 +1065,72 @@ impl Board {
         undo
     }
 
+    /// The zobrist key of the position after `m`, computed from the current key
+    /// without touching the board. Mirrors the key updates in `make_move`.
+    pub fn zobrist_after(&self, m: Move) -> ZKey {
+        let mut key = self.zobrist ^ zobrist::ZOB.side;
+
+        if self.en_passant_sq != NO_SQ {
+            key ^= zobrist::ZOB.ep_file[(self.en_passant_sq % 8) as usize];
+        }
+
+        let from = m.from().index();
+        let to = m.to().index();
+        let moving = self.piece_on[from];
+        key ^= zobrist::ZOB.piece_key(moving, from);
+
+        if m.is_capture() {
+            let cap_sq = match (m.is_en_passant(), self.turn) {
+                (true, Color::White) => to - 8,
+                (true, Color::Black) => to + 8,
+                (false, _) => to,
+            };
+            let captured = self.piece_on[cap_sq];
+            if !captured.is_empty() {
+                key ^= zobrist::ZOB.piece_key(captured, cap_sq);
+            }
+        }
+
+        let arriving = m
+            .promotion()
+            .map_or(moving, |pk| Piece::from_kind(pk, self.turn));
+        key ^= zobrist::ZOB.piece_key(arriving, to);
+
+        if m.is_castle() {
+            let (rook_from, rook_to) = if to > from {
+                (to + 1, to - 1)
+            } else {
+                (to - 2, to + 1)
+            };
+            let rook = self.piece_on[rook_from];
+            key ^= zobrist::ZOB.piece_key(rook, rook_from) ^ zobrist::ZOB.piece_key(rook, rook_to);
+        }
+
+        if m.is_double_push() {
+            key ^= zobrist::ZOB.ep_file[from % 8];
+        }
+
+        let mut castle = self.castle;
+        match moving {
+            Piece::WK => castle &= !(WK_CASTLE | WQ_CASTLE),
+            Piece::BK => castle &= !(BK_CASTLE | BQ_CASTLE),
+            _ => {}
+        }
+        for sq in [from, if m.is_capture() { to } else { from }] {
+            match sq {
+                0 => castle &= !WQ_CASTLE,
+                7 => castle &= !WK_CASTLE,
+                56 => castle &= !BQ_CASTLE,
+                63 => castle &= !BK_CASTLE,
+                _ => {}
+            }
+        }
+        key ^= zobrist::ZOB.castle[(self.castle & 0xF) as usize];
+        key ^= zobrist::ZOB.castle[(castle & 0xF) as usize];
+
+        key
+    }
+
     #[inline]
     pub fn unmake_move(&mut self, m: Move, u: Undo) {
         self.history.pop();
@@ -694,12 +1145,13 @@ impl Board {
         self.castle = u.old_castle;
         self.en_passant_sq = u.old_en_passant_sq;
         self.halfmove_clock = u.old_halfmove_clock;
+        self.pawn_key = u.old_pawn_key;
 
-        let from = m.from as usize;
-        let to = m.to as usize;
+        let from = m.from().index();
+        let to = m.to().index();
 
         let piece_that_arrived = self.piece_on[to];
-        let moving_piece = if m.promotion.is_some() {
+        let moving_piece = if m.promotion().is_some() {
             Piece::from_kind(PieceKind::Pawn, self.turn)
         } else {
             piece_that_arrived
@@ -724,11 +1176,11 @@ impl Board {
             }
         }
 
-        if m.capture {
+        if m.is_capture() {
             let captured = u.captured_piece;
             let cap_sq;
 
-            if m.en_passant {
+            if m.is_en_passant() {
                 self.piece_on[to] = Piece::Empty;
                 cap_sq = if self.turn == Color::White {
                     to - 8
@@ -754,7 +1206,7 @@ impl Board {
             self.piece_on[to] = Piece::Empty;
         }
 
-        if m.castle {
+        if m.is_castle() {
             let (rook_from, rook_to) = if to > from {
                 (to + 1, to - 1)
             } else {
@@ -783,6 +1235,7 @@ impl Board {
             old_castle: self.castle,
             old_en_passant_sq: self.en_passant_sq,
             old_halfmove_clock: self.halfmove_clock,
+            old_pawn_key: self.pawn_key,
         };
 
         if self.en_passant_sq != NO_SQ {
@@ -813,30 +1266,29 @@ impl Board {
     }
 
     pub fn to_san(&self, m: Move, legal_moves: &[Move]) -> String {
-        if m.castle {
-            return if m.to > m.from { "O-O" } else { "O-O-O" }.to_string();
+        if m.is_castle() {
+            return if m.to() > m.from() { "O-O" } else { "O-O-O" }.to_string();
         }
 
-        let from = m.from as usize;
-        let to = m.to as usize;
-        let moving_piece = self.piece_on[from];
+        let (from, to) = (m.from(), m.to());
+        let moving_piece = self.piece_on[from.index()];
         let mut san = String::new();
 
         if let Some(pk) = moving_piece.kind() {
             match pk {
                 PieceKind::Pawn => {
-                    if m.capture {
-                        san.push(file_char(from));
+                    if m.is_capture() {
+                        san.push(from.file().to_char());
                     }
                 }
                 _ => {
                     san.push(pk.to_char_upper());
                     let mut ambiguous_moves = Vec::new();
                     for other_move in legal_moves {
-                        let other_from = other_move.from as usize;
-                        if self.piece_on[other_from].kind() == Some(pk)
+                        let other_from = other_move.from();
+                        if self.piece_on[other_from.index()].kind() == Some(pk)
                             && other_from != from
-                            && other_move.to == m.to
+                            && other_move.to() == to
                         {
                             ambiguous_moves.push(other_move);
                         }
@@ -847,33 +1299,34 @@ impl Board {
                         let mut rank_is_unique = true;
 
                         for amb_move in &ambiguous_moves {
-                            if file_char(amb_move.from as usize) == file_char(from) {
+                            let amb_from = amb_move.from();
+                            if amb_from.file() == from.file() {
                                 file_is_unique = false;
                             }
-                            if rank_char(amb_move.from as usize) == rank_char(from) {
+                            if amb_from.rank() == from.rank() {
                                 rank_is_unique = false;
                             }
                         }
 
                         if file_is_unique {
-                            san.push(file_char(from));
+                            san.push(from.file().to_char());
                         } else if rank_is_unique {
-                            san.push(rank_char(from));
+                            san.push(from.rank().to_char());
                         } else {
-                            san.push_str(&sq_to_str(from));
+                            san.push_str(&from.to_string());
                         }
                     }
                 }
             }
         }
 
-        if m.capture {
+        if m.is_capture() {
             san.push('x');
         }
 
-        san.push_str(&sq_to_str(to));
+        san.push_str(&to.to_string());
 
-        if let Some(promo) = m.promotion {
+        if let Some(promo) = m.promotion() {
             san.push('=');
             san.push(promo.to_char_upper());
         }
@@ -881,13 +1334,9 @@ impl Board {
         let mut temp_board = self.clone();
         let undo = temp_board.make_move(m);
 
-        let opp_king_sq = temp_board.piece_bb
-            [Piece::from_kind(PieceKind::King, temp_board.turn).index()]
-        .trailing_zeros() as i32;
-
-        if temp_board.is_square_attacked(opp_king_sq, self.turn) {
+        if temp_board.in_check() {
             let mut has_legal_move = false;
-            let mut next_moves = Vec::new();
+            let mut next_moves = MoveList::new();
             temp_board.generate_legal_moves(&mut next_moves);
 
             if !next_moves.is_empty() {
@@ -906,3 +1355,110 @@ impl Board {
         san
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uci_io::parse_uci_move;
+    use std::collections::HashSet;
+
+    fn play(fen: &str, moves: &[&str]) -> Board {
+        let mut b = Board::from_fen(fen).unwrap();
+        for &text in moves {
+            let m = parse_uci_move(&mut b, text).unwrap();
+            b.make_move(m);
+        }
+        b
+    }
+
+    fn sq(name: &str) -> Square {
+        name.parse().unwrap()
+    }
+
+    #[test]
+    fn transpositions_are_equal() {
+        let a = play(START_FEN, &["g1f3", "g8f6", "b1c3"]);
+        let b = play(START_FEN, &["b1c3", "g8f6", "g1f3"]);
+        assert!(a == b);
+        // Counters and history don't take part.
+        let c = Board::from_fen(&a.to_fen().replace(" 3 2", " 0 1")).unwrap();
+        assert_ne!(a.to_fen(), c.to_fen());
+        assert!(a == c);
+        assert_eq!(HashSet::from([a, b, c]).len(), 1);
+
+        // The side to move and en passant square do.
+        let d = play(START_FEN, &["g1f3", "g8f6", "b1c3", "b8c6"]);
+        assert!(d != play(START_FEN, &["g1f3", "g8f6", "b1c3"]));
+        let ep = play(START_FEN, &["e2e4"]);
+        let no_ep = Board::from_fen(&ep.to_fen().replace(" e3 ", " - ")).unwrap();
+        assert!(ep != no_ep);
+    }
+
+    #[test]
+    fn try_make_move_rejects_illegal_moves() {
+        // The e2 knight is pinned against the king by the e8 rook.
+        let mut b = Board::from_fen("4r1k1/8/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        let fen = b.to_fen();
+        let pinned = Move::quiet(sq("e2"), sq("f4"));
+        assert_eq!(b.try_make_move(pinned).err(), Some(IllegalMove(pinned)));
+        assert_eq!(b.to_fen(), fen);
+        let mut free = Board::from_fen("6k1/8/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert!(free.try_make_move(pinned).is_ok());
+
+        // Right squares, wrong flags.
+        let mut b = Board::from_fen(START_FEN).unwrap();
+        assert!(b.try_make_move(Move::quiet(sq("e2"), sq("e4"))).is_err());
+        assert!(
+            b.try_make_move(Move::double_push(sq("e2"), sq("e4")))
+                .is_ok()
+        );
+        assert_eq!(b.turn, Color::Black);
+    }
+
+    /// Checks every move `depth` plies deep.
+    fn check_zobrist_after(b: &mut Board, depth: usize) {
+        for m in b.legal_moves() {
+            let predicted = b.zobrist_after(m);
+            let undo = b.make_move(m);
+            assert_eq!(
+                predicted,
+                b.zobrist,
+                "{} after {}",
+                b.to_fen(),
+                format_uci(m)
+            );
+            if depth > 1 {
+                check_zobrist_after(b, depth - 1);
+            }
+            b.unmake_move(m, undo);
+        }
+    }
+
+    #[test]
+    fn zobrist_after_matches_make_move() {
+        for fen in [
+            START_FEN,
+            // Castling both ways, en passant and promotions with captures.
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+        ] {
+            check_zobrist_after(&mut Board::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let b = play(START_FEN, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        let json = serde_json::to_string(&b).unwrap();
+        let back: Board = serde_json::from_str(&json).unwrap();
+        assert!(back == b);
+        assert_eq!(back.to_fen(), b.to_fen());
+        assert_eq!(back.history, b.history);
+
+        let mut saved: serde_json::Value = serde_json::from_str(&json).unwrap();
+        saved["history"] = serde_json::json!([1, 2, 3]);
+        assert!(serde_json::from_value::<Board>(saved).is_err());
+    }
+}
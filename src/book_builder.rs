@@ -0,0 +1,98 @@
+use crate::opening_book::BookEntry;
+use crate::pgn::PgnGame;
+use crate::polyglot_zobrist;
+use crate::types::Color;
+use std::collections::HashMap;
+
+/// Results of one move from one position, from the mover's point of view.
+#[derive(Clone, Copy, Default)]
+struct MoveStats {
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+impl MoveStats {
+    fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+}
+
+/// Accumulates move statistics per Polyglot key over a set of games.
+pub struct BookBuilder {
+    max_ply: usize,
+    stats: HashMap<(u64, u16), MoveStats>,
+    games: usize,
+}
+
+impl BookBuilder {
+    /// Only the first `max_ply` plies of each game are counted.
+    pub fn new(max_ply: usize) -> Self {
+        Self {
+            max_ply,
+            stats: HashMap::new(),
+            games: 0,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    /// Counts the mainline of a game; unfinished games (`*`) are skipped.
+    pub fn add_game(&mut self, game: &PgnGame) {
+        let white_score = match game.result.as_str() {
+            "1-0" => 2,
+            "1/2-1/2" => 1,
+            "0-1" => 0,
+            _ => return,
+        };
+        self.games += 1;
+
+        let mut b = game.start.clone();
+        for m in game.moves.iter().take(self.max_ply) {
+            let key = polyglot_zobrist::calculate_key(&b);
+            let mover_score = if b.turn == Color::White {
+                white_score
+            } else {
+                2 - white_score
+            };
+            let entry = self
+                .stats
+                .entry((key, BookEntry::encode_move(m.mv)))
+                .or_default();
+            match mover_score {
+                2 => entry.wins += 1,
+                1 => entry.draws += 1,
+                _ => entry.losses += 1,
+            }
+            b.make_move(m.mv);
+        }
+    }
+
+    /// Book entries for every move played at least `min_games` times,
+    /// weighted by 2 × wins + draws and sorted by key, best move first.
+    /// Moves that never scored are dropped.
+    pub fn entries(&self, min_games: u32) -> Vec<BookEntry> {
+        let mut raw: Vec<(u64, u16, u64)> = self
+            .stats
+            .iter()
+            .filter(|(_, s)| s.games() >= min_games.max(1))
+            .map(|(&(key, mv), s)| (key, mv, 2 * s.wins as u64 + s.draws as u64))
+            .filter(|&(_, _, w)| w > 0)
+            .collect();
+        raw.sort_unstable_by(|a, b| a.0.cmp(&b.0).then(b.2.cmp(&a.2)).then(a.1.cmp(&b.1)));
+
+        // Scale so the heaviest entry fits in Polyglot's u16 weight.
+        let max = raw.iter().map(|e| e.2).max().unwrap_or(1);
+        let scale = (max as f64 / u16::MAX as f64).max(1.0);
+        raw.into_iter()
+            .map(|(key, raw_move, w)| BookEntry {
+                key,
+                raw_move,
+                weight: ((w as f64 / scale).round() as u16).max(1),
+                _learn: 0,
+            })
+            .collect()
+    }
+}
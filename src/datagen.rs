@@ -0,0 +1,166 @@
+use crate::board::Board;
+use crate::movelist::MoveList;
+use crate::search::{MATE_THRESHOLD, SearchConfig, SearchLimits, search_with_limits};
+use crate::tt::SharedTransTable;
+use crate::types::Color;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+// Games still running after this many plies are scored as draws.
+const MAX_GAME_PLIES: usize = 400;
+
+pub struct DatagenConfig {
+    pub games: usize,
+    pub nodes: u64,
+    pub max_depth: usize,
+    /// Uniformly random moves played from the start position before searching.
+    pub random_plies: usize,
+    pub threads: usize,
+    pub hash_mb: usize,
+    pub seed: u64,
+}
+
+/// One training position: the search score is from White's point of view
+/// and the result is 1.0, 0.5 or 0.0 for a White win, draw or loss.
+pub struct Record {
+    pub fen: String,
+    pub score: i32,
+    pub result: f32,
+}
+
+impl Record {
+    /// `<fen> | <score> | <result>`, the text format most NNUE trainers read.
+    pub fn to_line(&self) -> String {
+        format!("{} | {} | {:.1}", self.fen, self.score, self.result)
+    }
+}
+
+fn white_pov(turn: Color, score: i32) -> i32 {
+    if turn == Color::White { score } else { -score }
+}
+
+/// Plays one game from `start` and returns its positions. In-check positions
+/// and those whose best move is tactical are left out, as is anything after
+/// a mate score is found, since the game is decided at that point.
+pub fn play_game(start: &Board, config: &DatagenConfig, rng: &mut StdRng) -> Vec<Record> {
+    let mut b = start.clone();
+    let mut tt = SharedTransTable::new(config.hash_mb);
+    let limits = SearchLimits {
+        time_ms: u64::MAX / 4,
+        hard_time_ms: u64::MAX / 4,
+        max_depth: config.max_depth,
+        nodes: config.nodes,
+        root_moves: Vec::new(),
+        root_scores: false,
+        config: SearchConfig::default(),
+    };
+
+    let mut moves = MoveList::new();
+    for _ in 0..config.random_plies {
+        moves.clear();
+        b.generate_legal_moves(&mut moves);
+        if moves.is_empty() {
+            break;
+        }
+        b.make_move(moves[rng.gen_range(0..moves.len())]);
+    }
+
+    let mut positions = Vec::new();
+    let mut plies = 0;
+    let result = loop {
+        if let Some(result) = b.game_result().white_score() {
+            break result as f32;
+        }
+        if plies >= MAX_GAME_PLIES {
+            break 0.5;
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let r = search_with_limits(
+            &b,
+            &mut tt,
+            limits.clone(),
+            stop,
+            Arc::new(AtomicU64::new(0)),
+            true,
+            None,
+        );
+        let Some(m) = r.best_move else {
+            break 0.5;
+        };
+        let score = white_pov(b.turn, r.score);
+        if r.score.abs() > MATE_THRESHOLD {
+            break if score > 0 { 1.0 } else { 0.0 };
+        }
+
+        if !b.in_check() && !m.is_capture() && m.promotion().is_none() {
+            positions.push((b.to_fen(), score));
+        }
+        b.make_move(m);
+        plies += 1;
+    };
+
+    positions
+        .into_iter()
+        .map(|(fen, score)| Record { fen, score, result })
+        .collect()
+}
+
+/// Plays `config.games` games on `config.threads` workers and writes every
+/// record to `out` as soon as its game finishes. Returns the number written.
+pub fn run(
+    start: &Board,
+    config: &DatagenConfig,
+    out: &mut (dyn Write + Send),
+) -> io::Result<usize> {
+    let next_game = AtomicUsize::new(0);
+    let written = AtomicUsize::new(0);
+    let out = Mutex::new(out);
+
+    thread::scope(|scope| -> io::Result<()> {
+        let workers: Vec<_> = (0..config.threads.max(1))
+            .map(|i| {
+                let (next_game, written, out) = (&next_game, &written, &out);
+                thread::Builder::new()
+                    .name(format!("datagen-{i}"))
+                    .spawn_scoped(scope, move || -> io::Result<()> {
+                        loop {
+                            let game = next_game.fetch_add(1, Ordering::Relaxed);
+                            if game >= config.games {
+                                return Ok(());
+                            }
+                            let mut rng = StdRng::seed_from_u64(config.seed ^ game as u64);
+                            let records = play_game(start, config, &mut rng);
+
+                            let mut out = out.lock().unwrap_or_else(|e| e.into_inner());
+                            for r in &records {
+                                writeln!(out, "{}", r.to_line())?;
+                            }
+                            out.flush()?;
+                            let total =
+                                written.fetch_add(records.len(), Ordering::Relaxed) + records.len();
+                            eprintln!(
+                                "game {}/{}: {} positions ({} total)",
+                                game + 1,
+                                config.games,
+                                records.len(),
+                                total
+                            );
+                        }
+                    })
+            })
+            .collect::<io::Result<_>>()?;
+
+        for w in workers {
+            w.join()
+                .unwrap_or_else(|_| Err(io::Error::other("datagen worker panicked")))?;
+        }
+        Ok(())
+    })?;
+
+    Ok(written.load(Ordering::Relaxed))
+}
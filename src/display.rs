@@ -0,0 +1,85 @@
+//! Text views of a position for the terminal and the UCI `d` command.
+
+use crate::board::Board;
+use crate::eval::evaluate_position;
+use crate::polyglot_zobrist;
+use crate::square::Squares;
+use crate::types::{Color, Piece};
+use std::fmt::Write;
+
+/// The board as an 8x8 diagram with White at the bottom. With `colored`,
+/// Black's pieces are drawn in blue using ANSI escapes.
+pub fn board_ascii(b: &Board, colored: bool) -> String {
+    const BLUE: &str = "\x1b[34m";
+    const RESET: &str = "\x1b[0m";
+    let mut out = String::new();
+    out.push_str("\n   a b c d e f g h\n");
+    out.push_str(" +-----------------+\n");
+    for r in (0..8).rev() {
+        let _ = write!(out, "{}| ", r + 1);
+        for f in 0..8 {
+            let c = match b.piece_on[r * 8 + f] {
+                Piece::Empty => '.',
+                Piece::WP => 'P',
+                Piece::WN => 'N',
+                Piece::WB => 'B',
+                Piece::WR => 'R',
+                Piece::WQ => 'Q',
+                Piece::WK => 'K',
+                Piece::BP => 'p',
+                Piece::BN => 'n',
+                Piece::BB => 'b',
+                Piece::BR => 'r',
+                Piece::BQ => 'q',
+                Piece::BK => 'k',
+            };
+            if colored && c.is_ascii_lowercase() {
+                let _ = write!(out, "{BLUE}{c}{RESET} ");
+            } else {
+                let _ = write!(out, "{c} ");
+            }
+        }
+        let _ = writeln!(out, "|{}", r + 1);
+    }
+    out.push_str(" +-----------------+\n");
+    out.push_str("   a b c d e f g h\n");
+    out
+}
+
+/// The diagram followed by the FEN, hash keys, checkers and static
+/// evaluation, like Stockfish's `d`.
+pub fn describe(b: &Board) -> String {
+    let mut out = board_ascii(b, false);
+    let checkers: Vec<_> = Squares(b.checkers()).map(|sq| sq.to_string()).collect();
+    let eval = evaluate_position(b);
+    let white_eval = if b.turn == Color::White { eval } else { -eval };
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Fen: {}", b.to_fen());
+    let _ = writeln!(out, "Key: {:016X}", b.zobrist);
+    let _ = writeln!(
+        out,
+        "Polyglot key: {:016X}",
+        polyglot_zobrist::calculate_key(b)
+    );
+    let _ = writeln!(out, "Checkers: {}", checkers.join(" "));
+    let _ = writeln!(
+        out,
+        "Static eval: {:+.2} (white side)",
+        white_eval as f64 / 100.0
+    );
+    out
+}
+
+/// The same details as [`describe`] as a JSON object.
+pub fn describe_json(b: &Board) -> serde_json::Value {
+    let checkers: Vec<_> = Squares(b.checkers()).map(|sq| sq.to_string()).collect();
+    let eval = evaluate_position(b);
+    serde_json::json!({
+        "type": "position",
+        "fen": b.to_fen(),
+        "key": format!("{:016X}", b.zobrist),
+        "polyglot_key": format!("{:016X}", polyglot_zobrist::calculate_key(b)),
+        "checkers": checkers,
+        "eval": if b.turn == Color::White { eval } else { -eval },
+    })
+}
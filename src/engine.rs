@@ -0,0 +1,150 @@
+use crate::board::Board;
+use crate::error::ChessError;
+use crate::fen;
+use crate::nnue::{self, NnueError};
+use crate::search::{InfoCallback, SearchLimits, SearchResult, ThreadPool};
+use crate::tt::SharedTransTable;
+use crate::types::START_FEN;
+use crate::uci_io::parse_uci_move;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+struct RunningSearch {
+    handle: JoinHandle<SearchResult>,
+    stop: Arc<AtomicBool>,
+}
+
+/// The engine as a library: holds a position and a hash table and searches
+/// in the background, reporting progress through an [`InfoCallback`].
+pub struct Engine {
+    board: Board,
+    tt: SharedTransTable,
+    pool: Arc<Mutex<ThreadPool>>,
+    running: Option<RunningSearch>,
+}
+
+impl Engine {
+    /// Loads the network on first use; later engines share it. Fails if
+    /// the network can't be loaded or the hash table allocated.
+    pub fn new(hash_mb: usize) -> Result<Self, ChessError> {
+        match nnue::init() {
+            Ok(()) | Err(NnueError::AlreadyInitialized) => {}
+            Err(e) => return Err(e.into()),
+        }
+        Ok(Self {
+            board: Board::from_fen(START_FEN)?,
+            tt: SharedTransTable::try_new(hash_mb).map_err(ChessError::InvalidOption)?,
+            pool: Arc::new(Mutex::new(ThreadPool::new(1))),
+            running: None,
+        })
+    }
+
+    /// Takes effect from the next search.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.stop();
+        lock(&self.pool).set_threads(threads);
+    }
+
+    /// Resizes the hash table, keeping what fits; stops any search in progress.
+    pub fn set_hash(&mut self, hash_mb: usize) -> Result<(), ChessError> {
+        self.stop();
+        self.tt.resize(hash_mb).map_err(ChessError::InvalidOption)
+    }
+
+    /// Empties the hash table without starting a new game.
+    pub fn clear_hash(&mut self) {
+        self.stop();
+        self.tt.clear();
+    }
+
+    pub fn new_game(&mut self) {
+        self.stop();
+        self.tt.clear();
+        lock(&self.pool).clear();
+    }
+
+    /// Sets up `fen` and plays `moves` (in UCI notation) from it. The FEN
+    /// must pass [`fen::validate`], since the search assumes a sane position.
+    pub fn set_position(&mut self, fen: &str, moves: &[&str]) -> Result<(), ChessError> {
+        let mut b = fen::parse_fen_strict(fen)?;
+        for mv in moves {
+            let m = parse_uci_move(&mut b, mv)
+                .ok_or_else(|| ChessError::IllegalMove(mv.to_string()))?;
+            b.make_move(m);
+        }
+        self.stop();
+        self.board = b;
+        Ok(())
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn is_searching(&self) -> bool {
+        self.running
+            .as_ref()
+            .is_some_and(|r| !r.handle.is_finished())
+    }
+
+    /// Starts searching the current position in the background. A search
+    /// already running is stopped first.
+    pub fn go(
+        &mut self,
+        limits: SearchLimits,
+        mut callback: impl InfoCallback + 'static,
+    ) -> Result<(), ChessError> {
+        self.stop();
+        let board = self.board.clone();
+        let mut tt = self.tt.clone();
+        let pool = Arc::clone(&self.pool);
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_main = Arc::clone(&stop);
+
+        let handle = thread::Builder::new()
+            .name("engine-main".to_string())
+            .spawn(move || {
+                lock(&pool).search(&board, &mut tt, limits, stop_main, Some(&mut callback))
+            })
+            .map_err(ChessError::ThreadSpawn)?;
+
+        self.running = Some(RunningSearch { handle, stop });
+        Ok(())
+    }
+
+    /// Stops the search and returns its result; `None` if nothing was running.
+    pub fn stop(&mut self) -> Option<SearchResult> {
+        let running = self.running.as_ref()?;
+        running.stop.store(true, Ordering::Relaxed);
+        self.wait()
+    }
+
+    /// Waits for the search to finish on its own limits.
+    pub fn wait(&mut self) -> Option<SearchResult> {
+        self.running.take()?.handle.join().ok()
+    }
+
+    /// Searches on the calling thread and blocks until done, so `callback`
+    /// may borrow from the caller.
+    pub fn search(
+        &mut self,
+        limits: SearchLimits,
+        callback: Option<&mut dyn InfoCallback>,
+    ) -> Result<SearchResult, ChessError> {
+        self.stop();
+        let stop = Arc::new(AtomicBool::new(false));
+        Ok(lock(&self.pool).search(&self.board, &mut self.tt, limits, stop, callback))
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// The pool, even if a search thread panicked while holding it.
+fn lock(pool: &Mutex<ThreadPool>) -> MutexGuard<'_, ThreadPool> {
+    pool.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,222 @@
+use crate::score::{mate_in, mated_in};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How to launch a UCI engine: `name=<label>,cmd=<path>,arg=<arg>,opt.<Name>=<value>`.
+/// `arg` and `opt.*` may be repeated. Without `cmd` this binary is run in UCI
+/// mode, which is how different configurations of the engine are compared.
+#[derive(Clone, Debug)]
+pub struct EngineSpec {
+    pub name: String,
+    pub cmd: PathBuf,
+    pub args: Vec<String>,
+    pub options: Vec<(String, String)>,
+}
+
+impl EngineSpec {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut name = None;
+        let mut cmd = None;
+        let mut args = Vec::new();
+        let mut options = Vec::new();
+        for field in spec.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("engine spec: expected key=value, got '{field}'"))?;
+            match key {
+                "name" => name = Some(value.to_string()),
+                "cmd" => cmd = Some(PathBuf::from(value)),
+                "arg" => args.push(value.to_string()),
+                _ => match key.strip_prefix("opt.") {
+                    Some(opt) => options.push((opt.to_string(), value.to_string())),
+                    None => return Err(format!("engine spec: unknown key '{key}'")),
+                },
+            }
+        }
+
+        let cmd = match cmd {
+            Some(cmd) => cmd,
+            None => {
+                args.insert(0, "uci".to_string());
+                std::env::current_exe().map_err(|e| format!("engine spec: {e}"))?
+            }
+        };
+        let name = name.unwrap_or_else(|| {
+            cmd.file_stem().map_or_else(
+                || "engine".to_string(),
+                |s| s.to_string_lossy().into_owned(),
+            )
+        });
+        Ok(Self {
+            name,
+            cmd,
+            args,
+            options,
+        })
+    }
+}
+
+/// A UCI engine running as a child process.
+pub struct UciEngine {
+    pub name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UciEngine {
+    /// Launches the engine, completes the `uci` handshake and applies the
+    /// spec's options.
+    pub fn start(spec: &EngineSpec) -> io::Result<Self> {
+        let mut child = Command::new(&spec.cmd)
+            .args(&spec.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = child.stdout.take().expect("stdout is piped");
+
+        // A reader thread lets every wait have a timeout.
+        let (tx, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else { break };
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self {
+            name: spec.name.clone(),
+            child,
+            stdin,
+            lines,
+        };
+        engine.send("uci")?;
+        engine.wait_for(|l| l == "uciok", HANDSHAKE_TIMEOUT)?;
+        for (name, value) in &spec.options {
+            engine.send(&format!("setoption name {name} value {value}"))?;
+        }
+        engine.sync()?;
+        Ok(engine)
+    }
+
+    pub fn send(&mut self, cmd: &str) -> io::Result<()> {
+        writeln!(self.stdin, "{cmd}")?;
+        self.stdin.flush()
+    }
+
+    /// Waits for a line matching `done` and returns it.
+    fn wait_for(
+        &mut self,
+        mut done: impl FnMut(&str) -> bool,
+        timeout: Duration,
+    ) -> io::Result<String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match self.lines.recv_timeout(left) {
+                Ok(line) if done(line.trim()) => return Ok(line),
+                Ok(_) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("{} did not respond in time", self.name),
+                    ));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("{} exited", self.name),
+                    ));
+                }
+            }
+        }
+    }
+
+    /// `isready` round trip.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.send("isready")?;
+        self.wait_for(|l| l == "readyok", HANDSHAKE_TIMEOUT)?;
+        Ok(())
+    }
+
+    pub fn new_game(&mut self) -> io::Result<()> {
+        self.send("ucinewgame")?;
+        self.sync()
+    }
+
+    /// Sends `position` and `go` and returns the move from `bestmove`. Fails
+    /// with `TimedOut` if nothing arrives within `timeout`.
+    pub fn best_move(&mut self, position: &str, go: &str, timeout: Duration) -> io::Result<String> {
+        self.search(position, go, timeout).map(|(mv, _)| mv)
+    }
+
+    /// Like [`best_move`](Self::best_move), but also returns the last score
+    /// the engine reported, from its own point of view.
+    pub fn search(
+        &mut self,
+        position: &str,
+        go: &str,
+        timeout: Duration,
+    ) -> io::Result<(String, Option<i32>)> {
+        self.send(position)?;
+        self.send(go)?;
+        let mut score = None;
+        let line = self.wait_for(
+            |l| {
+                if l.starts_with("info") {
+                    score = parse_info_score(l).or(score);
+                }
+                l.starts_with("bestmove")
+            },
+            timeout,
+        )?;
+        let mv = line
+            .split_whitespace()
+            .nth(1)
+            .map(str::to_string)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "empty bestmove"))?;
+        Ok((mv, score))
+    }
+}
+
+/// The `score cp <n>` or `score mate <n>` of an `info` line, with mates
+/// mapped onto the engine's own mate scores.
+fn parse_info_score(line: &str) -> Option<i32> {
+    let mut words = line
+        .split_whitespace()
+        .skip_while(|&w| w != "score")
+        .skip(1);
+    let kind = words.next()?;
+    let value: i32 = words.next()?.parse().ok()?;
+    match kind {
+        "cp" => Some(value),
+        "mate" if value > 0 => Some(mate_in(2 * value as usize - 1)),
+        "mate" => Some(mated_in(2 * value.unsigned_abs() as usize)),
+        _ => None,
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + Duration::from_secs(1);
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
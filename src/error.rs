@@ -0,0 +1,72 @@
+use crate::board::IllegalMove;
+use crate::fen::FenError;
+use crate::nnue::NnueError;
+use crate::uci_io::format_uci;
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Errors of the whole crate, from parsing a position up to the UCI loop
+/// and CLI modes, so callers can match on the kind of failure.
+#[derive(Debug)]
+pub enum ChessError {
+    InvalidFen(FenError),
+    /// A file or command that couldn't be parsed: PGN, EPD, tuning data.
+    Parse(String),
+    IllegalMove(String),
+    InvalidOption(String),
+    Nnue(NnueError),
+    ThreadSpawn(io::Error),
+    ThreadPanicked(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for ChessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChessError::InvalidFen(e) => write!(f, "invalid FEN: {}", e),
+            ChessError::Parse(msg) => write!(f, "parse error: {}", msg),
+            ChessError::IllegalMove(mv) => write!(f, "illegal move: {}", mv),
+            ChessError::InvalidOption(msg) => write!(f, "invalid option: {}", msg),
+            ChessError::Nnue(e) => write!(f, "NNUE: {}", e),
+            ChessError::ThreadSpawn(e) => write!(f, "failed to spawn search thread: {}", e),
+            ChessError::ThreadPanicked(name) => write!(f, "thread '{}' panicked", name),
+            ChessError::Io(e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl Error for ChessError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ChessError::InvalidFen(e) => Some(e),
+            ChessError::Nnue(e) => Some(e),
+            ChessError::ThreadSpawn(e) | ChessError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<NnueError> for ChessError {
+    fn from(e: NnueError) -> Self {
+        ChessError::Nnue(e)
+    }
+}
+
+impl From<FenError> for ChessError {
+    fn from(e: FenError) -> Self {
+        ChessError::InvalidFen(e)
+    }
+}
+
+impl From<IllegalMove> for ChessError {
+    fn from(e: IllegalMove) -> Self {
+        ChessError::IllegalMove(format_uci(e.0))
+    }
+}
+
+impl From<io::Error> for ChessError {
+    fn from(e: io::Error) -> Self {
+        ChessError::Io(e)
+    }
+}
@@ -0,0 +1,1209 @@
+use crate::attacks;
+use crate::bitbase::{self, Verdict};
+use crate::board::Board;
+use crate::eval_cache::{MaterialEntry, king_shield_tt, material_tt, pawn_tt};
+use crate::nnue;
+use crate::pst::{EG_MATERIAL, EG_PST, MG_MATERIAL, MG_PST};
+use crate::simd::{self, MAX_PIECES};
+use crate::types::{Bitboard, Color, Piece, PieceKind, ZKey};
+use crate::zobrist;
+use std::fmt::Write;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// A middlegame/endgame score pair, tapered by game phase at the end of evaluation.
+#[derive(Copy, Clone, Default, Debug, Eq, PartialEq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+pub const fn s(mg: i32, eg: i32) -> Score {
+    Score { mg, eg }
+}
+
+impl Add for Score {
+    type Output = Score;
+    #[inline(always)]
+    fn add(self, o: Score) -> Score {
+        s(self.mg + o.mg, self.eg + o.eg)
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+    #[inline(always)]
+    fn sub(self, o: Score) -> Score {
+        s(self.mg - o.mg, self.eg - o.eg)
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+    #[inline(always)]
+    fn neg(self) -> Score {
+        s(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+    #[inline(always)]
+    fn mul(self, n: i32) -> Score {
+        s(self.mg * n, self.eg * n)
+    }
+}
+
+impl AddAssign for Score {
+    #[inline(always)]
+    fn add_assign(&mut self, o: Score) {
+        self.mg += o.mg;
+        self.eg += o.eg;
+    }
+}
+
+impl SubAssign for Score {
+    #[inline(always)]
+    fn sub_assign(&mut self, o: Score) {
+        self.mg -= o.mg;
+        self.eg -= o.eg;
+    }
+}
+
+const PHASE_WEIGHTS: [i32; 6] = [0, 1, 1, 2, 4, 0]; // P, N, B, R, Q, K
+const MAX_PHASE: i32 = 24;
+
+// Bonus per safe square a piece attacks.
+const MOBILITY: [Score; 6] = [s(0, 0), s(4, 4), s(5, 5), s(2, 4), s(1, 2), s(0, 0)];
+
+const DOUBLED_PAWN: Score = s(-10, -20);
+const ISOLATED_PAWN: Score = s(-12, -15);
+// Indexed by the pawn's rank from its own side.
+const PASSED_PAWN: [Score; 8] = [
+    s(0, 0),
+    s(5, 10),
+    s(10, 15),
+    s(15, 25),
+    s(25, 45),
+    s(45, 80),
+    s(70, 120),
+    s(0, 0),
+];
+// Connected pawns by relative rank; a phalanx doubles the bonus.
+const CONNECTED_PAWN: [i32; 8] = [0, 3, 4, 6, 14, 24, 40, 0];
+// Per own pawn defending this one.
+const SUPPORTED_PAWN: Score = s(8, 6);
+
+// Attack units contributed by each piece that hits the enemy king zone.
+const KING_ATTACK_WEIGHT: [i32; 6] = [0, 2, 2, 3, 5, 0];
+const KING_ATTACK_MAX: i32 = 500;
+
+// Bonus per step of closeness to the enemy king, by piece kind.
+const KING_TROPISM: [Score; 6] = [s(0, 0), s(3, 1), s(2, 1), s(2, 1), s(5, 2), s(0, 0)];
+
+// For each file around the king: the nearest own pawn in front is one rank
+// ahead, two ranks ahead, or missing (or further up).
+const PAWN_SHIELD: [Score; 3] = [s(12, 0), s(6, 0), s(-14, 0)];
+
+const BISHOP_PAIR: Score = s(25, 50);
+// Per knight or rook, for each own pawn above five. Knights gain value in
+// closed positions, rooks in open ones.
+const KNIGHT_PAWNS: Score = s(2, 3);
+const ROOK_PAWNS: Score = s(-3, -5);
+
+// Squares in a side's camp that none of its pawns can ever defend.
+const HOLE: Score = s(-5, -2);
+const HOLE_KNIGHT_REACH: Score = s(-14, -6);
+const HOLE_BISHOP_COMPLEX: Score = s(-8, -4);
+
+// Opening development, middlegame only.
+const QUEEN_SORTIE: Score = s(-8, 0); // per minor still at home once the queen has left
+const BISHOP_BEHIND_CENTER_PAWN: Score = s(-12, 0);
+const BLOCKED_CENTER_PAWN: Score = s(-15, 0);
+
+// Bishop on a long diagonal that sees both of its central squares through pawns.
+const LONG_DIAGONAL_BISHOP: Score = s(20, 5);
+// Castled king behind a fianchetto with its bishop in place, or with the bishop gone.
+const FIANCHETTO: Score = s(15, 0);
+const MISSING_FIANCHETTO_BISHOP: Score = s(-25, -5);
+
+/// Every tunable weight of the classical evaluation. The piece-square tables
+/// include material and are given from White's side, indexed by piece kind.
+#[derive(Clone, Debug)]
+pub struct EvalParams {
+    pub mg_pst: [[i32; 64]; 6],
+    pub eg_pst: [[i32; 64]; 6],
+    pub mobility: [Score; 6],
+    pub doubled_pawn: Score,
+    pub isolated_pawn: Score,
+    pub passed_pawn: [Score; 8],
+    pub connected_pawn: [i32; 8],
+    pub supported_pawn: Score,
+    pub king_attack_weight: [i32; 6],
+    pub king_tropism: [Score; 6],
+    pub pawn_shield: [Score; 3],
+    pub bishop_pair: Score,
+    pub knight_pawns: Score,
+    pub rook_pawns: Score,
+    pub hole: Score,
+    pub hole_knight_reach: Score,
+    pub hole_bishop_complex: Score,
+    pub queen_sortie: Score,
+    pub bishop_behind_center_pawn: Score,
+    pub blocked_center_pawn: Score,
+    pub long_diagonal_bishop: Score,
+    pub fianchetto: Score,
+    pub missing_fianchetto_bishop: Score,
+}
+
+pub const DEFAULT_PARAMS: EvalParams = EvalParams {
+    mg_pst: [
+        MG_PST[1], MG_PST[2], MG_PST[3], MG_PST[4], MG_PST[5], MG_PST[6],
+    ],
+    eg_pst: [
+        EG_PST[1], EG_PST[2], EG_PST[3], EG_PST[4], EG_PST[5], EG_PST[6],
+    ],
+    mobility: MOBILITY,
+    doubled_pawn: DOUBLED_PAWN,
+    isolated_pawn: ISOLATED_PAWN,
+    passed_pawn: PASSED_PAWN,
+    connected_pawn: CONNECTED_PAWN,
+    supported_pawn: SUPPORTED_PAWN,
+    king_attack_weight: KING_ATTACK_WEIGHT,
+    king_tropism: KING_TROPISM,
+    pawn_shield: PAWN_SHIELD,
+    bishop_pair: BISHOP_PAIR,
+    knight_pawns: KNIGHT_PAWNS,
+    rook_pawns: ROOK_PAWNS,
+    hole: HOLE,
+    hole_knight_reach: HOLE_KNIGHT_REACH,
+    hole_bishop_complex: HOLE_BISHOP_COMPLEX,
+    queen_sortie: QUEEN_SORTIE,
+    bishop_behind_center_pawn: BISHOP_BEHIND_CENTER_PAWN,
+    blocked_center_pawn: BLOCKED_CENTER_PAWN,
+    long_diagonal_bishop: LONG_DIAGONAL_BISHOP,
+    fianchetto: FIANCHETTO,
+    missing_fianchetto_bishop: MISSING_FIANCHETTO_BISHOP,
+};
+
+const FILE_A: Bitboard = 0x0101_0101_0101_0101;
+const CENTER: Bitboard = (1 << 27) | (1 << 28) | (1 << 35) | (1 << 36);
+const LONG_DIAGONALS: Bitboard = 0x8040_2010_0804_0201 | 0x0102_0408_1020_4080;
+const CENTER_FILES: Bitboard = 0x3C3C_3C3C_3C3C_3C3C;
+const LIGHT_SQUARES: Bitboard = 0x55AA_55AA_55AA_55AA;
+
+const fn file_mask(file: usize) -> Bitboard {
+    FILE_A << file
+}
+
+const fn adjacent_files(file: usize) -> Bitboard {
+    let left = if file > 0 { file_mask(file - 1) } else { 0 };
+    let right = if file < 7 { file_mask(file + 1) } else { 0 };
+    left | right
+}
+
+/// Squares on the same and adjacent files strictly in front of `sq` for `white`.
+const fn front_span(sq: usize, white: bool) -> Bitboard {
+    let file = sq % 8;
+    let rank = sq / 8;
+    let files = file_mask(file) | adjacent_files(file);
+    let mut ahead: Bitboard = 0;
+    let mut r = 0;
+    while r < 8 {
+        if (white && r > rank) || (!white && r < rank) {
+            ahead |= 0xFFu64 << (r * 8);
+        }
+        r += 1;
+    }
+    files & ahead
+}
+
+const fn generate_passed_masks() -> [[Bitboard; 64]; 2] {
+    let mut masks = [[0; 64]; 2];
+    let mut sq = 0;
+    while sq < 64 {
+        masks[0][sq] = front_span(sq, true);
+        masks[1][sq] = front_span(sq, false);
+        sq += 1;
+    }
+    masks
+}
+
+const PASSED_MASKS: [[Bitboard; 64]; 2] = generate_passed_masks();
+
+#[inline(always)]
+fn pieces(b: &Board, kind: PieceKind, c: Color) -> Bitboard {
+    b.piece_bb[Piece::from_kind(kind, c).index()]
+}
+
+#[inline(always)]
+fn distance(a: usize, b: usize) -> i32 {
+    let df = ((a % 8) as i32 - (b % 8) as i32).abs();
+    let dr = ((a / 8) as i32 - (b / 8) as i32).abs();
+    df.max(dr)
+}
+
+#[inline]
+fn pawn_attacks(pawns: Bitboard, c: Color) -> Bitboard {
+    const NOT_A: Bitboard = !FILE_A;
+    const NOT_H: Bitboard = !(FILE_A << 7);
+    match c {
+        Color::White => ((pawns << 7) & NOT_H) | ((pawns << 9) & NOT_A),
+        Color::Black => ((pawns >> 9) & NOT_H) | ((pawns >> 7) & NOT_A),
+    }
+}
+
+/// Structure terms for one pawn, from the pawn owner's point of view.
+/// `own_files` holds the number of own pawns on each file.
+fn evaluate_single_pawn(
+    sq: usize,
+    c: Color,
+    own: Bitboard,
+    enemy: Bitboard,
+    own_files: &[u32; 8],
+    params: &EvalParams,
+) -> Score {
+    let file = sq % 8;
+    let relative_rank = if c == Color::White {
+        sq / 8
+    } else {
+        7 - sq / 8
+    };
+    let mut score = Score::default();
+
+    if own_files[file] > 1 {
+        score += params.doubled_pawn;
+    }
+    let left = if file > 0 { own_files[file - 1] } else { 0 };
+    let right = if file < 7 { own_files[file + 1] } else { 0 };
+    if left + right == 0 {
+        score += params.isolated_pawn;
+    }
+    if enemy & PASSED_MASKS[c as usize][sq] == 0 {
+        score += params.passed_pawn[relative_rank];
+    }
+
+    let phalanx = own & adjacent_files(file) & (0xFFu64 << (sq / 8 * 8));
+    let supporters = own & pawn_attacks(1u64 << sq, c.other());
+    if phalanx != 0 || supporters != 0 {
+        let bonus = params.connected_pawn[relative_rank] * if phalanx != 0 { 2 } else { 1 };
+        score += s(bonus, bonus * (relative_rank as i32 - 2).max(0) / 4);
+        score += params.supported_pawn * supporters.count_ones() as i32;
+    }
+
+    score
+}
+
+/// Pawn-structure score from White's point of view, cached in the pawn hash.
+fn evaluate_pawns(b: &Board, params: &EvalParams) -> Score {
+    let key = b.pawn_key;
+    if let Some(score) = pawn_tt().probe(key) {
+        return score;
+    }
+
+    let score = pawn_structure(b, Color::White, params) - pawn_structure(b, Color::Black, params);
+    pawn_tt().store(key, score);
+    score
+}
+
+/// Pawn-structure score of side `c`, from its own point of view.
+fn pawn_structure(b: &Board, c: Color, params: &EvalParams) -> Score {
+    let own = pieces(b, PieceKind::Pawn, c);
+    let enemy = pieces(b, PieceKind::Pawn, c.other());
+    let own_files = simd::file_counts(own);
+    let mut score = Score::default();
+
+    let mut bb = own;
+    while bb != 0 {
+        let sq = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        score += evaluate_single_pawn(sq, c, own, enemy, &own_files, params);
+    }
+    score
+}
+
+/// Keys the material cache by the number of each piece type, so positions
+/// with the same material share an entry whatever the squares.
+fn material_key(b: &Board) -> ZKey {
+    let mut key = 0;
+    for p in [
+        Piece::WP,
+        Piece::WN,
+        Piece::WB,
+        Piece::WR,
+        Piece::WQ,
+        Piece::BP,
+        Piece::BN,
+        Piece::BB,
+        Piece::BR,
+        Piece::BQ,
+    ] {
+        let count = b.piece_bb[p.index()].count_ones() as usize;
+        key ^= zobrist::ZOB.piece_key(p, count.min(63));
+    }
+    key
+}
+
+fn game_phase(b: &Board) -> i32 {
+    [
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+    ]
+    .iter()
+    .map(|&kind| {
+        let both = pieces(b, kind, Color::White) | pieces(b, kind, Color::Black);
+        both.count_ones() as i32 * PHASE_WEIGHTS[kind as usize]
+    })
+    .sum()
+}
+
+/// Bishop pair and the pawn-count adjustments of knights and rooks for side
+/// `c`, from its own point of view.
+fn imbalance(b: &Board, c: Color, params: &EvalParams) -> Score {
+    let count = |kind| pieces(b, kind, c).count_ones() as i32;
+    let extra_pawns = count(PieceKind::Pawn) - 5;
+    let mut score = Score::default();
+    if count(PieceKind::Bishop) >= 2 {
+        score += params.bishop_pair;
+    }
+    score += params.knight_pawns * (count(PieceKind::Knight) * extra_pawns);
+    score += params.rook_pawns * (count(PieceKind::Rook) * extra_pawns);
+    score
+}
+
+fn material(b: &Board, params: &EvalParams) -> MaterialEntry {
+    MaterialEntry {
+        phase: game_phase(b),
+        imbalance: imbalance(b, Color::White, params) - imbalance(b, Color::Black, params),
+    }
+}
+
+/// Phase and imbalance, cached in the material hash.
+fn evaluate_material(b: &Board, params: &EvalParams) -> MaterialEntry {
+    let key = material_key(b);
+    if let Some(entry) = material_tt().probe(key) {
+        return entry;
+    }
+
+    let entry = material(b, params);
+    material_tt().store(key, entry);
+    entry
+}
+
+/// Pawn shield in front of `c`'s king, from its own point of view.
+fn king_shield(b: &Board, c: Color, params: &EvalParams) -> Score {
+    let king = b.king_index(c);
+    if king >= 64 {
+        return Score::default();
+    }
+    let own_pawns = pieces(b, PieceKind::Pawn, c) & PASSED_MASKS[c as usize][king];
+    let king_rank = king / 8;
+    let mut score = Score::default();
+
+    let file = king % 8;
+    for f in file.saturating_sub(1)..=(file + 1).min(7) {
+        let on_file = own_pawns & file_mask(f);
+        let nearest = if on_file == 0 {
+            None
+        } else if c == Color::White {
+            Some(on_file.trailing_zeros() as usize / 8 - king_rank)
+        } else {
+            Some(king_rank - (63 - on_file.leading_zeros() as usize) / 8)
+        };
+        score += match nearest {
+            Some(1) => params.pawn_shield[0],
+            Some(2) => params.pawn_shield[1],
+            _ => params.pawn_shield[2],
+        };
+    }
+    score
+}
+
+/// [`king_shield`], cached by king square and pawn key.
+fn evaluate_king_shield(b: &Board, c: Color, params: &EvalParams) -> Score {
+    let king = Piece::from_kind(PieceKind::King, c);
+    let square = b.king_index(c).min(63);
+    let key = b.pawn_key ^ zobrist::ZOB.piece_key(king, square);
+    if let Some(score) = king_shield_tt().probe(key) {
+        return score;
+    }
+
+    let score = king_shield(b, c, params);
+    king_shield_tt().store(key, score);
+    score
+}
+
+/// Penalizes holes in `c`'s camp near the centre or its king, more so when an
+/// enemy knight can jump into them or an enemy bishop controls their colour.
+fn evaluate_weak_squares(b: &Board, c: Color, params: &EvalParams) -> Score {
+    let own_pawns = pieces(b, PieceKind::Pawn, c);
+    let mut defendable = 0;
+    let mut bb = own_pawns;
+    while bb != 0 {
+        let sq = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        defendable |= PASSED_MASKS[c as usize][sq] & adjacent_files(sq % 8);
+    }
+
+    // Relative ranks 3 to 5.
+    let camp: Bitboard = if c == Color::White {
+        0x0000_00FF_FFFF_0000
+    } else {
+        0x0000_FFFF_FF00_0000
+    };
+    let own_king = b.king_index(c);
+    let king_area = if own_king < 64 {
+        attacks::king(own_king)
+    } else {
+        0
+    };
+    let holes = camp & (CENTER_FILES | king_area) & !defendable & !own_pawns;
+    if holes == 0 {
+        return Score::default();
+    }
+
+    let mut knight_reach = 0;
+    let mut bb = pieces(b, PieceKind::Knight, c.other());
+    while bb != 0 {
+        let sq = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        knight_reach |= attacks::knight(sq);
+    }
+
+    let enemy_bishops = pieces(b, PieceKind::Bishop, c.other());
+    let mut bishop_complex = 0;
+    if enemy_bishops & LIGHT_SQUARES != 0 {
+        bishop_complex |= LIGHT_SQUARES;
+    }
+    if enemy_bishops & !LIGHT_SQUARES != 0 {
+        bishop_complex |= !LIGHT_SQUARES;
+    }
+
+    params.hole * holes.count_ones() as i32
+        + params.hole_knight_reach * (holes & knight_reach).count_ones() as i32
+        + params.hole_bishop_complex * (holes & bishop_complex).count_ones() as i32
+}
+
+/// Opening terms for `c`: early queen sorties while minors sit at home, and
+/// bishops or centre pawns stuck on their starting squares.
+fn evaluate_development(b: &Board, c: Color, params: &EvalParams) -> Score {
+    // Squares are given from White's side and mirrored for Black.
+    let rel = |sq: usize| if c == Color::White { sq } else { sq ^ 56 };
+    let has = |kind: PieceKind, sq: usize| b.piece_on[rel(sq)] == Piece::from_kind(kind, c);
+
+    let mut score = Score::default();
+
+    let minors_home = [
+        (PieceKind::Knight, 1),
+        (PieceKind::Bishop, 2),
+        (PieceKind::Bishop, 5),
+        (PieceKind::Knight, 6),
+    ]
+    .iter()
+    .filter(|&&(kind, sq)| has(kind, sq))
+    .count() as i32;
+    if minors_home >= 2 && pieces(b, PieceKind::Queen, c) != 0 && !has(PieceKind::Queen, 3) {
+        score += params.queen_sortie * minors_home;
+    }
+
+    // c1 bishop behind the d2 pawn, f1 bishop behind the e2 pawn.
+    for (bishop_sq, pawn_sq) in [(2, 11), (5, 12)] {
+        if has(PieceKind::Bishop, bishop_sq) && has(PieceKind::Pawn, pawn_sq) {
+            score += params.bishop_behind_center_pawn;
+        }
+    }
+
+    // d2/e2 pawns that cannot advance because something stands in front.
+    for pawn_sq in [11, 12] {
+        if has(PieceKind::Pawn, pawn_sq) && !b.piece_on[rel(pawn_sq + 8)].is_empty() {
+            score += params.blocked_center_pawn;
+        }
+    }
+
+    score
+}
+
+/// Long-diagonal bishops and fianchetto structures in front of a castled king.
+fn evaluate_bishops(b: &Board, c: Color, params: &EvalParams) -> Score {
+    let bishops = pieces(b, PieceKind::Bishop, c);
+    let pawns = pieces(b, PieceKind::Pawn, Color::White) | pieces(b, PieceKind::Pawn, Color::Black);
+    let mut score = Score::default();
+
+    let mut bb = bishops & LONG_DIAGONALS;
+    while bb != 0 {
+        let sq = bb.trailing_zeros() as usize;
+        bb &= bb - 1;
+        if (attacks::bishop(sq, pawns) & CENTER).count_ones() >= 2 {
+            score += params.long_diagonal_bishop;
+        }
+    }
+
+    // Squares are given from White's side and mirrored for Black.
+    let rel = |sq: usize| if c == Color::White { sq } else { sq ^ 56 };
+    let has = |kind: PieceKind, sq: usize| b.piece_on[rel(sq)] == Piece::from_kind(kind, c);
+    let king = b.king_index(c);
+    if king >= 64 {
+        return score;
+    }
+
+    // (king squares, bishop square, pushed knight-file pawn, centre-side pawn, edge pawns)
+    for (king_squares, bishop_sq, pushed, inner, edge) in [
+        ([5, 6, 7], 14, 22, 13, [15, 23]),
+        ([0, 1, 2], 9, 17, 10, [8, 16]),
+    ] {
+        let structure = king_squares.iter().any(|&k| rel(k) == king)
+            && has(PieceKind::Pawn, pushed)
+            && has(PieceKind::Pawn, inner)
+            && edge.iter().any(|&sq| has(PieceKind::Pawn, sq));
+        if !structure {
+            continue;
+        }
+
+        let complex = if LIGHT_SQUARES & (1u64 << rel(bishop_sq)) != 0 {
+            LIGHT_SQUARES
+        } else {
+            !LIGHT_SQUARES
+        };
+        if has(PieceKind::Bishop, bishop_sq) {
+            score += params.fianchetto;
+        } else if bishops & complex == 0 {
+            score += params.missing_fianchetto_bishop;
+        }
+    }
+
+    score
+}
+
+/// Mobility, and king attack plus king tropism, for the pieces of side `c`.
+fn evaluate_pieces(b: &Board, c: Color, params: &EvalParams) -> (Score, Score) {
+    let own = if c == Color::White {
+        b.w_pieces
+    } else {
+        b.b_pieces
+    };
+    let enemy_pawn_attacks = pawn_attacks(pieces(b, PieceKind::Pawn, c.other()), c.other());
+    let mobility_area = !own & !enemy_pawn_attacks;
+
+    let enemy_king = b.king_index(c.other());
+    let king_zone = if enemy_king < 64 {
+        attacks::king(enemy_king) | (1u64 << enemy_king)
+    } else {
+        0
+    };
+
+    // Gather every piece's attacks first so the popcounts can run in batches.
+    let mut kinds = [PieceKind::Knight; MAX_PIECES];
+    let mut squares = [0usize; MAX_PIECES];
+    let mut attacks = [0 as Bitboard; MAX_PIECES];
+    let mut n = 0;
+
+    for kind in [
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+    ] {
+        let mut bb = pieces(b, kind, c);
+        while bb != 0 && n < MAX_PIECES {
+            let sq = bb.trailing_zeros() as usize;
+            bb &= bb - 1;
+
+            kinds[n] = kind;
+            squares[n] = sq;
+            attacks[n] = match kind {
+                PieceKind::Knight => attacks::knight(sq),
+                PieceKind::Bishop => attacks::bishop(sq, b.all_pieces),
+                PieceKind::Rook => attacks::rook(sq, b.all_pieces),
+                _ => attacks::bishop(sq, b.all_pieces) | attacks::rook(sq, b.all_pieces),
+            };
+            n += 1;
+        }
+    }
+
+    let mobility = simd::masked_popcounts(&attacks, mobility_area);
+    let zone_hits = simd::masked_popcounts(&attacks, king_zone);
+
+    let mut score = Score::default();
+    let mut king_safety = Score::default();
+    let mut attack_units = 0;
+    let mut attackers = 0;
+
+    for i in 0..n {
+        let kind = kinds[i];
+        score += params.mobility[kind as usize] * mobility[i] as i32;
+
+        if zone_hits[i] > 0 {
+            attackers += 1;
+            attack_units += params.king_attack_weight[kind as usize] * zone_hits[i] as i32;
+        }
+
+        if enemy_king < 64 {
+            king_safety += king_tropism(kind, squares[i], enemy_king, params);
+        }
+    }
+
+    // A lone attacker is rarely dangerous; the bonus grows quadratically with pressure.
+    if attackers >= 2 {
+        king_safety.mg += (attack_units * attack_units).min(KING_ATTACK_MAX);
+    }
+
+    (score, king_safety)
+}
+
+/// Rewards pieces for standing close to the enemy king.
+#[inline]
+fn king_tropism(kind: PieceKind, sq: usize, enemy_king: usize, params: &EvalParams) -> Score {
+    params.king_tropism[kind as usize] * (7 - distance(sq, enemy_king))
+}
+
+/// Classical evaluation from White's point of view.
+pub fn evaluate_white_pov(b: &Board) -> i32 {
+    evaluate_terms(b, &DEFAULT_PARAMS, true)
+}
+
+/// Classical evaluation from White's point of view with the given weights.
+/// Bypasses the eval caches, whose entries are only valid for the defaults.
+pub fn evaluate_with_params(b: &Board, params: &EvalParams) -> i32 {
+    evaluate_terms(b, params, false)
+}
+
+fn evaluate_terms(b: &Board, params: &EvalParams, use_cache: bool) -> i32 {
+    let mut score = Score::default();
+
+    for sq in 0..64 {
+        let p = b.piece_on[sq];
+        if let (Some(kind), Some(c)) = (p.kind(), p.color()) {
+            let k = kind as usize;
+            if c == Color::White {
+                score += s(params.mg_pst[k][sq], params.eg_pst[k][sq]);
+            } else {
+                score -= s(params.mg_pst[k][sq ^ 56], params.eg_pst[k][sq ^ 56]);
+            }
+        }
+    }
+
+    let material = if use_cache {
+        score += evaluate_pawns(b, params);
+        score += evaluate_king_shield(b, Color::White, params)
+            - evaluate_king_shield(b, Color::Black, params);
+        evaluate_material(b, params)
+    } else {
+        score += pawn_structure(b, Color::White, params) - pawn_structure(b, Color::Black, params);
+        score += king_shield(b, Color::White, params) - king_shield(b, Color::Black, params);
+        material(b, params)
+    };
+    score += material.imbalance;
+    let (white_mobility, white_king) = evaluate_pieces(b, Color::White, params);
+    let (black_mobility, black_king) = evaluate_pieces(b, Color::Black, params);
+    score += white_mobility - black_mobility + white_king - black_king;
+    score += evaluate_weak_squares(b, Color::White, params)
+        - evaluate_weak_squares(b, Color::Black, params);
+    score += evaluate_development(b, Color::White, params)
+        - evaluate_development(b, Color::Black, params);
+    score += evaluate_bishops(b, Color::White, params) - evaluate_bishops(b, Color::Black, params);
+
+    taper(score, material.phase)
+}
+
+#[inline]
+fn taper(score: Score, phase: i32) -> i32 {
+    let phase = phase.min(MAX_PHASE);
+    (score.mg * phase + score.eg * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+/// Classical evaluation from the side to move's point of view.
+#[inline]
+pub fn evaluate(b: &Board) -> i32 {
+    let v = evaluate_white_pov(b);
+    if b.turn == Color::White { v } else { -v }
+}
+
+/// Which evaluation the search uses, set by the `UseNNUE` option.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvalMode {
+    /// NNUE in balanced positions, the classical eval once one side is
+    /// clearly ahead in material, and a mix of both in between.
+    Hybrid,
+    Nnue,
+    Classical,
+}
+
+impl EvalMode {
+    /// Values of the `UseNNUE` option.
+    pub const NAMES: &'static [&'static str] = &["hybrid", "true", "false"];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "hybrid" => Some(EvalMode::Hybrid),
+            "true" => Some(EvalMode::Nnue),
+            "false" => Some(EvalMode::Classical),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EvalMode::Hybrid => "hybrid",
+            EvalMode::Nnue => "nnue",
+            EvalMode::Classical => "classical",
+        }
+    }
+}
+
+static EVAL_MODE: AtomicU8 = AtomicU8::new(EvalMode::Hybrid as u8);
+
+pub fn set_eval_mode(mode: EvalMode) {
+    EVAL_MODE.store(mode as u8, Ordering::Relaxed);
+}
+
+pub fn eval_mode() -> EvalMode {
+    match EVAL_MODE.load(Ordering::Relaxed) {
+        0 => EvalMode::Hybrid,
+        1 => EvalMode::Nnue,
+        _ => EvalMode::Classical,
+    }
+}
+
+// Material lead (endgame values) where the hybrid eval starts mixing in the
+// classical eval, and where it relies on it alone. The net is trained mostly
+// on balanced positions; lopsided ones are better left to material counting.
+const HYBRID_BLEND_START: i32 = 400;
+const HYBRID_BLEND_END: i32 = 900;
+
+fn material_lead(b: &Board) -> i32 {
+    let side = |c: Color| -> i32 {
+        [
+            PieceKind::Pawn,
+            PieceKind::Knight,
+            PieceKind::Bishop,
+            PieceKind::Rook,
+            PieceKind::Queen,
+        ]
+        .iter()
+        .map(|&kind| pieces(b, kind, c).count_ones() as i32 * EG_MATERIAL[kind as usize])
+        .sum()
+    };
+    (side(Color::White) - side(Color::Black)).abs()
+}
+
+/// Base score of an ending the bitbases have decided as won, below any mate
+/// score.
+pub const KNOWN_WIN: i32 = 10_000;
+
+/// Bonus for `sq` being far from the centre, 0 to 6.
+#[inline]
+fn edge_distance(sq: usize) -> i32 {
+    let from_centre = |x: i32| (2 * x - 7).abs() / 2;
+    from_centre((sq % 8) as i32) + from_centre((sq / 8) as i32)
+}
+
+/// Score of a bitbase verdict from the side to move's point of view. Won
+/// positions still need progress: pawn endings reward advancing the pawn,
+/// the others driving the lone king to the edge with the own king close.
+fn bitbase_score(b: &Board, verdict: Verdict) -> i32 {
+    let Verdict::Win(strong) = verdict else {
+        return 0;
+    };
+    let strong_king = b.king_index(strong);
+    let weak_king = b.king_index(strong.other());
+    let pawns = pieces(b, PieceKind::Pawn, strong);
+    let score = if pawns != 0 {
+        let rank = pawns.trailing_zeros() as i32 / 8;
+        let relative_rank = if strong == Color::White {
+            rank
+        } else {
+            7 - rank
+        };
+        KNOWN_WIN + EG_MATERIAL[PieceKind::Pawn as usize] + 20 * relative_rank
+    } else {
+        let kind = if pieces(b, PieceKind::Queen, strong) != 0 {
+            PieceKind::Queen
+        } else {
+            PieceKind::Rook
+        };
+        KNOWN_WIN
+            + EG_MATERIAL[kind as usize]
+            + 20 * edge_distance(weak_king)
+            + 10 * (7 - distance(strong_king, weak_king))
+    };
+    if b.turn == strong { score } else { -score }
+}
+
+/// What an ending recognizer knows about a position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum EndingVerdict {
+    /// An exact score from `Color`'s point of view.
+    Score(Color, i32),
+    /// The generic evaluation, scaled by this many 64ths.
+    Scale(i32),
+}
+
+/// Pieces of each kind side `c` has, indexed by `PieceKind`.
+fn piece_counts(b: &Board, c: Color) -> [u32; 6] {
+    let mut counts = [0; 6];
+    for kind in [
+        PieceKind::Pawn,
+        PieceKind::Knight,
+        PieceKind::Bishop,
+        PieceKind::Rook,
+        PieceKind::Queen,
+    ] {
+        counts[kind as usize] = pieces(b, kind, c).count_ones();
+    }
+    counts
+}
+
+/// King, bishop and knight against king: a win, but only by driving the
+/// lone king into a corner of the bishop's colour.
+fn kbnk(b: &Board, strong: Color, own: [u32; 6], other: [u32; 6]) -> Option<EndingVerdict> {
+    if own != [0, 1, 1, 0, 0, 0] || other != [0; 6] {
+        return None;
+    }
+    let weak_king = b.king_index(strong.other());
+    let strong_king = b.king_index(strong);
+    let corners = if pieces(b, PieceKind::Bishop, strong) & LIGHT_SQUARES != 0 {
+        [7, 56]
+    } else {
+        [0, 63]
+    };
+    let corner_distance = corners
+        .iter()
+        .map(|&c| distance(weak_king, c))
+        .min()
+        .unwrap_or(7);
+    Some(EndingVerdict::Score(
+        strong,
+        KNOWN_WIN
+            + EG_MATERIAL[PieceKind::Bishop as usize]
+            + EG_MATERIAL[PieceKind::Knight as usize]
+            + 40 * (7 - corner_distance)
+            + 10 * (7 - distance(strong_king, weak_king)),
+    ))
+}
+
+/// Bishop and rook pawns against a bare king is a draw when the bishop
+/// cannot cover the promotion square and the king has reached it.
+fn wrong_bishop(b: &Board, strong: Color, own: [u32; 6], other: [u32; 6]) -> Option<EndingVerdict> {
+    if own[1..5] != [0, 1, 0, 0] || own[0] == 0 || other != [0; 6] {
+        return None;
+    }
+    let pawns = pieces(b, PieceKind::Pawn, strong);
+    let file = [0, 7].into_iter().find(|&f| pawns & !file_mask(f) == 0)?;
+    let promotion = if strong == Color::White {
+        56 + file
+    } else {
+        file
+    };
+    let bishop_light = pieces(b, PieceKind::Bishop, strong) & LIGHT_SQUARES != 0;
+    let promotion_light = (1 << promotion) & LIGHT_SQUARES != 0;
+    let weak_king = b.king_index(strong.other());
+    (bishop_light != promotion_light && distance(weak_king, promotion) <= 1)
+        .then_some(EndingVerdict::Score(strong, 0))
+}
+
+/// Rook and pawn against rook. The Philidor defence, with the king in front
+/// of a pawn that has not passed the fifth rank, holds the draw; a Lucena
+/// position, with the pawn on the seventh, the king in front of it and the
+/// defending king cut off, wins.
+fn krpkr(b: &Board, strong: Color, own: [u32; 6], other: [u32; 6]) -> Option<EndingVerdict> {
+    if own != [1, 0, 0, 1, 0, 0] || other != [0, 0, 0, 1, 0, 0] {
+        return None;
+    }
+    // Squares seen from the strong side, so its pawn moves up the board.
+    let relative = |sq: usize| if strong == Color::White { sq } else { sq ^ 56 };
+    let pawn = relative(pieces(b, PieceKind::Pawn, strong).trailing_zeros() as usize);
+    let strong_king = relative(b.king_index(strong));
+    let weak_king = relative(b.king_index(strong.other()));
+    let strong_rook = relative(pieces(b, PieceKind::Rook, strong).trailing_zeros() as usize);
+    let weak_rook = relative(pieces(b, PieceKind::Rook, strong.other()).trailing_zeros() as usize);
+    let (file, rank) = (pawn % 8, pawn / 8);
+    let promotion = 56 + file;
+
+    if rank <= 4 && strong_king / 8 <= 4 && distance(weak_king, promotion) <= 1 {
+        let philidor = weak_rook / 8 == 5 || (rank <= 2 && strong_rook / 8 != 5);
+        return Some(EndingVerdict::Scale(if philidor { 0 } else { 16 }));
+    }
+    let file_gap = ((weak_king % 8) as i32 - file as i32).abs();
+    if rank == 6 && strong_king / 8 == 7 && (strong_king % 8).abs_diff(file) <= 1 && file_gap >= 2 {
+        return Some(EndingVerdict::Scale(96));
+    }
+    None
+}
+
+/// Recognizes endings with dedicated knowledge, for either side.
+fn recognize_ending(b: &Board) -> Option<EndingVerdict> {
+    if b.all_pieces.count_ones() > 8 {
+        return None;
+    }
+    let counts = [piece_counts(b, Color::White), piece_counts(b, Color::Black)];
+    [Color::White, Color::Black].into_iter().find_map(|strong| {
+        let (own, other) = (counts[strong as usize], counts[strong.other() as usize]);
+        kbnk(b, strong, own, other)
+            .or_else(|| wrong_bishop(b, strong, own, other))
+            .or_else(|| krpkr(b, strong, own, other))
+    })
+}
+
+/// Evaluation used by the search, from the side to move's point of view,
+/// according to the `UseNNUE` mode. Endings covered by the bitbases are
+/// scored exactly instead, and other known endings override or scale the
+/// generic evaluation.
+pub fn evaluate_position(b: &Board) -> i32 {
+    if let Some(verdict) = bitbase::probe(b) {
+        return bitbase_score(b, verdict);
+    }
+    match recognize_ending(b) {
+        Some(EndingVerdict::Score(c, score)) => {
+            if b.turn == c {
+                score
+            } else {
+                -score
+            }
+        }
+        Some(EndingVerdict::Scale(scale)) => generic_eval(b) * scale / 64,
+        None => generic_eval(b),
+    }
+}
+
+/// Material in pawn units, ignoring pawns and the king.
+#[inline]
+fn non_pawn_material(b: &Board, c: Color) -> i32 {
+    let count = |kind: PieceKind| pieces(b, kind, c).count_ones() as i32;
+    3 * (count(PieceKind::Knight) + count(PieceKind::Bishop))
+        + 5 * count(PieceKind::Rook)
+        + 9 * count(PieceKind::Queen)
+}
+
+/// Pulls `eval`, from the side to move's view, toward a draw in material
+/// configurations that are hard to win: a pawnless side that is ahead by
+/// too little to force mate, bishops of opposite colours, and positions
+/// close to the fifty-move rule.
+fn scale_drawish(b: &Board, eval: i32) -> i32 {
+    let strong = if eval > 0 { b.turn } else { b.turn.other() };
+    let strong_material = non_pawn_material(b, strong);
+    let weak_material = non_pawn_material(b, strong.other());
+
+    let mut eval = eval;
+    if pieces(b, PieceKind::Pawn, strong) == 0 {
+        let only_knights =
+            strong_material == 3 * pieces(b, PieceKind::Knight, strong).count_ones() as i32;
+        if strong_material <= 3 || (only_knights && strong_material <= 6) {
+            eval /= 16;
+        } else if strong_material - weak_material <= 3 {
+            eval /= 4;
+        }
+    }
+
+    let bishops = [Color::White, Color::Black].map(|c| pieces(b, PieceKind::Bishop, c));
+    let opposite_bishops = bishops.iter().all(|bb| bb.count_ones() == 1)
+        && (bishops[0] & LIGHT_SQUARES == 0) != (bishops[1] & LIGHT_SQUARES == 0);
+    if opposite_bishops {
+        // With nothing else besides pawns the defender can usually blockade
+        // on the squares the other bishop cannot reach.
+        eval = if strong_material == 3 && weak_material == 3 {
+            eval / 2
+        } else {
+            eval * 3 / 4
+        };
+    }
+
+    // Nothing is gained by a lead the fifty-move rule is about to erase.
+    eval * (200 - b.halfmove_clock.clamp(0, 100)) / 200
+}
+
+/// The NNUE, classical or blended evaluation chosen by the `UseNNUE` mode,
+/// scaled down in drawish positions.
+fn generic_eval(b: &Board) -> i32 {
+    let eval = match eval_mode() {
+        EvalMode::Nnue => nnue::evaluate(b),
+        EvalMode::Classical => evaluate(b),
+        EvalMode::Hybrid => {
+            let weight = ((material_lead(b) - HYBRID_BLEND_START) * 256
+                / (HYBRID_BLEND_END - HYBRID_BLEND_START))
+                .clamp(0, 256);
+            match weight {
+                0 => nnue::evaluate(b),
+                256 => evaluate(b),
+                w => (nnue::evaluate(b) * (256 - w) + evaluate(b) * w) / 256,
+            }
+        }
+    };
+    scale_drawish(b, eval)
+}
+
+/// Material and PST (excluding material) of side `c`, from its own point of view.
+fn material_and_pst(b: &Board, c: Color) -> (Score, Score) {
+    let mut material = Score::default();
+    let mut pst = Score::default();
+    let sign = if c == Color::White { 1 } else { -1 };
+
+    for sq in 0..64 {
+        let p = b.piece_on[sq];
+        if let (Some(kind), Some(pc)) = (p.kind(), p.color())
+            && pc == c
+        {
+            let value = s(MG_MATERIAL[kind as usize], EG_MATERIAL[kind as usize]);
+            material += value;
+            pst += s(MG_PST[p.index()][sq], EG_PST[p.index()][sq]) * sign - value;
+        }
+    }
+    (material, pst)
+}
+
+type TraceRow = (&'static str, Score, Score);
+
+/// Every classical term per side, and the game phase.
+fn trace_terms(b: &Board) -> ([TraceRow; 10], i32) {
+    let params = &DEFAULT_PARAMS;
+    let (w_material, w_pst) = material_and_pst(b, Color::White);
+    let (b_material, b_pst) = material_and_pst(b, Color::Black);
+    let (w_mobility, w_king) = evaluate_pieces(b, Color::White, params);
+    let (b_mobility, b_king) = evaluate_pieces(b, Color::Black, params);
+
+    let rows = [
+        ("Material", w_material, b_material),
+        ("PST", w_pst, b_pst),
+        (
+            "Imbalance",
+            imbalance(b, Color::White, params),
+            imbalance(b, Color::Black, params),
+        ),
+        (
+            "Pawns",
+            pawn_structure(b, Color::White, params),
+            pawn_structure(b, Color::Black, params),
+        ),
+        ("Mobility", w_mobility, b_mobility),
+        ("King safety", w_king, b_king),
+        (
+            "King shield",
+            king_shield(b, Color::White, params),
+            king_shield(b, Color::Black, params),
+        ),
+        (
+            "Weak squares",
+            evaluate_weak_squares(b, Color::White, params),
+            evaluate_weak_squares(b, Color::Black, params),
+        ),
+        (
+            "Development",
+            evaluate_development(b, Color::White, params),
+            evaluate_development(b, Color::Black, params),
+        ),
+        (
+            "Bishops",
+            evaluate_bishops(b, Color::White, params),
+            evaluate_bishops(b, Color::Black, params),
+        ),
+    ];
+
+    (rows, game_phase(b))
+}
+
+fn search_white_pov(b: &Board) -> i32 {
+    if b.turn == Color::White {
+        evaluate_position(b)
+    } else {
+        -evaluate_position(b)
+    }
+}
+
+fn nnue_white_pov(b: &Board) -> i32 {
+    if b.turn == Color::White {
+        nnue::evaluate(b)
+    } else {
+        -nnue::evaluate(b)
+    }
+}
+
+/// Human-readable breakdown of the classical evaluation per term and side,
+/// followed by the tapered total, the NNUE score and the score the search
+/// uses, all from White's view.
+pub fn trace(b: &Board) -> String {
+    let (rows, phase) = trace_terms(b);
+    let cp = |v: i32| v as f64 / 100.0;
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "         Term |     White     |     Black     |     Total"
+    );
+    let _ = writeln!(
+        out,
+        "              |    MG     EG  |    MG     EG  |    MG     EG"
+    );
+    let _ = writeln!(
+        out,
+        " -------------+---------------+---------------+--------------"
+    );
+
+    let mut total = Score::default();
+    for (name, white, black) in rows {
+        let diff = white - black;
+        total += diff;
+        let _ = writeln!(
+            out,
+            " {:>12} | {:>5.2}  {:>5.2} | {:>5.2}  {:>5.2} | {:>5.2}  {:>5.2}",
+            name,
+            cp(white.mg),
+            cp(white.eg),
+            cp(black.mg),
+            cp(black.eg),
+            cp(diff.mg),
+            cp(diff.eg)
+        );
+    }
+    let _ = writeln!(
+        out,
+        " -------------+---------------+---------------+--------------"
+    );
+    let _ = writeln!(
+        out,
+        " {:>12} |               |               | {:>5.2}  {:>5.2}",
+        "Total",
+        cp(total.mg),
+        cp(total.eg)
+    );
+
+    let nnue_white = nnue_white_pov(b);
+    let _ = writeln!(out);
+    let _ = writeln!(out, "Phase:      {}/{}", phase.min(MAX_PHASE), MAX_PHASE);
+    let _ = writeln!(
+        out,
+        "Classical:  {:+.2} (white side)",
+        cp(taper(total, phase))
+    );
+    let _ = writeln!(out, "NNUE:       {:+.2} (white side)", cp(nnue_white));
+    let _ = writeln!(
+        out,
+        "Final:      {:+.2} (white side, {})",
+        cp(search_white_pov(b)),
+        eval_mode().name()
+    );
+    out
+}
+
+/// The same breakdown as [`trace`] as a JSON object, in centipawns.
+pub fn trace_json(b: &Board) -> serde_json::Value {
+    let (rows, phase) = trace_terms(b);
+    let score = |s: Score| serde_json::json!({ "mg": s.mg, "eg": s.eg });
+    let mut total = Score::default();
+    let terms: Vec<_> = rows
+        .into_iter()
+        .map(|(name, white, black)| {
+            total += white - black;
+            serde_json::json!({ "term": name, "white": score(white), "black": score(black) })
+        })
+        .collect();
+    serde_json::json!({
+        "type": "eval",
+        "fen": b.to_fen(),
+        "terms": terms,
+        "phase": phase.min(MAX_PHASE),
+        "classical": taper(total, phase),
+        "nnue": nnue_white_pov(b),
+        "final": search_white_pov(b),
+        "mode": eval_mode().name(),
+    })
+}
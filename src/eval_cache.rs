@@ -0,0 +1,111 @@
+use crate::eval::Score;
+use crate::types::ZKey;
+use num_cpus;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Game phase and material imbalance terms, which depend only on the piece
+/// counts.
+#[derive(Copy, Clone, Default)]
+pub struct MaterialEntry {
+    pub phase: i32,
+    pub imbalance: Score,
+}
+
+#[derive(Copy, Clone, Default)]
+struct Entry<E> {
+    key: ZKey,
+    value: E,
+}
+
+struct Table<E> {
+    slots: Vec<Entry<E>>,
+    mask: usize,
+}
+
+impl<E: Copy + Default> Table<E> {
+    fn with_mb(size_mb: usize) -> Self {
+        let bytes = (size_mb.max(1)) * 1024 * 1024;
+        let num_entries = (bytes / std::mem::size_of::<Entry<E>>()).next_power_of_two();
+        Self {
+            slots: vec![Entry::default(); num_entries],
+            mask: num_entries - 1,
+        }
+    }
+
+    #[inline]
+    fn idx(&self, key: ZKey) -> usize {
+        (key as usize) & self.mask
+    }
+
+    #[inline]
+    fn probe(&self, key: ZKey) -> Option<E> {
+        let entry = &self.slots[self.idx(key)];
+        if entry.key == key {
+            Some(entry.value)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    fn store(&mut self, key: ZKey, value: E) {
+        let idx = self.idx(key);
+        self.slots[idx] = Entry { key, value };
+    }
+}
+
+/// A small always-replace hash table of evaluation terms, sharded by key so
+/// search threads rarely contend for the same lock.
+pub struct EvalCache<E> {
+    shards: Vec<Arc<Mutex<Table<E>>>>,
+    shard_mask: usize,
+}
+
+impl<E: Copy + Default> EvalCache<E> {
+    pub fn new(size_mb: usize) -> Self {
+        let shard_count = (num_cpus::get().max(1)).next_power_of_two();
+        let per_shard_mb = (size_mb / shard_count).max(1);
+        let mut shards = Vec::with_capacity(shard_count);
+        for _ in 0..shard_count {
+            shards.push(Arc::new(Mutex::new(Table::with_mb(per_shard_mb))));
+        }
+        Self {
+            shards,
+            shard_mask: shard_count - 1,
+        }
+    }
+
+    #[inline]
+    fn shard_for(&self, key: ZKey) -> &Arc<Mutex<Table<E>>> {
+        &self.shards[(key as usize) & self.shard_mask]
+    }
+
+    #[inline]
+    pub fn probe(&self, key: ZKey) -> Option<E> {
+        self.shard_for(key).lock().unwrap().probe(key)
+    }
+
+    #[inline]
+    pub fn store(&self, key: ZKey, value: E) {
+        self.shard_for(key).lock().unwrap().store(key, value);
+    }
+}
+
+static PAWN_TT: OnceLock<EvalCache<Score>> = OnceLock::new();
+static MATERIAL_TT: OnceLock<EvalCache<MaterialEntry>> = OnceLock::new();
+static KING_SHIELD_TT: OnceLock<EvalCache<Score>> = OnceLock::new();
+
+/// Pawn-structure scores, keyed by the pawn key.
+pub fn pawn_tt() -> &'static EvalCache<Score> {
+    PAWN_TT.get_or_init(|| EvalCache::new(64)) // Default to 64 Slight increase.
+}
+
+/// Phase and imbalance, keyed by the piece counts.
+pub fn material_tt() -> &'static EvalCache<MaterialEntry> {
+    MATERIAL_TT.get_or_init(|| EvalCache::new(1))
+}
+
+/// One side's pawn shield, keyed by its king square and the pawn key.
+pub fn king_shield_tt() -> &'static EvalCache<Score> {
+    KING_SHIELD_TT.get_or_init(|| EvalCache::new(4))
+}
@@ -0,0 +1,80 @@
+use crate::board::Board;
+use crate::eval::{DEFAULT_PARAMS, evaluate_white_pov, evaluate_with_params};
+use crate::movelist::MoveList;
+use crate::nnue;
+use crate::uci_io::format_uci;
+
+/// Everything observable about a position that make/unmake must restore.
+fn snapshot(b: &Board) -> (String, u64, [u64; 13], i32, i32) {
+    (
+        b.to_fen(),
+        b.zobrist,
+        b.piece_bb,
+        evaluate_white_pov(b),
+        nnue::evaluate(b),
+    )
+}
+
+/// Runs the evaluation consistency checks on one position and returns a
+/// description of every failure.
+pub fn check_position(b: &mut Board) -> Vec<String> {
+    let mut failures = Vec::new();
+    let fen = b.to_fen();
+
+    // The classical eval must be colour symmetric.
+    let hce = evaluate_white_pov(b);
+    let mirrored = evaluate_white_pov(&b.mirrored());
+    if hce != -mirrored {
+        failures.push(format!(
+            "{fen}: HCE asymmetric ({hce} vs mirrored {mirrored})"
+        ));
+    }
+
+    // The eval caches must not change the result.
+    let uncached = evaluate_with_params(b, &DEFAULT_PARAMS);
+    if hce != uncached {
+        failures.push(format!(
+            "{fen}: HCE {hce} with eval caches, {uncached} without"
+        ));
+    }
+
+    let before = snapshot(b);
+    let mut moves = MoveList::new();
+    b.generate_legal_moves(&mut moves);
+
+    for m in moves {
+        let undo = b.make_move(m);
+
+        // A position reached by make_move must match one built from scratch.
+        let fresh = Board::from_fen(&b.to_fen()).expect("to_fen output parses");
+        if fresh.zobrist != b.zobrist {
+            failures.push(format!(
+                "{fen} {}: zobrist differs from refresh",
+                format_uci(m)
+            ));
+        }
+        if fresh.piece_bb != b.piece_bb {
+            failures.push(format!(
+                "{fen} {}: bitboards differ from refresh",
+                format_uci(m)
+            ));
+        }
+        let (incremental, refreshed) = (nnue::evaluate(b), nnue::evaluate(&fresh));
+        if incremental != refreshed {
+            failures.push(format!(
+                "{fen} {}: NNUE {incremental} after make_move, {refreshed} after refresh",
+                format_uci(m)
+            ));
+        }
+
+        b.unmake_move(m, undo);
+        if snapshot(b) != before {
+            failures.push(format!(
+                "{fen} {}: make/unmake changed state",
+                format_uci(m)
+            ));
+        }
+    }
+
+    failures
+}
@@ -1,45 +1,185 @@
 use crate::board::Board;
-use crate::types::{BK_CASTLE, BQ_CASTLE, Color, NO_SQ, Piece, WK_CASTLE, WQ_CASTLE};
+use crate::square::Square;
+use crate::types::{BK_CASTLE, BQ_CASTLE, Color, NO_SQ, Piece, PieceKind, WK_CASTLE, WQ_CASTLE};
+use std::error::Error;
+use std::fmt;
 
-pub fn parse_fen(fen: &str) -> Result<Board, String> {
+/// Why a FEN was rejected. The first group of variants is checked by
+/// [`parse_fen`], the rest only by [`parse_fen_strict`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenError {
+    /// A required field is absent: placement, side, castling or en passant.
+    MissingField(&'static str),
+    /// A character that is neither a piece, a digit nor `/`.
+    BadPiece(char),
+    /// The side to move isn't `w` or `b`.
+    BadSide,
+    /// The castling field isn't `-` or a set of `KQkq`.
+    BadCastling,
+    /// The en passant field isn't `-` or a square.
+    BadEnPassant,
+    /// The placement has this many ranks instead of eight.
+    RankCount(usize),
+    /// This rank (1 to 8) doesn't describe exactly eight squares.
+    RankLength(u8),
+    /// A side has this many kings instead of one.
+    KingCount(Color, u32),
+    /// A side has more than sixteen pieces or eight pawns.
+    TooManyPieces(Color),
+    /// A pawn stands on the first or eighth rank.
+    PawnOnBackRank,
+    /// The side that just moved left its king in check.
+    OpponentInCheck,
+    /// A castling right without the king and rook on their starting squares.
+    CastlingRights,
+    /// The en passant square isn't behind a pawn that just made a double push.
+    EnPassant,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "missing {field}"),
+            FenError::BadPiece(c) => write!(f, "bad char '{c}' in placement"),
+            FenError::BadSide => write!(f, "bad side to move"),
+            FenError::BadCastling => write!(f, "bad castling"),
+            FenError::BadEnPassant => write!(f, "bad en passant square"),
+            FenError::RankCount(n) => write!(f, "{n} ranks instead of 8"),
+            FenError::RankLength(rank) => write!(f, "rank {rank} is not 8 squares long"),
+            FenError::KingCount(c, n) => write!(f, "{c:?} has {n} kings"),
+            FenError::TooManyPieces(c) => write!(f, "{c:?} has too many pieces"),
+            FenError::PawnOnBackRank => write!(f, "pawn on the first or eighth rank"),
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+            FenError::CastlingRights => write!(f, "castling rights without king and rook at home"),
+            FenError::EnPassant => write!(f, "impossible en passant square"),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+/// Parses `fen` like [`parse_fen`], then rejects positions no game can
+/// reach; see [`validate`].
+pub fn parse_fen_strict(fen: &str) -> Result<Board, FenError> {
+    let b = parse_fen(fen)?;
+    validate(&b)?;
+    Ok(b)
+}
+
+/// Checks that `b` could come up in a game: one king a side, at most
+/// sixteen pieces and eight pawns a side, no pawns on the back ranks, the
+/// side not to move not in check, castling rights backed by an unmoved king
+/// and rook, and an en passant square only behind a pawn that just pushed
+/// two squares.
+pub fn validate(b: &Board) -> Result<(), FenError> {
+    for color in [Color::White, Color::Black] {
+        let bb = |kind| b.piece_bb[Piece::from_kind(kind, color).index()];
+        let kings = bb(PieceKind::King).count_ones();
+        if kings != 1 {
+            return Err(FenError::KingCount(color, kings));
+        }
+        let own = match color {
+            Color::White => b.w_pieces,
+            Color::Black => b.b_pieces,
+        };
+        if own.count_ones() > 16 || bb(PieceKind::Pawn).count_ones() > 8 {
+            return Err(FenError::TooManyPieces(color));
+        }
+    }
+
+    const BACK_RANKS: u64 = 0xFF00_0000_0000_00FF;
+    let pawns = b.piece_bb[Piece::WP.index()] | b.piece_bb[Piece::BP.index()];
+    if pawns & BACK_RANKS != 0 {
+        return Err(FenError::PawnOnBackRank);
+    }
+
+    let mover = b.turn;
+    let their_king = b
+        .king_square(mover.other())
+        .ok_or(FenError::KingCount(mover.other(), 0))?;
+    if b.is_square_attacked(their_king, mover) {
+        return Err(FenError::OpponentInCheck);
+    }
+
+    let rights = [
+        (WK_CASTLE, Piece::WK, 4, Piece::WR, 7),
+        (WQ_CASTLE, Piece::WK, 4, Piece::WR, 0),
+        (BK_CASTLE, Piece::BK, 60, Piece::BR, 63),
+        (BQ_CASTLE, Piece::BK, 60, Piece::BR, 56),
+    ];
+    for (right, king, king_sq, rook, rook_sq) in rights {
+        if b.castle & right != 0 && (b.piece_on[king_sq] != king || b.piece_on[rook_sq] != rook) {
+            return Err(FenError::CastlingRights);
+        }
+    }
+
+    if let Some(ep) = b.en_passant() {
+        // The pawn that pushed stands one square beyond the en passant
+        // square, which it passed over from its starting square.
+        let (ep_rank, back, pawn) = match mover {
+            Color::White => (5, -1, Piece::BP),
+            Color::Black => (2, 1, Piece::WP),
+        };
+        let piece_at = |ranks| {
+            ep.offset(0, ranks)
+                .map_or(Piece::Empty, |sq| b.piece_on[sq.index()])
+        };
+        if ep.rank().index() != ep_rank
+            || piece_at(back) != pawn
+            || !b.piece_on[ep.index()].is_empty()
+            || !piece_at(-back).is_empty()
+        {
+            return Err(FenError::EnPassant);
+        }
+    }
+    Ok(())
+}
+
+/// Parses a FEN whose fields are well formed, without checking that the
+/// position makes sense; the move counters may be left out.
+pub fn parse_fen(fen: &str) -> Result<Board, FenError> {
     let mut b = Board::empty();
 
     let mut parts = fen.split_whitespace();
-    let placement = parts.next().ok_or("missing placement")?;
-    let side = parts.next().ok_or("missing side")?;
-    let castle = parts.next().ok_or("missing castling")?;
-    let ep = parts.next().ok_or("missing en-passant")?;
+    let placement = parts.next().ok_or(FenError::MissingField("placement"))?;
+    let side = parts.next().ok_or(FenError::MissingField("side"))?;
+    let castle = parts.next().ok_or(FenError::MissingField("castling"))?;
+    let ep = parts.next().ok_or(FenError::MissingField("en passant"))?;
     let halfmove = parts.next().unwrap_or("0");
     let fullmove = parts.next().unwrap_or("1");
 
-    let mut rank = 7;
-    let mut file = 0;
-    for ch in placement.chars() {
-        match ch {
-            '/' => {
-                rank -= 1;
-                file = 0;
-            }
-            '1'..='8' => {
-                file += (ch as u8 - b'0') as i32;
-            }
-            c if c.is_ascii_alphabetic() => {
-                if !(0..8).contains(&file) || !(0..8).contains(&rank) {
-                    return Err("bad board in FEN".into());
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::RankCount(ranks.len()));
+    }
+    for (i, rank_str) in ranks.iter().enumerate() {
+        let rank = 7 - i;
+        let mut file = 0;
+        for ch in rank_str.chars() {
+            match ch {
+                '1'..='8' => file += (ch as u8 - b'0') as usize,
+                c => {
+                    let piece = Piece::from(c);
+                    if piece.is_empty() {
+                        return Err(FenError::BadPiece(c));
+                    }
+                    if file >= 8 {
+                        return Err(FenError::RankLength(rank as u8 + 1));
+                    }
+                    b.place_piece(piece, Square::from_index(rank * 8 + file));
+                    file += 1;
                 }
-                let sq = (rank * 8 + file) as usize;
-                let piece = Piece::from(c);
-                b.place_piece(piece, sq);
-                file += 1;
             }
-            _ => return Err("bad char in placement".into()),
+        }
+        if file != 8 {
+            return Err(FenError::RankLength(rank as u8 + 1));
         }
     }
 
     b.turn = match side {
         "w" => Color::White,
         "b" => Color::Black,
-        _ => return Err("bad side".into()),
+        _ => return Err(FenError::BadSide),
     };
 
     b.castle = 0;
@@ -50,7 +190,7 @@ pub fn parse_fen(fen: &str) -> Result<Board, String> {
                 'Q' => b.castle |= WQ_CASTLE,
                 'k' => b.castle |= BK_CASTLE,
                 'q' => b.castle |= BQ_CASTLE,
-                _ => return Err("bad castling".into()),
+                _ => return Err(FenError::BadCastling),
             }
         }
     }
@@ -58,18 +198,11 @@ pub fn parse_fen(fen: &str) -> Result<Board, String> {
     if ep == "-" {
         b.en_passant_sq = NO_SQ;
     } else {
-        let bytes = ep.as_bytes();
-        if bytes.len() != 2 {
-            return Err("bad ep".into());
-        }
-        let f = (bytes[0] as char).to_ascii_lowercase() as u8 - b'a';
-        let r = (bytes[1] as char) as u8 - b'1';
-
-        if f > 7 || r > 7 {
-            return Err("bad ep coord".into());
-        }
-
-        b.en_passant_sq = (r as i32) * 8 + (f as i32);
+        let sq: Square = ep
+            .to_ascii_lowercase()
+            .parse()
+            .map_err(|_| FenError::BadEnPassant)?;
+        b.en_passant_sq = sq.index() as i32;
     }
 
     b.halfmove_clock = halfmove.parse().unwrap_or(0);
@@ -137,13 +270,9 @@ pub fn to_fen(b: &Board) -> String {
     }
 
     s.push(' ');
-    if b.en_passant_sq == NO_SQ {
-        s.push('-');
-    } else {
-        let f = (b.en_passant_sq % 8) as u8 + b'a';
-        let r = (b.en_passant_sq / 8) as u8 + b'1';
-        s.push(f as char);
-        s.push(r as char);
+    match b.en_passant() {
+        Some(ep) => s.push_str(&ep.to_string()),
+        None => s.push('-'),
     }
 
     s.push(' ');
@@ -152,3 +281,84 @@ pub fn to_fen(b: &Board) -> String {
     s.push_str(&b.fullmove_number.to_string());
     s
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::START_FEN;
+    use crate::uci_io::parse_uci_move;
+
+    fn round_trip(b: &Board) {
+        let fen = to_fen(b);
+        let parsed = parse_fen_strict(&fen).unwrap();
+        assert_eq!(to_fen(&parsed), fen);
+        assert_eq!(parsed.zobrist, b.zobrist, "{fen}");
+    }
+
+    fn play(fen: &str, moves: &[&str]) -> Board {
+        let mut b = parse_fen(fen).unwrap();
+        for &text in moves {
+            let m = parse_uci_move(&mut b, text).unwrap();
+            b.make_move(m);
+        }
+        b
+    }
+
+    #[test]
+    fn round_trips() {
+        for fen in [
+            START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 b - - 13 42",
+        ] {
+            let b = parse_fen(fen).unwrap();
+            assert_eq!(to_fen(&b), fen);
+            round_trip(&b);
+        }
+    }
+
+    #[test]
+    fn round_trips_after_null_move() {
+        let mut b = play(START_FEN, &["e2e4"]);
+        b.make_null_move();
+        assert_eq!(b.en_passant(), None);
+        round_trip(&b);
+    }
+
+    #[test]
+    fn round_trips_with_repetition_history() {
+        let b = play(START_FEN, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        assert_eq!(b.repetition_count(), 2);
+        assert_eq!(
+            to_fen(&b),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 4 3"
+        );
+        round_trip(&b);
+    }
+
+    #[test]
+    fn rejects_bad_positions() {
+        for (fen, err) in [
+            ("8/8/8/8/8/8/8 w - - 0 1", FenError::RankCount(7)),
+            (
+                "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::RankLength(7),
+            ),
+            (
+                "8/8/8/8/8/8/8/K6K w - - 0 1",
+                FenError::KingCount(Color::White, 2),
+            ),
+            (
+                "4k3/8/8/8/8/P7/PPPPPPPP/4K3 w - - 0 1",
+                FenError::TooManyPieces(Color::White),
+            ),
+            ("4k2P/8/8/8/8/8/8/4K3 w - - 0 1", FenError::PawnOnBackRank),
+            ("4k3/8/8/8/8/8/8/4RK2 w - - 0 1", FenError::OpponentInCheck),
+            ("4k3/8/8/8/8/8/8/4K3 w K - 0 1", FenError::CastlingRights),
+            ("4k3/8/8/8/8/8/8/4K3 w - e6 0 1", FenError::EnPassant),
+        ] {
+            assert_eq!(parse_fen_strict(fen).err(), Some(err), "{fen}");
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! C interface for embedding the engine in other programs. The declarations
+//! are mirrored in `include/chess.h`.
+
+use crate::engine::Engine;
+use crate::search::SearchLimits;
+use crate::types::Move;
+use crate::uci_io::format_uci;
+use std::ffi::{CStr, c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+const MAX_DEPTH: usize = 128;
+
+/// Runs the body of an exported function, returning `on_panic` instead of
+/// unwinding into the caller. Release builds abort on panic, so this only
+/// matters for library builds with `panic = "unwind"`.
+fn guard<T>(on_panic: T, body: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(on_panic)
+}
+
+/// Opaque handle owned by the caller between `engine_create` and
+/// `engine_destroy`.
+pub struct ChessEngine {
+    engine: Engine,
+    best_move: Option<Move>,
+}
+
+/// Creates an engine with a `hash_mb` megabyte hash table, set up at the
+/// start position. Returns null if the network cannot be loaded or the
+/// table allocated.
+#[unsafe(no_mangle)]
+pub extern "C" fn engine_create(hash_mb: u32) -> *mut ChessEngine {
+    guard(ptr::null_mut(), || match Engine::new(hash_mb as usize) {
+        Ok(engine) => Box::into_raw(Box::new(ChessEngine {
+            engine,
+            best_move: None,
+        })),
+        Err(_) => ptr::null_mut(),
+    })
+}
+
+/// Sets the position from a NUL-terminated FEN. Returns 0 on success and -1
+/// if the FEN is invalid or an argument is null.
+///
+/// # Safety
+/// `engine` must come from `engine_create` and `fen` must be null or a
+/// valid C string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn engine_set_fen(engine: *mut ChessEngine, fen: *const c_char) -> c_int {
+    guard(-1, || {
+        // SAFETY: guaranteed by the caller.
+        let (Some(e), false) = (unsafe { engine.as_mut() }, fen.is_null()) else {
+            return -1;
+        };
+        let Ok(fen) = unsafe { CStr::from_ptr(fen) }.to_str() else {
+            return -1;
+        };
+        match e.engine.set_position(fen, &[]) {
+            Ok(()) => {
+                e.best_move = None;
+                0
+            }
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Searches the current position for `movetime_ms` milliseconds, blocking
+/// until done. Returns the score in centipawns from the side to move's view
+/// (mate in n plies is `30000 - n`), or `INT_MIN` on error.
+///
+/// # Safety
+/// `engine` must come from `engine_create`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn engine_go_movetime(engine: *mut ChessEngine, movetime_ms: u64) -> c_int {
+    guard(c_int::MIN, || {
+        // SAFETY: guaranteed by the caller.
+        let Some(e) = (unsafe { engine.as_mut() }) else {
+            return c_int::MIN;
+        };
+        match e
+            .engine
+            .search(SearchLimits::movetime(movetime_ms, MAX_DEPTH), None)
+        {
+            Ok(result) => {
+                e.best_move = result.best_move;
+                result.score
+            }
+            Err(_) => c_int::MIN,
+        }
+    })
+}
+
+/// Writes the best move of the last search in UCI notation (`e2e4`,
+/// `e7e8q`) as a NUL-terminated string into `buf` of `len` bytes. Returns
+/// the length without the NUL, 0 if there is no move (mate, stalemate or no
+/// search yet), or -1 if `buf` is too small.
+///
+/// # Safety
+/// `engine` must come from `engine_create` and `buf` must point to `len`
+/// writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn engine_best_move(
+    engine: *const ChessEngine,
+    buf: *mut c_char,
+    len: usize,
+) -> c_int {
+    guard(-1, || {
+        // SAFETY: guaranteed by the caller.
+        let Some(e) = (unsafe { engine.as_ref() }) else {
+            return -1;
+        };
+        let text = e.best_move.map(format_uci).unwrap_or_default();
+        if buf.is_null() || text.len() >= len {
+            return -1;
+        }
+        // SAFETY: `text.len() + 1 <= len` bytes fit in `buf`.
+        unsafe {
+            ptr::copy_nonoverlapping(text.as_ptr().cast(), buf, text.len());
+            *buf.add(text.len()) = 0;
+        }
+        text.len() as c_int
+    })
+}
+
+/// Frees an engine. Null is ignored.
+///
+/// # Safety
+/// `engine` must come from `engine_create` and not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn engine_destroy(engine: *mut ChessEngine) {
+    guard((), || {
+        if !engine.is_null() {
+            // SAFETY: guaranteed by the caller.
+            drop(unsafe { Box::from_raw(engine) });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn create_fails_cleanly_without_memory() {
+        assert!(engine_create(u32::MAX).is_null());
+    }
+
+    #[test]
+    fn plays_a_move() {
+        let e = engine_create(1);
+        assert!(!e.is_null());
+        let mut buf = [0 as c_char; 8];
+        // SAFETY: `e` is live and `buf` holds 8 bytes.
+        unsafe {
+            assert_ne!(engine_go_movetime(e, 0), c_int::MIN);
+            assert!(engine_best_move(e, buf.as_mut_ptr(), buf.len()) >= 4);
+            engine_destroy(e);
+        }
+    }
+}
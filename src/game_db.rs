@@ -0,0 +1,278 @@
+use crate::board::Board;
+use crate::types::{Color, START_FEN};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Plies of the move list that name a game's opening in the summary.
+const OPENING_PLIES: usize = 6;
+
+/// One finished game, stored as a line of JSON.
+#[derive(Clone, Debug)]
+pub struct GameRecord {
+    /// Seconds since the Unix epoch when the game was recorded.
+    pub timestamp: u64,
+    /// `uci` or `self-play`.
+    pub source: String,
+    /// `white`, `black`, `both` for self-play, or `none` if the engine
+    /// never searched.
+    pub engine_side: String,
+    pub opponent: Option<String>,
+    /// `1-0`, `0-1`, `1/2-1/2`, or `*` when the game was left unfinished.
+    pub result: String,
+    pub time_control: String,
+    pub start_fen: String,
+    /// Moves in UCI notation.
+    pub moves: Vec<String>,
+    /// The engine's score in centipawns for each ply it searched, from its
+    /// own point of view; `None` for the opponent's moves.
+    pub evals: Vec<Option<i32>>,
+}
+
+impl GameRecord {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "timestamp": self.timestamp,
+            "source": self.source,
+            "engine_side": self.engine_side,
+            "opponent": self.opponent,
+            "result": self.result,
+            "time_control": self.time_control,
+            "start_fen": self.start_fen,
+            "moves": self.moves,
+            "evals": self.evals,
+        })
+    }
+
+    /// The record [`to_json`](Self::to_json) wrote, or `None` if a field
+    /// is missing or of the wrong type.
+    pub fn from_json(v: &serde_json::Value) -> Option<Self> {
+        let text = |key: &str| v[key].as_str().map(str::to_string);
+        Some(Self {
+            timestamp: v["timestamp"].as_u64()?,
+            source: text("source")?,
+            engine_side: text("engine_side")?,
+            opponent: text("opponent"),
+            result: text("result")?,
+            time_control: text("time_control")?,
+            start_fen: text("start_fen")?,
+            moves: v["moves"]
+                .as_array()?
+                .iter()
+                .map(|m| m.as_str().map(str::to_string))
+                .collect::<Option<_>>()?,
+            evals: v["evals"]
+                .as_array()?
+                .iter()
+                .map(|e| match e {
+                    serde_json::Value::Null => Some(None),
+                    e => e.as_i64().and_then(|cp| i32::try_from(cp).ok()).map(Some),
+                })
+                .collect::<Option<_>>()?,
+        })
+    }
+
+    /// The first few moves when the game started from the initial position,
+    /// otherwise the start FEN.
+    pub fn opening(&self) -> String {
+        if self.start_fen == START_FEN {
+            let n = self.moves.len().min(OPENING_PLIES);
+            self.moves[..n].join(" ")
+        } else {
+            format!("fen {}", self.start_fen)
+        }
+    }
+
+    /// The engine's score (1, ½ or 0); self-play games count from White's side.
+    pub fn engine_score(&self) -> Option<f64> {
+        if self.engine_side == "none" {
+            return None;
+        }
+        let white = match self.result.as_str() {
+            "1-0" => 1.0,
+            "1/2-1/2" => 0.5,
+            "0-1" => 0.0,
+            _ => return None,
+        };
+        Some(if self.engine_side == "black" {
+            1.0 - white
+        } else {
+            white
+        })
+    }
+}
+
+/// Collects a game as it is played and turns it into a [`GameRecord`].
+pub struct GameRecorder {
+    source: &'static str,
+    start_fen: String,
+    moves: Vec<String>,
+    evals: BTreeMap<usize, i32>,
+    engine_sides: [bool; 2],
+    time_control: String,
+    opponent: Option<String>,
+}
+
+impl GameRecorder {
+    pub fn new(source: &'static str) -> Self {
+        Self {
+            source,
+            start_fen: START_FEN.to_string(),
+            moves: Vec::new(),
+            evals: BTreeMap::new(),
+            engine_sides: [false; 2],
+            time_control: String::new(),
+            opponent: None,
+        }
+    }
+
+    pub fn ply(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Replaces the move list; UCI resends the whole game each move.
+    pub fn set_position(&mut self, start_fen: String, moves: Vec<String>) {
+        self.start_fen = start_fen;
+        self.moves = moves;
+    }
+
+    pub fn push_move(&mut self, uci: String) {
+        self.moves.push(uci);
+    }
+
+    /// Notes that the engine searched the position at `ply` as `side`.
+    pub fn record_search(&mut self, ply: usize, side: Color, score: Option<i32>) {
+        self.engine_sides[side as usize] = true;
+        if let Some(score) = score {
+            self.evals.insert(ply, score);
+        }
+    }
+
+    /// The first time control seen is kept for the whole game.
+    pub fn set_time_control(&mut self, tc: String) {
+        if self.time_control.is_empty() {
+            self.time_control = tc;
+        }
+    }
+
+    pub fn set_opponent(&mut self, opponent: Option<String>) {
+        self.opponent = opponent;
+    }
+
+    /// Ends the game, taking the result from `final_board` unless one is
+    /// given, and resets the recorder. Returns `None` if no moves were made.
+    pub fn finish(&mut self, final_board: &Board, result: Option<&str>) -> Option<GameRecord> {
+        let recorder = std::mem::replace(self, Self::new(self.source));
+        if recorder.moves.is_empty() {
+            return None;
+        }
+
+        let result = result
+            .map(str::to_string)
+            .unwrap_or_else(|| final_board.game_result().pgn().to_string());
+        let engine_side = match recorder.engine_sides {
+            [true, true] => "both",
+            [true, false] => "white",
+            [false, true] => "black",
+            [false, false] => "none",
+        };
+        let evals = (0..recorder.moves.len())
+            .map(|ply| recorder.evals.get(&ply).copied())
+            .collect();
+
+        Some(GameRecord {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            source: recorder.source.to_string(),
+            engine_side: engine_side.to_string(),
+            opponent: recorder.opponent,
+            result,
+            time_control: recorder.time_control,
+            start_fen: recorder.start_fen,
+            moves: recorder.moves,
+            evals,
+        })
+    }
+}
+
+/// Appends `record` as one JSON line.
+pub fn append(path: &Path, record: &GameRecord) -> io::Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", record.to_json())
+}
+
+/// Reads every record; malformed lines are skipped.
+pub fn load(path: &Path) -> io::Result<Vec<GameRecord>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|l| serde_json::from_str(l).ok())
+        .filter_map(|v| GameRecord::from_json(&v))
+        .collect())
+}
+
+#[derive(Default)]
+struct Row {
+    games: u32,
+    wins: u32,
+    draws: u32,
+    losses: u32,
+}
+
+fn table(out: &mut String, title: &str, rows: &BTreeMap<String, Row>) {
+    let _ = writeln!(
+        out,
+        "{:<40} {:>6} {:>5} {:>5} {:>5} {:>7}",
+        title, "Games", "W", "D", "L", "Score"
+    );
+    for (key, r) in rows {
+        let decided = r.wins + r.draws + r.losses;
+        let score = if decided == 0 {
+            "-".to_string()
+        } else {
+            let pct = (r.wins as f64 + r.draws as f64 / 2.0) / decided as f64 * 100.0;
+            format!("{pct:.1}%")
+        };
+        let _ = writeln!(
+            out,
+            "{:<40} {:>6} {:>5} {:>5} {:>5} {:>7}",
+            key, r.games, r.wins, r.draws, r.losses, score
+        );
+    }
+}
+
+/// Engine performance grouped by opening and by time control. Unfinished
+/// games count towards `Games` only.
+pub fn summarize(records: &[GameRecord]) -> String {
+    let mut by_opening: BTreeMap<String, Row> = BTreeMap::new();
+    let mut by_tc: BTreeMap<String, Row> = BTreeMap::new();
+
+    for r in records {
+        let tc = if r.time_control.is_empty() {
+            "-".to_string()
+        } else {
+            r.time_control.clone()
+        };
+        for row in [
+            by_opening.entry(r.opening()).or_default(),
+            by_tc.entry(tc).or_default(),
+        ] {
+            row.games += 1;
+            match r.engine_score() {
+                Some(s) if s > 0.5 => row.wins += 1,
+                Some(s) if s < 0.5 => row.losses += 1,
+                Some(_) => row.draws += 1,
+                None => {}
+            }
+        }
+    }
+
+    let mut out = format!("{} games\n\n", records.len());
+    table(&mut out, "Opening", &by_opening);
+    out.push('\n');
+    table(&mut out, "Time control", &by_tc);
+    out
+}
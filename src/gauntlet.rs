@@ -0,0 +1,412 @@
+use crate::board::Board;
+use crate::engine_process::{EngineSpec, UciEngine};
+use crate::error::ChessError;
+use crate::opening_book::OpeningBook;
+use crate::sprt::{Sprt, SprtResult};
+use crate::types::{Color, START_FEN};
+use crate::uci_io::{format_uci, parse_uci_move};
+use std::fmt::Write as _;
+use std::io;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Extra time an engine gets before a silent move counts as a loss.
+const TIME_GRACE: Duration = Duration::from_millis(1000);
+
+#[derive(Clone, Copy, Debug)]
+pub enum TimeControl {
+    MoveTime(u64),
+    /// Base time and increment per move, in milliseconds.
+    Clock {
+        base_ms: u64,
+        inc_ms: u64,
+    },
+}
+
+impl TimeControl {
+    /// Parses `<seconds>+<increment>`, e.g. `10+0.1`.
+    pub fn parse_clock(s: &str) -> Result<Self, String> {
+        let (base, inc) = s.split_once('+').unwrap_or((s, "0"));
+        let secs = |v: &str| {
+            v.trim()
+                .parse::<f64>()
+                .map(|x| (x * 1000.0) as u64)
+                .map_err(|_| format!("time control: bad number '{v}' in '{s}'"))
+        };
+        Ok(TimeControl::Clock {
+            base_ms: secs(base)?,
+            inc_ms: secs(inc)?,
+        })
+    }
+}
+
+/// Wins, draws and losses from one player's point of view.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tally {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Tally {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    pub fn add(&mut self, score: f64) {
+        match score {
+            s if s > 0.5 => self.wins += 1,
+            s if s < 0.5 => self.losses += 1,
+            _ => self.draws += 1,
+        }
+    }
+
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games().max(1) as f64
+    }
+
+    /// The same games from the opponent's point of view.
+    pub fn reversed(&self) -> Tally {
+        Tally {
+            wins: self.losses,
+            draws: self.draws,
+            losses: self.wins,
+        }
+    }
+
+    /// The tallies of several pairings added together.
+    pub fn combined(tallies: &[Tally]) -> Tally {
+        tallies.iter().fold(Tally::default(), |acc, t| Tally {
+            wins: acc.wins + t.wins,
+            draws: acc.draws + t.draws,
+            losses: acc.losses + t.losses,
+        })
+    }
+
+    /// Elo difference and the half-width of its 95% confidence interval.
+    pub fn elo(&self) -> (f64, f64) {
+        let n = self.games().max(1) as f64;
+        let p = self.score();
+        let var = (self.wins as f64 * (1.0 - p).powi(2)
+            + self.draws as f64 * (0.5 - p).powi(2)
+            + self.losses as f64 * p.powi(2))
+            / n;
+        let margin = 1.96 * (var / n).sqrt();
+        let (lo, hi) = (elo_from_score(p - margin), elo_from_score(p + margin));
+        (elo_from_score(p), (hi - lo) / 2.0)
+    }
+}
+
+/// Logistic Elo difference for an expected score, clamped away from 0 and 1.
+pub fn elo_from_score(p: f64) -> f64 {
+    let p = p.clamp(0.001, 0.999);
+    400.0 * (p / (1.0 - p)).log10()
+}
+
+/// Reads one start position per line (FEN or EPD); blank lines and `#`
+/// comments are skipped.
+pub fn load_openings(text: &str) -> Result<Vec<String>, ChessError> {
+    text.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let full = fields[..fields.len().min(6)].join(" ");
+            Board::from_fen(&full)
+                .or_else(|_| Board::from_fen(&fields[..fields.len().min(4)].join(" ")))
+                .map(|b| b.to_fen())
+        })
+        .collect()
+}
+
+/// Picks `count` openings by playing `plies` weighted book moves from the
+/// start position; a line ends early when it leaves the book.
+pub fn book_openings(book: &mut OpeningBook, count: usize, plies: usize) -> Vec<String> {
+    (0..count)
+        .map(|_| {
+            let mut b = Board::from_fen(START_FEN).expect("start FEN is valid");
+            for _ in 0..plies {
+                match book.pick_move(&b, 100).map(|m| b.try_make_move(m)) {
+                    Some(Ok(_)) => {}
+                    _ => break,
+                }
+            }
+            b.to_fen()
+        })
+        .collect()
+}
+
+/// Ends games early on the engines' own evaluations. A rule is off while
+/// its score is `None`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Adjudication {
+    /// A side loses once its score has been at or below minus this many
+    /// centipawns for `resign_moves` of its moves in a row.
+    pub resign_score: Option<i32>,
+    pub resign_moves: u32,
+    /// The game is drawn once both sides have scored within this many
+    /// centipawns of zero for `draw_moves` moves each in a row, from move
+    /// `draw_after` on.
+    pub draw_score: Option<i32>,
+    pub draw_moves: u32,
+    pub draw_after: u32,
+}
+
+pub struct GauntletConfig {
+    pub candidate: EngineSpec,
+    pub baselines: Vec<EngineSpec>,
+    /// Each opening is played twice against every baseline, once per colour.
+    pub openings: Vec<String>,
+    pub rounds: usize,
+    pub tc: TimeControl,
+    pub concurrency: usize,
+    /// Games still running after this many plies are drawn.
+    pub max_plies: usize,
+    pub adjudication: Adjudication,
+    /// Stops scheduling games once the candidate's combined result decides
+    /// the test.
+    pub sprt: Option<Sprt>,
+}
+
+/// Plays one game and returns White's score and how it ended.
+pub fn play_game(
+    white: &mut UciEngine,
+    black: &mut UciEngine,
+    fen: &str,
+    tc: TimeControl,
+    max_plies: usize,
+    adj: Adjudication,
+) -> io::Result<(f64, String)> {
+    let mut b = Board::from_fen(fen).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    white.new_game()?;
+    black.new_game()?;
+
+    let mut moves = Vec::new();
+    let mut clock = match tc {
+        TimeControl::Clock { base_ms, .. } => [base_ms as i64; 2],
+        TimeControl::MoveTime(_) => [0; 2],
+    };
+    let loss_for = |c: Color| if c == Color::White { 0.0 } else { 1.0 };
+    let mut resign_streak = [0; 2];
+    let mut draw_streak = 0;
+
+    for _ in 0..max_plies {
+        let result = b.game_result();
+        if let Some(score) = result.white_score() {
+            return Ok((score, result.reason().into()));
+        }
+
+        let side = b.turn as usize;
+        let (go, timeout) = match tc {
+            TimeControl::MoveTime(ms) => (
+                format!("go movetime {ms}"),
+                Duration::from_millis(ms) + TIME_GRACE,
+            ),
+            TimeControl::Clock { inc_ms, .. } => (
+                format!(
+                    "go wtime {} btime {} winc {inc_ms} binc {inc_ms}",
+                    clock[0].max(1),
+                    clock[1].max(1)
+                ),
+                Duration::from_millis(clock[side].max(0) as u64) + TIME_GRACE,
+            ),
+        };
+        let position = if moves.is_empty() {
+            format!("position fen {fen}")
+        } else {
+            format!("position fen {fen} moves {}", moves.join(" "))
+        };
+
+        let engine = if b.turn == Color::White {
+            &mut *white
+        } else {
+            &mut *black
+        };
+        let start = Instant::now();
+        let reply = engine.search(&position, &go, timeout);
+        let name = engine.name.clone();
+        let (mv, score) = match reply {
+            Ok(reply) => reply,
+            Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                return Ok((loss_for(b.turn), format!("{name} loses on time")));
+            }
+            Err(e) => return Ok((loss_for(b.turn), format!("{name} failed: {e}"))),
+        };
+
+        if let TimeControl::Clock { inc_ms, .. } = tc {
+            clock[side] -= start.elapsed().as_millis() as i64;
+            if clock[side] < 0 {
+                return Ok((loss_for(b.turn), format!("{name} loses on time")));
+            }
+            clock[side] += inc_ms as i64;
+        }
+
+        let Some(m) = parse_uci_move(&mut b, &mv) else {
+            return Ok((loss_for(b.turn), format!("{name} played illegal move {mv}")));
+        };
+        moves.push(format_uci(m));
+
+        match (score, adj.resign_score) {
+            (Some(s), Some(limit)) if s <= -limit => resign_streak[side] += 1,
+            _ => resign_streak[side] = 0,
+        }
+        if resign_streak[side] >= adj.resign_moves.max(1) {
+            return Ok((loss_for(b.turn), format!("{name} resigns")));
+        }
+        match (score, adj.draw_score) {
+            (Some(s), Some(limit)) if s.abs() <= limit => draw_streak += 1,
+            _ => draw_streak = 0,
+        }
+        if draw_streak >= 2 * adj.draw_moves.max(1) && b.fullmove_number >= adj.draw_after as i32 {
+            return Ok((0.5, "adjudicated draw".into()));
+        }
+        b.make_move(m);
+    }
+    Ok((0.5, "move limit".into()))
+}
+
+/// Plays every scheduled game on `config.concurrency` workers. Returns the
+/// candidate's tally against each baseline, in `config.baselines` order.
+pub fn run(config: &GauntletConfig) -> Vec<Tally> {
+    let openings: Vec<String> = if config.openings.is_empty() {
+        vec![START_FEN.to_string()]
+    } else {
+        config.openings.clone()
+    };
+    // (baseline, opening, candidate plays white)
+    let mut jobs: Vec<(usize, usize, bool)> = Vec::new();
+    for opp in 0..config.baselines.len() {
+        for r in 0..config.rounds {
+            jobs.push((opp, r % openings.len(), true));
+            jobs.push((opp, r % openings.len(), false));
+        }
+    }
+
+    let next = AtomicUsize::new(0);
+    let decided = AtomicBool::new(false);
+    let tallies = Mutex::new(vec![Tally::default(); config.baselines.len()]);
+
+    thread::scope(|scope| {
+        for _ in 0..config.concurrency.max(1) {
+            scope.spawn(|| {
+                let mut candidate: Option<UciEngine> = None;
+                let mut baselines: Vec<Option<UciEngine>> =
+                    config.baselines.iter().map(|_| None).collect();
+
+                while !decided.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(&(opp, opening, cand_white)) = jobs.get(i) else {
+                        break;
+                    };
+                    let fen = &openings[opening];
+
+                    // (Re)start engines lazily; a crashed engine is replaced.
+                    let cand = match candidate.take() {
+                        Some(e) => Ok(e),
+                        None => UciEngine::start(&config.candidate),
+                    };
+                    let base = match baselines[opp].take() {
+                        Some(e) => Ok(e),
+                        None => UciEngine::start(&config.baselines[opp]),
+                    };
+                    let (mut cand, mut base) = match (cand, base) {
+                        (Ok(c), Ok(b)) => (c, b),
+                        (Err(e), _) | (_, Err(e)) => {
+                            eprintln!("game {}: could not start engine: {e}", i + 1);
+                            continue;
+                        }
+                    };
+
+                    let (tc, max_plies, adj) = (config.tc, config.max_plies, config.adjudication);
+                    let played = if cand_white {
+                        play_game(&mut cand, &mut base, fen, tc, max_plies, adj)
+                    } else {
+                        play_game(&mut base, &mut cand, fen, tc, max_plies, adj)
+                            .map(|(s, why)| (1.0 - s, why))
+                    };
+                    match played {
+                        Ok((score, why)) => {
+                            let mut t = tallies.lock().unwrap_or_else(|e| e.into_inner());
+                            t[opp].add(score);
+                            println!(
+                                "game {}/{}: {} vs {} ({}): {} ({why})",
+                                i + 1,
+                                jobs.len(),
+                                config.candidate.name,
+                                config.baselines[opp].name,
+                                if cand_white { "white" } else { "black" },
+                                match score {
+                                    s if s > 0.5 => "1-0",
+                                    s if s < 0.5 => "0-1",
+                                    _ => "1/2-1/2",
+                                },
+                            );
+                            if config.sprt.is_some_and(|sprt| {
+                                sprt.status(&Tally::combined(&t)) != SprtResult::Continue
+                            }) {
+                                decided.store(true, Ordering::Relaxed);
+                            }
+                            candidate = Some(cand);
+                            baselines[opp] = Some(base);
+                        }
+                        Err(e) => eprintln!("game {}: aborted: {e}", i + 1),
+                    }
+                }
+            });
+        }
+    });
+
+    tallies.into_inner().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Per-baseline results and the combined rating of the candidate against
+/// the whole pool.
+pub fn report(config: &GauntletConfig, tallies: &[Tally]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<20} {:>6} {:>5} {:>5} {:>5} {:>7} {:>14}",
+        "Opponent", "Games", "W", "D", "L", "Score", "Elo"
+    );
+    let total = Tally::combined(tallies);
+    let rows = config
+        .baselines
+        .iter()
+        .map(|s| s.name.as_str())
+        .zip(tallies);
+    for (name, t) in rows.chain(std::iter::once(("Total", &total))) {
+        let (elo, margin) = t.elo();
+        let _ = writeln!(
+            out,
+            "{:<20} {:>6} {:>5} {:>5} {:>5} {:>6.1}% {:>+7.1} ±{:.1}",
+            name,
+            t.games(),
+            t.wins,
+            t.draws,
+            t.losses,
+            t.score() * 100.0,
+            elo,
+            margin
+        );
+    }
+    if let Some(sprt) = &config.sprt {
+        let (lower, upper) = sprt.bounds();
+        let verdict = match sprt.status(&total) {
+            SprtResult::AcceptH1 => "H1 accepted",
+            SprtResult::AcceptH0 => "H0 accepted",
+            SprtResult::Continue => "inconclusive",
+        };
+        let _ = writeln!(
+            out,
+            "SPRT [{}, {}]: LLR {:.2} ({:.2}, {:.2}), {verdict}",
+            sprt.elo0,
+            sprt.elo1,
+            sprt.llr(&total),
+            lower,
+            upper
+        );
+    }
+    out
+}
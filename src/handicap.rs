@@ -0,0 +1,66 @@
+use crate::board::Board;
+use crate::square::Square;
+use crate::types::{BK_CASTLE, BQ_CASTLE, Color, Piece, WK_CASTLE, WQ_CASTLE};
+
+// Files that each odds letter removes a piece from, in the order repeated
+// letters use them: the queenside piece goes first.
+const ODDS_SQUARES: [(char, &[usize]); 5] = [
+    ('q', &[3]),
+    ('r', &[0, 7]),
+    ('n', &[1, 6]),
+    ('b', &[2, 5]),
+    ('p', &[5, 4, 3, 2, 6, 1, 7, 0]),
+];
+
+/// Removes material for a handicap game. `spec` is `<white|black>:<pieces>`,
+/// e.g. `black:n` for knight odds or `white:qr` for queen-and-rook odds; `p`
+/// removes the f-pawn first. Castling rights through a removed rook are lost.
+pub fn apply_odds(b: &Board, spec: &str) -> Result<Board, String> {
+    let (side, pieces) = spec
+        .split_once(':')
+        .ok_or_else(|| format!("odds: expected '<white|black>:<pieces>', got '{spec}'"))?;
+    let side = match side.to_ascii_lowercase().as_str() {
+        "white" | "w" => Color::White,
+        "black" | "b" => Color::Black,
+        other => return Err(format!("odds: unknown side '{other}'")),
+    };
+
+    let mut b = b.clone();
+    let mut used = [0usize; ODDS_SQUARES.len()];
+    for c in pieces.chars().map(|c| c.to_ascii_lowercase()) {
+        let i = ODDS_SQUARES
+            .iter()
+            .position(|&(letter, _)| letter == c)
+            .ok_or_else(|| format!("odds: unknown piece '{c}'"))?;
+        let Some(&file) = ODDS_SQUARES[i].1.get(used[i]) else {
+            return Err(format!("odds: no more '{c}' to remove"));
+        };
+        used[i] += 1;
+
+        let rank = match (side, c == 'p') {
+            (Color::White, false) => 0,
+            (Color::White, true) => 1,
+            (Color::Black, true) => 6,
+            (Color::Black, false) => 7,
+        };
+        let sq = Square::from_index(rank * 8 + file);
+        if b.piece_on[sq.index()].color() != Some(side) {
+            return Err(format!("odds: no {side:?} piece on {sq}"));
+        }
+        b.place_piece(Piece::Empty, sq);
+
+        b.castle &= !match (side, sq) {
+            (Color::White, Square::A1) => WQ_CASTLE,
+            (Color::White, Square::H1) => WK_CASTLE,
+            (Color::Black, Square::A8) => BQ_CASTLE,
+            (Color::Black, Square::H8) => BK_CASTLE,
+            _ => 0,
+        };
+    }
+
+    b.rebuild_derived();
+    b.recompute_zobrist();
+    b.history.clear();
+    b.history.push(b.zobrist);
+    Ok(b)
+}
@@ -1,18 +1,49 @@
-#![feature(portable_simd)]
+#![cfg_attr(feature = "portable_simd", feature(portable_simd))]
 
+pub mod attacks;
+pub mod bench;
+pub mod bitbase;
 pub mod board;
+pub mod book_builder;
+pub mod datagen;
+pub mod display;
+pub mod engine;
+pub mod engine_process;
+pub mod error;
+pub mod eval;
+pub mod eval_cache;
+pub mod eval_check;
 pub mod fen;
-pub mod magics;
+pub mod ffi;
+pub mod game_db;
+pub mod gauntlet;
+pub mod handicap;
+pub mod movegen;
+pub mod movelist;
+pub mod movepick;
 pub mod nnue;
+pub mod nnue_simd;
 pub mod opening_book;
-pub mod pawn_hash;
+pub mod opponent;
+pub mod options;
+pub mod output;
 pub mod perft;
+pub mod pgn;
 pub mod polyglot_zobrist;
 pub mod pst;
+pub mod san;
+pub mod score;
 pub mod search;
 pub mod see;
+pub mod simd;
+pub mod skill;
+pub mod sprt;
+pub mod square;
+pub mod test_suite;
 pub mod time;
+pub mod tournament;
 pub mod tt;
+pub mod tuner;
 pub mod types;
 pub mod uci;
 pub mod uci_io;
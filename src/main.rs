@@ -1,18 +1,46 @@
+use chess::bench::{BENCH_DEPTH, micro_bench, print_bench};
 use chess::board::Board;
+use chess::book_builder::BookBuilder;
+use chess::datagen::{self, DatagenConfig};
+use chess::display;
+use chess::engine_process::EngineSpec;
+use chess::error::ChessError;
+use chess::eval;
+use chess::eval_check::check_position;
+use chess::game_db::{self, GameRecorder};
+use chess::gauntlet::{self, Adjudication, GauntletConfig, TimeControl};
+use chess::handicap;
+use chess::movelist::MoveList;
 use chess::nnue;
-use chess::perft::{divide, perft};
-use chess::search::{best_move_timed, get_pv_from_tt};
+use chess::nnue_simd;
+use chess::opening_book::{OpeningBook, write_book};
+use chess::options::Options;
+use chess::output;
+use chess::perft::{self, Perft, print_divide};
+use chess::pgn;
+use chess::san;
+use chess::search::{
+    InfoCallback, SearchConfig, SearchInfo, SearchLimits, ThreadPool, UciInfoPrinter,
+    best_move_timed, get_pv_from_tt, search_with_limits,
+};
+use chess::sprt::Sprt;
+use chess::test_suite;
+use chess::time::HARD_LIMIT_FACTOR;
+use chess::tournament::{self, Adjudicator, Format, Player, TournamentConfig};
 use chess::tt::SharedTransTable;
-use chess::types::{Color, Move, Piece, PieceKind, START_FEN};
+use chess::tuner;
+use chess::types::{Color, GameResult, Move, START_FEN, ZKey};
 use chess::uci;
-use chess::uci_io::{format_uci, parse_uci_move};
+use chess::uci_io::{self, format_uci, parse_uci_move};
 use clap::{Parser, Subcommand};
 use std::io::{self, Write};
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::thread;
 
-const SEARCH_THREAD_STACK: usize = 32 * 1024 * 1024; // 32 MiB
+/// Depth cap for PlayCli analysis, which otherwise runs until stopped.
+const ANALYSIS_DEPTH: usize = 128;
 
 #[derive(Parser)]
 #[command(
@@ -21,6 +49,16 @@ const SEARCH_THREAD_STACK: usize = 32 * 1024 * 1024; // 32 MiB
     about = "Chess engine with perft/uci/play modes"
 )]
 struct Cli {
+    /// Read engine options from a file of `Name = Value` lines.
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Set an engine option, e.g. `--option Hash=512`. May be repeated.
+    #[arg(long = "option", value_name = "NAME=VALUE", global = true)]
+    options: Vec<String>,
+    /// Print search info, bestmove, perft and eval results as JSON lines;
+    /// the same as `--option OutputFormat=json`.
+    #[arg(long, global = true)]
+    json: bool,
     #[command(subcommand)]
     cmd: Option<Cmd>,
 }
@@ -33,6 +71,16 @@ enum Cmd {
         fen: Option<String>,
         #[arg(long)]
         divide: bool,
+        /// Split the root moves between this many threads.
+        #[arg(long, default_value_t = 1)]
+        threads: usize,
+        /// Megabytes of subtree cache; 0 disables it.
+        #[arg(long, default_value_t = 0)]
+        hash: usize,
+        /// Check the standard perft positions up to `depth` against their
+        /// known counts instead.
+        #[arg(long, conflicts_with_all = ["fen", "divide"])]
+        verify: bool,
     },
     PlayCli {
         #[arg(long)]
@@ -55,35 +103,290 @@ enum Cmd {
         fen: Option<String>,
         #[arg(long)]
         threads: Option<usize>,
+        /// Per-side overrides of --time, --depth and the node limit, for
+        /// time-odds games.
+        #[arg(long)]
+        white_time: Option<u64>,
+        #[arg(long)]
+        black_time: Option<u64>,
+        #[arg(long)]
+        white_depth: Option<usize>,
+        #[arg(long)]
+        black_depth: Option<usize>,
+        #[arg(long)]
+        white_nodes: Option<u64>,
+        #[arg(long)]
+        black_nodes: Option<u64>,
+        /// Material odds, e.g. `black:n` or `white:qr`.
+        #[arg(long)]
+        odds: Option<String>,
+        /// Adjudicate a win once both sides' scores have favoured the same
+        /// side by at least this many centipawns for --resign-moves moves
+        /// each.
+        #[arg(long)]
+        resign_score: Option<i32>,
+        #[arg(long, default_value_t = 3)]
+        resign_moves: u32,
+        /// Adjudicate a draw once both scores stay within this many
+        /// centipawns of zero for --draw-moves moves each, from move
+        /// --draw-after on.
+        #[arg(long)]
+        draw_score: Option<i32>,
+        #[arg(long, default_value_t = 8)]
+        draw_moves: u32,
+        #[arg(long, default_value_t = 40)]
+        draw_after: u32,
+        /// Play a tournament between these configurations instead, e.g.
+        /// `name=a,threads=2,hash=64,depth=12,eval=classical`. May be
+        /// repeated; --rounds is then the games per pairing.
+        #[arg(long = "player", conflicts_with_all = [
+            "white_time", "black_time", "white_depth", "black_depth", "white_nodes", "black_nodes",
+        ])]
+        players: Vec<String>,
+        /// Pair the first player with each of the others instead of
+        /// playing a round robin.
+        #[arg(long, requires = "players")]
+        gauntlet: bool,
+        /// Tournament games still running after this many plies are drawn.
+        #[arg(long, default_value_t = 400)]
+        max_plies: usize,
+    },
+    Eval {
+        #[arg(long)]
+        fen: Option<String>,
+    },
+    CheckEval {
+        /// EPD or FEN file, one position per line.
+        #[arg(long)]
+        positions: PathBuf,
+    },
+    Datagen {
+        /// Output file of `<fen> | <score> | <result>` lines.
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long, default_value_t = 100)]
+        games: usize,
+        /// Node limit per move.
+        #[arg(long, default_value_t = 5000)]
+        nodes: u64,
+        #[arg(long, default_value_t = 64)]
+        depth: usize,
+        /// Random moves played at the start of each game.
+        #[arg(long, default_value_t = 8)]
+        random_plies: usize,
+        #[arg(long)]
+        fen: Option<String>,
+        /// Concurrent games; defaults to the Threads option.
+        #[arg(long)]
+        threads: Option<usize>,
+        #[arg(long, default_value_t = 16)]
+        hash: usize,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    Gauntlet {
+        /// The configuration under test, e.g. `name=dev,opt.IterationSkip=80`
+        /// or `name=other,cmd=/path/to/engine`.
+        #[arg(long)]
+        candidate: String,
+        /// A fixed opponent, in the same format. May be repeated.
+        #[arg(long = "baseline", required = true)]
+        baselines: Vec<String>,
+        /// FEN/EPD start positions shared by all pairings.
+        #[arg(long)]
+        openings: Option<PathBuf>,
+        /// Opening pairs (one game per colour) against each baseline.
+        #[arg(long, default_value_t = 10)]
+        rounds: usize,
+        /// `<seconds>+<increment>` clock for both sides.
+        #[arg(long, default_value = "10+0.1")]
+        tc: String,
+        /// Fixed time per move in milliseconds; overrides --tc.
+        #[arg(long)]
+        movetime: Option<u64>,
+        #[arg(long, default_value_t = 1)]
+        concurrency: usize,
+        #[arg(long, default_value_t = 400)]
+        max_plies: usize,
+    },
+    /// Play two engines against each other, optionally as an SPRT.
+    Match {
+        /// Engine binaries, or full engine specs as for `gauntlet`.
+        engine1: String,
+        engine2: String,
+        /// FEN/EPD start positions.
+        #[arg(long, conflicts_with = "book")]
+        openings: Option<PathBuf>,
+        /// Polyglot book to draw random openings from.
+        #[arg(long)]
+        book: Option<PathBuf>,
+        #[arg(long, default_value_t = 8)]
+        book_plies: usize,
+        /// Opening pairs (one game per colour).
+        #[arg(long, default_value_t = 100)]
+        rounds: usize,
+        /// `<seconds>+<increment>` clock for both sides.
+        #[arg(long, default_value = "10+0.1")]
+        tc: String,
+        /// Fixed time per move in milliseconds; overrides --tc.
+        #[arg(long)]
+        movetime: Option<u64>,
+        #[arg(long, default_value_t = 1)]
+        concurrency: usize,
+        #[arg(long, default_value_t = 400)]
+        max_plies: usize,
+        /// Resign once a side's score stays below minus this many
+        /// centipawns for --resign-moves moves.
+        #[arg(long)]
+        resign: Option<i32>,
+        #[arg(long, default_value_t = 3)]
+        resign_moves: u32,
+        /// Draw once both scores stay within this many centipawns of zero
+        /// for --draw-moves moves each, from move --draw-after on.
+        #[arg(long)]
+        draw: Option<i32>,
+        #[arg(long, default_value_t = 8)]
+        draw_moves: u32,
+        #[arg(long, default_value_t = 40)]
+        draw_after: u32,
+        /// `<elo0>,<elo1>`: stop as soon as the SPRT accepts either bound.
+        #[arg(long, allow_hyphen_values = true)]
+        sprt: Option<String>,
+        #[arg(long, default_value_t = 0.05)]
+        alpha: f64,
+        #[arg(long, default_value_t = 0.05)]
+        beta: f64,
+    },
+    BuildBook {
+        /// PGN files to read.
+        #[arg(required = true)]
+        pgn: Vec<PathBuf>,
+        #[arg(long, default_value = "book.bin")]
+        out: PathBuf,
+        /// Plies of each game to include.
+        #[arg(long, default_value_t = 24)]
+        max_ply: usize,
+        /// Drop moves seen in fewer games than this.
+        #[arg(long, default_value_t = 2)]
+        min_games: u32,
+    },
+    /// Summarise the games recorded through the GameLog option.
+    Stats {
+        /// The JSONL game log; defaults to the GameLog option.
+        log: Option<PathBuf>,
+    },
+    Tune {
+        /// Lines of `<fen> <result>` from quiet positions.
+        data: PathBuf,
+        #[arg(long, default_value_t = 100)]
+        passes: usize,
+        /// Also tune the piece-square tables.
+        #[arg(long)]
+        pst: bool,
+        /// Where to write the tuned DEFAULT_PARAMS; printed if omitted.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Run an EPD test suite (WAC, STS, ...) and score the `bm`/`am` hits.
+    TestSuite {
+        /// EPD file with `bm` or `am` operations.
+        suite: PathBuf,
+        /// Milliseconds per position.
+        #[arg(long, default_value_t = 1000)]
+        movetime: u64,
+        /// Search every position to this depth instead of for --movetime.
+        #[arg(long)]
+        depth: Option<usize>,
+        #[arg(long)]
+        threads: Option<usize>,
+    },
+    Bench {
+        #[arg(default_value_t = BENCH_DEPTH)]
+        depth: usize,
+    },
+    MicroBench {
+        #[arg(long, default_value_t = 1000)]
+        iterations: u32,
+    },
+    InteractiveAnalyze {
+        #[arg(long)]
+        fen: Option<String>,
+        /// Moves (UCI or SAN) to play from the start position before analysing.
+        #[arg(long, num_args = 1..)]
+        moves: Vec<String>,
+        /// Load the first game of a PGN file instead.
+        #[arg(long, conflicts_with_all = ["fen", "moves"])]
+        pgn: Option<PathBuf>,
     },
     Uci,
 }
 
 fn main() {
-    // Initialize the NNUE network.
-    if let Err(e) = nnue::init() {
-        panic!("Failed to load embedded NNUE data: {}", e);
+    if let Err(e) = run(Cli::parse()) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
     }
+}
 
-    println!("NNUE loaded successfully.");
+fn run(cli: Cli) -> Result<(), ChessError> {
+    // Initialize the NNUE network.
+    nnue::init()?;
+
+    let mut opts = Options::default();
+    if let Some(path) = &cli.config {
+        opts.load_file(path).map_err(ChessError::InvalidOption)?;
+    }
+    for pair in &cli.options {
+        opts.set_pair(pair).map_err(ChessError::InvalidOption)?;
+    }
+    if cli.json {
+        opts.set("OutputFormat", "json")
+            .map_err(ChessError::InvalidOption)?;
+    }
+    opts.sync_search().map_err(ChessError::InvalidOption)?;
+    let search_config = opts.search_config().map_err(ChessError::InvalidOption)?;
+    if !opts.string("EvalFile").is_empty() {
+        nnue::load_eval_file(opts.string("EvalFile"))?;
+    }
+    uci_io::set_log_file(opts.string("LogFile"))?;
+    // JSON consumers expect nothing but JSON objects on stdout.
+    if !output::is_json() {
+        println!(
+            "NNUE loaded successfully ({}, {}).",
+            nnue::architecture(),
+            nnue_simd::level().name()
+        );
+    }
 
-    let cli = Cli::parse();
     match cli.cmd.unwrap_or(Cmd::Uci) {
         Cmd::Perft {
             depth,
             fen,
             divide: div,
+            threads,
+            hash,
+            verify,
         } => {
             let fen_str = fen.unwrap_or_else(|| START_FEN.to_string());
-            let mut b = Board::from_fen(&fen_str).unwrap_or_else(|e| {
-                eprintln!("FEN parse error: {e}");
-                std::process::exit(1);
-            });
-            if div {
-                divide(&mut b, depth);
+            let b = Board::from_fen(&fen_str)?;
+            let perft = Perft::new(threads, hash);
+            if verify {
+                if !perft::verify(&perft, depth) {
+                    std::process::exit(1);
+                }
+            } else if div {
+                print_divide(depth, &perft.divide(&b, depth));
             } else {
-                let n = perft(&mut b, depth);
-                println!("perft({depth}) = {n}");
+                let n = perft.count(&b, depth);
+                if output::is_json() {
+                    output::emit(&serde_json::json!({
+                        "type": "perft",
+                        "depth": depth,
+                        "nodes": n,
+                    }));
+                } else {
+                    println!("perft({depth}) = {n}");
+                }
             }
         }
         Cmd::PlayCli {
@@ -93,11 +396,15 @@ fn main() {
             threads,
         } => {
             let fen_str = fen.unwrap_or_else(|| START_FEN.to_string());
-            let mut b = Board::from_fen(&fen_str).unwrap_or_else(|e| {
-                eprintln!("FEN parse error: {e}");
-                std::process::exit(1);
-            });
-            play_cli(&mut b, time, depth, threads);
+            let mut b = Board::from_fen(&fen_str)?;
+            play_cli(
+                &mut b,
+                time,
+                depth,
+                threads.max(1),
+                opts.hash_mb(),
+                search_config,
+            )?;
         }
         Cmd::SelfPlay {
             rounds,
@@ -105,39 +412,419 @@ fn main() {
             depth,
             fen,
             threads,
+            white_time,
+            black_time,
+            white_depth,
+            black_depth,
+            white_nodes,
+            black_nodes,
+            odds,
+            resign_score,
+            resign_moves,
+            draw_score,
+            draw_moves,
+            draw_after,
+            players,
+            gauntlet,
+            max_plies,
+        } => {
+            let threads_count = threads.unwrap_or_else(|| opts.threads()).max(1);
+            let mut fen_str = fen.unwrap_or_else(|| START_FEN.to_string());
+            if let Some(spec) = odds {
+                let b = Board::from_fen(&fen_str)?;
+                fen_str = handicap::apply_odds(&b, &spec)
+                    .map_err(ChessError::InvalidOption)?
+                    .to_fen();
+            }
+            let side = |t: Option<u64>, d: Option<usize>, n: Option<u64>| SearchLimits {
+                time_ms: t.unwrap_or(time),
+                hard_time_ms: t.unwrap_or(time),
+                max_depth: d.unwrap_or(depth),
+                nodes: n.unwrap_or(u64::MAX),
+                root_moves: Vec::new(),
+                root_scores: false,
+                config: search_config,
+            };
+            let limits = [
+                side(white_time, white_depth, white_nodes),
+                side(black_time, black_depth, black_nodes),
+            ];
+            let adjudication = Adjudication {
+                resign_score,
+                resign_moves,
+                draw_score,
+                draw_moves,
+                draw_after,
+            };
+            if !players.is_empty() {
+                if players.len() < 2 {
+                    return Err(ChessError::InvalidOption(
+                        "a tournament needs at least two --player".into(),
+                    ));
+                }
+                let config = TournamentConfig {
+                    players: players
+                        .iter()
+                        .map(|s| Player::parse(s, threads_count, opts.hash_mb()))
+                        .collect::<Result<_, _>>()
+                        .map_err(ChessError::InvalidOption)?,
+                    format: if gauntlet {
+                        Format::Gauntlet
+                    } else {
+                        Format::RoundRobin
+                    },
+                    fen: fen_str,
+                    rounds,
+                    limits: SearchLimits {
+                        config: search_config,
+                        ..SearchLimits::movetime(time, depth)
+                    },
+                    max_plies,
+                    adjudication,
+                };
+                let results = tournament::run(&config, |line| println!("{line}"))?;
+                print!("{}", tournament::report(&config, &results));
+                return Ok(());
+            }
+            self_play(
+                &fen_str,
+                rounds,
+                limits,
+                adjudication,
+                threads_count,
+                opts.hash_mb(),
+                opts.game_log(),
+            )?;
+        }
+        Cmd::Eval { fen } => {
+            let fen_str = fen.unwrap_or_else(|| START_FEN.to_string());
+            let b = Board::from_fen(&fen_str)?;
+            if output::is_json() {
+                output::emit(&eval::trace_json(&b));
+            } else {
+                print!("{}", eval::trace(&b));
+            }
+        }
+        Cmd::CheckEval { positions } => {
+            let text = std::fs::read_to_string(&positions)?;
+            let mut checked = 0;
+            let mut failed = 0;
+            for line in text.lines().filter(|l| !l.trim().is_empty()) {
+                // EPD lines carry only the first four FEN fields.
+                let fields: Vec<&str> = line.split_whitespace().take(6).collect();
+                let mut b = Board::from_fen(&fields.join(" "))
+                    .or_else(|_| Board::from_fen(&fields[..4.min(fields.len())].join(" ")))?;
+                let failures = check_position(&mut b);
+                for f in &failures {
+                    println!("FAIL {f}");
+                }
+                checked += 1;
+                failed += !failures.is_empty() as usize;
+            }
+            println!("{checked} positions checked, {failed} with failures");
+            if failed > 0 {
+                std::process::exit(1);
+            }
+        }
+        Cmd::Datagen {
+            out,
+            games,
+            nodes,
+            depth,
+            random_plies,
+            fen,
+            threads,
+            hash,
+            seed,
         } => {
-            let threads_count = threads.unwrap_or_else(num_cpus::get).max(1);
             let fen_str = fen.unwrap_or_else(|| START_FEN.to_string());
-            self_play(&fen_str, rounds, time, depth, threads_count);
+            let b = Board::from_fen(&fen_str)?;
+            let config = DatagenConfig {
+                games,
+                nodes,
+                max_depth: depth,
+                random_plies,
+                threads: threads.unwrap_or_else(|| opts.threads()).max(1),
+                hash_mb: hash,
+                seed,
+            };
+            let mut file = io::BufWriter::new(std::fs::File::create(&out)?);
+            let written = datagen::run(&b, &config, &mut file)?;
+            println!("Wrote {written} positions to {}", out.display());
+        }
+        Cmd::Gauntlet {
+            candidate,
+            baselines,
+            openings,
+            rounds,
+            tc,
+            movetime,
+            concurrency,
+            max_plies,
+        } => {
+            let openings = match openings {
+                Some(path) => gauntlet::load_openings(&std::fs::read_to_string(path)?)?,
+                None => Vec::new(),
+            };
+            let tc = match movetime {
+                Some(ms) => TimeControl::MoveTime(ms),
+                None => TimeControl::parse_clock(&tc).map_err(ChessError::InvalidOption)?,
+            };
+            let config = GauntletConfig {
+                candidate: EngineSpec::parse(&candidate).map_err(ChessError::InvalidOption)?,
+                baselines: baselines
+                    .iter()
+                    .map(|s| EngineSpec::parse(s))
+                    .collect::<Result<_, _>>()
+                    .map_err(ChessError::InvalidOption)?,
+                openings,
+                rounds,
+                tc,
+                concurrency,
+                max_plies,
+                adjudication: Adjudication::default(),
+                sprt: None,
+            };
+            let tallies = gauntlet::run(&config);
+            print!("{}", gauntlet::report(&config, &tallies));
+        }
+        Cmd::Match {
+            engine1,
+            engine2,
+            openings,
+            book,
+            book_plies,
+            rounds,
+            tc,
+            movetime,
+            concurrency,
+            max_plies,
+            resign,
+            resign_moves,
+            draw,
+            draw_moves,
+            draw_after,
+            sprt,
+            alpha,
+            beta,
+        } => {
+            // A bare path is shorthand for `cmd=<path>`.
+            let spec = |s: &str| {
+                if s.contains('=') {
+                    EngineSpec::parse(s)
+                } else {
+                    EngineSpec::parse(&format!("cmd={s}"))
+                }
+                .map_err(ChessError::InvalidOption)
+            };
+            let openings = match (openings, book) {
+                (Some(path), _) => gauntlet::load_openings(&std::fs::read_to_string(path)?)?,
+                (None, Some(path)) => {
+                    gauntlet::book_openings(&mut OpeningBook::load(&path)?, rounds, book_plies)
+                }
+                (None, None) => Vec::new(),
+            };
+            let tc = match movetime {
+                Some(ms) => TimeControl::MoveTime(ms),
+                None => TimeControl::parse_clock(&tc).map_err(ChessError::InvalidOption)?,
+            };
+            let config = GauntletConfig {
+                candidate: spec(&engine1)?,
+                baselines: vec![spec(&engine2)?],
+                openings,
+                rounds,
+                tc,
+                concurrency,
+                max_plies,
+                adjudication: Adjudication {
+                    resign_score: resign,
+                    resign_moves,
+                    draw_score: draw,
+                    draw_moves,
+                    draw_after,
+                },
+                sprt: sprt
+                    .map(|s| Sprt::parse(&s, alpha, beta))
+                    .transpose()
+                    .map_err(ChessError::InvalidOption)?,
+            };
+            let tallies = gauntlet::run(&config);
+            print!("{}", gauntlet::report(&config, &tallies));
+        }
+        Cmd::BuildBook {
+            pgn: files,
+            out,
+            max_ply,
+            min_games,
+        } => {
+            let mut builder = BookBuilder::new(max_ply);
+            for path in &files {
+                for game in pgn::parse_file(path).map_err(ChessError::Parse)? {
+                    builder.add_game(&game);
+                }
+            }
+            let entries = builder.entries(min_games);
+            write_book(&out, &entries)?;
+            println!(
+                "Wrote {} entries from {} games to {}",
+                entries.len(),
+                builder.games(),
+                out.display()
+            );
+        }
+        Cmd::Stats { log } => {
+            let path = log.or_else(|| opts.game_log()).ok_or_else(|| {
+                ChessError::InvalidOption("no game log given and GameLog is unset".into())
+            })?;
+            print!("{}", game_db::summarize(&game_db::load(&path)?));
+        }
+        Cmd::Tune {
+            data,
+            passes,
+            pst,
+            out,
+        } => {
+            let text = std::fs::read_to_string(&data)?;
+            let samples = tuner::parse_samples(&text).map_err(ChessError::Parse)?;
+            println!("Loaded {} positions", samples.len());
+            let source = tuner::to_rust(&tuner::tune(&samples, pst, passes));
+            match out {
+                Some(path) => std::fs::write(path, source)?,
+                None => print!("{source}"),
+            }
+        }
+        Cmd::TestSuite {
+            suite,
+            movetime,
+            depth,
+            threads,
+        } => {
+            let tests =
+                test_suite::load(&std::fs::read_to_string(&suite)?).map_err(ChessError::Parse)?;
+            let limits = SearchLimits {
+                config: search_config,
+                ..match depth {
+                    Some(d) => SearchLimits::movetime(u64::MAX / 4, d),
+                    None => SearchLimits::movetime(movetime, 128),
+                }
+            };
+            let threads = threads.unwrap_or_else(|| opts.threads()).max(1);
+            let outcomes = test_suite::run(&tests, &limits, opts.hash_mb(), threads, |test, o| {
+                let mut legal = MoveList::new();
+                test.board.generate_legal_moves(&mut legal);
+                let played = o
+                    .played
+                    .map_or("(none)".to_string(), |m| test.board.to_san(m, &legal));
+                println!(
+                    "{} {:<24} {:<16} played {:<8} depth {}",
+                    if o.passed { "PASS" } else { "FAIL" },
+                    o.id,
+                    test.expected(),
+                    played,
+                    o.depth
+                );
+            })?;
+            print!("{}", test_suite::report(&outcomes));
+        }
+        Cmd::Bench { depth } => print_bench(depth),
+        Cmd::MicroBench { iterations } => {
+            for r in micro_bench(iterations) {
+                println!("{:<24} {:>10.1} ns/op ({} ops)", r.name, r.ns_per_op, r.ops);
+            }
+        }
+        Cmd::InteractiveAnalyze { fen, moves, pgn } => {
+            if let Some(path) = pgn {
+                let game = pgn::parse_file(&path)
+                    .map_err(ChessError::Parse)?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| ChessError::Parse(format!("{}: no games", path.display())))?;
+                let moves: Vec<String> = game.mainline().into_iter().map(format_uci).collect();
+                interactive_analyze(game.start, &moves, opts.hash_mb())?;
+            } else {
+                let fen_str = fen.unwrap_or_else(|| START_FEN.to_string());
+                let b = Board::from_fen(&fen_str)?;
+                interactive_analyze(b, &moves, opts.hash_mb())?;
+            }
         }
-        Cmd::Uci => uci::run_uci(),
+        Cmd::Uci => uci::run_uci(opts)?,
     }
+    Ok(())
 }
 
-fn self_play(fen_str: &str, rounds: usize, time_ms: u64, max_depth: usize, threads_count: usize) {
+/// `limits` is indexed by colour, so the two sides may play under different
+/// time controls, depths or node limits.
+fn self_play(
+    fen_str: &str,
+    rounds: usize,
+    limits: [SearchLimits; 2],
+    adj: Adjudication,
+    threads_count: usize,
+    tt_size_mb: usize,
+    game_log: Option<PathBuf>,
+) -> Result<(), ChessError> {
     let mut white_wins = 0;
     let mut black_wins = 0;
     let mut draws = 0;
 
     println!("Starting self-play session:");
     println!("- Rounds: {}", rounds);
-    println!("- Time per move: {}ms", time_ms);
-    println!("- Max depth: {}", max_depth);
+    for (side, l) in ["White", "Black"].iter().zip(&limits) {
+        let nodes = if l.nodes == u64::MAX {
+            "-".to_string()
+        } else {
+            l.nodes.to_string()
+        };
+        println!(
+            "- {side}: {}ms per move, max depth {}, max nodes {nodes}",
+            l.time_ms, l.max_depth
+        );
+    }
+    if let Some(score) = adj.resign_score {
+        println!("- Resign: {score}cp for {} moves", adj.resign_moves);
+    }
+    if let Some(score) = adj.draw_score {
+        println!(
+            "- Draw: within {score}cp for {} moves from move {}",
+            adj.draw_moves, adj.draw_after
+        );
+    }
+    println!("- Start: {}", fen_str);
     println!("- Threads: {}", threads_count);
     println!("--------------------------------");
 
     for i in 1..=rounds {
-        let mut b = Board::from_fen(fen_str).unwrap_or_else(|e| {
-            eprintln!("FEN parse error: {e}");
-            std::process::exit(1);
-        });
+        let mut b = Board::from_fen(fen_str)?;
+
+        // One table and pool per side, so a stronger side's search doesn't
+        // help the other.
+        let mut tts = [
+            SharedTransTable::new(tt_size_mb),
+            SharedTransTable::new(tt_size_mb),
+        ];
+        let mut pools = [
+            ThreadPool::new(threads_count),
+            ThreadPool::new(threads_count),
+        ];
 
-        let tt_size_mb = 1024;
-        let mut tt = SharedTransTable::new(tt_size_mb);
+        let mut recorder = GameRecorder::new("self-play");
+        recorder.set_position(fen_str.to_string(), Vec::new());
+        let tc_label = |l: &SearchLimits| match l.nodes {
+            u64::MAX => format!("movetime {} depth {}", l.time_ms, l.max_depth),
+            n => format!("movetime {} depth {} nodes {n}", l.time_ms, l.max_depth),
+        };
+        recorder.set_time_control(format!(
+            "{} / {}",
+            tc_label(&limits[0]),
+            tc_label(&limits[1])
+        ));
 
         println!("\nGame {}/{}", i, rounds);
         println!("Starting FEN: {}", b.to_fen());
 
+        let mut adjudicator = Adjudicator::new(adj);
+        let mut adjudicated: Option<&str> = None;
+
         'gameloop: loop {
             print!("\x1B[2J\x1B[H"); // Clear screen
             println!("Game {}/{}", i, rounds);
@@ -145,73 +832,35 @@ fn self_play(fen_str: &str, rounds: usize, time_ms: u64, max_depth: usize, threa
             print_board_ascii(&b);
             println!("Turn: {:?}, Move: {}", b.turn, b.fullmove_number);
 
-            let mut legal_moves = Vec::new();
-            b.generate_legal_moves(&mut legal_moves);
-
-            if legal_moves.is_empty() {
-                let king_piece = Piece::from_kind(PieceKind::King, b.turn);
-                let king_sq_opt = b.piece_bb[king_piece.index()].trailing_zeros();
-                if king_sq_opt < 64 && b.is_square_attacked(king_sq_opt as i32, b.turn.other()) {
-                    println!("Result: Checkmate! {:?} wins.", b.turn.other());
-                    if b.turn.other() == Color::White {
-                        white_wins += 1;
-                    } else {
-                        black_wins += 1;
-                    }
-                } else {
-                    println!("Result: Stalemate!");
-                    draws += 1;
-                }
-                break 'gameloop;
+            // Finished games are scored here; the score-based adjudication
+            // rules are applied after each move.
+            let result = b.game_result();
+            match result {
+                GameResult::Ongoing => {}
+                GameResult::WhiteWins => white_wins += 1,
+                GameResult::BlackWins => black_wins += 1,
+                _ => draws += 1,
             }
-
-            if b.is_draw_by_repetition() || b.halfmove_clock >= 100 {
-                println!("Result: Draw!");
-                draws += 1;
+            if result.is_over() {
+                println!("Result: {} ({})", result.pgn(), result.reason());
                 break 'gameloop;
             }
 
-            println!("Engine ({:?}) is thinking...", b.turn);
-
-            let stop_signal = Arc::new(AtomicBool::new(false));
-            let mut helpers = vec![];
-            let helper_depth = max_depth.min(64);
+            let mut legal_moves = MoveList::new();
+            b.generate_legal_moves(&mut legal_moves);
 
-            for i in 0..(threads_count - 1) {
-                let board_clone = b.clone();
-                let tt_clone = tt.clone();
-                let stop_clone = Arc::clone(&stop_signal);
-                let name = format!("self-play-helper-{}", i);
-                let _ = thread::Builder::new()
-                    .name(name)
-                    .stack_size(SEARCH_THREAD_STACK)
-                    .spawn(move || {
-                        let mut tt_local = tt_clone;
-                        best_move_timed(
-                            &board_clone,
-                            &mut tt_local,
-                            u64::MAX / 4,
-                            helper_depth,
-                            stop_clone,
-                            false,
-                        );
-                    })
-                    .map(|jh| helpers.push(jh));
-            }
+            println!("Engine ({:?}) is thinking...", b.turn);
 
-            let (engine_move_opt, _, _) = best_move_timed(
+            let side = b.turn as usize;
+            let result = pools[side].search(
                 &b,
-                &mut tt,
-                time_ms,
-                max_depth,
-                Arc::clone(&stop_signal),
-                true,
+                &mut tts[side],
+                limits[side].clone(),
+                Arc::new(AtomicBool::new(false)),
+                Some(&mut UciInfoPrinter),
             );
-
-            stop_signal.store(true, Ordering::Relaxed);
-            for h in helpers {
-                let _ = h.join();
-            }
+            let engine_move_opt = result.best_move;
+            recorder.record_search(recorder.ply(), b.turn, Some(result.score));
 
             let engine_move = if let Some(m) = engine_move_opt {
                 m
@@ -226,9 +875,41 @@ fn self_play(fen_str: &str, rounds: usize, time_ms: u64, max_depth: usize, threa
                 b.to_san(engine_move, &legal_moves),
                 format_uci(engine_move)
             );
+            recorder.push_move(format_uci(engine_move));
             let _u = b.make_move(engine_move);
+
+            let white_score = if side == 0 {
+                result.score
+            } else {
+                -result.score
+            };
+            adjudicated = match adjudicator.update(white_score, b.fullmove_number) {
+                Some(s) if s > 0.5 => {
+                    white_wins += 1;
+                    Some("1-0")
+                }
+                Some(s) if s < 0.5 => {
+                    black_wins += 1;
+                    Some("0-1")
+                }
+                Some(_) => {
+                    draws += 1;
+                    Some("1/2-1/2")
+                }
+                None => None,
+            };
+            if let Some(result) = adjudicated {
+                println!("Result: {result} (adjudicated)");
+                break 'gameloop;
+            }
             thread::sleep(std::time::Duration::from_millis(100));
         }
+
+        if let Some(record) = recorder.finish(&b, adjudicated)
+            && let Some(path) = &game_log
+        {
+            game_db::append(path, &record)?;
+        }
     }
 
     println!("\nSelf-Play Session Complete");
@@ -237,16 +918,101 @@ fn self_play(fen_str: &str, rounds: usize, time_ms: u64, max_depth: usize, threa
     println!("  Black Wins: {}", black_wins);
     println!("  Draws: {}", draws);
     println!("------------------------------------");
+    Ok(())
+}
+
+/// Prints each finished iteration of a PlayCli analysis, with the score
+/// from White's side and the line in SAN.
+struct AnalysisPrinter {
+    board: Board,
+}
+
+impl InfoCallback for AnalysisPrinter {
+    fn info(&mut self, info: &SearchInfo) {
+        let sign = if self.board.turn == Color::White {
+            1
+        } else {
+            -1
+        };
+        let score = match info.mate_in() {
+            Some(n) => format!("#{}", n * sign),
+            None => format!("{:+.2}", (info.score * sign) as f64 / 100.0),
+        };
+        println!(
+            "depth {:>2}  {score:>7}  {:>10} nodes  {}",
+            info.depth,
+            info.nodes,
+            pv_san(&self.board, &info.pv)
+        );
+    }
 }
 
-fn play_cli(b: &mut Board, time_ms: u64, max_depth: usize, threads_count: usize) {
+/// `pv` in SAN, stopping at the first move that is not legal.
+fn pv_san(b: &Board, pv: &[Move]) -> String {
+    let mut b = b.clone();
+    let mut line = Vec::with_capacity(pv.len());
+    for &m in pv {
+        let mut legal = MoveList::new();
+        b.generate_legal_moves(&mut legal);
+        if !legal.contains(&m) {
+            break;
+        }
+        line.push(b.to_san(m, &legal));
+        let _ = b.make_move(m);
+    }
+    line.join(" ")
+}
+
+/// Searches `b` in the background, printing every new best line, until the
+/// user presses Enter.
+fn analyze(b: &Board, tt: &SharedTransTable) -> Result<(), ChessError> {
+    println!("Analyzing, press Enter to stop.");
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = {
+        let board = b.clone();
+        let mut tt = tt.clone();
+        let stop = Arc::clone(&stop);
+        thread::Builder::new()
+            .name("analysis-cli".to_string())
+            .spawn(move || {
+                let mut printer = AnalysisPrinter {
+                    board: board.clone(),
+                };
+                search_with_limits(
+                    &board,
+                    &mut tt,
+                    SearchLimits::movetime(u64::MAX / 4, ANALYSIS_DEPTH),
+                    stop,
+                    Arc::new(AtomicU64::new(0)),
+                    true,
+                    Some(&mut printer),
+                );
+            })
+            .map_err(ChessError::ThreadSpawn)?
+    };
+    let mut line = String::new();
+    let _ = io::stdin().read_line(&mut line);
+    stop.store(true, Ordering::Relaxed);
+    handle
+        .join()
+        .map_err(|_| ChessError::ThreadPanicked("analysis-cli".into()))
+}
+
+fn play_cli(
+    b: &mut Board,
+    time_ms: u64,
+    max_depth: usize,
+    threads_count: usize,
+    tt_size_mb: usize,
+    search_config: SearchConfig,
+) -> Result<(), ChessError> {
     {
-        let mut _moves = Vec::new();
+        let mut _moves = MoveList::new();
         b.generate_legal_moves(&mut _moves);
     }
 
-    let tt_size_mb = 1024;
     let mut tt = SharedTransTable::new(tt_size_mb);
+    let mut pool = ThreadPool::new(threads_count);
 
     struct PonderState {
         handle: Option<thread::JoinHandle<()>>,
@@ -267,18 +1033,17 @@ fn play_cli(b: &mut Board, time_ms: u64, max_depth: usize, threads_count: usize)
             println!("(Engine is pondering your move: {})", format_uci(pm));
         }
 
-        let mut legal_moves = Vec::new();
-        b.generate_legal_moves(&mut legal_moves);
-
-        if legal_moves.is_empty() {
-            println!("You have no legal moves. Game Over.");
+        if report_game_over(b) {
             break;
         }
 
+        let mut legal_moves = MoveList::new();
+        b.generate_legal_moves(&mut legal_moves);
+
         let mut user_move_made = false;
         while !user_move_made {
-            print!("\nYour move (e.g., Nf3, e2e4, or 'quit'): ");
-            io::stdout().flush().unwrap();
+            print!("\nYour move (e.g., Nf3, e2e4, 'analyze' or 'quit'): ");
+            io::stdout().flush()?;
             let mut line = String::new();
             if io::stdin().read_line(&mut line).is_err() {
                 break 'gameloop;
@@ -291,23 +1056,17 @@ fn play_cli(b: &mut Board, time_ms: u64, max_depth: usize, threads_count: usize)
 
             if let Some(handle) = ponder_state.handle.take() {
                 ponder_state.stop_signal.store(true, Ordering::Relaxed);
-                handle.join().unwrap();
+                handle
+                    .join()
+                    .map_err(|_| ChessError::ThreadPanicked("ponder-helper-cli".into()))?;
             }
 
-            let mut user_move_opt = parse_uci_move(b, input_str);
-
-            if user_move_opt.is_none() {
-                for &legal_move in &legal_moves {
-                    // remove check/mate suffix to accept "Nf3" style inputs
-                    let san_str = b.to_san(legal_move, &legal_moves).replace(['+', '#'], "");
-                    if san_str == input_str {
-                        user_move_opt = Some(legal_move);
-                        break;
-                    }
-                }
+            if input_str.eq_ignore_ascii_case("analyze") {
+                analyze(b, &tt)?;
+                continue;
             }
 
-            if let Some(user_move) = user_move_opt {
+            if let Some(user_move) = parse_user_move(b, input_str) {
                 if legal_moves.contains(&user_move) {
                     if Some(user_move) == ponder_move_opt {
                         println!("(Ponder hit!)");
@@ -325,56 +1084,32 @@ fn play_cli(b: &mut Board, time_ms: u64, max_depth: usize, threads_count: usize)
         print!("\x1B[2J\x1B[H"); // Clear screen
         println!("FEN: {}", b.to_fen());
         print_board_ascii(b);
+        if report_game_over(b) {
+            break;
+        }
         println!(
-            "\nEngine is thinking for up to {} seconds using {} threads...",
+            "\nEngine is thinking for about {} seconds using {} threads...",
             time_ms / 1000,
             threads_count
         );
         println!("(Search information will appear below)");
         println!("--------------------------------");
-        io::stdout().flush().unwrap();
-
-        let stop_signal = Arc::new(AtomicBool::new(false));
-        let mut helpers = vec![];
-
-        // Conservative recursion cap for helpers to avoid stack blowups
-        let helper_depth = max_depth.min(64);
-
-        for i in 0..(threads_count - 1) {
-            let board_clone = b.clone();
-            let tt_clone = tt.clone();
-            let stop_clone = Arc::clone(&stop_signal);
-            let name = format!("helper-{}", i);
-            let _ = thread::Builder::new()
-                .name(name)
-                .stack_size(SEARCH_THREAD_STACK)
-                .spawn(move || {
-                    let mut tt_local = tt_clone;
-                    best_move_timed(
-                        &board_clone,
-                        &mut tt_local,
-                        u64::MAX / 4,
-                        helper_depth,
-                        stop_clone,
-                        false,
-                    );
-                })
-                .map(|jh| helpers.push(jh));
-        }
+        io::stdout().flush()?;
 
-        let (engine_move_opt, _, _) = best_move_timed(
-            b,
-            &mut tt,
-            time_ms,
-            max_depth,
-            Arc::clone(&stop_signal),
-            true,
-        );
-
-        stop_signal.store(true, Ordering::Relaxed);
-        for h in helpers {
-            let _ = h.join();
-        }
+        let limits = SearchLimits {
+            hard_time_ms: time_ms * HARD_LIMIT_FACTOR as u64,
+            config: search_config,
+            ..SearchLimits::movetime(time_ms, max_depth)
+        };
+        let engine_move_opt = pool
+            .search(
+                b,
+                &mut tt,
+                limits,
+                Arc::new(AtomicBool::new(false)),
+                Some(&mut UciInfoPrinter),
+            )
+            .best_move;
 
         let engine_move = if let Some(m) = engine_move_opt {
             m
@@ -392,7 +1127,7 @@ fn play_cli(b: &mut Board, time_ms: u64, max_depth: usize, threads_count: usize)
         thread::sleep(std::time::Duration::from_millis(500));
 
         if let Some(ponder_move) = ponder_move_opt {
-            let mut legal_moves = Vec::new();
+            let mut legal_moves = MoveList::new();
             b.generate_legal_moves(&mut legal_moves);
             if legal_moves.contains(&ponder_move) {
                 let mut ponder_board = b.clone();
@@ -403,14 +1138,13 @@ fn play_cli(b: &mut Board, time_ms: u64, max_depth: usize, threads_count: usize)
 
                 let _ = thread::Builder::new()
                     .name("ponder-helper-cli".to_string())
-                    .stack_size(SEARCH_THREAD_STACK)
                     .spawn(move || {
                         let mut tt_local = tt_clone;
                         best_move_timed(
                             &ponder_board,
                             &mut tt_local,
-                            u64::MAX / 4,
-                            helper_depth,
+                            (u64::MAX / 4, u64::MAX / 4),
+                            max_depth,
                             stop_clone,
                             false,
                         );
@@ -425,37 +1159,217 @@ fn play_cli(b: &mut Board, time_ms: u64, max_depth: usize, threads_count: usize)
         let _ = handle.join();
     }
     println!("Exiting game.");
+    Ok(())
 }
 
-fn print_board_ascii(b: &Board) {
-    use chess::types::Piece;
-    const BLUE: &str = "\x1b[34m";
-    const RESET: &str = "\x1b[0m";
-    println!("\n   a b c d e f g h");
-    println!(" +-----------------+");
-    for r in (0..8).rev() {
-        print!("{}| ", r + 1);
-        for f in 0..8 {
-            let p = b.piece_on[(r * 8 + f) as usize];
-            let s = match p {
-                Piece::Empty => ".".to_string(),
-                Piece::WP => "P".to_string(),
-                Piece::WN => "N".to_string(),
-                Piece::WB => "B".to_string(),
-                Piece::WR => "R".to_string(),
-                Piece::WQ => "Q".to_string(),
-                Piece::WK => "K".to_string(),
-                Piece::BP => format!("{BLUE}p{RESET}"),
-                Piece::BN => format!("{BLUE}n{RESET}"),
-                Piece::BB => format!("{BLUE}b{RESET}"),
-                Piece::BR => format!("{BLUE}r{RESET}"),
-                Piece::BQ => format!("{BLUE}q{RESET}"),
-                Piece::BK => format!("{BLUE}k{RESET}"),
-            };
-            print!("{s} ");
+/// Prints the result and returns true if the game has ended.
+/// Accepts a move in UCI notation or SAN (check and mate suffixes optional).
+fn parse_user_move(b: &mut Board, input: &str) -> Option<Move> {
+    parse_uci_move(b, input).or_else(|| san::parse_san(b, input))
+}
+
+/// A background infinite search that prints its own `info` lines.
+struct Analysis {
+    handle: Option<thread::JoinHandle<()>>,
+    stop_signal: Arc<AtomicBool>,
+    key: ZKey,
+}
+
+impl Analysis {
+    fn start(b: &Board, tt: &SharedTransTable) -> Result<Self, ChessError> {
+        let board = b.clone();
+        let mut tt_local = tt.clone();
+        let stop_signal = Arc::new(AtomicBool::new(false));
+        let stop_clone = Arc::clone(&stop_signal);
+        let handle = thread::Builder::new()
+            .name("analysis".into())
+            .spawn(move || {
+                best_move_timed(
+                    &board,
+                    &mut tt_local,
+                    (u64::MAX / 4, u64::MAX / 4),
+                    128,
+                    stop_clone,
+                    true,
+                );
+            })
+            .map_err(ChessError::ThreadSpawn)?;
+        Ok(Self {
+            handle: Some(handle),
+            stop_signal,
+            key: b.zobrist,
+        })
+    }
+
+    fn stop(&mut self) {
+        self.stop_signal.store(true, Ordering::Relaxed);
+        if let Some(h) = self.handle.take() {
+            let _ = h.join();
+        }
+    }
+}
+
+/// The line being analysed plus the alternatives branched off it.
+struct AnalysisBoard {
+    root: Board,
+    moves: Vec<Move>,
+    cursor: usize,
+    variations: Vec<Vec<Move>>,
+}
+
+impl AnalysisBoard {
+    fn position(&self) -> Board {
+        let mut b = self.root.clone();
+        for &m in &self.moves[..self.cursor] {
+            b.make_move(m);
+        }
+        b
+    }
+
+    /// Plays `m` at the cursor. If it departs from the current line, the old
+    /// continuation is kept as a variation.
+    fn play(&mut self, m: Move) {
+        if self.moves.get(self.cursor) != Some(&m) {
+            if self.cursor < self.moves.len() {
+                self.variations.push(self.moves.clone());
+            }
+            self.moves.truncate(self.cursor);
+            self.moves.push(m);
+        }
+        self.cursor += 1;
+    }
+
+    /// Makes variation `i` the main line, keeping the current line as a
+    /// variation, and puts the cursor where the two lines diverge.
+    fn switch(&mut self, i: usize) -> bool {
+        if i >= self.variations.len() {
+            return false;
         }
-        println!("|{}", r + 1);
+        let line = std::mem::replace(&mut self.variations[i], self.moves.clone());
+        self.cursor = self
+            .moves
+            .iter()
+            .zip(&line)
+            .take_while(|(a, b)| a == b)
+            .count();
+        self.moves = line;
+        true
     }
-    println!(" +-----------------+");
-    println!("   a b c d e f g h\n");
+
+    /// Moves in UCI notation, with the move at `cursor` (the next one to be
+    /// played) bracketed.
+    fn line_to_string(line: &[Move], cursor: Option<usize>) -> String {
+        line.iter()
+            .enumerate()
+            .map(|(i, &m)| {
+                if Some(i) == cursor {
+                    format!("[{}]", format_uci(m))
+                } else {
+                    format_uci(m)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+fn interactive_analyze(root: Board, moves: &[String], tt_size_mb: usize) -> Result<(), ChessError> {
+    let tt = SharedTransTable::new(tt_size_mb);
+    let mut board = AnalysisBoard {
+        root,
+        moves: Vec::new(),
+        cursor: 0,
+        variations: Vec::new(),
+    };
+    for input in moves {
+        let mut b = board.position();
+        let m =
+            parse_user_move(&mut b, input).ok_or_else(|| ChessError::IllegalMove(input.clone()))?;
+        board.play(m);
+    }
+
+    let mut analysis: Option<Analysis> = None;
+    println!(
+        "Commands: <move>, back, forward, start, end, go, stop, line, vars, var <n>, fen, help, quit"
+    );
+
+    loop {
+        let mut b = board.position();
+        println!("\nFEN: {}", b.to_fen());
+        print_board_ascii(&b);
+        println!(
+            "Line: {}",
+            AnalysisBoard::line_to_string(&board.moves, Some(board.cursor))
+        );
+        report_game_over(&b);
+
+        // Keep analysing the new position if analysis was switched on.
+        if let Some(a) = analysis.as_mut()
+            && a.key != b.zobrist
+        {
+            a.stop();
+            analysis = Some(Analysis::start(&b, &tt)?);
+        }
+
+        print!("analyze> ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if matches!(io::stdin().read_line(&mut line), Ok(0) | Err(_)) {
+            break;
+        }
+        let input = line.trim();
+        let mut words = input.split_whitespace();
+
+        match words.next().unwrap_or("") {
+            "" => {}
+            "quit" | "q" => break,
+            "help" => println!("Play moves in UCI or SAN; 'go' toggles infinite analysis."),
+            "back" | "b" => board.cursor = board.cursor.saturating_sub(1),
+            "forward" | "f" => board.cursor = (board.cursor + 1).min(board.moves.len()),
+            "start" => board.cursor = 0,
+            "end" => board.cursor = board.moves.len(),
+            "go" => {
+                if analysis.is_none() {
+                    analysis = Some(Analysis::start(&b, &tt)?);
+                }
+            }
+            "stop" => {
+                if let Some(mut a) = analysis.take() {
+                    a.stop();
+                }
+            }
+            "fen" => println!("{}", b.to_fen()),
+            "line" => {}
+            "vars" => {
+                for (i, v) in board.variations.iter().enumerate() {
+                    println!("{i}: {}", AnalysisBoard::line_to_string(v, None));
+                }
+            }
+            "var" => match words.next().and_then(|n| n.parse().ok()) {
+                Some(i) if board.switch(i) => {}
+                _ => println!("No such variation."),
+            },
+            mv => match parse_user_move(&mut b, mv) {
+                Some(m) => board.play(m),
+                None => println!("Unrecognized or illegal move."),
+            },
+        }
+    }
+
+    if let Some(mut a) = analysis.take() {
+        a.stop();
+    }
+    Ok(())
+}
+
+fn report_game_over(b: &Board) -> bool {
+    let result = b.game_result();
+    if result.is_over() {
+        println!("Game over: {} ({}).", result.pgn(), result.reason());
+    }
+    result.is_over()
+}
+
+fn print_board_ascii(b: &Board) {
+    println!("{}", display::board_ascii(b, true));
 }
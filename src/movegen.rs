@@ -0,0 +1,120 @@
+use crate::board::Board;
+use crate::movelist::MoveList;
+use crate::types::{GenKind, Move};
+
+/// An iterator over a position's legal moves, generated up front. Built by
+/// [`Board::legal_moves`] and narrowed with [`captures`](Self::captures),
+/// [`quiets`](Self::quiets) or [`evasions`](Self::evasions).
+#[derive(Clone, Debug)]
+pub struct MoveGen {
+    moves: MoveList,
+    next: usize,
+    in_check: bool,
+}
+
+impl MoveGen {
+    pub fn new(b: &Board, kind: GenKind) -> Self {
+        let mut moves = MoveList::new();
+        b.generate_legal(&mut moves, kind);
+        Self {
+            moves,
+            next: 0,
+            in_check: b.in_check(),
+        }
+    }
+
+    fn retain(mut self, keep: impl Fn(&Move) -> bool) -> Self {
+        self.moves = self.moves[self.next..]
+            .iter()
+            .copied()
+            .filter(keep)
+            .collect();
+        self.next = 0;
+        self
+    }
+
+    /// Only the remaining captures, en passant included.
+    pub fn captures(self) -> Self {
+        self.retain(|m| m.is_capture())
+    }
+
+    /// Only the remaining moves that neither capture nor promote.
+    pub fn quiets(self) -> Self {
+        self.retain(|m| !m.is_capture() && m.promotion().is_none())
+    }
+
+    /// The remaining moves if the side to move is in check, otherwise none.
+    pub fn evasions(self) -> Self {
+        let in_check = self.in_check;
+        self.retain(|_| in_check)
+    }
+}
+
+impl Iterator for MoveGen {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        let m = self.moves.get(self.next).copied()?;
+        self.next += 1;
+        Some(m)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.moves.len() - self.next;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for MoveGen {}
+
+#[cfg(test)]
+mod tests {
+    use crate::board::Board;
+    use crate::movelist::MoveList;
+    use crate::types::{Move, START_FEN};
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    fn matches_generate_legal_moves() {
+        for fen in [START_FEN, KIWIPETE] {
+            let b = Board::from_fen(fen).unwrap();
+            let mut list = MoveList::new();
+            b.generate_legal_moves(&mut list);
+            let moves = b.legal_moves();
+            assert_eq!(moves.len(), list.len());
+            assert_eq!(moves.collect::<Vec<_>>(), list.to_vec());
+        }
+    }
+
+    #[test]
+    fn captures_and_quiets_split_the_moves() {
+        let b = Board::from_fen(KIWIPETE).unwrap();
+        let captures: Vec<Move> = b.captures().collect();
+        let quiets: Vec<Move> = b.quiets().collect();
+        assert_eq!(captures.len(), 8);
+        assert!(captures.iter().all(|m| m.is_capture()));
+        assert!(
+            quiets
+                .iter()
+                .all(|m| !m.is_capture() && m.promotion().is_none())
+        );
+        assert_eq!(captures.len() + quiets.len(), b.legal_moves().len());
+    }
+
+    #[test]
+    fn evasions_only_in_check() {
+        assert_eq!(Board::from_fen(START_FEN).unwrap().evasions().count(), 0);
+        let b = Board::from_fen("4k3/8/8/8/8/8/8/r3K3 w - - 0 1").unwrap();
+        assert_eq!(b.evasions().count(), b.legal_moves().count());
+        assert_eq!(b.evasions().count(), 3);
+    }
+
+    #[test]
+    fn narrowing_keeps_only_what_is_left() {
+        let mut moves = Board::from_fen(START_FEN).unwrap().legal_moves();
+        moves.by_ref().take(5).for_each(drop);
+        assert_eq!(moves.len(), 15);
+        assert_eq!(moves.quiets().count(), 15);
+    }
+}
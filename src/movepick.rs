@@ -0,0 +1,230 @@
+use crate::board::Board;
+use crate::movelist::{ArrayVec, MAX_MOVES, MoveList};
+use crate::see::{PIECE_VALUES, see};
+use crate::types::{GenKind, Move, PieceKind};
+
+/// Quiet-move history, indexed by `[piece][to_square]`.
+pub type History = [[i32; 64]; 13];
+
+type ScoredMoves = ArrayVec<(Move, i32), MAX_MOVES>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Stage {
+    TtMove,
+    GenNoisy,
+    GoodNoisy,
+    Killer1,
+    Killer2,
+    CounterMove,
+    GenQuiet,
+    Quiet,
+    BadNoisy,
+    Done,
+}
+
+/// Yields legal moves in search order, generating each group only when the
+/// previous one is exhausted: TT move, winning captures and queen
+/// promotions, killers, countermove, quiets by history, then losing
+/// captures and underpromotions.
+pub struct MovePicker {
+    stage: Stage,
+    tt_move: Option<Move>,
+    killers: [Option<Move>; 2],
+    counter: Option<Move>,
+    /// Stop after the noisy moves (quiescence search outside of check).
+    noisy_only: bool,
+    moves: ScoredMoves,
+    bad_noisy: ScoredMoves,
+    next: usize,
+}
+
+impl MovePicker {
+    pub fn new(tt_move: Option<Move>, killers: [Option<Move>; 2], counter: Option<Move>) -> Self {
+        let mut picker = Self {
+            stage: Stage::Done,
+            tt_move: None,
+            killers: [None; 2],
+            counter: None,
+            noisy_only: false,
+            moves: ScoredMoves::new(),
+            bad_noisy: ScoredMoves::new(),
+            next: 0,
+        };
+        picker.reset(tt_move, killers, counter);
+        picker
+    }
+
+    /// Captures and promotions only, for quiescence search.
+    pub fn noisy() -> Self {
+        let mut picker = Self::new(None, [None; 2], None);
+        picker.reset_noisy();
+        picker
+    }
+
+    /// Starts over at a new node, keeping the move buffers so the search
+    /// can hold one picker per ply instead of one per stack frame.
+    pub fn reset(
+        &mut self,
+        tt_move: Option<Move>,
+        killers: [Option<Move>; 2],
+        counter: Option<Move>,
+    ) {
+        self.stage = Stage::TtMove;
+        self.tt_move = tt_move;
+        self.killers = killers;
+        self.counter = counter;
+        self.noisy_only = false;
+        self.moves.clear();
+        self.bad_noisy.clear();
+        self.next = 0;
+    }
+
+    /// Like [`MovePicker::noisy`], reusing this picker.
+    pub fn reset_noisy(&mut self) {
+        self.reset(None, [None; 2], None);
+        self.stage = Stage::GenNoisy;
+        self.noisy_only = true;
+    }
+
+    /// `cont_history` is the continuation history of replies to the
+    /// previous move, added to `history` when ordering quiets.
+    pub fn next(
+        &mut self,
+        b: &Board,
+        history: &History,
+        cont_history: Option<&History>,
+    ) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::TtMove => {
+                    self.stage = Stage::GenNoisy;
+                    match self.tt_move {
+                        Some(m) if b.is_legal(m) => return Some(m),
+                        _ => self.tt_move = None,
+                    }
+                }
+                Stage::GenNoisy => {
+                    let mut moves = MoveList::new();
+                    b.generate_legal(&mut moves, GenKind::Noisy);
+                    for m in moves {
+                        if Some(m) == self.tt_move {
+                            continue;
+                        }
+                        let (score, good) = noisy_score(b, m);
+                        if good {
+                            self.moves.push((m, score));
+                        } else {
+                            self.bad_noisy.push((m, score));
+                        }
+                    }
+                    self.next = 0;
+                    self.stage = Stage::GoodNoisy;
+                }
+                Stage::GoodNoisy => {
+                    if let Some(m) = self.pick_best() {
+                        return Some(m);
+                    }
+                    self.stage = if self.noisy_only {
+                        self.start_bad_noisy()
+                    } else {
+                        Stage::Killer1
+                    };
+                }
+                Stage::Killer1 | Stage::Killer2 | Stage::CounterMove => {
+                    let (m, repeat, next) = match self.stage {
+                        Stage::Killer1 => (self.killers[0], false, Stage::Killer2),
+                        Stage::Killer2 => (
+                            self.killers[1],
+                            self.killers[1] == self.killers[0],
+                            Stage::CounterMove,
+                        ),
+                        _ => (
+                            self.counter,
+                            self.counter == self.killers[0] || self.counter == self.killers[1],
+                            Stage::GenQuiet,
+                        ),
+                    };
+                    self.stage = next;
+                    if let Some(m) = m
+                        && !repeat
+                        && Some(m) != self.tt_move
+                        && !m.is_capture()
+                        && m.promotion().is_none()
+                        && b.is_legal(m)
+                    {
+                        return Some(m);
+                    }
+                }
+                Stage::GenQuiet => {
+                    let mut moves = MoveList::new();
+                    b.generate_legal(&mut moves, GenKind::Quiet);
+                    self.moves.clear();
+                    self.moves.extend(
+                        moves
+                            .into_iter()
+                            .filter(|&m| {
+                                Some(m) != self.tt_move
+                                    && Some(m) != self.killers[0]
+                                    && Some(m) != self.killers[1]
+                                    && Some(m) != self.counter
+                            })
+                            .map(|m| {
+                                let (piece, to) =
+                                    (b.piece_on[m.from().index()].index(), m.to().index());
+                                let cont = cont_history.map_or(0, |h| h[piece][to]);
+                                (m, history[piece][to] + cont)
+                            }),
+                    );
+                    self.next = 0;
+                    self.stage = Stage::Quiet;
+                }
+                Stage::Quiet => {
+                    if let Some(m) = self.pick_best() {
+                        return Some(m);
+                    }
+                    self.stage = self.start_bad_noisy();
+                }
+                Stage::BadNoisy => {
+                    if let Some(m) = self.pick_best() {
+                        return Some(m);
+                    }
+                    self.stage = Stage::Done;
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    fn start_bad_noisy(&mut self) -> Stage {
+        self.moves = std::mem::take(&mut self.bad_noisy);
+        self.next = 0;
+        Stage::BadNoisy
+    }
+
+    /// Selection sort step: moves the best remaining move to the front.
+    fn pick_best(&mut self) -> Option<Move> {
+        let rest = self.moves.get_mut(self.next..)?;
+        let best = rest
+            .iter()
+            .enumerate()
+            .max_by_key(|&(i, &(_, score))| (score, std::cmp::Reverse(i)))?
+            .0;
+        rest.swap(0, best);
+        self.next += 1;
+        Some(rest[0].0)
+    }
+}
+
+/// Ordering score of a capture or promotion and whether it belongs with the
+/// good moves: captures that don't lose material by SEE and queen promotions.
+fn noisy_score(b: &Board, m: Move) -> (i32, bool) {
+    let promo = m.promotion().map_or(0, |k| {
+        PIECE_VALUES[k as usize] - PIECE_VALUES[PieceKind::Pawn as usize]
+    });
+    if m.is_capture() {
+        let see_val = see(b, m);
+        (see_val + promo, see_val >= 0)
+    } else {
+        (promo, m.promotion() == Some(PieceKind::Queen))
+    }
+}
@@ -1,13 +1,12 @@
 use crate::board::Board;
+use crate::nnue_simd;
 use crate::types::{Color, Piece, PieceKind};
 use byteorder::{LittleEndian, ReadBytesExt};
-use once_cell::sync::OnceCell;
 use std::error::Error;
 use std::fmt;
-use std::io::{BufReader, Cursor, Read, Seek};
-
-#[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::*;
+use std::io::{Cursor, Read};
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, Ordering};
 
 const FEATURE_TRANSFORMER_HALF_DIMENSIONS: usize = 256;
 const SQUARE_NB: usize = 64;
@@ -16,6 +15,24 @@ const HL1_INPUT_DIM: usize = 512;
 const HL1_OUTPUT_DIM: usize = 32;
 const HL2_OUTPUT_DIM: usize = 32;
 
+const HALFKP_FT_HASH: u32 = (0x5D69D5B9_u32 ^ 1) ^ (2 * FEATURE_TRANSFORMER_HALF_DIMENSIONS as u32);
+
+// HalfKAv2_hm: every piece including both kings, relative to the own king's
+// square mirrored onto files e-h and bucketed into 32 regions.
+const HALFKA_HASH: u32 = 0x7F234CB8;
+const HALFKA_PS_NB: usize = 11 * SQUARE_NB;
+const HALFKA_INPUT_DIM: usize = 32 * HALFKA_PS_NB;
+/// Feature transformer widths of released HalfKAv2_hm nets.
+const HALFKA_DIMENSIONS: [usize; 7] = [512, 768, 1024, 1536, 2048, 2560, 3072];
+const HALFKA_MAX_DIM: usize = 3072;
+const LAYER_STACKS: usize = 8;
+const FC0_OUTPUTS: usize = 15;
+const FC1_INPUT_DIM: usize = 32; // 2 * FC0_OUTPUTS, padded
+const FC1_OUTPUTS: usize = 32;
+const OUTPUT_SCALE: i32 = 16;
+const WEIGHT_SCALE_BITS: u32 = 6;
+
+/// The HalfKP network. Its accumulators are rebuilt at every evaluation.
 pub struct Model {
     ft_weights: Vec<i16>,
     ft_biases: Vec<i16>,
@@ -27,7 +44,63 @@ pub struct Model {
     out_bias: i32,
 }
 
-static MODEL: OnceCell<Model> = OnceCell::new();
+/// One subnetwork per piece-count bucket.
+struct LayerStack {
+    fc0_biases: [i32; FC0_OUTPUTS + 1],
+    fc0_weights: Vec<i8>,
+    fc1_biases: [i32; FC1_OUTPUTS],
+    fc1_weights: Vec<i8>,
+    fc2_bias: i32,
+    fc2_weights: [i8; FC1_OUTPUTS],
+}
+
+/// A HalfKAv2_hm network in the SFNNv5 layout: a feature transformer with
+/// PSQT outputs, then eight layer stacks selected by piece count.
+pub struct HalfKaModel {
+    half_dims: usize,
+    ft_biases: Vec<i16>,
+    ft_weights: Vec<i16>,
+    psqt_weights: Vec<i32>,
+    stacks: Vec<LayerStack>,
+}
+
+pub enum Network {
+    HalfKp(Model),
+    HalfKaV2Hm(HalfKaModel),
+}
+
+impl Network {
+    /// Architecture name for logs and `info string` lines.
+    pub fn architecture(&self) -> String {
+        match self {
+            Network::HalfKp(_) => "HalfKP 256x2-32-32".to_string(),
+            Network::HalfKaV2Hm(m) => format!("HalfKAv2_hm {}x2-15-32", m.half_dims),
+        }
+    }
+}
+
+// Set once at startup and replaced when another net is loaded. A replaced
+// network is leaked rather than freed: running searches may still read it,
+// and nets are only switched by hand.
+static NETWORK: AtomicPtr<Network> = AtomicPtr::new(ptr::null_mut());
+
+const NNUE_BYTES: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/nn-9931db908a9b.nnue"));
+
+/// The loaded network, loading the embedded one if [`init`] hasn't been
+/// called yet.
+fn network() -> &'static Network {
+    let mut net = NETWORK.load(Ordering::Acquire);
+    if net.is_null() {
+        match init() {
+            Ok(()) | Err(NnueError::AlreadyInitialized) => {}
+            // Only a corrupt download at build time gets here.
+            Err(e) => panic!("embedded network failed to load: {e}"),
+        }
+        net = NETWORK.load(Ordering::Acquire);
+    }
+    // SAFETY: non-null pointers come from `Box::into_raw` and are never freed.
+    unsafe { &*net }
+}
 
 #[derive(Debug)]
 pub enum NnueError {
@@ -61,10 +134,36 @@ impl From<std::io::Error> for NnueError {
     }
 }
 
-/// Initializes the NNUE model from the given file path.
+/// Loads the embedded network.
 pub fn init() -> Result<(), NnueError> {
-    const NNUE_BYTES: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/nn-9931db908a9b.nnue"));
-    let mut reader = BufReader::new(Cursor::new(NNUE_BYTES));
+    let net = Box::into_raw(Box::new(parse(NNUE_BYTES)?));
+    NETWORK
+        .compare_exchange(ptr::null_mut(), net, Ordering::AcqRel, Ordering::Acquire)
+        .map_err(|_| {
+            // SAFETY: `net` was never published.
+            drop(unsafe { Box::from_raw(net) });
+            NnueError::AlreadyInitialized
+        })?;
+    Ok(())
+}
+
+/// Replaces the network with the one in `path`, or with the embedded one when
+/// `path` is empty, and returns its architecture.
+pub fn load_eval_file(path: &str) -> Result<String, NnueError> {
+    let net = if path.is_empty() {
+        parse(NNUE_BYTES)?
+    } else {
+        parse(&std::fs::read(path)?)?
+    };
+    let arch = net.architecture();
+    NETWORK.store(Box::into_raw(Box::new(net)), Ordering::Release);
+    Ok(arch)
+}
+
+/// Reads a network file, picking the architecture from the feature
+/// transformer hash that follows the header.
+fn parse(bytes: &[u8]) -> Result<Network, NnueError> {
+    let mut reader = Cursor::new(bytes);
 
     // Read headers and metadata
     let _version = reader.read_u32::<LittleEndian>()?;
@@ -73,15 +172,30 @@ pub fn init() -> Result<(), NnueError> {
     let mut desc_bytes = vec![0u8; desc_size];
     reader.read_exact(&mut desc_bytes)?;
 
-    // Feature Transformer Weights and Biases
     let ft_header = reader.read_u32::<LittleEndian>()?;
-    let expected_ft_hash = (0x5D69D5B9_u32 ^ 1) ^ (2 * FEATURE_TRANSFORMER_HALF_DIMENSIONS as u32);
-    if ft_header != expected_ft_hash {
+    let net = if ft_header == HALFKP_FT_HASH {
+        Network::HalfKp(read_halfkp(&mut reader)?)
+    } else if let Some(&dims) = HALFKA_DIMENSIONS
+        .iter()
+        .find(|&&d| ft_header == HALFKA_HASH ^ (2 * d as u32))
+    {
+        Network::HalfKaV2Hm(read_halfka(&mut reader, dims)?)
+    } else {
+        return Err(NnueError::ValueError(format!(
+            "Unsupported network: feature transformer hash {ft_header:#010x}"
+        )));
+    };
+
+    if reader.position() != bytes.len() as u64 {
         return Err(NnueError::ValueError(
-            "Feature transformer header does not match expected hash!".to_string(),
+            "Did not read all parameters from NNUE file!".to_string(),
         ));
     }
+    Ok(net)
+}
 
+fn read_halfkp(reader: &mut Cursor<&[u8]>) -> Result<Model, NnueError> {
+    // Feature Transformer Weights and Biases
     let mut ft_biases = vec![0i16; FEATURE_TRANSFORMER_HALF_DIMENSIONS];
     reader.read_i16_into::<LittleEndian>(&mut ft_biases)?;
     let ft_weights_count = FEATURE_TRANSFORMER_HALF_DIMENSIONS * FT_INPUT_DIM;
@@ -108,15 +222,7 @@ pub fn init() -> Result<(), NnueError> {
     let mut out_weights = vec![0i8; HL2_OUTPUT_DIM];
     reader.read_i8_into(&mut out_weights)?;
 
-    let current_pos = reader.stream_position()?;
-    let end_pos = reader.get_ref().get_ref().len() as u64;
-    if end_pos - current_pos != 0 {
-        return Err(NnueError::ValueError(
-            "Did not read all parameters from NNUE file!".to_string(),
-        ));
-    }
-
-    let model = Model {
+    Ok(Model {
         ft_weights,
         ft_biases,
         hl1_weights,
@@ -125,20 +231,126 @@ pub fn init() -> Result<(), NnueError> {
         hl2_biases,
         out_weights,
         out_bias,
+    })
+}
+
+const LEB128_MAGIC: &[u8] = b"COMPRESSED_LEB128";
+
+/// Reads `n` little-endian integers, or a signed LEB128 block when the data
+/// starts with the compression marker newer nets use.
+fn read_ints<T>(reader: &mut Cursor<&[u8]>, n: usize) -> Result<Vec<T>, NnueError>
+where
+    T: TryFrom<i64>,
+{
+    let bytes = *reader.get_ref();
+    let pos = reader.position() as usize;
+    let narrow = |v: i64| {
+        T::try_from(v).map_err(|_| NnueError::ValueError(format!("Parameter {v} out of range")))
     };
 
-    MODEL
-        .set(model)
-        .map_err(|_| NnueError::AlreadyInitialized)?;
-    Ok(())
+    if !bytes[pos.min(bytes.len())..].starts_with(LEB128_MAGIC) {
+        let width = std::mem::size_of::<T>();
+        let mut out = Vec::with_capacity(n);
+        for _ in 0..n {
+            let v = match width {
+                2 => reader.read_i16::<LittleEndian>()? as i64,
+                _ => reader.read_i32::<LittleEndian>()? as i64,
+            };
+            out.push(narrow(v)?);
+        }
+        return Ok(out);
+    }
+
+    reader.set_position((pos + LEB128_MAGIC.len()) as u64);
+    let len = reader.read_u32::<LittleEndian>()? as usize;
+    let start = reader.position() as usize;
+    let block = bytes
+        .get(start..start + len)
+        .ok_or_else(|| NnueError::ValueError("Truncated LEB128 block".to_string()))?;
+    let mut out = Vec::with_capacity(n);
+    let mut it = block.iter();
+    for _ in 0..n {
+        let (mut value, mut shift) = (0i64, 0);
+        loop {
+            let &byte = it
+                .next()
+                .ok_or_else(|| NnueError::ValueError("Truncated LEB128 block".to_string()))?;
+            value |= ((byte & 0x7F) as i64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                if shift < 64 && byte & 0x40 != 0 {
+                    value |= -1 << shift;
+                }
+                break;
+            }
+        }
+        out.push(narrow(value)?);
+    }
+    if it.len() != 0 {
+        return Err(NnueError::ValueError(
+            "LEB128 block longer than its parameters".to_string(),
+        ));
+    }
+    reader.set_position((start + len) as u64);
+    Ok(out)
+}
+
+fn read_halfka(reader: &mut Cursor<&[u8]>, half_dims: usize) -> Result<HalfKaModel, NnueError> {
+    let ft_biases = read_ints::<i16>(reader, half_dims)?;
+    let ft_weights = read_ints::<i16>(reader, half_dims * HALFKA_INPUT_DIM)?;
+    let psqt_weights = read_ints::<i32>(reader, LAYER_STACKS * HALFKA_INPUT_DIM)?;
+
+    let mut stacks = Vec::with_capacity(LAYER_STACKS);
+    for _ in 0..LAYER_STACKS {
+        let _stack_hash = reader.read_u32::<LittleEndian>()?;
+
+        let mut fc0_biases = [0i32; FC0_OUTPUTS + 1];
+        reader.read_i32_into::<LittleEndian>(&mut fc0_biases)?;
+        let mut fc0_weights = vec![0i8; (FC0_OUTPUTS + 1) * half_dims];
+        reader.read_i8_into(&mut fc0_weights)?;
+
+        let mut fc1_biases = [0i32; FC1_OUTPUTS];
+        reader.read_i32_into::<LittleEndian>(&mut fc1_biases)?;
+        let mut fc1_weights = vec![0i8; FC1_OUTPUTS * FC1_INPUT_DIM];
+        reader.read_i8_into(&mut fc1_weights)?;
+
+        let fc2_bias = reader.read_i32::<LittleEndian>()?;
+        let mut fc2_weights = [0i8; FC1_OUTPUTS];
+        reader.read_i8_into(&mut fc2_weights)?;
+
+        stacks.push(LayerStack {
+            fc0_biases,
+            fc0_weights,
+            fc1_biases,
+            fc1_weights,
+            fc2_bias,
+            fc2_weights,
+        });
+    }
+
+    Ok(HalfKaModel {
+        half_dims,
+        ft_biases,
+        ft_weights,
+        psqt_weights,
+        stacks,
+    })
+}
+
+/// Architecture of the loaded network.
+pub fn architecture() -> String {
+    network().architecture()
 }
 
 /// Evaluates the board position using the loaded NNUE model.
 pub fn evaluate(board: &Board) -> i32 {
-    let model = MODEL
-        .get()
-        .expect("NNUE model not initialized! Call init() first.");
+    match network() {
+        Network::HalfKp(model) => evaluate_halfkp(model, board),
+        Network::HalfKaV2Hm(model) => model.evaluate(board),
+    }
+}
 
+fn evaluate_halfkp(model: &Model, board: &Board) -> i32 {
     let is_white_turn = board.turn == Color::White;
 
     // Get features from both points of view
@@ -149,10 +361,15 @@ pub fn evaluate(board: &Board) -> i32 {
     let features_them = &indices_them_array[..count_them];
 
     // Apply feature transformer
-    let ft_us =
-        unsafe { feature_transformer_simd(features_us, &model.ft_weights, &model.ft_biases) };
-    let ft_them =
-        unsafe { feature_transformer_simd(features_them, &model.ft_weights, &model.ft_biases) };
+    let mut ft_us = [0i32; FEATURE_TRANSFORMER_HALF_DIMENSIONS];
+    let mut ft_them = [0i32; FEATURE_TRANSFORMER_HALF_DIMENSIONS];
+    nnue_simd::feature_transform(features_us, &model.ft_weights, &model.ft_biases, &mut ft_us);
+    nnue_simd::feature_transform(
+        features_them,
+        &model.ft_weights,
+        &model.ft_biases,
+        &mut ft_them,
+    );
 
     // Concatenate features for the first dense layer
     let mut concat_features = [0i32; HL1_INPUT_DIM];
@@ -180,6 +397,126 @@ pub fn evaluate(board: &Board) -> i32 {
     nn_value_to_centipawn(out_value)
 }
 
+impl HalfKaModel {
+    fn evaluate(&self, board: &Board) -> i32 {
+        let dims = self.half_dims;
+        let us = board.turn;
+        let pieces = board.all_pieces.count_ones() as usize;
+        let bucket = (pieces.max(1) - 1) / 4;
+
+        let mut acc_us = [0i16; HALFKA_MAX_DIM];
+        let mut acc_them = [0i16; HALFKA_MAX_DIM];
+        let psqt_us = self.accumulate(board, us, &mut acc_us[..dims]);
+        let psqt_them = self.accumulate(board, us.other(), &mut acc_them[..dims]);
+
+        // Pairwise products of the two halves of each accumulator, side to
+        // move first.
+        let mut transformed = [0u8; HALFKA_MAX_DIM];
+        let half = dims / 2;
+        for (p, acc) in [&acc_us, &acc_them].into_iter().enumerate() {
+            for j in 0..half {
+                let a = acc[j].clamp(0, 127) as i32;
+                let b = acc[j + half].clamp(0, 127) as i32;
+                transformed[p * half + j] = (a * b / 128) as u8;
+            }
+        }
+
+        let psqt = (psqt_us[bucket] - psqt_them[bucket]) / 2;
+        let positional = self.stacks[bucket].propagate(&transformed[..dims]);
+        floor_div((psqt + positional) / OUTPUT_SCALE * 100, 208)
+    }
+
+    /// Fills `acc` with the feature transformer output for `perspective` and
+    /// returns its PSQT outputs.
+    fn accumulate(
+        &self,
+        board: &Board,
+        perspective: Color,
+        acc: &mut [i16],
+    ) -> [i32; LAYER_STACKS] {
+        let dims = self.half_dims;
+        acc.copy_from_slice(&self.ft_biases);
+        let mut psqt = [0i32; LAYER_STACKS];
+
+        let king_sq = board.king_index(perspective);
+        let mut bb = board.all_pieces;
+        while bb != 0 {
+            let sq = bb.trailing_zeros() as usize;
+            bb &= bb - 1;
+            let idx = halfka_index(perspective, king_sq, sq, board.piece_on[sq]);
+            let weights = &self.ft_weights[idx * dims..(idx + 1) * dims];
+            for (a, &w) in acc.iter_mut().zip(weights) {
+                *a = a.wrapping_add(w);
+            }
+            for (p, &w) in psqt
+                .iter_mut()
+                .zip(&self.psqt_weights[idx * LAYER_STACKS..(idx + 1) * LAYER_STACKS])
+            {
+                *p += w;
+            }
+        }
+        psqt
+    }
+}
+
+impl LayerStack {
+    fn propagate(&self, input: &[u8]) -> i32 {
+        let dims = input.len();
+        let mut fc0 = self.fc0_biases;
+        for (i, out) in fc0.iter_mut().enumerate() {
+            let weights = &self.fc0_weights[i * dims..(i + 1) * dims];
+            *out += input
+                .iter()
+                .zip(weights)
+                .map(|(&x, &w)| x as i32 * w as i32)
+                .sum::<i32>();
+        }
+
+        // Squared and plain clipped activations side by side.
+        let mut hidden = [0i32; FC1_INPUT_DIM];
+        for i in 0..FC0_OUTPUTS {
+            let x = fc0[i] as i64;
+            hidden[i] = ((x * x) >> (2 * WEIGHT_SCALE_BITS + 7)).min(127) as i32;
+            hidden[FC0_OUTPUTS + i] = (fc0[i] >> WEIGHT_SCALE_BITS).clamp(0, 127);
+        }
+
+        let mut out = self.fc2_bias;
+        for i in 0..FC1_OUTPUTS {
+            let weights = &self.fc1_weights[i * FC1_INPUT_DIM..(i + 1) * FC1_INPUT_DIM];
+            let sum = self.fc1_biases[i]
+                + hidden
+                    .iter()
+                    .zip(weights)
+                    .map(|(&x, &w)| x * w as i32)
+                    .sum::<i32>();
+            out += (sum >> WEIGHT_SCALE_BITS).clamp(0, 127) * self.fc2_weights[i] as i32;
+        }
+
+        // The last fc0 output skips the hidden layers.
+        let forward = fc0[FC0_OUTPUTS] * (600 * OUTPUT_SCALE) / (127 * (1 << WEIGHT_SCALE_BITS));
+        out + forward
+    }
+}
+
+/// HalfKAv2_hm feature index of the piece on `sq`. The board is seen from
+/// `perspective` and mirrored so its king stands on files e-h.
+#[inline]
+fn halfka_index(perspective: Color, king_sq: usize, sq: usize, piece: Piece) -> usize {
+    let rank_flip = if perspective == Color::White { 0 } else { 56 };
+    let file_flip = if king_sq % 8 < 4 { 7 } else { 0 };
+    let flip = rank_flip ^ file_flip;
+    let king = king_sq ^ flip;
+    let bucket = (7 - king / 8) * 4 + (7 - king % 8);
+
+    let kind = piece.kind().unwrap();
+    let plane = if kind == PieceKind::King {
+        10
+    } else {
+        kind as usize * 2 + (piece.color() != Some(perspective)) as usize
+    };
+    (sq ^ flip) + plane * SQUARE_NB + bucket * HALFKA_PS_NB
+}
+
 /// Generates the list of active feature indices for one side.
 #[inline]
 fn get_halfkp_indices(board: &Board, is_white_pov: bool) -> ([usize; 32], usize) {
@@ -191,7 +528,7 @@ fn get_halfkp_indices(board: &Board, is_white_pov: bool) -> ([usize; 32], usize)
     } else {
         Color::Black
     };
-    let king_sq = board.king_square(us) as usize;
+    let king_sq = board.king_index(us);
     let king_oriented = orient(is_white_pov, king_sq);
 
     for sq in 0..64 {
@@ -248,138 +585,6 @@ fn piece_square_from_piece(piece_kind: PieceKind, piece_color: Color, is_white_p
     (piece_offset * 2 + color_offset) * SQUARE_NB + 1
 }
 
-#[allow(dead_code)]
-fn feature_transformer(
-    indices: &[usize],
-    ft_weights: &[i16],
-    ft_biases: &[i16],
-) -> [i32; FEATURE_TRANSFORMER_HALF_DIMENSIONS] {
-    let mut out = [0i32; FEATURE_TRANSFORMER_HALF_DIMENSIONS];
-    for i in 0..FEATURE_TRANSFORMER_HALF_DIMENSIONS {
-        out[i] = ft_biases[i] as i32;
-    }
-    for &idx in indices {
-        let base = idx * FEATURE_TRANSFORMER_HALF_DIMENSIONS;
-        for i in 0..FEATURE_TRANSFORMER_HALF_DIMENSIONS {
-            out[i] += ft_weights[base + i] as i32;
-        }
-    }
-    for v in &mut out {
-        *v = (*v).clamp(0, 127);
-    }
-    out
-}
-
-#[cfg(target_arch = "x86_64")]
-#[target_feature(enable = "avx2")]
-fn feature_transformer_simd(
-    indices: &[usize],
-    ft_weights: &[i16],
-    ft_biases: &[i16],
-) -> [i32; FEATURE_TRANSFORMER_HALF_DIMENSIONS] {
-    let mut out = [0i32; FEATURE_TRANSFORMER_HALF_DIMENSIONS];
-    let mut out_vecs: Vec<__m256i> = Vec::with_capacity(FEATURE_TRANSFORMER_HALF_DIMENSIONS / 8);
-
-    // Initialize with biases
-    let mut i = 0;
-    while i < FEATURE_TRANSFORMER_HALF_DIMENSIONS {
-        unsafe {
-            let bias_ptr = ft_biases.as_ptr().add(i);
-            let bias_i16 = _mm_loadu_si128(bias_ptr as *const __m128i);
-            out_vecs.push(_mm256_cvtepi16_epi32(bias_i16));
-        }
-        i += 8;
-    }
-
-    // Accumulate weights
-    for &idx in indices {
-        let base = idx * FEATURE_TRANSFORMER_HALF_DIMENSIONS;
-        let mut i = 0;
-        while i < FEATURE_TRANSFORMER_HALF_DIMENSIONS {
-            let out_vec = out_vecs[i / 8];
-            unsafe {
-                let wt_ptr = ft_weights.as_ptr().add(base + i);
-                let wt_i16 = _mm_loadu_si128(wt_ptr as *const __m128i);
-                let wt_vec = _mm256_cvtepi16_epi32(wt_i16);
-                let sum_vec = _mm256_add_epi32(out_vec, wt_vec);
-                out_vecs[i / 8] = sum_vec;
-            }
-            i += 8;
-        }
-    }
-
-    // Clamp and store
-    let zero = _mm256_setzero_si256();
-    let max = _mm256_set1_epi32(127);
-    let mut i = 0;
-    while i < FEATURE_TRANSFORMER_HALF_DIMENSIONS {
-        let v = out_vecs[i / 8];
-        let clamped = _mm256_min_epi32(_mm256_max_epi32(v, zero), max);
-        unsafe {
-            _mm256_storeu_si256(out.as_mut_ptr().add(i) as *mut __m256i, clamped);
-        }
-        i += 8;
-    }
-    out
-}
-
-#[cfg(not(target_arch = "x86_64"))]
-fn feature_transformer_simd(
-    indices: &[usize],
-    ft_weights: &[i16],
-    ft_biases: &[i16],
-) -> [i32; FEATURE_TRANSFORMER_HALF_DIMENSIONS] {
-    feature_transformer(indices, ft_weights, ft_biases)
-}
-
-#[cfg(target_arch = "x86_64")]
-#[target_feature(enable = "avx2")]
-fn dot_product_avx2(input: &[i32], weights: &[i8]) -> i32 {
-    let len = input.len();
-    let mut i = 0;
-    let mut acc = _mm256_setzero_si256();
-
-    while i + 16 <= len {
-        unsafe {
-            let in_vec1 = _mm256_loadu_si256(input.as_ptr().add(i) as *const __m256i);
-            let wt_chunk1 = _mm_loadl_epi64(weights.as_ptr().add(i) as *const __m128i);
-            let wt_vec1 = _mm256_cvtepi8_epi32(wt_chunk1);
-
-            let in_vec2 = _mm256_loadu_si256(input.as_ptr().add(i + 8) as *const __m256i);
-            let wt_chunk2 = _mm_loadl_epi64(weights.as_ptr().add(i + 8) as *const __m128i);
-            let wt_vec2 = _mm256_cvtepi8_epi32(wt_chunk2);
-
-            let prod1 = _mm256_madd_epi16(
-                _mm256_packs_epi32(in_vec1, in_vec2),
-                _mm256_packs_epi32(wt_vec1, wt_vec2),
-            );
-            acc = _mm256_add_epi32(acc, prod1);
-        }
-        i += 16;
-    }
-
-    let mut acc_arr = [0i32; 8];
-    unsafe {
-        _mm256_storeu_si256(acc_arr.as_mut_ptr() as *mut __m256i, acc);
-    }
-    let mut sum = acc_arr.iter().sum();
-
-    while i < len {
-        sum += input[i] * (weights[i] as i32);
-        i += 1;
-    }
-    sum
-}
-
-#[cfg(not(target_arch = "x86_64"))]
-fn dot_product_avx2(input: &[i32], weights: &[i8]) -> i32 {
-    input
-        .iter()
-        .zip(weights.iter())
-        .map(|(&x, &w)| x * (w as i32))
-        .sum()
-}
-
 #[inline]
 fn dense_layer(
     input: &[i32],
@@ -391,7 +596,7 @@ fn dense_layer(
     let mut out = [0i32; HL1_OUTPUT_DIM];
     for j in 0..out_dim {
         let weight_slice = &weights[j * in_dim..(j + 1) * in_dim];
-        let sum = biases[j] + unsafe { dot_product_avx2(input, weight_slice) };
+        let sum = biases[j] + nnue_simd::dot(input, weight_slice);
         out[j] = nnue_relu(sum);
     }
     out
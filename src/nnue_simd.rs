@@ -0,0 +1,357 @@
+//! NNUE inner loops, with one kernel per instruction set. The best one the
+//! CPU supports is picked at runtime, so a single binary runs everywhere.
+
+use std::sync::OnceLock;
+
+#[cfg(target_arch = "aarch64")]
+use std::arch::aarch64::*;
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SimdLevel {
+    Scalar,
+    Ssse3,
+    Avx2,
+    Avx512Vnni,
+    Neon,
+}
+
+impl SimdLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            SimdLevel::Scalar => "scalar",
+            SimdLevel::Ssse3 => "ssse3",
+            SimdLevel::Avx2 => "avx2",
+            SimdLevel::Avx512Vnni => "avx512-vnni",
+            SimdLevel::Neon => "neon",
+        }
+    }
+}
+
+static LEVEL: OnceLock<SimdLevel> = OnceLock::new();
+
+/// The instruction set the kernels use on this CPU.
+pub fn level() -> SimdLevel {
+    *LEVEL.get_or_init(detect)
+}
+
+#[cfg(target_arch = "x86_64")]
+fn detect() -> SimdLevel {
+    if is_x86_feature_detected!("avx512f")
+        && is_x86_feature_detected!("avx512bw")
+        && is_x86_feature_detected!("avx512vnni")
+    {
+        SimdLevel::Avx512Vnni
+    } else if is_x86_feature_detected!("avx2") {
+        SimdLevel::Avx2
+    } else if is_x86_feature_detected!("ssse3") {
+        SimdLevel::Ssse3
+    } else {
+        SimdLevel::Scalar
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+fn detect() -> SimdLevel {
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        SimdLevel::Neon
+    } else {
+        SimdLevel::Scalar
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn detect() -> SimdLevel {
+    SimdLevel::Scalar
+}
+
+/// Sums the bias and the weight rows of the active features into `out`,
+/// clamped to `0..=127`. Rows are `out.len()` wide, a multiple of 32.
+pub fn feature_transform(indices: &[usize], weights: &[i16], biases: &[i16], out: &mut [i32]) {
+    // The kernels index without bounds checks, so these hold in release too.
+    assert!(out.len().is_multiple_of(32) && biases.len() == out.len());
+    assert!(
+        indices
+            .iter()
+            .all(|&idx| (idx + 1) * out.len() <= weights.len()),
+        "feature index out of range"
+    );
+    // SAFETY: `level` only reports instruction sets the CPU supports, and
+    // the asserts above keep every row the kernels read inside `weights`
+    // and every chunk they write inside `out`.
+    match level() {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx512Vnni => unsafe { transform_avx512(indices, weights, biases, out) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { transform_avx2(indices, weights, biases, out) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Ssse3 => unsafe { transform_ssse3(indices, weights, biases, out) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { transform_neon(indices, weights, biases, out) },
+        _ => transform_scalar(indices, weights, biases, out),
+    }
+}
+
+/// Dot product of activations in `0..=127` with a row of weights.
+pub fn dot(input: &[i32], weights: &[i8]) -> i32 {
+    assert!(weights.len() >= input.len());
+    // SAFETY: `level` only reports instruction sets the CPU supports; the
+    // kernels read `input.len()` items of both slices, which the assert
+    // above keeps in bounds.
+    match level() {
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx512Vnni => unsafe { dot_avx512(input, weights) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Avx2 => unsafe { dot_avx2(input, weights) },
+        #[cfg(target_arch = "x86_64")]
+        SimdLevel::Ssse3 => unsafe { dot_ssse3(input, weights) },
+        #[cfg(target_arch = "aarch64")]
+        SimdLevel::Neon => unsafe { dot_neon(input, weights) },
+        _ => dot_scalar(input, weights),
+    }
+}
+
+fn transform_scalar(indices: &[usize], weights: &[i16], biases: &[i16], out: &mut [i32]) {
+    let n = out.len();
+    for (o, &b) in out.iter_mut().zip(biases) {
+        *o = b as i32;
+    }
+    for &idx in indices {
+        for (o, &w) in out.iter_mut().zip(&weights[idx * n..(idx + 1) * n]) {
+            *o += w as i32;
+        }
+    }
+    for o in out {
+        *o = (*o).clamp(0, 127);
+    }
+}
+
+fn dot_scalar(input: &[i32], weights: &[i8]) -> i32 {
+    input.iter().zip(weights).map(|(&x, &w)| x * w as i32).sum()
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+fn transform_ssse3(indices: &[usize], weights: &[i16], biases: &[i16], out: &mut [i32]) {
+    let n = out.len();
+    let zero = _mm_setzero_si128();
+    let max = _mm_set1_epi32(127);
+    // SSSE3 has no 32-bit min/max, so clamp with compares.
+    let clamp = |v: __m128i| {
+        let v = _mm_and_si128(v, _mm_cmpgt_epi32(v, zero));
+        let over = _mm_cmpgt_epi32(v, max);
+        _mm_or_si128(_mm_andnot_si128(over, v), _mm_and_si128(over, max))
+    };
+    // Sign-extends the low and high four i16 lanes to i32.
+    let widen = |v: __m128i| {
+        (
+            _mm_srai_epi32(_mm_unpacklo_epi16(v, v), 16),
+            _mm_srai_epi32(_mm_unpackhi_epi16(v, v), 16),
+        )
+    };
+
+    for c in (0..n).step_by(8) {
+        unsafe {
+            let (mut lo, mut hi) = widen(_mm_loadu_si128(biases.as_ptr().add(c).cast()));
+            for &idx in indices {
+                let (wl, wh) = widen(_mm_loadu_si128(weights.as_ptr().add(idx * n + c).cast()));
+                lo = _mm_add_epi32(lo, wl);
+                hi = _mm_add_epi32(hi, wh);
+            }
+            _mm_storeu_si128(out.as_mut_ptr().add(c).cast(), clamp(lo));
+            _mm_storeu_si128(out.as_mut_ptr().add(c + 4).cast(), clamp(hi));
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "ssse3")]
+fn dot_ssse3(input: &[i32], weights: &[i8]) -> i32 {
+    let len = input.len();
+    let ones = _mm_set1_epi16(1);
+    let mut acc = _mm_setzero_si128();
+    let mut i = 0;
+    // Narrow 16 activations to bytes for the u8 x i8 multiply; the pair
+    // sums stay below i16::MAX.
+    while i + 16 <= len {
+        unsafe {
+            let load = |k: usize| _mm_loadu_si128(input.as_ptr().add(i + k).cast());
+            let x = _mm_packus_epi16(
+                _mm_packs_epi32(load(0), load(4)),
+                _mm_packs_epi32(load(8), load(12)),
+            );
+            let w = _mm_loadu_si128(weights.as_ptr().add(i).cast());
+            acc = _mm_add_epi32(acc, _mm_madd_epi16(_mm_maddubs_epi16(x, w), ones));
+        }
+        i += 16;
+    }
+
+    let acc = _mm_add_epi32(acc, _mm_shuffle_epi32(acc, 0b01_00_11_10));
+    let acc = _mm_add_epi32(acc, _mm_shuffle_epi32(acc, 0b10_11_00_01));
+    _mm_cvtsi128_si32(acc) + dot_scalar(&input[i..], &weights[i..])
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn transform_avx2(indices: &[usize], weights: &[i16], biases: &[i16], out: &mut [i32]) {
+    let n = out.len();
+    let zero = _mm256_setzero_si256();
+    let max = _mm256_set1_epi32(127);
+
+    for c in (0..n).step_by(16) {
+        unsafe {
+            let b = _mm256_loadu_si256(biases.as_ptr().add(c).cast());
+            let mut lo = _mm256_cvtepi16_epi32(_mm256_castsi256_si128(b));
+            let mut hi = _mm256_cvtepi16_epi32(_mm256_extracti128_si256(b, 1));
+            for &idx in indices {
+                let w = _mm256_loadu_si256(weights.as_ptr().add(idx * n + c).cast());
+                lo = _mm256_add_epi32(lo, _mm256_cvtepi16_epi32(_mm256_castsi256_si128(w)));
+                hi = _mm256_add_epi32(hi, _mm256_cvtepi16_epi32(_mm256_extracti128_si256(w, 1)));
+            }
+            let lo = _mm256_min_epi32(_mm256_max_epi32(lo, zero), max);
+            let hi = _mm256_min_epi32(_mm256_max_epi32(hi, zero), max);
+            _mm256_storeu_si256(out.as_mut_ptr().add(c).cast(), lo);
+            _mm256_storeu_si256(out.as_mut_ptr().add(c + 8).cast(), hi);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+fn dot_avx2(input: &[i32], weights: &[i8]) -> i32 {
+    let len = input.len();
+    let mut i = 0;
+    let mut acc = _mm256_setzero_si256();
+
+    while i + 16 <= len {
+        unsafe {
+            let in_vec1 = _mm256_loadu_si256(input.as_ptr().add(i).cast());
+            let wt_vec1 = _mm256_cvtepi8_epi32(_mm_loadl_epi64(weights.as_ptr().add(i).cast()));
+            let in_vec2 = _mm256_loadu_si256(input.as_ptr().add(i + 8).cast());
+            let wt_vec2 = _mm256_cvtepi8_epi32(_mm_loadl_epi64(weights.as_ptr().add(i + 8).cast()));
+
+            // Both packs interleave their 128-bit lanes the same way, so the
+            // pairs still line up.
+            let prod = _mm256_madd_epi16(
+                _mm256_packs_epi32(in_vec1, in_vec2),
+                _mm256_packs_epi32(wt_vec1, wt_vec2),
+            );
+            acc = _mm256_add_epi32(acc, prod);
+        }
+        i += 16;
+    }
+
+    let sum = _mm_add_epi32(
+        _mm256_castsi256_si128(acc),
+        _mm256_extracti128_si256(acc, 1),
+    );
+    let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b01_00_11_10));
+    let sum = _mm_add_epi32(sum, _mm_shuffle_epi32(sum, 0b10_11_00_01));
+    _mm_cvtsi128_si32(sum) + dot_scalar(&input[i..], &weights[i..])
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512bw")]
+fn transform_avx512(indices: &[usize], weights: &[i16], biases: &[i16], out: &mut [i32]) {
+    let n = out.len();
+    let zero = _mm512_setzero_si512();
+    let max = _mm512_set1_epi32(127);
+
+    for c in (0..n).step_by(32) {
+        unsafe {
+            let b = _mm512_loadu_si512(biases.as_ptr().add(c).cast());
+            let mut lo = _mm512_cvtepi16_epi32(_mm512_castsi512_si256(b));
+            let mut hi = _mm512_cvtepi16_epi32(_mm512_extracti64x4_epi64(b, 1));
+            for &idx in indices {
+                let w = _mm512_loadu_si512(weights.as_ptr().add(idx * n + c).cast());
+                lo = _mm512_add_epi32(lo, _mm512_cvtepi16_epi32(_mm512_castsi512_si256(w)));
+                hi = _mm512_add_epi32(hi, _mm512_cvtepi16_epi32(_mm512_extracti64x4_epi64(w, 1)));
+            }
+            let lo = _mm512_min_epi32(_mm512_max_epi32(lo, zero), max);
+            let hi = _mm512_min_epi32(_mm512_max_epi32(hi, zero), max);
+            _mm512_storeu_si512(out.as_mut_ptr().add(c).cast(), lo);
+            _mm512_storeu_si512(out.as_mut_ptr().add(c + 16).cast(), hi);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512bw,avx512vnni")]
+fn dot_avx512(input: &[i32], weights: &[i8]) -> i32 {
+    let len = input.len();
+    let mut acc = _mm512_setzero_si512();
+    let mut i = 0;
+
+    // The activations fit in a byte, so 64 of them at a time go through
+    // VNNI's u8 x i8 multiply-accumulate.
+    while i + 64 <= len {
+        unsafe {
+            let bytes = |k: usize| {
+                _mm512_cvtusepi32_epi8(_mm512_loadu_si512(input.as_ptr().add(i + 16 * k).cast()))
+            };
+            let x = _mm512_castsi128_si512(bytes(0));
+            let x = _mm512_inserti32x4(x, bytes(1), 1);
+            let x = _mm512_inserti32x4(x, bytes(2), 2);
+            let x = _mm512_inserti32x4(x, bytes(3), 3);
+            let w = _mm512_loadu_si512(weights.as_ptr().add(i).cast());
+            acc = _mm512_dpbusd_epi32(acc, x, w);
+        }
+        i += 64;
+    }
+    while i + 16 <= len {
+        unsafe {
+            let x = _mm512_loadu_si512(input.as_ptr().add(i).cast());
+            let w = _mm512_cvtepi8_epi32(_mm_loadu_si128(weights.as_ptr().add(i).cast()));
+            acc = _mm512_add_epi32(acc, _mm512_mullo_epi32(x, w));
+        }
+        i += 16;
+    }
+
+    _mm512_reduce_add_epi32(acc) + dot_scalar(&input[i..], &weights[i..])
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+fn transform_neon(indices: &[usize], weights: &[i16], biases: &[i16], out: &mut [i32]) {
+    let n = out.len();
+    let zero = vdupq_n_s32(0);
+    let max = vdupq_n_s32(127);
+
+    for c in (0..n).step_by(8) {
+        unsafe {
+            let b = vld1q_s16(biases.as_ptr().add(c));
+            let mut lo = vmovl_s16(vget_low_s16(b));
+            let mut hi = vmovl_high_s16(b);
+            for &idx in indices {
+                let w = vld1q_s16(weights.as_ptr().add(idx * n + c));
+                lo = vaddw_s16(lo, vget_low_s16(w));
+                hi = vaddw_high_s16(hi, w);
+            }
+            vst1q_s32(out.as_mut_ptr().add(c), vminq_s32(vmaxq_s32(lo, zero), max));
+            vst1q_s32(
+                out.as_mut_ptr().add(c + 4),
+                vminq_s32(vmaxq_s32(hi, zero), max),
+            );
+        }
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[target_feature(enable = "neon")]
+fn dot_neon(input: &[i32], weights: &[i8]) -> i32 {
+    let len = input.len();
+    let mut acc = vdupq_n_s32(0);
+    let mut i = 0;
+    while i + 8 <= len {
+        unsafe {
+            let w = vmovl_s8(vld1_s8(weights.as_ptr().add(i)));
+            let x_lo = vld1q_s32(input.as_ptr().add(i));
+            let x_hi = vld1q_s32(input.as_ptr().add(i + 4));
+            acc = vmlaq_s32(acc, x_lo, vmovl_s16(vget_low_s16(w)));
+            acc = vmlaq_s32(acc, x_hi, vmovl_high_s16(w));
+        }
+        i += 8;
+    }
+    vaddvq_s32(acc) + dot_scalar(&input[i..], &weights[i..])
+}
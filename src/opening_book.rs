@@ -1,12 +1,11 @@
 use crate::board::Board;
 use crate::polyglot_zobrist;
+use crate::square::Square;
 use crate::types::Move;
-use crate::uci_io::format_uci;
-use byteorder::{BigEndian, ReadBytesExt};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use std::fs::File;
-use std::io::{BufReader, Read};
-use std::path::PathBuf;
-use std::sync::{Mutex, OnceLock};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 
 // A simple random number generator
 struct Rng(u64);
@@ -22,11 +21,6 @@ impl Rng {
     }
 }
 
-static BOOK_RNG: OnceLock<Mutex<Rng>> = OnceLock::new();
-fn get_rng() -> &'static Mutex<Rng> {
-    BOOK_RNG.get_or_init(|| Mutex::new(Rng::new()))
-}
-
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct BookEntry {
@@ -37,40 +31,33 @@ pub struct BookEntry {
 }
 
 impl BookEntry {
-    fn to_move(self) -> Option<Move> {
-        use crate::types::{Move, PieceKind};
-
-        let from_sq = ((self.raw_move >> 6) & 0x3F) as u8;
-        let mut to_sq = (self.raw_move & 0x3F) as u8;
-        let promo_piece = (self.raw_move >> 12) & 0x7;
-
-        // Remap Polyglot castling encodings:
-        // e1->h1 (4->7)  => e1->g1 (4->6)
-        // e1->a1 (4->0)  => e1->c1 (4->2)
-        // e8->h8 (60->63)=> e8->g8 (60->62)
-        // e8->a8 (60->56)=> e8->c8 (60->58)
-        let mut is_castle = false;
-        match (from_sq, to_sq) {
-            (4, 7) => {
-                to_sq = 6;
-                is_castle = true;
-            } // white O-O
-            (4, 0) => {
-                to_sq = 2;
-                is_castle = true;
-            } // white O-O-O
-            (60, 63) => {
-                to_sq = 62;
-                is_castle = true;
-            } // black O-O
-            (60, 56) => {
-                to_sq = 58;
-                is_castle = true;
-            } // black O-O-O
-            _ => {}
-        }
+    /// Polyglot move encoding: castling is written as king-takes-rook.
+    pub fn encode_move(m: Move) -> u16 {
+        use crate::types::PieceKind;
+
+        let to = if m.is_castle() {
+            let rook_file = if m.to() > m.from() { 7 } else { 0 };
+            (m.to().index() & !7) | rook_file
+        } else {
+            m.to().index()
+        };
+        let promo = match m.promotion() {
+            Some(PieceKind::Knight) => 1,
+            Some(PieceKind::Bishop) => 2,
+            Some(PieceKind::Rook) => 3,
+            Some(PieceKind::Queen) => 4,
+            _ => 0,
+        };
+        (promo << 12) | ((m.from().index() as u16) << 6) | to as u16
+    }
+
+    /// The legal move in `b` this entry stands for, if any.
+    fn to_move(self, b: &Board) -> Option<Move> {
+        use crate::types::PieceKind;
 
-        let promo_kind = match promo_piece {
+        let from = Square::from_index(((self.raw_move >> 6) & 0x3F) as usize);
+        let mut to = Square::from_index((self.raw_move & 0x3F) as usize);
+        let promo = match (self.raw_move >> 12) & 0x7 {
             1 => Some(PieceKind::Knight),
             2 => Some(PieceKind::Bishop),
             3 => Some(PieceKind::Rook),
@@ -78,19 +65,26 @@ impl BookEntry {
             _ => None,
         };
 
-        let mut m = Move::quiet(from_sq, to_sq);
-        m.promotion = promo_kind;
-        m.castle = is_castle;
-        Some(m)
+        // King takes own rook is castling: e1h1 => e1g1, e1a1 => e1c1.
+        if b.piece_on[from.index()].kind() == Some(PieceKind::King)
+            && b.piece_on[to.index()].color() == b.piece_on[from.index()].color()
+        {
+            let file = if to > from { 6 } else { 2 };
+            to = Square::from_index((to.index() & !7) | file);
+        }
+
+        b.legal_moves()
+            .find(|m| m.from() == from && m.to() == to && m.promotion() == promo)
     }
 }
 
 pub struct OpeningBook {
     entries: Vec<BookEntry>,
+    rng: Rng,
 }
 
 impl OpeningBook {
-    fn new(path: &str) -> Result<Self, std::io::Error> {
+    pub fn load(path: &Path) -> Result<Self, std::io::Error> {
         let file = File::open(path)?;
         let mut reader = BufReader::new(file);
         let mut entries = Vec::new();
@@ -110,7 +104,18 @@ impl OpeningBook {
             });
         }
 
-        Ok(OpeningBook { entries })
+        Ok(OpeningBook {
+            entries,
+            rng: Rng::new(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
     }
 
     fn find_entries(&self, key: u64) -> &[BookEntry] {
@@ -128,75 +133,72 @@ impl OpeningBook {
             Err(_) => &[],
         }
     }
-}
-
-static BOOK: OnceLock<Option<OpeningBook>> = OnceLock::new();
-
-fn get_book() -> &'static Option<OpeningBook> {
-    BOOK.get_or_init(|| {
-        let book_filename = "moves/book.bin";
-        let mut potential_paths: Vec<PathBuf> = Vec::new();
-
-        if let Ok(mut exe_path) = std::env::current_exe() {
-            exe_path.pop(); // Remove the executable name to get the directory
-            potential_paths.push(exe_path.join(book_filename));
-        }
 
-        if let Ok(cwd) = std::env::current_dir() {
-            potential_paths.push(cwd.join(book_filename));
-        }
-
-        potential_paths.push(PathBuf::from("/home/will/projects/chess/moves/book.bin"));
-
-        if let Ok(exe_path) = std::env::current_exe()
-            && exe_path.to_string_lossy().contains("target")
-            && let Some(target_pos) = exe_path.to_string_lossy().find("target")
-        {
-            let project_root = PathBuf::from(&exe_path.to_string_lossy()[..target_pos]);
-            potential_paths.push(project_root.join(book_filename));
+    /// Picks a book move for `b`. `variety` runs from 0 (always the heaviest
+    /// entry) to 100 (chosen in proportion to the Polyglot weights); values in
+    /// between sharpen the distribution towards the heavier moves.
+    pub fn pick_move(&mut self, b: &Board, variety: u32) -> Option<Move> {
+        let random = self.rng.rand();
+        let key = polyglot_zobrist::calculate_key(b);
+        let entries = self.find_entries(key);
+        let heaviest = entries.iter().max_by_key(|e| e.weight)?;
+        if variety == 0 || heaviest.weight == 0 {
+            return heaviest.to_move(b);
         }
 
-        for path in potential_paths {
-            if let Ok(book) = OpeningBook::new(path.to_str().unwrap())
-                && path.exists()
-            {
-                println!("info string Loaded opening book from: {}", path.display());
-                return Some(book);
+        let exponent = 100.0 / variety.min(100) as f64;
+        let weights: Vec<f64> = entries
+            .iter()
+            .map(|e| (e.weight as f64 / heaviest.weight as f64).powf(exponent))
+            .collect();
+        let total: f64 = weights.iter().sum();
+        let mut target = (random >> 11) as f64 / (1u64 << 53) as f64 * total;
+        for (entry, w) in entries.iter().zip(&weights) {
+            if target < *w {
+                return entry.to_move(b);
             }
+            target -= w;
         }
-
-        println!(
-            "info string Opening book '{}' not found in any standard location.",
-            book_filename
-        );
-        None
-    })
+        heaviest.to_move(b)
+    }
 }
 
-pub fn get_book_move(b: &Board) -> Option<String> {
-    if let Some(book) = get_book() {
-        let key = polyglot_zobrist::calculate_key(b);
-        let entries = book.find_entries(key);
+/// Where to look for `moves/book.bin` when no `BookFile` is configured.
+pub fn default_book_path() -> Option<PathBuf> {
+    let book_filename = "moves/book.bin";
+    let mut potential_paths: Vec<PathBuf> = Vec::new();
 
-        if entries.is_empty() {
-            return None;
-        }
+    if let Ok(mut exe_path) = std::env::current_exe() {
+        exe_path.pop(); // Remove the executable name to get the directory
+        potential_paths.push(exe_path.join(book_filename));
+    }
 
-        let total_weight: u32 = entries.iter().map(|e| e.weight as u32).sum();
-        if total_weight == 0 {
-            return entries.first()?.to_move().map(format_uci);
-        }
+    if let Ok(cwd) = std::env::current_dir() {
+        potential_paths.push(cwd.join(book_filename));
+    }
 
-        let mut rng = get_rng().lock().unwrap();
-        let mut random_weight = rng.rand() as u32 % total_weight;
+    potential_paths.push(PathBuf::from("/home/will/projects/chess/moves/book.bin"));
 
-        for entry in entries {
-            if random_weight < entry.weight as u32 {
-                println!("info string Playing book move.");
-                return entry.to_move().map(format_uci);
-            }
-            random_weight -= entry.weight as u32;
-        }
+    if let Ok(exe_path) = std::env::current_exe()
+        && exe_path.to_string_lossy().contains("target")
+        && let Some(target_pos) = exe_path.to_string_lossy().find("target")
+    {
+        let project_root = PathBuf::from(&exe_path.to_string_lossy()[..target_pos]);
+        potential_paths.push(project_root.join(book_filename));
+    }
+
+    potential_paths.into_iter().find(|p| p.exists())
+}
+
+/// Writes entries in Polyglot's 16-byte big-endian layout. They must already
+/// be sorted by key, as readers binary-search the file.
+pub fn write_book(path: &Path, entries: &[BookEntry]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    for e in entries {
+        out.write_u64::<BigEndian>(e.key)?;
+        out.write_u16::<BigEndian>(e.raw_move)?;
+        out.write_u16::<BigEndian>(e.weight)?;
+        out.write_u32::<BigEndian>(e._learn)?;
     }
-    None
+    out.flush()
 }
@@ -0,0 +1,65 @@
+/// Rough playing strength assumed for the engine when comparing it with the
+/// rating reported through `UCI_Opponent`.
+pub const ENGINE_RATING: i32 = 2600;
+
+// Centipawns of contempt per 100 rating points of difference, and the cap.
+const CONTEMPT_PER_100_ELO: i32 = 5;
+const MAX_OPPONENT_CONTEMPT: i32 = 50;
+
+/// The opponent as described by `setoption name UCI_Opponent value
+/// <title> <rating> <computer|human> <name>`, where unknown fields are `none`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Opponent {
+    pub title: Option<String>,
+    pub rating: Option<i32>,
+    pub computer: bool,
+    pub name: String,
+}
+
+impl Opponent {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut parts = value.split_whitespace();
+        let (Some(title), Some(rating), Some(kind)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(format!(
+                "UCI_Opponent: expected '<title> <rating> <computer|human> <name>', got '{value}'"
+            ));
+        };
+
+        let title = (!title.eq_ignore_ascii_case("none")).then(|| title.to_string());
+        let rating = if rating.eq_ignore_ascii_case("none") {
+            None
+        } else {
+            Some(
+                rating
+                    .parse::<i32>()
+                    .map_err(|_| format!("UCI_Opponent: bad rating '{rating}'"))?,
+            )
+        };
+        let computer = match kind.to_ascii_lowercase().as_str() {
+            "computer" => true,
+            "human" => false,
+            _ => {
+                return Err(format!(
+                    "UCI_Opponent: expected computer or human, got '{kind}'"
+                ));
+            }
+        };
+
+        Ok(Self {
+            title,
+            rating,
+            computer,
+            name: parts.collect::<Vec<_>>().join(" "),
+        })
+    }
+
+    /// Contempt in centipawns: positive (avoid draws) against weaker
+    /// opponents, negative (accept draws) against stronger ones.
+    pub fn contempt(&self) -> i32 {
+        self.rating.map_or(0, |r| {
+            ((ENGINE_RATING - r) / 100 * CONTEMPT_PER_100_ELO)
+                .clamp(-MAX_OPPONENT_CONTEMPT, MAX_OPPONENT_CONTEMPT)
+        })
+    }
+}
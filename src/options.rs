@@ -0,0 +1,443 @@
+use crate::eval::{EvalMode, set_eval_mode};
+use crate::opponent::Opponent;
+use crate::output::{OutputFormat, set_output_format};
+use crate::search::{DEFAULT_ITERATION_SKIP_PERCENT, SearchConfig};
+use crate::skill::{DEFAULT_ELO, MAX_ELO, MIN_ELO, Skill};
+use crate::time::{DEFAULT_MAX_TIME_PERCENT, DEFAULT_MOVE_OVERHEAD_MS};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_HASH_MB: i64 = 256;
+/// 32 TB where the address space allows it, 2 GB on 32-bit targets.
+pub const MAX_HASH_MB: i64 = if cfg!(target_pointer_width = "64") {
+    33_554_432
+} else {
+    2048
+};
+pub const MAX_THREADS: i64 = 128;
+pub const DEFAULT_BOOK_DEPTH: i64 = 40;
+
+#[derive(Clone, Debug)]
+pub enum OptionKind {
+    Check {
+        default: bool,
+    },
+    Spin {
+        default: i64,
+        min: i64,
+        max: i64,
+    },
+    Combo {
+        default: &'static str,
+        vars: &'static [&'static str],
+    },
+    Str {
+        default: String,
+    },
+    Button,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OptionValue {
+    Check(bool),
+    Spin(i64),
+    Str(String),
+    Button,
+}
+
+impl fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionValue::Check(v) => write!(f, "{v}"),
+            OptionValue::Spin(v) => write!(f, "{v}"),
+            OptionValue::Str(v) => write!(f, "{v}"),
+            OptionValue::Button => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct EngineOption {
+    pub name: &'static str,
+    pub kind: OptionKind,
+    pub value: OptionValue,
+}
+
+impl EngineOption {
+    pub fn new(name: &'static str, kind: OptionKind) -> Self {
+        let value = match &kind {
+            OptionKind::Check { default } => OptionValue::Check(*default),
+            OptionKind::Spin { default, .. } => OptionValue::Spin(*default),
+            OptionKind::Combo { default, .. } => OptionValue::Str(default.to_string()),
+            OptionKind::Str { default } => OptionValue::Str(default.clone()),
+            OptionKind::Button => OptionValue::Button,
+        };
+        Self { name, kind, value }
+    }
+
+    /// The `option name ...` line advertised in response to `uci`. The current
+    /// value is reported as the default so config overrides reach the GUI.
+    pub fn uci_line(&self) -> String {
+        let head = format!("option name {}", self.name);
+        let current = match &self.value {
+            OptionValue::Str(v) if v.is_empty() => "<empty>".to_string(),
+            v => v.to_string(),
+        };
+        match &self.kind {
+            OptionKind::Check { .. } => format!("{head} type check default {current}"),
+            OptionKind::Spin { min, max, .. } => {
+                format!("{head} type spin default {current} min {min} max {max}")
+            }
+            OptionKind::Combo { vars, .. } => {
+                let vars: String = vars.iter().map(|v| format!(" var {v}")).collect();
+                format!("{head} type combo default {current}{vars}")
+            }
+            OptionKind::Str { .. } => format!("{head} type string default {current}"),
+            OptionKind::Button => format!("{head} type button"),
+        }
+    }
+
+    fn parse(&self, raw: &str) -> Result<OptionValue, String> {
+        let raw = raw.trim();
+        match &self.kind {
+            OptionKind::Check { .. } => match raw.to_ascii_lowercase().as_str() {
+                "true" | "1" | "on" => Ok(OptionValue::Check(true)),
+                "false" | "0" | "off" => Ok(OptionValue::Check(false)),
+                _ => Err(format!(
+                    "{}: expected true or false, got '{raw}'",
+                    self.name
+                )),
+            },
+            OptionKind::Spin { min, max, .. } => match raw.parse::<i64>() {
+                Ok(v) if (*min..=*max).contains(&v) => Ok(OptionValue::Spin(v)),
+                Ok(v) => Err(format!("{}: {v} is outside {min}..={max}", self.name)),
+                Err(_) => Err(format!("{}: expected an integer, got '{raw}'", self.name)),
+            },
+            OptionKind::Combo { vars, .. } => vars
+                .iter()
+                .find(|v| v.eq_ignore_ascii_case(raw))
+                .map(|v| OptionValue::Str(v.to_string()))
+                .ok_or_else(|| format!("{}: '{raw}' is not one of {vars:?}", self.name)),
+            OptionKind::Str { .. } => {
+                if raw == "<empty>" {
+                    Ok(OptionValue::Str(String::new()))
+                } else {
+                    Ok(OptionValue::Str(raw.to_string()))
+                }
+            }
+            OptionKind::Button => Ok(OptionValue::Button),
+        }
+    }
+}
+
+/// Registry of every tunable engine option. UCI `setoption`, the `--option`
+/// command line flag and config files all go through [`Options::set`].
+#[derive(Clone, Debug)]
+pub struct Options {
+    entries: Vec<EngineOption>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        let threads = (num_cpus::get() as i64).clamp(1, MAX_THREADS);
+        Self {
+            entries: vec![
+                EngineOption::new(
+                    "Hash",
+                    OptionKind::Spin {
+                        default: DEFAULT_HASH_MB,
+                        min: 1,
+                        max: MAX_HASH_MB,
+                    },
+                ),
+                EngineOption::new(
+                    "Threads",
+                    OptionKind::Spin {
+                        default: threads,
+                        min: 1,
+                        max: MAX_THREADS,
+                    },
+                ),
+                EngineOption::new("Ponder", OptionKind::Check { default: false }),
+                EngineOption::new("Clear Hash", OptionKind::Button),
+                EngineOption::new("OwnBook", OptionKind::Check { default: true }),
+                EngineOption::new(
+                    "BookFile",
+                    OptionKind::Str {
+                        default: String::new(),
+                    },
+                ),
+                EngineOption::new(
+                    "BookDepth",
+                    OptionKind::Spin {
+                        default: DEFAULT_BOOK_DEPTH,
+                        min: 0,
+                        max: 1000,
+                    },
+                ),
+                EngineOption::new(
+                    "BookVariety",
+                    OptionKind::Spin {
+                        default: 100,
+                        min: 0,
+                        max: 100,
+                    },
+                ),
+                EngineOption::new(
+                    "UCI_Opponent",
+                    OptionKind::Str {
+                        default: String::new(),
+                    },
+                ),
+                EngineOption::new(
+                    "GameLog",
+                    OptionKind::Str {
+                        default: String::new(),
+                    },
+                ),
+                EngineOption::new(
+                    "MoveOverhead",
+                    OptionKind::Spin {
+                        default: DEFAULT_MOVE_OVERHEAD_MS,
+                        min: 0,
+                        max: 5000,
+                    },
+                ),
+                EngineOption::new(
+                    "MaxTimePercent",
+                    OptionKind::Spin {
+                        default: DEFAULT_MAX_TIME_PERCENT,
+                        min: 1,
+                        max: 100,
+                    },
+                ),
+                EngineOption::new(
+                    "Contempt",
+                    OptionKind::Spin {
+                        default: 0,
+                        min: -100,
+                        max: 100,
+                    },
+                ),
+                EngineOption::new(
+                    "nodestime",
+                    OptionKind::Spin {
+                        default: 0,
+                        min: 0,
+                        max: 100_000,
+                    },
+                ),
+                EngineOption::new("UCI_ShowWDL", OptionKind::Check { default: false }),
+                EngineOption::new("UCI_LimitStrength", OptionKind::Check { default: false }),
+                EngineOption::new(
+                    "UCI_Elo",
+                    OptionKind::Spin {
+                        default: DEFAULT_ELO,
+                        min: MIN_ELO,
+                        max: MAX_ELO,
+                    },
+                ),
+                EngineOption::new(
+                    "OutputFormat",
+                    OptionKind::Combo {
+                        default: "uci",
+                        vars: OutputFormat::NAMES,
+                    },
+                ),
+                EngineOption::new(
+                    "EvalFile",
+                    OptionKind::Str {
+                        default: String::new(),
+                    },
+                ),
+                EngineOption::new(
+                    "LogFile",
+                    OptionKind::Str {
+                        default: String::new(),
+                    },
+                ),
+                EngineOption::new(
+                    "UseNNUE",
+                    OptionKind::Combo {
+                        default: "hybrid",
+                        vars: EvalMode::NAMES,
+                    },
+                ),
+                EngineOption::new(
+                    "IterationSkip",
+                    OptionKind::Spin {
+                        default: DEFAULT_ITERATION_SKIP_PERCENT as i64,
+                        min: 0,
+                        max: 1000,
+                    },
+                ),
+            ],
+        }
+    }
+}
+
+impl Options {
+    pub fn iter(&self) -> impl Iterator<Item = &EngineOption> {
+        self.entries.iter()
+    }
+
+    pub fn find(&self, name: &str) -> Option<&EngineOption> {
+        self.entries
+            .iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn uci_lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.entries.iter().map(EngineOption::uci_line)
+    }
+
+    /// Validates and stores `value`. Returns the canonical option name when the
+    /// stored value changed (buttons always count as a change), `None` otherwise.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<Option<&'static str>, String> {
+        let opt = self
+            .entries
+            .iter_mut()
+            .find(|o| o.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unknown option '{name}'"))?;
+        let parsed = opt.parse(value)?;
+        if parsed == opt.value && parsed != OptionValue::Button {
+            return Ok(None);
+        }
+        opt.value = parsed;
+        Ok(Some(opt.name))
+    }
+
+    /// Applies a `Name=Value` pair as given on the command line.
+    pub fn set_pair(&mut self, pair: &str) -> Result<Option<&'static str>, String> {
+        let (name, value) = pair
+            .split_once('=')
+            .ok_or_else(|| format!("expected NAME=VALUE, got '{pair}'"))?;
+        self.set(name.trim(), value)
+    }
+
+    /// Loads `Name = Value` lines from a config file; blank lines and `#`
+    /// comments are ignored.
+    pub fn load_file(&mut self, path: &Path) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            self.set_pair(line)
+                .map_err(|e| format!("{}:{}: {e}", path.display(), i + 1))?;
+        }
+        Ok(())
+    }
+
+    pub fn spin(&self, name: &str) -> i64 {
+        match self.find(name).map(|o| &o.value) {
+            Some(OptionValue::Spin(v)) => *v,
+            _ => panic!("option '{name}' is not a spin"),
+        }
+    }
+
+    pub fn check(&self, name: &str) -> bool {
+        match self.find(name).map(|o| &o.value) {
+            Some(OptionValue::Check(v)) => *v,
+            _ => panic!("option '{name}' is not a check"),
+        }
+    }
+
+    pub fn string(&self, name: &str) -> &str {
+        match self.find(name).map(|o| &o.value) {
+            Some(OptionValue::Str(v)) => v,
+            _ => panic!("option '{name}' is not a string or combo"),
+        }
+    }
+
+    pub fn hash_mb(&self) -> usize {
+        self.spin("Hash") as usize
+    }
+
+    pub fn threads(&self) -> usize {
+        self.spin("Threads") as usize
+    }
+
+    pub fn ponder(&self) -> bool {
+        self.check("Ponder")
+    }
+
+    /// JSONL file that finished games are appended to, if set.
+    pub fn game_log(&self) -> Option<PathBuf> {
+        match self.string("GameLog") {
+            "" => None,
+            path => Some(PathBuf::from(path)),
+        }
+    }
+
+    pub fn own_book(&self) -> bool {
+        self.check("OwnBook")
+    }
+
+    /// The configured book, or `None` to search the standard locations.
+    pub fn book_file(&self) -> Option<PathBuf> {
+        match self.string("BookFile") {
+            "" => None,
+            path => Some(PathBuf::from(path)),
+        }
+    }
+
+    /// Plies from the start of the game during which the book is used.
+    pub fn book_depth(&self) -> usize {
+        self.spin("BookDepth") as usize
+    }
+
+    pub fn book_variety(&self) -> u32 {
+        self.spin("BookVariety") as u32
+    }
+
+    pub fn move_overhead_ms(&self) -> i64 {
+        self.spin("MoveOverhead")
+    }
+
+    /// The most of the remaining clock one move may use, in percent.
+    pub fn max_time_percent(&self) -> i64 {
+        self.spin("MaxTimePercent")
+    }
+
+    /// Nodes per millisecond in `nodestime` mode; 0 searches on the clock.
+    pub fn nodestime(&self) -> i64 {
+        self.spin("nodestime")
+    }
+
+    /// The strength to play at, if `UCI_LimitStrength` is on.
+    pub fn skill(&self) -> Option<Skill> {
+        self.check("UCI_LimitStrength")
+            .then(|| Skill::from_elo(self.spin("UCI_Elo")))
+    }
+
+    pub fn opponent(&self) -> Result<Option<Opponent>, String> {
+        match self.string("UCI_Opponent") {
+            "" => Ok(None),
+            value => Opponent::parse(value).map(Some),
+        }
+    }
+
+    /// The per-search settings; the opponent's rating adjusts the
+    /// configured contempt.
+    pub fn search_config(&self) -> Result<SearchConfig, String> {
+        let opponent = self.opponent()?.map_or(0, |o| o.contempt());
+        Ok(SearchConfig {
+            contempt: self.spin("Contempt") as i32 + opponent,
+            show_wdl: self.check("UCI_ShowWDL"),
+            iteration_skip_percent: self.spin("IterationSkip") as u32,
+        })
+    }
+
+    /// Pushes the options read globally (evaluation mode, output format)
+    /// into their modules, and checks that the per-search ones are valid.
+    pub fn sync_search(&self) -> Result<(), String> {
+        set_eval_mode(EvalMode::parse(self.string("UseNNUE")).unwrap_or(EvalMode::Hybrid));
+        set_output_format(
+            OutputFormat::parse(self.string("OutputFormat")).unwrap_or(OutputFormat::Uci),
+        );
+        self.search_config().map(|_| ())
+    }
+}
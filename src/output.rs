@@ -0,0 +1,38 @@
+use crate::uci_io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How search info, `bestmove`, perft and eval results are printed: UCI
+/// text, or one JSON object per line for scripts and web frontends.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Uci,
+    Json,
+}
+
+impl OutputFormat {
+    /// Values of the `OutputFormat` option.
+    pub const NAMES: &'static [&'static str] = &["uci", "json"];
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "uci" => Some(OutputFormat::Uci),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+pub fn set_output_format(format: OutputFormat) {
+    JSON_OUTPUT.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON_OUTPUT.load(Ordering::Relaxed)
+}
+
+/// Prints `value` on a line of its own and flushes.
+pub fn emit(value: &serde_json::Value) {
+    uci_io::send(value);
+}
@@ -1,12 +1,18 @@
 use crate::board::Board;
-use crate::types::Move;
+use crate::movelist::MoveList;
+use crate::output;
+use crate::types::{Move, START_FEN, ZKey};
+use crate::uci_io::{self, format_uci};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
 
 fn perft_inner(b: &mut Board, depth: usize) -> u64 {
     if depth == 0 {
         return 1;
     }
 
-    let mut moves = Vec::<Move>::with_capacity(128);
+    let mut moves = MoveList::new();
     b.generate_legal_moves(&mut moves);
     if depth == 1 {
         return moves.len() as u64;
@@ -25,25 +31,216 @@ pub fn perft(b: &mut Board, depth: usize) -> u64 {
     perft_inner(b, depth)
 }
 
-pub fn divide(b: &mut Board, depth: usize) {
-    let mut moves = Vec::with_capacity(128);
+/// Perft split by root move, in move generation order.
+pub fn divide_map(b: &mut Board, depth: usize) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    let mut moves = MoveList::new();
     b.generate_legal_moves(&mut moves);
-    let mut total = 0u64;
 
+    moves
+        .into_iter()
+        .map(|m| {
+            let u = b.make_move(m);
+            let n = perft_inner(b, depth - 1);
+            b.unmake_move(m, u);
+            (m, n)
+        })
+        .collect()
+}
+
+/// Subtree counts keyed by position and depth, shared by all threads. Each
+/// slot stores `key ^ data` next to `data`, so a slot torn by a concurrent
+/// write fails the key check instead of returning a wrong count.
+pub struct PerftHash {
+    slots: Vec<(AtomicU64, AtomicU64)>,
+}
+
+impl PerftHash {
+    pub fn new(mb: usize) -> Self {
+        let len = (mb.max(1) << 20) / std::mem::size_of::<(AtomicU64, AtomicU64)>();
+        Self {
+            slots: (0..len)
+                .map(|_| (AtomicU64::new(0), AtomicU64::new(0)))
+                .collect(),
+        }
+    }
+
+    fn slot(&self, key: ZKey) -> &(AtomicU64, AtomicU64) {
+        &self.slots[(key % self.slots.len() as u64) as usize]
+    }
+
+    fn probe(&self, key: ZKey, depth: usize) -> Option<u64> {
+        let (check, data) = self.slot(key);
+        let data = data.load(Ordering::Relaxed);
+        (check.load(Ordering::Relaxed) ^ data == key && data & 0xFF == depth as u64)
+            .then_some(data >> 8)
+    }
+
+    fn store(&self, key: ZKey, depth: usize, nodes: u64) {
+        let (check, data) = self.slot(key);
+        let packed = nodes << 8 | depth as u64;
+        check.store(key ^ packed, Ordering::Relaxed);
+        data.store(packed, Ordering::Relaxed);
+    }
+}
+
+fn perft_hashed(b: &mut Board, depth: usize, hash: &PerftHash) -> u64 {
+    if depth <= 1 {
+        return perft_inner(b, depth);
+    }
+    if let Some(nodes) = hash.probe(b.zobrist, depth) {
+        return nodes;
+    }
+
+    let mut moves = MoveList::new();
+    b.generate_legal_moves(&mut moves);
+    let mut nodes = 0u64;
     for m in moves {
         let u = b.make_move(m);
-        let n = perft_inner(b, depth - 1);
+        nodes += perft_hashed(b, depth - 1, hash);
         b.unmake_move(m, u);
-        total += n;
-
-        let from_file = (m.from % 8) + b'a';
-        let from_rank = (m.from / 8) + b'1';
-        let to_file = (m.to % 8) + b'a';
-        let to_rank = (m.to / 8) + b'1';
-        println!(
-            "{}{}{}{}: {}",
-            from_file as char, from_rank as char, to_file as char, to_rank as char, n
-        );
-    }
-    println!("Total: {total}");
+    }
+    hash.store(b.zobrist, depth, nodes);
+    nodes
+}
+
+/// Perft for deep validation runs: root moves are shared out between
+/// `threads` workers, and subtrees are cached when a hash size is given.
+pub struct Perft {
+    threads: usize,
+    hash: Option<PerftHash>,
+}
+
+impl Perft {
+    /// `hash_mb` of 0 disables the cache.
+    pub fn new(threads: usize, hash_mb: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            hash: (hash_mb > 0).then(|| PerftHash::new(hash_mb)),
+        }
+    }
+
+    pub fn count(&self, b: &Board, depth: usize) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        self.divide(b, depth).iter().map(|&(_, n)| n).sum()
+    }
+
+    /// Like [`divide_map`], computed in parallel.
+    pub fn divide(&self, b: &Board, depth: usize) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return Vec::new();
+        }
+        let mut moves = MoveList::new();
+        b.generate_legal_moves(&mut moves);
+        let counts = Mutex::new(vec![0u64; moves.len()]);
+        let next = AtomicUsize::new(0);
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(moves.len()) {
+                scope.spawn(|| {
+                    let mut b = b.clone();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(&m) = moves.get(i) else { break };
+                        let u = b.make_move(m);
+                        let n = match &self.hash {
+                            Some(hash) => perft_hashed(&mut b, depth - 1, hash),
+                            None => perft_inner(&mut b, depth - 1),
+                        };
+                        b.unmake_move(m, u);
+                        counts.lock().unwrap_or_else(|e| e.into_inner())[i] = n;
+                    }
+                });
+            }
+        });
+
+        let counts = counts.into_inner().unwrap_or_else(|e| e.into_inner());
+        moves.into_iter().zip(counts).collect()
+    }
+}
+
+/// The standard perft positions with their known node counts from depth 1.
+pub const PERFT_SUITE: &[(&str, &str, &[u64])] = &[
+    (
+        "startpos",
+        START_FEN,
+        &[20, 400, 8_902, 197_281, 4_865_609, 119_060_324],
+    ),
+    (
+        "Kiwipete",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        &[48, 2_039, 97_862, 4_085_603, 193_690_690],
+    ),
+    (
+        "Position 3",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        &[14, 191, 2_812, 43_238, 674_624, 11_030_083],
+    ),
+    (
+        "Position 4",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        &[6, 264, 9_467, 422_333, 15_833_292],
+    ),
+    (
+        "Position 5",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        &[44, 1_486, 62_379, 2_103_487, 89_941_194],
+    ),
+    (
+        "Position 6",
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        &[46, 2_079, 89_890, 3_894_594, 164_075_551],
+    ),
+];
+
+/// Checks every [`PERFT_SUITE`] position up to `max_depth`, printing a line
+/// per position. Returns whether all of them matched.
+pub fn verify(perft: &Perft, max_depth: usize) -> bool {
+    let mut all_ok = true;
+    for &(name, fen, expected) in PERFT_SUITE {
+        let b = Board::from_fen(fen).expect("suite FENs are valid");
+        let depths = expected.len().min(max_depth);
+        let mismatch = (1..=depths)
+            .map(|d| (d, perft.count(&b, d), expected[d - 1]))
+            .find(|&(_, got, want)| got != want);
+        match mismatch {
+            None => println!("PASS {name} (depth 1-{depths})"),
+            Some((d, got, want)) => {
+                all_ok = false;
+                println!("FAIL {name} depth {d}: {got}, expected {want}");
+            }
+        }
+    }
+    all_ok
+}
+
+/// Prints per-move counts and their total, as text or JSON.
+pub fn print_divide(depth: usize, counts: &[(Move, u64)]) {
+    let total: u64 = counts.iter().map(|&(_, n)| n).sum();
+    if output::is_json() {
+        let moves: serde_json::Map<_, _> = counts
+            .iter()
+            .map(|&(m, n)| (format_uci(m), n.into()))
+            .collect();
+        output::emit(&serde_json::json!({
+            "type": "perft",
+            "depth": depth,
+            "nodes": total,
+            "moves": moves,
+        }));
+        return;
+    }
+    for &(m, n) in counts {
+        uci_io::send(format!("{}: {}", format_uci(m), n));
+    }
+    uci_io::send(format!("Total: {total}"));
+}
+
+pub fn divide(b: &mut Board, depth: usize) {
+    print_divide(depth, &divide_map(b, depth));
 }
@@ -0,0 +1,302 @@
+use crate::board::Board;
+use crate::san::resolve_san;
+use crate::types::{Move, START_FEN};
+use std::path::Path;
+
+/// A mainline or variation move with the annotations that followed it.
+#[derive(Clone, Debug)]
+pub struct PgnMove {
+    pub mv: Move,
+    pub san: String,
+    /// Numeric annotation glyphs; `!`, `?` and friends are mapped to 1..=6.
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+    /// Alternatives to this move, each played from the position before it.
+    pub variations: Vec<Vec<PgnMove>>,
+}
+
+#[derive(Clone)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    /// The `FEN` tag's position, or the standard start position.
+    pub start: Board,
+    pub moves: Vec<PgnMove>,
+    /// `1-0`, `0-1`, `1/2-1/2` or `*`.
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn mainline(&self) -> Vec<Move> {
+        self.moves.iter().map(|m| m.mv).collect()
+    }
+
+    /// The position after the last mainline move.
+    pub fn final_board(&self) -> Board {
+        let mut b = self.start.clone();
+        for m in &self.moves {
+            b.make_move(m.mv);
+        }
+        b
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    Open,
+    Close,
+    San(String),
+    Result(String),
+}
+
+fn suffix_nag(s: &str) -> Option<u8> {
+    match s {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '[' => {
+                chars.next();
+                let name: String = chars
+                    .by_ref()
+                    .skip_while(|ch| ch.is_whitespace())
+                    .take_while(|ch| !ch.is_whitespace())
+                    .collect();
+                if chars.by_ref().find(|&ch| ch == '"').is_none() {
+                    return Err(format!("malformed tag [{name}"));
+                }
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => value.extend(chars.next()),
+                        Some('"') => break,
+                        Some(ch) => value.push(ch),
+                        None => return Err(format!("unterminated tag [{name}")),
+                    }
+                }
+                chars.by_ref().find(|&ch| ch == ']');
+                tokens.push(Token::Tag(name, value));
+            }
+            '{' => {
+                chars.next();
+                let comment: String = chars.by_ref().take_while(|&ch| ch != '}').collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            ';' => {
+                let comment: String = chars
+                    .by_ref()
+                    .skip(1)
+                    .take_while(|&ch| ch != '\n')
+                    .collect();
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '%' => {
+                // Escape line, ignored.
+                chars.by_ref().take_while(|&ch| ch != '\n').for_each(drop);
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '$' => {
+                chars.next();
+                let mut digits = String::new();
+                while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    digits.push(d);
+                    chars.next();
+                }
+                let nag = digits.parse().map_err(|_| format!("bad NAG ${digits}"))?;
+                tokens.push(Token::Nag(nag));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || "{}();[$".contains(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                push_word(&mut tokens, &word)?;
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Classifies a bare word: result, move number, or SAN with an optional
+/// `!`/`?` suffix.
+fn push_word(tokens: &mut Vec<Token>, word: &str) -> Result<(), String> {
+    if matches!(word, "1-0" | "0-1" | "1/2-1/2" | "*") {
+        tokens.push(Token::Result(word.to_string()));
+        return Ok(());
+    }
+    // Move numbers may be glued to the move: `12.e4`, `12...Nf6`.
+    let word = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    if word.is_empty() {
+        return Ok(());
+    }
+    if let Some(nag) = suffix_nag(word) {
+        tokens.push(Token::Nag(nag));
+        return Ok(());
+    }
+    let san = word.trim_end_matches(['!', '?']);
+    tokens.push(Token::San(san.to_string()));
+    if let Some(nag) = suffix_nag(&word[san.len()..]) {
+        tokens.push(Token::Nag(nag));
+    }
+    Ok(())
+}
+
+/// Parses one line of play from `tokens[*pos..]` starting at `board`, up to
+/// the closing `)` of a variation, the result, or the next game's tags.
+fn parse_line(
+    tokens: &[Token],
+    pos: &mut usize,
+    mut board: Board,
+    result: &mut Option<String>,
+) -> Result<Vec<PgnMove>, String> {
+    let mut line: Vec<PgnMove> = Vec::new();
+    // Position before the last move, where its variations start.
+    let mut before = board.clone();
+
+    while let Some(token) = tokens.get(*pos) {
+        match token {
+            Token::Tag(..) => break,
+            Token::Close => {
+                *pos += 1;
+                break;
+            }
+            Token::Result(r) => {
+                *pos += 1;
+                *result = Some(r.clone());
+                break;
+            }
+            Token::San(san) => {
+                *pos += 1;
+                let mv =
+                    resolve_san(&board, san).map_err(|e| format!("{e} at {}", board.to_fen()))?;
+                before = board.clone();
+                board.make_move(mv);
+                line.push(PgnMove {
+                    mv,
+                    san: san.clone(),
+                    nags: Vec::new(),
+                    comment: None,
+                    variations: Vec::new(),
+                });
+            }
+            Token::Nag(n) => {
+                *pos += 1;
+                if let Some(last) = line.last_mut() {
+                    last.nags.push(*n);
+                }
+            }
+            Token::Comment(c) => {
+                *pos += 1;
+                if let Some(last) = line.last_mut() {
+                    match &mut last.comment {
+                        Some(existing) => {
+                            existing.push(' ');
+                            existing.push_str(c);
+                        }
+                        None => last.comment = Some(c.clone()),
+                    }
+                }
+            }
+            Token::Open => {
+                *pos += 1;
+                let mut ignored = None;
+                let variation = parse_line(tokens, pos, before.clone(), &mut ignored)?;
+                let last = line.last_mut().ok_or("variation before the first move")?;
+                last.variations.push(variation);
+            }
+        }
+    }
+    Ok(line)
+}
+
+fn parse_tokens(tokens: &[Token], pos: &mut usize) -> Result<PgnGame, String> {
+    let mut tags = Vec::new();
+    while let Some(Token::Tag(name, value)) = tokens.get(*pos) {
+        tags.push((name.clone(), value.clone()));
+        *pos += 1;
+    }
+
+    let fen = tags
+        .iter()
+        .find(|(n, _)| n == "FEN")
+        .map_or(START_FEN, |(_, v)| v.as_str());
+    let start = Board::from_fen(fen).map_err(|e| e.to_string())?;
+
+    let mut result = None;
+    let moves = parse_line(tokens, pos, start.clone(), &mut result)?;
+    let result = result
+        .or_else(|| {
+            tags.iter()
+                .find(|(n, _)| n == "Result")
+                .map(|(_, v)| v.clone())
+        })
+        .unwrap_or_else(|| "*".to_string());
+    Ok(PgnGame {
+        tags,
+        start,
+        moves,
+        result,
+    })
+}
+
+/// Parses the first game in `text`.
+pub fn parse_game(text: &str) -> Result<PgnGame, String> {
+    let tokens = tokenize(text)?;
+    parse_tokens(&tokens, &mut 0)
+}
+
+/// Parses every game in `text`.
+pub fn parse_games(text: &str) -> Result<Vec<PgnGame>, String> {
+    let tokens = tokenize(text)?;
+    let mut pos = 0;
+    let mut games = Vec::new();
+    while pos < tokens.len() {
+        let game = parse_tokens(&tokens, &mut pos)
+            .map_err(|e| format!("game {}: {e}", games.len() + 1))?;
+        // Stray tokens between games don't make a game of their own.
+        if !game.tags.is_empty() || !game.moves.is_empty() {
+            games.push(game);
+        }
+    }
+    Ok(games)
+}
+
+pub fn parse_file(path: &Path) -> Result<Vec<PgnGame>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    parse_games(&text)
+}
@@ -1,5 +1,5 @@
-const MG_MATERIAL: [i32; 6] = [82, 337, 365, 477, 1025, 0]; // P, N, B, R, Q, K
-const EG_MATERIAL: [i32; 6] = [94, 281, 297, 512, 936, 0]; // P, N, B, R, Q, K
+pub const MG_MATERIAL: [i32; 6] = [82, 337, 365, 477, 1025, 0]; // P, N, B, R, Q, K
+pub const EG_MATERIAL: [i32; 6] = [94, 281, 297, 512, 936, 0]; // P, N, B, R, Q, K
 
 const MG_PAWN_TABLE: [i32; 64] = [
     0, 0, 0, 0, 0, 0, 0, 0, 98, 134, 61, 95, 68, 126, 34, -11, -6, 7, 26, 31, 65, 56, 25, -20, -14,
@@ -0,0 +1,151 @@
+use crate::board::Board;
+use crate::square::{File, Rank, Square};
+use crate::types::{Move, PieceKind};
+
+fn piece_kind(c: char) -> Option<PieceKind> {
+    match c {
+        'N' => Some(PieceKind::Knight),
+        'B' => Some(PieceKind::Bishop),
+        'R' => Some(PieceKind::Rook),
+        'Q' => Some(PieceKind::Queen),
+        'K' => Some(PieceKind::King),
+        _ => None,
+    }
+}
+
+/// Finds the legal move a SAN string names, or `None` if it names no legal
+/// move or more than one.
+pub fn parse_san(b: &mut Board, san: &str) -> Option<Move> {
+    resolve_san(b, san).ok()
+}
+
+/// Like [`parse_san`] but says why the move was rejected. Check marks,
+/// trailing `!`/`?` annotations, `x`, `=` and `e.p.` are optional, castling
+/// may be written with zeros, promotion letters may be lowercase and
+/// over-disambiguated moves (`Ng1f3`) are accepted.
+pub fn resolve_san(b: &Board, san: &str) -> Result<Move, String> {
+    let text = san
+        .trim()
+        .trim_end_matches(['!', '?'])
+        .trim_end_matches(['+', '#'])
+        .trim_end_matches("e.p.")
+        .trim_end();
+    let castling = text.replace('0', "O");
+    if castling == "O-O" || castling == "O-O-O" {
+        let kingside = castling == "O-O";
+        return b
+            .legal_moves()
+            .find(|m| m.is_castle() && (m.to() > m.from()) == kingside)
+            .ok_or_else(|| format!("illegal move {san}"));
+    }
+
+    let text = text.replace(['x', '=', '-', ':'], "");
+    let mut chars: Vec<char> = text.chars().collect();
+    let promotion = match chars
+        .last()
+        .map(|c| c.to_ascii_uppercase())
+        .and_then(piece_kind)
+    {
+        Some(kind) if chars.len() > 2 && chars[chars.len() - 2].is_ascii_digit() => {
+            chars.pop();
+            Some(kind)
+        }
+        _ => None,
+    };
+    let kind = match chars.first().copied().and_then(piece_kind) {
+        Some(kind) => {
+            chars.remove(0);
+            kind
+        }
+        None => PieceKind::Pawn,
+    };
+    if chars.len() < 2 {
+        return Err(format!("malformed move {san}"));
+    }
+    let dest = String::from_iter(&chars[chars.len() - 2..])
+        .parse::<Square>()
+        .ok();
+    let (from_file, from_rank) =
+        chars[..chars.len() - 2]
+            .iter()
+            .fold((None, None), |(f, r), &c| match c {
+                'a'..='h' => (File::from_char(c), r),
+                '1'..='8' => (f, Rank::from_char(c)),
+                _ => (f, r),
+            });
+
+    let mut found = b.legal_moves().filter(|m| {
+        Some(m.to()) == dest
+            && b.piece_on[m.from().index()].kind() == Some(kind)
+            && m.promotion() == promotion
+            && !m.is_castle()
+            && from_file.is_none_or(|f| m.from().file() == f)
+            && from_rank.is_none_or(|r| m.from().rank() == r)
+    });
+    match (found.next(), found.next()) {
+        (Some(m), None) => Ok(m),
+        (Some(_), Some(_)) => Err(format!("ambiguous move {san}")),
+        _ => Err(format!("illegal move {san}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::START_FEN;
+    use crate::uci_io::format_uci;
+
+    fn round_trip(b: &mut Board, depth: usize) {
+        let legal: Vec<Move> = b.legal_moves().collect();
+        for &m in &legal {
+            let san = b.to_san(m, &legal);
+            assert_eq!(resolve_san(b, &san), Ok(m), "{san} in {}", b.to_fen());
+            if depth > 1 {
+                let undo = b.make_move(m);
+                round_trip(b, depth - 1);
+                b.unmake_move(m, undo);
+            }
+        }
+    }
+
+    #[test]
+    fn parses_what_to_san_writes() {
+        for fen in [
+            START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            // Knights and rooks needing file, rank or square disambiguation.
+            "4k3/8/1N3N2/8/1N3N2/8/8/R3K2R w - - 0 1",
+        ] {
+            round_trip(&mut Board::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    fn uci(fen: &str, san: &str) -> Result<String, String> {
+        resolve_san(&Board::from_fen(fen).unwrap(), san).map(format_uci)
+    }
+
+    #[test]
+    fn accepts_loose_notation() {
+        let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        assert_eq!(uci(kiwipete, "0-0").as_deref(), Ok("e1g1"));
+        assert_eq!(uci(kiwipete, "O-O-O+").as_deref(), Ok("e1c1"));
+        assert_eq!(uci(kiwipete, "Nxf7!?").as_deref(), Ok("e5f7"));
+        assert_eq!(uci(kiwipete, "Ne5-f7").as_deref(), Ok("e5f7"));
+        let ep = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+        assert_eq!(uci(ep, "exf6 e.p.").as_deref(), Ok("e5f6"));
+        let promo = "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N w - - 0 1";
+        assert_eq!(uci(promo, "bxa8=n").as_deref(), Ok("b7a8n"));
+        assert_eq!(uci(promo, "b8Q").as_deref(), Ok("b7b8q"));
+    }
+
+    #[test]
+    fn rejects_ambiguous_and_illegal_moves() {
+        let fen = "4k3/8/1N3N2/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(uci(fen, "Nd5"), Err("ambiguous move Nd5".into()));
+        assert_eq!(uci(fen, "Nbd5").as_deref(), Ok("b6d5"));
+        assert_eq!(uci(fen, "Nd6"), Err("illegal move Nd6".into()));
+        assert_eq!(uci(fen, "e"), Err("malformed move e".into()));
+        assert_eq!(uci(START_FEN, "O-O"), Err("illegal move O-O".into()));
+    }
+}
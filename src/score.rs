@@ -0,0 +1,91 @@
+// A mate is scored `MATE_SCORE - plies` from the root for the winning side,
+// so shorter mates score higher. Scores in the transposition table are kept
+// relative to the node instead, since a position recurs at different plies.
+
+pub const MATE_SCORE: i32 = 30_000;
+/// Scores beyond this magnitude are mates.
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 512;
+
+/// Score for giving mate `ply` plies from the root.
+#[inline(always)]
+pub const fn mate_in(ply: usize) -> i32 {
+    MATE_SCORE - ply as i32
+}
+
+/// Score for being mated `ply` plies from the root.
+#[inline(always)]
+pub const fn mated_in(ply: usize) -> i32 {
+    -MATE_SCORE + ply as i32
+}
+
+#[inline(always)]
+pub const fn is_mate(score: i32) -> bool {
+    score.abs() > MATE_THRESHOLD
+}
+
+/// Converts a root-relative score found at `ply` to the node-relative form
+/// kept in the transposition table.
+#[inline(always)]
+pub fn mate_store(score: i32, ply: usize) -> i32 {
+    match score {
+        s if s > MATE_THRESHOLD => s + ply as i32,
+        s if s < -MATE_THRESHOLD => s - ply as i32,
+        s => s,
+    }
+}
+
+/// Inverse of [`mate_store`] for a table score probed at `ply`.
+#[inline(always)]
+pub fn mate_load(score: i32, ply: usize) -> i32 {
+    match score {
+        s if s > MATE_THRESHOLD => s - ply as i32,
+        s if s < -MATE_THRESHOLD => s + ply as i32,
+        s => s,
+    }
+}
+
+/// Full moves until mate: positive when the side to move mates, negative
+/// when it gets mated. `None` for ordinary scores.
+pub fn mate_moves(score: i32) -> Option<i32> {
+    if !is_mate(score) {
+        return None;
+    }
+    let plies = MATE_SCORE - score.abs();
+    Some(if score > 0 {
+        (plies + 1) / 2
+    } else {
+        -(plies / 2)
+    })
+}
+
+// Logistic win-rate model: the side ahead by WDL_MIDPOINT centipawns wins
+// half its games, and WDL_SCALE sets how fast the win rate rises around that
+// point. Both are meant to be refitted from self-play results as the
+// evaluation changes.
+const WDL_MIDPOINT: f64 = 100.0;
+const WDL_SCALE: f64 = 40.0;
+
+fn win_rate(score: i32) -> u32 {
+    let x = score.clamp(-4000, 4000) as f64;
+    (1000.0 / (1.0 + ((WDL_MIDPOINT - x) / WDL_SCALE).exp())).round() as u32
+}
+
+/// Expected win, draw and loss chances in per mille for the side to move.
+pub fn wdl(score: i32) -> (u32, u32, u32) {
+    match mate_moves(score) {
+        Some(n) if n > 0 => (1000, 0, 0),
+        Some(_) => (0, 0, 1000),
+        None => {
+            let (w, l) = (win_rate(score), win_rate(-score));
+            (w, 1000 - w - l, l)
+        }
+    }
+}
+
+/// `cp <n>` or `mate <n>` as used in UCI `info score`.
+pub fn to_uci_score(score: i32) -> String {
+    match mate_moves(score) {
+        Some(n) => format!("mate {n}"),
+        None => format!("cp {score}"),
+    }
+}
@@ -1,115 +1,396 @@
+use crate::bitbase::{self, Verdict};
 use crate::board::Board;
-use crate::nnue::evaluate;
-use crate::see::see;
+use crate::eval::evaluate_position;
+use crate::movelist::MoveList;
+use crate::movepick::{History, MovePicker};
+use crate::output;
+pub use crate::score::{MATE_SCORE, MATE_THRESHOLD};
+use crate::score::{is_mate, mate_in, mate_load, mate_store, mated_in, to_uci_score};
+use crate::see::{PIECE_VALUES, see};
 use crate::tt::{Bound, SharedTransTable};
-use crate::types::{Move, Piece, PieceKind};
-use crate::uci_io::format_uci;
+use crate::types::{Color, GameStatus, GenKind, Move, Piece, PieceKind, Undo};
+use crate::uci_io::{self, format_uci};
+use std::fmt;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
 use std::time::{Duration, Instant};
 
-pub const MATE_SCORE: i32 = 30_000;
-const MATE_THRESHOLD: i32 = MATE_SCORE - 512;
 const MAX_PLY: usize = 128;
 const DRAW_SCORE: i32 = 0;
 
-const FUTILITY_MARGIN: [i32; 8] = [0, 125, 250, 450, 700, 950, 1200, 1500];
+const RFP_MAX_DEPTH: i32 = 8;
+const RFP_MARGIN_PER_PLY: i32 = 100;
+const RAZOR_MAX_DEPTH: i32 = 3;
+const RAZOR_MARGIN_BASE: i32 = 250;
+const RAZOR_MARGIN_PER_PLY: i32 = 150;
 const LMP_LIMITS: [i32; 4] = [0, 3, 5, 8];
 const HISTORY_PRUNE_THRESHOLD: i32 = 4000;
+const SEE_PRUNE_MAX_DEPTH: i32 = 8;
+const SEE_CAPTURE_MARGIN: i32 = 90; // per ply
+const SEE_QUIET_MARGIN: i32 = 20; // per ply squared
 const IID_MIN_DEPTH: i32 = 5;
+const QS_DELTA_MARGIN: i32 = 200;
+const QS_MAX_PLY: usize = 32; // qsearch plies below the main search horizon
+const EASY_MOVE_MIN_DEPTH: usize = 6;
+const EASY_MOVE_STABILITY: usize = 3; // iterations the best move must survive
+const EASY_MOVE_MARGIN: i32 = 150;
+const EASY_MOVE_TIME_DIVISOR: u32 = 5; // fraction of the budget spent on an easy move
+const EBF_MIN_DEPTH: usize = 4;
+// Scaling of the soft limit between iterations, when the hard limit leaves
+// room for it.
+const SOFT_SCALE_MIN: f64 = 0.5;
+const SOFT_SCALE_MAX: f64 = 2.5;
+const SCORE_DROP_FULL_CP: i32 = 100; // a drop this large adds the full bonus
+const SCORE_DROP_BONUS: f64 = 0.5;
+const STABILITY_CREDIT: f64 = 0.05; // per stable iteration, up to six
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+// Root moves are announced with `currmove` only once the search has run this long.
+const CURRMOVE_DELAY: Duration = Duration::from_secs(3);
+pub const DEFAULT_ITERATION_SKIP_PERCENT: u32 = 100;
 
-const TT_MOVE_SCORE: i32 = 2_000_000_000;
-const GOOD_CAPTURE_SCORE: i32 = 1_900_000_000;
-const KILLER_1_SCORE: i32 = 1_800_000_000;
-const KILLER_2_SCORE: i32 = 1_700_000_000;
-const COUNTERMOVE_SCORE: i32 = 1_650_000_000;
-const QUIET_MOVE_SCORE: i32 = 1_600_000_000;
-const BAD_CAPTURE_SCORE: i32 = -1_900_000_000;
 const HISTORY_MAX: i32 = 16_384;
+const SINGULAR_MIN_DEPTH: i32 = 8;
+const SINGULAR_TT_DEPTH_SLACK: i32 = 3; // how much shallower the TT entry may be
+const SINGULAR_MARGIN_PER_PLY: i32 = 2;
+/// Beta cutoffs are counted by move number up to this; the last slot takes
+/// every later move.
+const FAIL_HIGH_SLOTS: usize = 8;
 
 struct SearchController {
     start_time: Instant,
-    time_budget: Duration,
+    hard_limit: Duration,
     stop_signal: Arc<AtomicBool>,
     is_main_thread: bool,
     nodes: u64,
+    node_limit: u64,
+    /// Nodes of every thread in the search; each adds its own in batches.
+    shared_nodes: Arc<AtomicU64>,
+    flushed_nodes: u64,
 }
 
 impl SearchController {
+    fn new(
+        limits: &SearchLimits,
+        stop_signal: Arc<AtomicBool>,
+        shared_nodes: Arc<AtomicU64>,
+        is_main_thread: bool,
+    ) -> Self {
+        Self {
+            start_time: Instant::now(),
+            hard_limit: Duration::from_millis(limits.hard_time_ms.max(limits.time_ms)),
+            stop_signal,
+            is_main_thread,
+            nodes: 0,
+            node_limit: limits.nodes,
+            shared_nodes,
+            flushed_nodes: 0,
+        }
+    }
+
     fn time_is_up(&mut self) -> bool {
+        if (self.nodes & 1023) == 0 && self.nodes != self.flushed_nodes {
+            self.flush_nodes();
+        }
         if self.is_main_thread
-            && (self.nodes & 4095) == 0
-            && self.start_time.elapsed() >= self.time_budget
+            && (self.nodes >= self.node_limit
+                || ((self.nodes & 4095) == 0 && self.start_time.elapsed() >= self.hard_limit))
         {
             self.stop_signal.store(true, Ordering::Relaxed);
             return true;
         }
         self.stop_signal.load(Ordering::Relaxed)
     }
+
+    fn flush_nodes(&mut self) {
+        self.shared_nodes
+            .fetch_add(self.nodes - self.flushed_nodes, Ordering::Relaxed);
+        self.flushed_nodes = self.nodes;
+    }
+
+    /// Nodes searched so far by all threads.
+    fn total_nodes(&self) -> u64 {
+        self.shared_nodes.load(Ordering::Relaxed) + self.nodes - self.flushed_nodes
+    }
+}
+
+/// Move-ordering tables kept between the searches of one game, so each
+/// search starts from what the previous ones learned.
+pub struct SearchContext {
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    history: History,                             // [piece][to_square]
+    counter_moves: [[[Option<Move>; 64]; 13]; 2], // [is_capture][piece][to_square]
+    /// Quiet-move history following the previous move, indexed by
+    /// `[prev_piece * 64 + prev_to][piece][to_square]`.
+    cont_history: Box<[History]>,
+}
+
+impl SearchContext {
+    pub fn new() -> Self {
+        Self {
+            killers: [[None; 2]; MAX_PLY],
+            history: [[0; 64]; 13],
+            counter_moves: [[[None; 64]; 13]; 2],
+            cont_history: vec![[[0; 64]; 13]; 13 * 64].into_boxed_slice(),
+        }
+    }
+
+    /// Forgets everything learned, for a new game.
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Readies the tables for the next move: the histories are halved so
+    /// the new search's results soon outweigh them, and the killers are
+    /// dropped since their plies no longer line up.
+    fn age(&mut self) {
+        self.killers = [[None; 2]; MAX_PLY];
+        for table in std::iter::once(&mut self.history).chain(self.cont_history.iter_mut()) {
+            for row in table.iter_mut() {
+                for v in row.iter_mut() {
+                    *v /= 2;
+                }
+            }
+        }
+    }
+}
+
+impl Default for SearchContext {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub struct Search<'a> {
     board: Board,
     tt: &'a SharedTransTable,
     controller: SearchController,
-    killers: [[Option<Move>; 2]; MAX_PLY],
-    history: [[i32; 64]; 13],                     // [piece][to_square]
-    counter_moves: [[[Option<Move>; 64]; 13]; 2], // [is_capture][piece][to_square]
+    info: Option<&'a mut dyn InfoCallback>,
+    last_progress: Instant,
+    ctx: &'a mut SearchContext,
     ply: usize,
+    /// Length of the board's history at the root.
+    root_history_len: usize,
     seldepth: usize,
-    prev_move: [Option<Move>; MAX_PLY],
+    /// The move into each ply with the piece that made it, whose origin
+    /// square is empty by the time the child looks at it. `None` at the
+    /// root and after a null move.
+    prev_move: [Option<(Piece, Move)>; MAX_PLY],
+    root_moves: Vec<Move>,
+    /// Best move of the last completed root node. Taken from here rather
+    /// than the table, whose root entry may be an older, deeper one.
+    root_best: Option<Move>,
+    /// Exact scores of the root moves searched so far in this iteration,
+    /// kept only when `want_root_scores`.
+    root_scores: Vec<(Move, i32)>,
+    want_root_scores: bool,
+    config: SearchConfig,
+    pv: Box<PvTable>,
+    /// Move left out of the singular-extension verification search at a ply.
+    excluded: [Option<Move>; MAX_PLY],
+    /// Whether the move into a ply was a null move, so two never follow
+    /// each other.
+    null_move: [bool; MAX_PLY],
+    /// Static evaluation at each ply, `None` when in check.
+    eval_stack: [Option<i32>; MAX_PLY],
+    /// Move picker of the node at each ply, reused so a search frame stays
+    /// small enough for a default thread stack.
+    pickers: Box<[MovePicker]>,
+    /// Quiet moves of the node at each ply: those tried before a cutoff in
+    /// the main search, the quiet checks in quiescence.
+    quiets: Box<[MoveList]>,
+    stats: SearchStats,
 }
 
-/// Assigns a score to a move to guide the search algorithm.
-fn score_move(s: &Search, m: Move, tt_move: Option<Move>) -> i32 {
-    if Some(m) == tt_move {
-        return TT_MOVE_SCORE;
+/// Triangular principal-variation table: row `ply` holds the best line found
+/// from that ply, rebuilt from the row below whenever a PV node raises alpha.
+struct PvTable {
+    moves: [[Move; MAX_PLY]; MAX_PLY],
+    len: [usize; MAX_PLY],
+}
+
+impl PvTable {
+    fn new() -> Box<Self> {
+        Box::new(Self {
+            moves: [[Move::default(); MAX_PLY]; MAX_PLY],
+            len: [0; MAX_PLY],
+        })
     }
 
-    if m.capture {
-        let see_val = see(&s.board, m);
-        return if see_val >= 0 {
-            GOOD_CAPTURE_SCORE + see_val
-        } else {
-            BAD_CAPTURE_SCORE + see_val
-        };
+    fn clear(&mut self, ply: usize) {
+        self.len[ply] = 0;
+    }
+
+    /// `m` followed by the line of the child node.
+    fn update(&mut self, ply: usize, m: Move) {
+        let child_len = self.len[ply + 1];
+        let (row, below) = self.moves.split_at_mut(ply + 1);
+        row[ply][0] = m;
+        row[ply][1..=child_len].copy_from_slice(&below[0][..child_len]);
+        self.len[ply] = child_len + 1;
     }
 
-    if Some(m) == s.killers[s.ply][0] {
-        return KILLER_1_SCORE;
+    fn line(&self, ply: usize) -> &[Move] {
+        &self.moves[ply][..self.len[ply]]
     }
+}
 
-    if Some(m) == s.killers[s.ply][1] {
-        return KILLER_2_SCORE;
+impl Search<'_> {
+    fn is_root_move(&self, m: Move) -> bool {
+        self.root_moves.is_empty() || self.root_moves.contains(&m)
     }
 
-    if let Some(prev_m) = s.prev_move[s.ply.saturating_sub(1)] {
-        let piece_idx = s.board.piece_on[prev_m.from as usize].index();
-        if Some(m) == s.counter_moves[prev_m.capture as usize][piece_idx][prev_m.to as usize] {
-            return COUNTERMOVE_SCORE;
+    /// Plays `m` and steps one ply deeper, recording it as the child's
+    /// previous move.
+    fn make_move(&mut self, m: Move) -> Undo {
+        let piece = self.board.piece_on[m.from().index()];
+        let undo = self.board.make_move(m);
+        self.ply += 1;
+        self.prev_move[self.ply] = Some((piece, m));
+        undo
+    }
+
+    fn unmake_move(&mut self, m: Move, undo: Undo) {
+        self.ply -= 1;
+        self.board.unmake_move(m, undo);
+    }
+
+    /// Index into the continuation history for replies to the move that
+    /// led here.
+    fn cont_index(&self) -> Option<usize> {
+        self.prev_move[self.ply].map(|(piece, m)| piece.index() * 64 + m.to().index())
+    }
+
+    /// Reports node totals if no report has gone out for a while.
+    fn report_progress(&mut self) {
+        if self.last_progress.elapsed() < PROGRESS_INTERVAL {
+            return;
         }
+        self.last_progress = Instant::now();
+        let Some(cb) = self.info.as_deref_mut() else {
+            return;
+        };
+        let nodes = self.controller.total_nodes();
+        let time_ms = self.controller.start_time.elapsed().as_millis() as u64;
+        cb.progress(&SearchProgress {
+            nodes,
+            nps: (nodes * 1000).checked_div(time_ms).unwrap_or(0),
+            time_ms,
+            hashfull: self.tt.hashfull_permill(),
+        });
     }
+}
+
+/// Rewards the quiet move `m` that caused a cutoff and penalises the quiets
+/// tried before it, halving the table if an entry grows too large.
+fn update_history(table: &mut History, b: &Board, m: Move, tried: &MoveList, bonus: i32) {
+    let piece_idx = b.piece_on[m.from().index()].index();
+    table[piece_idx][m.to().index()] += bonus;
+    if table[piece_idx][m.to().index()] > HISTORY_MAX {
+        for row in table.iter_mut() {
+            for v in row.iter_mut() {
+                *v >>= 1;
+            }
+        }
+    }
+    for failed_move in tried {
+        let p_idx = b.piece_on[failed_move.from().index()].index();
+        table[p_idx][failed_move.to().index()] -= bonus;
+    }
+}
+
+/// Static evaluation from the side to move's perspective, with dead
+/// material configurations scored as draws.
+#[inline]
+fn static_eval(b: &Board) -> i32 {
+    if b.is_insufficient_material() {
+        return DRAW_SCORE;
+    }
+    evaluate_position(b)
+}
+
+/// Score of a drawn node, shifted by contempt from the root side's view.
+#[inline]
+fn draw_score(s: &Search) -> i32 {
+    let contempt = s.config.contempt;
+    if s.ply.is_multiple_of(2) {
+        -contempt
+    } else {
+        contempt
+    }
+}
 
-    let piece_idx = s.board.piece_on[m.from as usize].index();
-    QUIET_MOVE_SCORE + s.history[piece_idx][m.to as usize]
+/// Readies the current ply's picker to order the node's moves from the
+/// search's heuristics.
+fn reset_picker(s: &mut Search, tt_move: Option<Move>) {
+    let counter = s.prev_move[s.ply].and_then(|(piece, prev_m)| {
+        s.ctx.counter_moves[prev_m.is_capture() as usize][piece.index()][prev_m.to().index()]
+    });
+    s.pickers[s.ply].reset(tt_move, s.ctx.killers[s.ply], counter);
+}
+
+/// Most material a move can win outright: the victim plus any promotion gain.
+#[inline]
+fn material_gain(b: &Board, m: Move) -> i32 {
+    let victim = if m.is_en_passant() {
+        PIECE_VALUES[PieceKind::Pawn as usize]
+    } else {
+        b.piece_on[m.to().index()]
+            .kind()
+            .map_or(0, |k| PIECE_VALUES[k as usize])
+    };
+    let promo = m.promotion().map_or(0, |k| {
+        PIECE_VALUES[k as usize] - PIECE_VALUES[PieceKind::Pawn as usize]
+    });
+    victim + promo
 }
 
 /// A specialized search that only considers tactical moves
-fn quiesce(s: &mut Search, mut alpha: i32, beta: i32) -> i32 {
+fn quiesce(s: &mut Search, mut alpha: i32, beta: i32, qs_ply: usize) -> i32 {
     s.seldepth = s.seldepth.max(s.ply);
+    s.pv.clear(s.ply);
     s.controller.nodes += 1;
+    s.stats.qnodes += 1;
 
     if s.controller.time_is_up() {
         return 0;
     }
 
-    let king_sq = s.board.piece_bb[Piece::from_kind(PieceKind::King, s.board.turn).index()]
-        .trailing_zeros() as i32;
-    let in_check = s.board.is_square_attacked(king_sq, s.board.turn.other());
+    if qs_ply >= QS_MAX_PLY || s.ply >= MAX_PLY - 1 {
+        return static_eval(&s.board);
+    }
+
+    // Only the first ply is hashed; deeper ones are mostly capture chains
+    // that won't be seen again.
+    let key = s.board.zobrist;
+    let mut tt_move = None;
+    let mut hashed = qs_ply == 0;
+    s.stats.tt_probes += u64::from(hashed);
+    if hashed && let Some(entry) = s.tt.probe(key) {
+        s.stats.tt_hits += 1;
+        let score = mate_load(entry.score(), s.ply);
+        match entry.bound() {
+            Bound::Exact => return score,
+            Bound::Lower if score >= beta => return score,
+            Bound::Upper if score <= alpha => return score,
+            _ => {}
+        }
+        tt_move = entry.best_move();
+        // Don't overwrite a main-search entry with a shallower one.
+        hashed = entry.depth() <= 0;
+    }
+    let alpha_orig = alpha;
+    let mut best_move = None;
+
+    let in_check = s.board.in_check();
 
+    let mut stand_pat = -MATE_SCORE;
     if !in_check {
-        let stand_pat = evaluate(&s.board);
+        stand_pat = static_eval(&s.board);
         if stand_pat >= beta {
+            if hashed {
+                s.tt.store(key, 0, mate_store(beta, s.ply), Bound::Lower, tt_move);
+            }
             return beta;
         }
         if stand_pat > alpha {
@@ -117,85 +398,145 @@ fn quiesce(s: &mut Search, mut alpha: i32, beta: i32) -> i32 {
         }
     }
 
-    let mut pseudo_moves = Vec::with_capacity(64);
-    s.board.generate_pseudo_legal_moves(&mut pseudo_moves);
-
-    let mut scored_moves: Vec<(Move, i32)> = pseudo_moves
-        .into_iter()
-        .filter(|&m| m.capture || m.promotion.is_some() || in_check)
-        .map(|m| (m, score_move(s, m, None)))
-        .collect();
-
-    scored_moves.sort_unstable_by_key(|&(_, score)| -score);
-
+    if in_check {
+        reset_picker(s, None);
+    } else {
+        s.pickers[s.ply].reset_noisy();
+    }
     let mut legal_moves_found = false;
-    for (m, _) in &scored_moves {
-        if !in_check && m.capture && see(&s.board, *m) < 0 {
-            continue;
+    while let Some(m) = s.pickers[s.ply].next(&s.board, &s.ctx.history, None) {
+        if !in_check {
+            // Delta pruning: even winning the victim for free can't lift alpha.
+            if stand_pat + material_gain(&s.board, m) + QS_DELTA_MARGIN <= alpha {
+                continue;
+            }
+            let underpromotion = m.promotion().is_some_and(|k| k != PieceKind::Queen);
+            if (m.is_capture() || underpromotion) && see(&s.board, m) < 0 {
+                continue;
+            }
         }
 
-        let undo = s.board.make_move(*m);
-        let us = s.board.turn.other();
-        let king_bb = s.board.piece_bb[Piece::from_kind(PieceKind::King, us).index()];
-        if king_bb != 0
-            && s.board
-                .is_square_attacked(king_bb.trailing_zeros() as i32, s.board.turn)
-        {
-            s.board.unmake_move(*m, undo);
-            continue;
-        }
+        let undo = s.make_move(m);
         legal_moves_found = true;
-
-        s.ply += 1;
-        s.prev_move[s.ply] = Some(*m);
-        let score = -quiesce(s, -beta, -alpha);
-        s.ply -= 1;
-        s.board.unmake_move(*m, undo);
+        let score = -quiesce(s, -beta, -alpha, qs_ply + 1);
+        s.unmake_move(m, undo);
 
         if score >= beta {
+            if hashed && !s.controller.time_is_up() {
+                s.tt.store(key, 0, mate_store(beta, s.ply), Bound::Lower, Some(m));
+            }
             return beta;
         }
         if score > alpha {
             alpha = score;
+            best_move = Some(m);
         }
     }
 
     if in_check && !legal_moves_found {
-        return -MATE_SCORE + s.ply as i32;
+        return mated_in(s.ply);
+    }
+
+    // Quiet checks on the first ply, which can win material the captures
+    // alone miss (forks, mating nets).
+    if qs_ply == 0 && !in_check {
+        let ply = s.ply;
+        s.quiets[ply].clear();
+        s.board.generate_legal(&mut s.quiets[ply], GenKind::Quiet);
+        for i in 0..s.quiets[ply].len() {
+            let m = s.quiets[ply][i];
+            if !s.board.gives_check(m) {
+                continue;
+            }
+            let undo = s.make_move(m);
+            let score = -quiesce(s, -beta, -alpha, qs_ply + 1);
+            s.unmake_move(m, undo);
+
+            if score >= beta {
+                if hashed && !s.controller.time_is_up() {
+                    s.tt.store(key, 0, mate_store(beta, s.ply), Bound::Lower, Some(m));
+                }
+                return beta;
+            }
+            if score > alpha {
+                alpha = score;
+                best_move = Some(m);
+            }
+        }
+    }
+
+    if hashed && !s.controller.time_is_up() {
+        let bound = if alpha > alpha_orig {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        s.tt.store(
+            key,
+            0,
+            mate_store(alpha, s.ply),
+            bound,
+            best_move.or(tt_move),
+        );
     }
     alpha
 }
 
-fn negamax(s: &mut Search, mut alpha: i32, beta: i32, mut depth: i32) -> i32 {
+fn negamax(s: &mut Search, mut alpha: i32, mut beta: i32, depth: i32) -> i32 {
     s.seldepth = s.seldepth.max(s.ply);
+    s.pv.clear(s.ply);
     if s.controller.time_is_up() {
         return 0;
     }
 
-    if s.ply > 0 && (s.board.is_draw_by_repetition() || s.board.halfmove_clock >= 100) {
-        return DRAW_SCORE;
+    if s.ply > 0 {
+        if s.board.is_search_repetition(s.root_history_len)
+            || s.board.is_insufficient_material()
+            || bitbase::probe(&s.board) == Some(Verdict::Draw)
+        {
+            return draw_score(s);
+        }
+        // A mate delivered on the 100th halfmove still wins.
+        if s.board.halfmove_clock >= 100 {
+            return if s.board.status() == GameStatus::Checkmate {
+                mated_in(s.ply)
+            } else {
+                draw_score(s)
+            };
+        }
     }
 
     if s.ply >= MAX_PLY - 1 {
-        return evaluate(&s.board);
+        return static_eval(&s.board);
+    }
+
+    if s.ply > 0 {
+        // Mate distance pruning: no line from here can beat mating sooner or
+        // being mated later than is already possible.
+        alpha = alpha.max(mated_in(s.ply));
+        beta = beta.min(mate_in(s.ply + 1));
+        if alpha >= beta {
+            return alpha;
+        }
     }
 
     let is_pv = beta - alpha > 1;
     let alpha_orig = alpha;
     let key = s.board.zobrist;
+    let excluded = s.excluded[s.ply];
     let mut tt_move: Option<Move> = None;
+    // (score, depth, bound) of the hash entry, for the singular extension.
+    let mut tt_hit: Option<(i32, i32, Bound)> = None;
 
+    s.stats.tt_probes += 1;
     if let Some(entry) = s.tt.probe(key) {
-        if entry.depth() >= depth as i16 && s.ply > 0 {
-            let mut score = entry.score();
-            if score.abs() > MATE_THRESHOLD {
-                if score > 0 {
-                    score -= s.ply as i32;
-                } else {
-                    score += s.ply as i32;
-                }
-            }
+        s.stats.tt_hits += 1;
+        let score = mate_load(entry.score(), s.ply);
+        tt_hit = Some((score, entry.depth() as i32, entry.bound()));
 
+        // The entry describes the full node, not the one without `excluded`.
+        // PV nodes are searched anyway so the PV table gets the whole line.
+        if entry.depth() >= depth as i16 && s.ply > 0 && !is_pv && excluded.is_none() {
             match entry.bound() {
                 Bound::Exact => return score,
                 Bound::Lower if score >= beta => return score,
@@ -206,18 +547,26 @@ fn negamax(s: &mut Search, mut alpha: i32, beta: i32, mut depth: i32) -> i32 {
         tt_move = entry.best_move();
     }
 
-    let king_sq = s.board.piece_bb[Piece::from_kind(PieceKind::King, s.board.turn).index()]
-        .trailing_zeros() as i32;
-    let in_check = s.board.is_square_attacked(king_sq, s.board.turn.other());
+    let in_check = s.board.in_check();
 
-    if in_check {
-        depth += 1;
-    }
     if depth <= 0 {
-        return quiesce(s, alpha, beta);
+        return quiesce(s, alpha, beta, 0);
     }
 
     s.controller.nodes += 1;
+    if s.controller.is_main_thread && (s.controller.nodes & 4095) == 0 {
+        s.report_progress();
+    }
+
+    let eval = (!in_check).then(|| static_eval(&s.board));
+    s.eval_stack[s.ply] = eval;
+    // Whether our eval has risen since our previous move; assumed so when
+    // there is nothing to compare with.
+    let improving = match (eval, s.ply.checked_sub(2).and_then(|p| s.eval_stack[p])) {
+        (Some(now), Some(before)) => now > before,
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
 
     if is_pv && depth >= IID_MIN_DEPTH && tt_move.is_none() && !s.controller.time_is_up() {
         let _ = negamax(s, alpha, beta, depth - 2);
@@ -226,14 +575,28 @@ fn negamax(s: &mut Search, mut alpha: i32, beta: i32, mut depth: i32) -> i32 {
         }
     }
 
-    if !is_pv && !in_check && depth < 8 {
-        let eval = evaluate(&s.board);
-        if eval - FUTILITY_MARGIN[depth as usize] >= beta {
+    if let Some(eval) = eval
+        && !is_pv
+        && excluded.is_none()
+    {
+        // Reverse futility: too far above beta to fall back within a few plies.
+        let margin = RFP_MARGIN_PER_PLY * (depth - i32::from(improving));
+        if depth < RFP_MAX_DEPTH && eval - margin >= beta {
             return beta;
         }
+        // Razoring: so far below alpha that only captures could help.
+        if depth <= RAZOR_MAX_DEPTH
+            && !is_mate(alpha)
+            && eval + RAZOR_MARGIN_BASE + RAZOR_MARGIN_PER_PLY * depth < alpha
+        {
+            let score = quiesce(s, alpha - 1, alpha, 0);
+            if score < alpha {
+                return score;
+            }
+        }
     }
 
-    let our_pieces = if s.board.turn == crate::types::Color::White {
+    let our_pieces = if s.board.turn == Color::White {
         s.board.w_pieces
     } else {
         s.board.b_pieces
@@ -244,81 +607,156 @@ fn negamax(s: &mut Search, mut alpha: i32, beta: i32, mut depth: i32) -> i32 {
             | s.board.piece_bb[Piece::WK.index()]
             | s.board.piece_bb[Piece::BK.index()]);
 
-    if !is_pv && !in_check && depth >= 3 && non_pawn_king_material != 0 {
+    // A hash upper bound below beta says passing is unlikely to fail high.
+    let tt_fails_low = matches!(tt_hit, Some((score, _, Bound::Upper)) if score < beta);
+    if !is_pv
+        && !in_check
+        && depth >= 3
+        && non_pawn_king_material != 0
+        && excluded.is_none()
+        && !s.null_move[s.ply]
+        && !tt_fails_low
+    {
         let r = 3 + depth / 6; // Adaptive reduction
+        s.stats.null_move_tries += 1;
         let undo = s.board.make_null_move();
         s.ply += 1;
+        s.prev_move[s.ply] = None;
+        s.null_move[s.ply] = true;
         let null_score = -negamax(s, -beta, -beta + 1, depth - r);
+        s.null_move[s.ply] = false;
         s.ply -= 1;
         s.board.unmake_null_move(undo);
+        if s.controller.time_is_up() {
+            return 0;
+        }
         if null_score >= beta {
-            if depth < 10 {
-                return beta;
+            let cutoff = depth < 10 || negamax(s, beta - 1, beta, depth - 6) >= beta;
+            if s.controller.time_is_up() {
+                return 0;
             }
-            let verification_score = negamax(s, beta - 1, beta, depth - 6);
-            if verification_score >= beta {
+            if cutoff {
+                s.stats.null_move_cutoffs += 1;
+                // Mate scores found after passing are not proven.
+                let score = if is_mate(null_score) {
+                    beta
+                } else {
+                    null_score
+                };
+                s.tt.store(key, depth as i16, score, Bound::Lower, tt_move);
                 return beta;
             }
+            s.stats.null_move_refuted += 1;
         }
     }
 
-    let mut pseudo_moves = Vec::with_capacity(128);
-    s.board.generate_pseudo_legal_moves(&mut pseudo_moves);
-
-    let mut scored_moves: Vec<(Move, i32)> = pseudo_moves
-        .into_iter()
-        .map(|m| (m, score_move(s, m, tt_move)))
-        .collect();
-
-    scored_moves.sort_unstable_by_key(|&(_, score)| -score);
+    // Singular extension: if every alternative falls well short of the TT
+    // score at reduced depth, the TT move is extended. If one of them still
+    // beats beta, at least two moves fail high and the node is cut (multicut).
+    let mut singular_move = None;
+    if let (Some(tt_m), Some((tt_score, tt_depth, tt_bound))) = (tt_move, tt_hit)
+        && excluded.is_none()
+        && s.ply > 0
+        && depth >= SINGULAR_MIN_DEPTH
+        && tt_depth >= depth - SINGULAR_TT_DEPTH_SLACK
+        && tt_bound != Bound::Upper
+        && !is_mate(tt_score)
+    {
+        let singular_beta = tt_score - SINGULAR_MARGIN_PER_PLY * depth;
+        s.excluded[s.ply] = Some(tt_m);
+        let score = negamax(s, singular_beta - 1, singular_beta, (depth - 1) / 2);
+        s.excluded[s.ply] = None;
+        if s.controller.time_is_up() {
+            return 0;
+        }
+        if score < singular_beta {
+            singular_move = Some(tt_m);
+        } else if singular_beta >= beta {
+            return singular_beta;
+        }
+    }
 
+    reset_picker(s, tt_move);
+    let cont = s.cont_index();
     let mut best_score = -MATE_SCORE;
     let mut best_move: Option<Move> = None;
-    let mut moves_searched = 0;
+    let mut moves_searched: usize = 0;
+    // Quiet moves searched before the current one, penalised on a cutoff.
+    s.quiets[s.ply].clear();
+    let root_scores = s.ply == 0 && s.want_root_scores;
+    if root_scores {
+        s.root_scores.clear();
+    }
+    // Internal iterative deepening may have left a line here.
+    s.pv.clear(s.ply);
+
+    while let Some(m) = s.pickers[s.ply].next(
+        &s.board,
+        &s.ctx.history,
+        cont.map(|i| &s.ctx.cont_history[i]),
+    ) {
+        if Some(m) == excluded || (s.ply == 0 && !s.is_root_move(m)) {
+            continue;
+        }
+        // Checks and the singular move are searched a ply deeper.
+        let extend = Some(m) == singular_move || s.board.gives_check(m);
+        let new_depth = depth - 1 + i32::from(extend);
 
-    for (m, _) in &scored_moves {
-        if !is_pv && !in_check && depth <= 3 && !m.capture && m.promotion.is_none() {
+        if !is_pv && !in_check && depth <= 3 && !m.is_capture() && m.promotion().is_none() {
             let lmp_limit = LMP_LIMITS[depth as usize];
             if moves_searched as i32 >= lmp_limit {
                 continue;
             }
         }
 
-        if depth <= 2 && !in_check && !m.capture && m.promotion.is_none() {
-            let piece_idx = s.board.piece_on[m.from as usize].index();
-            let hist_score = s.history[piece_idx][m.to as usize];
+        if depth <= 2 && !in_check && !m.is_capture() && m.promotion().is_none() {
+            let piece_idx = s.board.piece_on[m.from().index()].index();
+            let hist_score = s.ctx.history[piece_idx][m.to().index()];
             if hist_score < -HISTORY_PRUNE_THRESHOLD {
                 continue;
             }
         }
 
-        let undo = s.board.make_move(*m);
-        let us = s.board.turn.other();
-        let king_bb = s.board.piece_bb[Piece::from_kind(PieceKind::King, us).index()];
-        if king_bb != 0
-            && s.board
-                .is_square_attacked(king_bb.trailing_zeros() as i32, s.board.turn)
+        // SEE pruning: skip moves that lose more material than the remaining
+        // depth could plausibly win back.
+        if s.ply > 0
+            && moves_searched > 0
+            && best_score > -MATE_THRESHOLD
+            && depth < SEE_PRUNE_MAX_DEPTH
+            && !in_check
+            && (m.is_capture() || m.promotion().is_none())
         {
-            s.board.unmake_move(*m, undo);
-            continue;
+            let threshold = if m.is_capture() {
+                -SEE_CAPTURE_MARGIN * depth
+            } else {
+                -SEE_QUIET_MARGIN * depth * depth
+            };
+            if see(&s.board, m) < threshold {
+                continue;
+            }
         }
 
-        s.ply += 1;
-        s.prev_move[s.ply] = Some(*m);
+        if s.ply == 0
+            && s.controller.is_main_thread
+            && s.controller.start_time.elapsed() >= CURRMOVE_DELAY
+            && let Some(cb) = s.info.as_deref_mut()
+        {
+            cb.currmove(depth as usize, m, moves_searched + 1);
+        }
+
+        let undo = s.make_move(m);
+        s.tt.prefetch(s.board.zobrist);
         moves_searched += 1;
 
         let score;
         if moves_searched == 1 {
-            score = -negamax(s, -beta, -alpha, depth - 1);
+            score = -negamax(s, -beta, -alpha, new_depth);
+        } else if root_scores {
+            // Open below so a weaker move's score is exact, not a bound.
+            score = -negamax(s, -beta, MATE_SCORE, new_depth);
         } else {
-            if depth < 8 && !in_check && m.capture && see(&s.board, *m) < 0 {
-                s.ply -= 1;
-                s.board.unmake_move(*m, undo);
-                continue;
-            }
-
             let mut reduction = 0;
-            if depth >= 3 && !m.capture && !in_check {
+            if depth >= 3 && !m.is_capture() && !in_check {
                 let d = depth as f32;
                 let mn = moves_searched as f32;
                 reduction = (0.5 + d.ln() * mn.ln() / 2.0) as i32;
@@ -327,77 +765,81 @@ fn negamax(s: &mut Search, mut alpha: i32, beta: i32, mut depth: i32) -> i32 {
                 }
 
                 let history_score =
-                    s.history[s.board.piece_on[m.from as usize].index()][m.to as usize];
+                    s.ctx.history[s.board.piece_on[m.from().index()].index()][m.to().index()];
                 reduction -= history_score / 4096;
                 reduction = reduction.clamp(0, depth - 2);
             }
 
-            let mut search_score = -negamax(s, -alpha - 1, -alpha, depth - 1 - reduction);
+            let mut search_score = -negamax(s, -alpha - 1, -alpha, new_depth - reduction);
 
             if search_score > alpha && reduction > 0 {
-                search_score = -negamax(s, -alpha - 1, -alpha, depth - 1);
+                s.stats.lmr_researches += 1;
+                search_score = -negamax(s, -alpha - 1, -alpha, new_depth);
             }
             if search_score > alpha && search_score < beta {
-                search_score = -negamax(s, -beta, -alpha, depth - 1);
+                s.stats.pvs_researches += 1;
+                search_score = -negamax(s, -beta, -alpha, new_depth);
             }
             score = search_score;
         };
 
-        s.ply -= 1;
-        s.board.unmake_move(*m, undo);
+        s.unmake_move(m, undo);
 
         if s.controller.time_is_up() {
             return 0;
         }
+        if root_scores {
+            s.root_scores.push((m, score));
+        }
 
         if score > best_score {
             best_score = score;
-            best_move = Some(*m);
+            best_move = Some(m);
             if score > alpha {
                 alpha = score;
+                if is_pv {
+                    s.pv.update(s.ply, m);
+                }
                 if alpha >= beta {
-                    if !m.capture {
-                        if Some(*m) != s.killers[s.ply][0] {
-                            s.killers[s.ply][1] = s.killers[s.ply][0];
-                            s.killers[s.ply][0] = Some(*m);
-                        }
-
-                        if let Some(prev_m) = s.prev_move[s.ply.saturating_sub(1)] {
-                            let piece_idx = s.board.piece_on[prev_m.from as usize].index();
-                            s.counter_moves[prev_m.capture as usize][piece_idx]
-                                [prev_m.to as usize] = Some(*m);
+                    s.stats.fail_highs[(moves_searched - 1).min(FAIL_HIGH_SLOTS - 1)] += 1;
+                    if !m.is_capture() {
+                        if Some(m) != s.ctx.killers[s.ply][0] {
+                            s.ctx.killers[s.ply][1] = s.ctx.killers[s.ply][0];
+                            s.ctx.killers[s.ply][0] = Some(m);
                         }
 
-                        let piece_idx = s.board.piece_on[m.from as usize].index();
                         let bonus = (depth * depth).min(1000);
-                        s.history[piece_idx][m.to as usize] += bonus;
-
-                        if s.history[piece_idx][m.to as usize] > HISTORY_MAX {
-                            for p in 1..13 {
-                                for sq in 0..64 {
-                                    s.history[p][sq] >>= 1;
-                                }
-                            }
+                        let prev = s.prev_move[s.ply];
+                        if let Some((piece, prev_m)) = prev {
+                            s.ctx.counter_moves[prev_m.is_capture() as usize][piece.index()]
+                                [prev_m.to().index()] = Some(m);
                         }
 
-                        for (failed_move, _) in scored_moves.iter().take(moves_searched - 1) {
-                            if !failed_move.capture {
-                                let p_idx = s.board.piece_on[failed_move.from as usize].index();
-                                s.history[p_idx][failed_move.to as usize] -= bonus;
-                            }
+                        let tried = &s.quiets[s.ply];
+                        update_history(&mut s.ctx.history, &s.board, m, tried, bonus);
+                        if let Some(i) = cont {
+                            let table = &mut s.ctx.cont_history[i];
+                            update_history(table, &s.board, m, tried, bonus);
                         }
                     }
                     break; // Beta cutoff
                 }
             }
         }
+        if !m.is_capture() {
+            s.quiets[s.ply].push(m);
+        }
     }
 
     if moves_searched == 0 {
+        if excluded.is_some() {
+            // The excluded move was the only one.
+            return alpha;
+        }
         return if in_check {
-            -MATE_SCORE + s.ply as i32
+            mated_in(s.ply)
         } else {
-            DRAW_SCORE
+            draw_score(s)
         };
     }
 
@@ -409,16 +851,18 @@ fn negamax(s: &mut Search, mut alpha: i32, beta: i32, mut depth: i32) -> i32 {
         Bound::Exact
     };
 
-    let mut score_to_store = best_score;
-    if score_to_store.abs() > MATE_THRESHOLD {
-        if score_to_store > 0 {
-            score_to_store += s.ply as i32;
-        } else {
-            score_to_store -= s.ply as i32;
-        }
+    if s.ply == 0 {
+        s.root_best = best_move;
+    }
+    if excluded.is_none() {
+        s.tt.store(
+            key,
+            depth as i16,
+            mate_store(best_score, s.ply),
+            bound,
+            best_move,
+        );
     }
-
-    s.tt.store(key, depth as i16, score_to_store, bound, best_move);
     best_score
 }
 
@@ -436,15 +880,549 @@ pub fn get_pv_from_tt(mut pos: Board, tt: &SharedTransTable, max_len: usize) ->
     pv
 }
 
-/// The main entry point for starting a search.
+/// True when every root move other than `best` fails low against
+/// `score - EASY_MOVE_MARGIN` in a reduced-depth null-window search.
+fn is_easy_move(s: &mut Search, best: Move, score: i32, depth: i32) -> bool {
+    let mut moves = MoveList::new();
+    s.board.generate_legal_moves(&mut moves);
+    moves.retain(|&m| m != best && s.is_root_move(m));
+    let bound = score - EASY_MOVE_MARGIN;
+
+    for m in moves {
+        let undo = s.make_move(m);
+        let v = -negamax(s, -bound, -bound + 1, depth - 1);
+        s.unmake_move(m, undo);
+
+        if v >= bound || s.controller.time_is_up() {
+            return false;
+        }
+    }
+    true
+}
+
+/// The hash move if it is a legal root move, otherwise the first legal
+/// root move; `None` only when there is nothing to play.
+fn fallback_root_move(s: &Search) -> Option<Move> {
+    let mut moves = MoveList::new();
+    s.board.generate_legal_moves(&mut moves);
+    let tt_move = s.tt.probe(s.board.zobrist).and_then(|e| e.best_move());
+    let mut candidates = moves.iter().copied().filter(|&m| s.is_root_move(m));
+    match tt_move {
+        Some(m) if moves.contains(&m) && s.is_root_move(m) => Some(m),
+        _ => candidates.next(),
+    }
+}
+
+/// How long a search may run; whichever limit is hit first stops it.
+#[derive(Clone, Debug)]
+pub struct SearchLimits {
+    /// Soft limit: no new iteration is started once it has passed.
+    pub time_ms: u64,
+    /// Hard limit: the running iteration is abandoned.
+    pub hard_time_ms: u64,
+    pub max_depth: usize,
+    pub nodes: u64,
+    /// Root moves to consider (UCI `searchmoves`); empty means all of them.
+    pub root_moves: Vec<Move>,
+    /// Search every root move with an open window so each gets an exact
+    /// score in [`SearchResult::root_scores`], as [`Skill`](crate::skill::Skill)
+    /// needs.
+    pub root_scores: bool,
+    pub config: SearchConfig,
+}
+
+/// Settings that change how the search plays rather than how long it runs.
+/// They travel with each search, so engines sharing a process can differ.
+#[derive(Clone, Copy, Debug)]
+pub struct SearchConfig {
+    /// Centipawns the side to move at the root gives up to avoid a draw.
+    pub contempt: i32,
+    /// Whether `info` lines carry `wdl` (UCI_ShowWDL).
+    pub show_wdl: bool,
+    /// Scales the predicted duration of the next iteration before comparing
+    /// it with the time left; larger values skip doomed iterations more
+    /// eagerly.
+    pub iteration_skip_percent: u32,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            contempt: 0,
+            show_wdl: false,
+            iteration_skip_percent: DEFAULT_ITERATION_SKIP_PERCENT,
+        }
+    }
+}
+
+impl SearchLimits {
+    /// A fixed time per move, stopping exactly at `time_ms`.
+    pub fn movetime(time_ms: u64, max_depth: usize) -> Self {
+        Self {
+            time_ms,
+            hard_time_ms: time_ms,
+            max_depth,
+            nodes: u64::MAX,
+            root_moves: Vec::new(),
+            root_scores: false,
+            config: SearchConfig::default(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    /// Score of the last completed iteration, from the side to move's view.
+    pub score: i32,
+    pub depth: usize,
+    /// Nodes searched by all threads.
+    pub nodes: u64,
+    /// Each root move with its score in the last completed iteration, when
+    /// [`SearchLimits::root_scores`] asked for them.
+    pub root_scores: Vec<(Move, i32)>,
+    /// Counters of all threads.
+    pub stats: SearchStats,
+}
+
+/// Progress of a search, reported once per completed iteration.
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: usize,
+    pub seldepth: usize,
+    /// From the side to move's point of view; see [`SearchInfo::mate_in`].
+    pub score: i32,
+    pub nodes: u64,
+    pub nps: u64,
+    pub time_ms: u64,
+    pub hashfull: u32,
+    pub pv: Vec<Move>,
+    /// Whether [`SearchInfo::to_uci`] and [`SearchInfo::to_json`] include
+    /// the win/draw/loss chances.
+    pub show_wdl: bool,
+}
+
+impl SearchInfo {
+    /// Moves to mate (negative when being mated), if the score is a mate score.
+    pub fn mate_in(&self) -> Option<i32> {
+        crate::score::mate_moves(self.score)
+    }
+
+    /// Win, draw and loss chances in per mille for the side to move.
+    pub fn wdl(&self) -> (u32, u32, u32) {
+        crate::score::wdl(self.score)
+    }
+
+    /// The `info` line UCI expects.
+    pub fn to_uci(&self) -> String {
+        let mut score = to_uci_score(self.score);
+        if self.show_wdl {
+            let (w, d, l) = self.wdl();
+            score = format!("{score} wdl {w} {d} {l}");
+        }
+        let pv = self
+            .pv
+            .iter()
+            .map(|&m| format_uci(m))
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!(
+            "info depth {} seldepth {} score {} hashfull {} nodes {} nps {} time {} pv {}",
+            self.depth, self.seldepth, score, self.hashfull, self.nodes, self.nps, self.time_ms, pv
+        )
+    }
+
+    /// The same fields as [`to_uci`](Self::to_uci) as a JSON object.
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut v = serde_json::json!({
+            "type": "info",
+            "depth": self.depth,
+            "seldepth": self.seldepth,
+            "nodes": self.nodes,
+            "nps": self.nps,
+            "time": self.time_ms,
+            "hashfull": self.hashfull,
+            "pv": self.pv.iter().map(|&m| format_uci(m)).collect::<Vec<_>>(),
+        });
+        match self.mate_in() {
+            Some(n) => v["mate"] = n.into(),
+            None => v["cp"] = self.score.into(),
+        }
+        if self.show_wdl {
+            let (w, d, l) = self.wdl();
+            v["wdl"] = serde_json::json!([w, d, l]);
+        }
+        v
+    }
+}
+
+/// Node totals, reported about once a second while an iteration runs.
+#[derive(Clone, Debug)]
+pub struct SearchProgress {
+    pub nodes: u64,
+    pub nps: u64,
+    pub time_ms: u64,
+    pub hashfull: u32,
+}
+
+impl SearchProgress {
+    pub fn to_uci(&self) -> String {
+        format!(
+            "info nodes {} nps {} hashfull {} time {}",
+            self.nodes, self.nps, self.hashfull, self.time_ms
+        )
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "progress",
+            "nodes": self.nodes,
+            "nps": self.nps,
+            "hashfull": self.hashfull,
+            "time": self.time_ms,
+        })
+    }
+}
+
+/// Counters gathered while searching, for judging move-ordering and pruning
+/// changes by more than node counts and playing strength.
+#[derive(Clone, Debug, Default)]
+pub struct SearchStats {
+    /// Nodes searched, quiescence included.
+    pub nodes: u64,
+    pub qnodes: u64,
+    /// Beta cutoffs by the number of the move that caused them:
+    /// `fail_highs[0]` for the first move tried.
+    pub fail_highs: [u64; FAIL_HIGH_SLOTS],
+    /// Reduced searches that beat alpha and were repeated at full depth.
+    pub lmr_researches: u64,
+    /// Null-window searches that beat alpha and were repeated with the full
+    /// window.
+    pub pvs_researches: u64,
+    pub null_move_tries: u64,
+    pub null_move_cutoffs: u64,
+    /// Null-move fail highs the verification search did not confirm.
+    pub null_move_refuted: u64,
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+}
+
+fn percent(part: u64, whole: u64) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+impl SearchStats {
+    /// Adds another thread's counts to these.
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.nodes += other.nodes;
+        self.qnodes += other.qnodes;
+        for (a, b) in self.fail_highs.iter_mut().zip(other.fail_highs) {
+            *a += b;
+        }
+        self.lmr_researches += other.lmr_researches;
+        self.pvs_researches += other.pvs_researches;
+        self.null_move_tries += other.null_move_tries;
+        self.null_move_cutoffs += other.null_move_cutoffs;
+        self.null_move_refuted += other.null_move_refuted;
+        self.tt_probes += other.tt_probes;
+        self.tt_hits += other.tt_hits;
+    }
+
+    pub fn total_fail_highs(&self) -> u64 {
+        self.fail_highs.iter().sum()
+    }
+
+    /// Percentage of cutoffs made by the first move tried, the usual
+    /// measure of move ordering.
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        percent(self.fail_highs[0], self.total_fail_highs())
+    }
+
+    pub fn tt_hit_rate(&self) -> f64 {
+        percent(self.tt_hits, self.tt_probes)
+    }
+
+    /// Percentage of the nodes spent in quiescence search.
+    pub fn qsearch_ratio(&self) -> f64 {
+        percent(self.qnodes, self.nodes)
+    }
+
+    /// The headline numbers on one line, for an `info string`.
+    pub fn summary(&self) -> String {
+        format!(
+            "stats fail-high first {:.1}% lmr re-searches {} pvs re-searches {} \
+             null cutoffs {}/{} tt hits {:.1}% qnodes {:.1}%",
+            self.first_move_cutoff_rate(),
+            self.lmr_researches,
+            self.pvs_researches,
+            self.null_move_cutoffs,
+            self.null_move_tries,
+            self.tt_hit_rate(),
+            self.qsearch_ratio()
+        )
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type": "stats",
+            "nodes": self.nodes,
+            "qnodes": self.qnodes,
+            "fail_highs": self.fail_highs,
+            "lmr_researches": self.lmr_researches,
+            "pvs_researches": self.pvs_researches,
+            "null_move_tries": self.null_move_tries,
+            "null_move_cutoffs": self.null_move_cutoffs,
+            "null_move_refuted": self.null_move_refuted,
+            "tt_probes": self.tt_probes,
+            "tt_hits": self.tt_hits,
+        })
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fail_highs = self.total_fail_highs();
+        writeln!(f, "Nodes:            {}", self.nodes)?;
+        writeln!(
+            f,
+            "Quiescence nodes: {} ({:.1}%)",
+            self.qnodes,
+            self.qsearch_ratio()
+        )?;
+        writeln!(f, "Fail highs:       {fail_highs}")?;
+        for (i, &n) in self.fail_highs.iter().enumerate() {
+            let label = if i + 1 == FAIL_HIGH_SLOTS {
+                format!("{}+", i + 1)
+            } else {
+                (i + 1).to_string()
+            };
+            writeln!(
+                f,
+                "  move {label:<3}       {n} ({:.1}%)",
+                percent(n, fail_highs)
+            )?;
+        }
+        writeln!(f, "LMR re-searches:  {}", self.lmr_researches)?;
+        writeln!(f, "PVS re-searches:  {}", self.pvs_researches)?;
+        writeln!(
+            f,
+            "Null move:        {} tried, {} cut, {} refuted by verification",
+            self.null_move_tries, self.null_move_cutoffs, self.null_move_refuted
+        )?;
+        writeln!(
+            f,
+            "TT hits:          {}/{} ({:.1}%)",
+            self.tt_hits,
+            self.tt_probes,
+            self.tt_hit_rate()
+        )
+    }
+}
+
+/// Receives search progress, for embedding the engine without parsing stdout.
+pub trait InfoCallback: Send {
+    fn info(&mut self, info: &SearchInfo);
+
+    fn progress(&mut self, _progress: &SearchProgress) {}
+
+    /// The root move now being searched, numbered from 1, once the search
+    /// has been running for a few seconds.
+    fn currmove(&mut self, _depth: usize, _m: Move, _number: usize) {}
+
+    /// Called once the search has finished, before its result is returned.
+    fn finished(&mut self, _result: &SearchResult) {}
+}
+
+/// Prints every iteration as a UCI `info` line, or as JSON when the
+/// `OutputFormat` option asks for it.
+pub struct UciInfoPrinter;
+
+impl InfoCallback for UciInfoPrinter {
+    fn info(&mut self, info: &SearchInfo) {
+        if output::is_json() {
+            output::emit(&info.to_json());
+        } else {
+            uci_io::send(info.to_uci());
+        }
+    }
+
+    fn progress(&mut self, progress: &SearchProgress) {
+        if output::is_json() {
+            output::emit(&progress.to_json());
+        } else {
+            uci_io::send(progress.to_uci());
+        }
+    }
+
+    fn currmove(&mut self, depth: usize, m: Move, number: usize) {
+        if output::is_json() {
+            output::emit(&serde_json::json!({
+                "type": "currmove",
+                "depth": depth,
+                "move": format_uci(m),
+                "number": number,
+            }));
+        } else {
+            uci_io::send(format!(
+                "info depth {depth} currmove {} currmovenumber {number}",
+                format_uci(m)
+            ));
+        }
+    }
+}
+
+/// Searches with a `(soft, hard)` budget in milliseconds, as given by
+/// [`TimeControl::allocation_ms`](crate::time::TimeControl::allocation_ms).
 pub fn best_move_timed(
     b: &Board,
     tt: &mut SharedTransTable,
-    time_ms: u64,
+    (soft_ms, hard_ms): (u64, u64),
     max_depth: usize,
     stop_signal: Arc<AtomicBool>,
     is_main_thread: bool,
 ) -> (Option<Move>, usize, u64) {
+    let limits = SearchLimits {
+        hard_time_ms: hard_ms,
+        ..SearchLimits::movetime(soft_ms, max_depth)
+    };
+    let mut printer = UciInfoPrinter;
+    let info: Option<&mut dyn InfoCallback> = if is_main_thread {
+        Some(&mut printer)
+    } else {
+        None
+    };
+    let nodes = Arc::new(AtomicU64::new(0));
+    let r = search_with_limits(b, tt, limits, stop_signal, nodes, is_main_thread, info);
+    (r.best_move, max_depth, r.nodes)
+}
+
+/// Iterative deepening under `limits`. Only the main thread enforces the
+/// limits and raises `stop_signal`; progress goes to `info` if given.
+/// Threads searching together share `nodes`, so the main thread can report
+/// their combined count.
+pub fn search_with_limits(
+    b: &Board,
+    tt: &mut SharedTransTable,
+    limits: SearchLimits,
+    stop_signal: Arc<AtomicBool>,
+    nodes: Arc<AtomicU64>,
+    is_main_thread: bool,
+    info: Option<&mut dyn InfoCallback>,
+) -> SearchResult {
+    let controller = SearchController::new(&limits, stop_signal, nodes, is_main_thread);
+    iterate(b, tt, limits, controller, info, &mut SearchContext::new())
+}
+
+/// Lazy-SMP search threads. Every thread searches the same position,
+/// sharing only the transposition table, the stop flag and the node count;
+/// each keeps its own killers and histories in a [`SearchContext`], carried
+/// from one search of the game to the next.
+pub struct ThreadPool {
+    /// One per thread; the first belongs to the main thread.
+    contexts: Vec<SearchContext>,
+}
+
+impl ThreadPool {
+    pub fn new(threads: usize) -> Self {
+        let mut pool = Self {
+            contexts: Vec::new(),
+        };
+        pool.set_threads(threads);
+        pool
+    }
+
+    pub fn threads(&self) -> usize {
+        self.contexts.len()
+    }
+
+    /// Adds or drops helper threads, keeping the tables of those that stay.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.contexts
+            .resize_with(threads.max(1), SearchContext::new);
+    }
+
+    /// Forgets every thread's move-ordering tables, for a new game.
+    pub fn clear(&mut self) {
+        self.contexts.iter_mut().for_each(SearchContext::clear);
+    }
+
+    /// Searches `b` under `limits` on every thread. The calling thread is
+    /// the main one: it enforces the limits, reports to `info` and raises
+    /// `stop_signal` when done, which ends the helpers.
+    pub fn search(
+        &mut self,
+        b: &Board,
+        tt: &mut SharedTransTable,
+        limits: SearchLimits,
+        stop_signal: Arc<AtomicBool>,
+        info: Option<&mut dyn InfoCallback>,
+    ) -> SearchResult {
+        let nodes = Arc::new(AtomicU64::new(0));
+        let helper_limits = SearchLimits {
+            root_moves: limits.root_moves.clone(),
+            config: limits.config,
+            ..SearchLimits::movetime(u64::MAX / 4, limits.max_depth.min(MAX_PLY))
+        };
+        let (main_ctx, helper_ctxs) = self
+            .contexts
+            .split_first_mut()
+            .expect("a pool has at least one thread");
+
+        thread::scope(|scope| {
+            let helpers: Vec<_> = helper_ctxs
+                .iter_mut()
+                .enumerate()
+                .filter_map(|(i, ctx)| {
+                    let board = b.clone();
+                    let mut tt = tt.clone();
+                    let limits = helper_limits.clone();
+                    let controller = SearchController::new(
+                        &limits,
+                        Arc::clone(&stop_signal),
+                        Arc::clone(&nodes),
+                        false,
+                    );
+                    thread::Builder::new()
+                        .name(format!("search-helper-{i}"))
+                        .spawn_scoped(scope, move || {
+                            ctx.age();
+                            iterate(&board, &mut tt, limits, controller, None, ctx)
+                        })
+                        .ok()
+                })
+                .collect();
+
+            main_ctx.age();
+            let controller = SearchController::new(&limits, Arc::clone(&stop_signal), nodes, true);
+            let mut result = iterate(b, tt, limits, controller, info, main_ctx);
+            stop_signal.store(true, Ordering::Relaxed);
+            for h in helpers {
+                if let Ok(helper) = h.join() {
+                    result.stats.merge(&helper.stats);
+                }
+            }
+            result
+        })
+    }
+}
+
+fn iterate(
+    b: &Board,
+    tt: &mut SharedTransTable,
+    limits: SearchLimits,
+    controller: SearchController,
+    info: Option<&mut dyn InfoCallback>,
+    ctx: &mut SearchContext,
+) -> SearchResult {
+    let SearchLimits {
+        time_ms, max_depth, ..
+    } = limits;
+    let soft_limit = Duration::from_millis(time_ms);
+    let is_main_thread = controller.is_main_thread;
     if is_main_thread {
         tt.tick_age();
     }
@@ -452,26 +1430,44 @@ pub fn best_move_timed(
     let mut search = Search {
         board: b.clone(),
         tt,
-        controller: SearchController {
-            start_time: Instant::now(),
-            time_budget: Duration::from_millis(time_ms),
-            stop_signal,
-            is_main_thread,
-            nodes: 0,
-        },
-        killers: [[None; 2]; MAX_PLY],
-        history: [[0; 64]; 13],
-        counter_moves: [[[None; 64]; 13]; 2],
+        controller,
+        info: info.map(|cb| cb as &mut dyn InfoCallback),
+        last_progress: Instant::now(),
+        ctx,
         ply: 0,
+        root_history_len: b.history.len(),
         seldepth: 0,
         prev_move: [None; MAX_PLY],
+        root_moves: limits.root_moves,
+        root_best: None,
+        root_scores: Vec::new(),
+        want_root_scores: limits.root_scores,
+        config: limits.config,
+        pv: PvTable::new(),
+        excluded: [None; MAX_PLY],
+        null_move: [false; MAX_PLY],
+        eval_stack: [None; MAX_PLY],
+        pickers: (0..MAX_PLY).map(|_| MovePicker::noisy()).collect(),
+        quiets: (0..MAX_PLY).map(|_| MoveList::new()).collect(),
+        stats: SearchStats::default(),
     };
 
     let mut best_move: Option<Move> = None;
     let mut score = 0;
+    let mut best_score = 0;
+    let mut root_scores = Vec::new();
+    let mut completed_depth = 0;
+    let mut stable_iterations = 0;
+    let mut easy_checked = false;
+    let mut easy_move = false;
+    let mut last_iteration_nodes = 0u64;
+    // Decaying count of best-move changes between iterations.
+    let mut best_move_changes = 0.0;
 
     for d in 1..=max_depth {
         search.seldepth = 0;
+        let iteration_start = Instant::now();
+        let nodes_before = search.controller.nodes;
         let (mut alpha, mut beta) = if d > 3 {
             (score - 40, score + 40)
         } else {
@@ -496,49 +1492,167 @@ pub fn best_move_timed(
         if search.controller.time_is_up() {
             break;
         }
+        let score_drop = if completed_depth > 0 {
+            (best_score - score).clamp(0, SCORE_DROP_FULL_CP)
+        } else {
+            0
+        };
+        best_score = score;
+        completed_depth = d;
+        root_scores.clone_from(&search.root_scores);
+
+        let new_best = search.root_best;
+        best_move_changes *= 0.5;
+        if new_best == best_move {
+            stable_iterations += 1;
+        } else {
+            stable_iterations = 0;
+            easy_checked = false;
+            easy_move = false;
+            if best_move.is_some() {
+                best_move_changes += 1.0;
+            }
+        }
+        best_move = new_best;
+
+        search.last_progress = Instant::now();
+        if let Some(cb) = search.info.as_deref_mut() {
+            let elapsed_ms = search.controller.start_time.elapsed().as_millis() as u64;
+            let nodes = search.controller.total_nodes();
+            cb.info(&SearchInfo {
+                depth: d,
+                seldepth: search.seldepth,
+                score,
+                nodes,
+                nps: (nodes * 1000).checked_div(elapsed_ms).unwrap_or(0),
+                time_ms: elapsed_ms,
+                hashfull: search.tt.hashfull_permill(),
+                pv: search.pv.line(0).to_vec(),
+                show_wdl: search.config.show_wdl,
+            });
+        }
 
-        if let Some(entry) = search.tt.probe(search.board.zobrist) {
-            best_move = entry.best_move();
+        // Once the iteration is deep enough to have seen every shorter mate,
+        // the distance can't improve.
+        if is_mate(score) && (MATE_SCORE - score.abs()) as usize <= d {
+            break;
         }
 
-        if is_main_thread {
-            let elapsed_ms = search.controller.start_time.elapsed().as_millis();
-            let nps = if elapsed_ms > 0 {
-                (search.controller.nodes * 1000) / elapsed_ms as u64
-            } else {
-                0
-            };
+        // Easy move: one clearly best, stable root move is played early.
+        if is_main_thread
+            && !easy_checked
+            && d >= EASY_MOVE_MIN_DEPTH
+            && stable_iterations >= EASY_MOVE_STABILITY
+            && let Some(best) = best_move
+        {
+            easy_checked = true;
+            easy_move = is_easy_move(&mut search, best, score, d as i32 / 2);
+        }
+        if easy_move
+            && search.controller.start_time.elapsed() >= soft_limit / EASY_MOVE_TIME_DIVISOR
+        {
+            break;
+        }
 
-            let hashfull = search.tt.hashfull_permill();
-            let pv = get_pv_from_tt(search.board.clone(), search.tt, d);
-            let pv_str = pv
-                .iter()
-                .map(|&m| format_uci(m))
-                .collect::<Vec<_>>()
-                .join(" ");
-            let score_str = if score.abs() > MATE_THRESHOLD {
-                let mate_in = (MATE_SCORE - score.abs() + 1) / 2;
-                format!("mate {}", if score > 0 { mate_in } else { -mate_in })
-            } else {
-                format!("cp {}", score)
-            };
-            println!(
-                "info depth {} seldepth {} score {} hashfull {} nodes {} nps {} time {} pv {}",
-                d,
-                search.seldepth,
-                score_str,
-                hashfull,
-                search.controller.nodes,
-                nps,
-                elapsed_ms,
-                pv_str
-            );
+        // Spend longer while the best move keeps changing or the score is
+        // falling, less once it has settled; never past the hard limit. A
+        // fixed move time (no room above the soft limit) is used in full.
+        let scale = (1.0 + best_move_changes)
+            * (1.0 + SCORE_DROP_BONUS * score_drop as f64 / SCORE_DROP_FULL_CP as f64)
+            * (1.0 - STABILITY_CREDIT * stable_iterations.min(6) as f64);
+        let scaled_limit = if search.controller.hard_limit > soft_limit {
+            soft_limit
+                .mul_f64(scale.clamp(SOFT_SCALE_MIN, SOFT_SCALE_MAX))
+                .min(search.controller.hard_limit)
+        } else {
+            soft_limit
+        };
+        let elapsed = search.controller.start_time.elapsed();
+        if is_main_thread && elapsed >= scaled_limit {
+            break;
         }
 
-        if score.abs() > MATE_THRESHOLD {
-            break; // Mate found, no need to search deeper.
+        // Don't start an iteration that the effective branching factor says
+        // can't finish within the remaining soft budget: an aborted
+        // iteration yields nothing usable.
+        let iteration_nodes = search.controller.nodes - nodes_before;
+        if is_main_thread && d >= EBF_MIN_DEPTH && last_iteration_nodes > 0 {
+            let ebf = (iteration_nodes as f64 / last_iteration_nodes as f64).max(1.0);
+            let factor = search.config.iteration_skip_percent as f64 / 100.0;
+            let predicted = iteration_start.elapsed().mul_f64(ebf * factor);
+            let remaining = scaled_limit.saturating_sub(elapsed);
+            if predicted > remaining {
+                break;
+            }
         }
+        last_iteration_nodes = iteration_nodes;
+    }
+
+    // A search stopped inside its first iteration still owes a move.
+    if best_move.is_none() {
+        best_move = search.root_best.or_else(|| fallback_root_move(&search));
+    }
+
+    search.controller.flush_nodes();
+    search.stats.nodes = search.controller.nodes;
+    let result = SearchResult {
+        best_move,
+        score: best_score,
+        depth: completed_depth,
+        nodes: search.controller.total_nodes(),
+        root_scores,
+        stats: search.stats,
+    };
+    if let Some(cb) = search.info {
+        cb.finished(&result);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::{self, EvalMode};
+
+    /// Best move of a fixed-depth, single-threaded search with the
+    /// classical evaluation.
+    fn search(fen: &str, depth: usize) -> Option<String> {
+        eval::set_eval_mode(EvalMode::Classical);
+        let b = Board::from_fen(fen).unwrap();
+        let mut tt = SharedTransTable::new(16);
+        let limits = SearchLimits::movetime(u64::MAX / 4, depth);
+        let stop = Arc::new(AtomicBool::new(false));
+        let nodes = Arc::new(AtomicU64::new(0));
+        let r = search_with_limits(&b, &mut tt, limits, stop, nodes, true, None);
+        r.best_move.map(format_uci)
+    }
+
+    #[test]
+    fn finds_back_rank_mate() {
+        let m = search("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", 4);
+        assert_eq!(m.as_deref(), Some("d1d8"));
     }
 
-    (best_move, max_depth, search.controller.nodes)
+    #[test]
+    fn takes_hanging_queen() {
+        let m = search("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 5);
+        assert_eq!(m.as_deref(), Some("d1d5"));
+    }
+
+    #[test]
+    fn finds_knight_fork() {
+        let m = search("r3k3/8/8/1N6/8/8/8/4K3 w - - 0 1", 6);
+        assert_eq!(m.as_deref(), Some("b5c7"));
+    }
+
+    /// Deep enough for singular extensions and multicut to run at inner
+    /// nodes; pruning there must not lose the move a shallow search finds.
+    #[test]
+    fn multicut_keeps_best_move() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+        let shallow = search(fen, 3);
+        let deep = search(fen, SINGULAR_MIN_DEPTH as usize + 1);
+        assert_eq!(shallow.as_deref(), Some("h5f7"));
+        assert_eq!(deep, shallow);
+    }
 }
@@ -1,8 +1,8 @@
+use crate::attacks;
 use crate::board::Board;
-use crate::magics;
-use crate::types::{Move, Piece, PieceKind};
+use crate::types::{Bitboard, Color, Move, Piece, PieceKind};
 
-const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20000]; // P, N, B, R, Q, K
+pub const PIECE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20000]; // P, N, B, R, Q, K
 
 #[inline(always)]
 fn val(p: Piece) -> i32 {
@@ -13,134 +13,98 @@ fn val(p: Piece) -> i32 {
     }
 }
 
-fn get_attackers(
-    b: &Board,
-    sq: usize,
-    occupied: u64,
-    side: crate::types::Color,
-) -> (u64, Option<(Piece, usize)>) {
-    let mut attackers = 0u64;
-
-    let pawn_kind = Piece::from_kind(PieceKind::Pawn, side);
-    let pawn_attacks = if side == crate::types::Color::White {
-        magics::BLACK_PAWN_ATTACKS[sq]
-    } else {
-        magics::WHITE_PAWN_ATTACKS[sq]
-    };
-    let pawns = b.piece_bb[pawn_kind.index()] & occupied;
-    let mut current_attackers = pawn_attacks & pawns;
-    if current_attackers != 0 {
-        attackers |= current_attackers;
-        return (
-            attackers,
-            Some((pawn_kind, current_attackers.trailing_zeros() as usize)),
-        );
-    }
-
-    let knight_kind = Piece::from_kind(PieceKind::Knight, side);
-    let knights = b.piece_bb[knight_kind.index()] & occupied;
-    current_attackers = magics::knight_attacks_from(sq) & knights;
-    if current_attackers != 0 {
-        attackers |= current_attackers;
-        return (
-            attackers,
-            Some((knight_kind, current_attackers.trailing_zeros() as usize)),
-        );
-    }
-
-    let bishop_kind = Piece::from_kind(PieceKind::Bishop, side);
-    let bishop_attacks = magics::get_bishop_attacks(sq, occupied);
-    let bishops = b.piece_bb[bishop_kind.index()] & occupied;
-    current_attackers = bishop_attacks & bishops;
-    if current_attackers != 0 {
-        attackers |= current_attackers;
-        return (
-            attackers,
-            Some((bishop_kind, current_attackers.trailing_zeros() as usize)),
-        );
-    }
-
-    let rook_kind = Piece::from_kind(PieceKind::Rook, side);
-    let rook_attacks = magics::get_rook_attacks(sq, occupied);
-    let rooks = b.piece_bb[rook_kind.index()] & occupied;
-    current_attackers = rook_attacks & rooks;
-    if current_attackers != 0 {
-        attackers |= current_attackers;
-        return (
-            attackers,
-            Some((rook_kind, current_attackers.trailing_zeros() as usize)),
-        );
-    }
+const LVA_ORDER: [PieceKind; 6] = [
+    PieceKind::Pawn,
+    PieceKind::Knight,
+    PieceKind::Bishop,
+    PieceKind::Rook,
+    PieceKind::Queen,
+    PieceKind::King,
+];
 
-    let queen_kind = Piece::from_kind(PieceKind::Queen, side);
-    let queens = b.piece_bb[queen_kind.index()] & occupied;
-    current_attackers = (bishop_attacks | rook_attacks) & queens;
-    if current_attackers != 0 {
-        attackers |= current_attackers;
-        return (
-            attackers,
-            Some((queen_kind, current_attackers.trailing_zeros() as usize)),
-        );
-    }
-
-    let king_kind = Piece::from_kind(PieceKind::King, side);
-    let kings = b.piece_bb[king_kind.index()] & occupied;
-    current_attackers = magics::king_attacks_from(sq) & kings;
-    if current_attackers != 0 {
-        attackers |= current_attackers;
-        return (
-            attackers,
-            Some((king_kind, current_attackers.trailing_zeros() as usize)),
-        );
-    }
-
-    (attackers, None)
+/// The least valuable of `side`'s pieces in `attackers`.
+#[inline(always)]
+fn least_valuable(b: &Board, attackers: Bitboard, side: Color) -> Option<(Piece, usize)> {
+    LVA_ORDER.iter().find_map(|&kind| {
+        let piece = Piece::from_kind(kind, side);
+        let bb = attackers & b.piece_bb[piece.index()];
+        (bb != 0).then(|| (piece, bb.trailing_zeros() as usize))
+    })
 }
 
+/// Static exchange value of `mov` for the side making it. A quiet move
+/// scores the exchange started by moving onto its destination: zero if the
+/// square is safe, otherwise what the opponent's recaptures win.
+///
+/// The attackers of the square are found once; after each capture only the
+/// sliders the capturing piece was screening are added.
 pub fn see(b: &Board, mov: Move) -> i32 {
-    if !mov.capture {
-        return 0;
-    }
-
-    let from_sq = mov.from as usize;
-    let to_sq = mov.to as usize;
+    let from_sq = mov.from().index();
+    let to_sq = mov.to().index();
 
     let mut gain = [0; 32];
     let mut gain_idx = 1;
 
     let mut from_piece = b.piece_on[from_sq];
-    let mut occupied = b.all_pieces;
+    let mut occupied = b.all_pieces ^ (1u64 << from_sq);
     let mut current_turn = b.turn;
 
-    let captured_piece = if mov.en_passant {
+    let captured_piece = if mov.is_en_passant() {
+        occupied ^= 1u64 << (to_sq ^ 8);
         Piece::from_kind(PieceKind::Pawn, b.turn.other())
     } else {
         b.piece_on[to_sq]
     };
-
     gain[0] = val(captured_piece);
 
-    occupied ^= 1u64 << from_sq;
-
-    loop {
+    let bb = &b.piece_bb;
+    let diagonal = bb[Piece::WB.index()]
+        | bb[Piece::BB.index()]
+        | bb[Piece::WQ.index()]
+        | bb[Piece::BQ.index()];
+    let orthogonal = bb[Piece::WR.index()]
+        | bb[Piece::BR.index()]
+        | bb[Piece::WQ.index()]
+        | bb[Piece::BQ.index()];
+    let mut attackers = b.attackers_to(mov.to(), occupied) & occupied;
+
+    while gain_idx < gain.len() {
         current_turn = current_turn.other();
+        let side_pieces = if current_turn == Color::White {
+            b.w_pieces
+        } else {
+            b.b_pieces
+        };
+        let Some((attacker_piece, attacker_sq)) =
+            least_valuable(b, attackers & side_pieces, current_turn)
+        else {
+            break;
+        };
+        // A king can't capture onto a square the other side still covers.
+        if attacker_piece.kind() == Some(PieceKind::King) && attackers & !side_pieces != 0 {
+            break;
+        }
 
-        let (_, lva) = get_attackers(b, to_sq, occupied, current_turn);
-
-        if let Some((attacker_piece, attacker_sq)) = lva {
-            occupied ^= 1u64 << attacker_sq;
+        gain[gain_idx] = val(from_piece) - gain[gain_idx - 1];
+        gain_idx += 1;
 
-            if gain_idx >= 32 {
-                break;
+        occupied ^= 1u64 << attacker_sq;
+        // X-rays: pieces moving off a line uncover the sliders behind them.
+        match attacker_piece.kind() {
+            Some(PieceKind::Pawn | PieceKind::Bishop) => {
+                attackers |= attacks::bishop(to_sq, occupied) & diagonal;
             }
-
-            gain[gain_idx] = val(from_piece) - gain[gain_idx - 1];
-            gain_idx += 1;
-
-            from_piece = attacker_piece;
-        } else {
-            break;
+            Some(PieceKind::Rook) => {
+                attackers |= attacks::rook(to_sq, occupied) & orthogonal;
+            }
+            Some(PieceKind::Queen) => {
+                attackers |= attacks::bishop(to_sq, occupied) & diagonal;
+                attackers |= attacks::rook(to_sq, occupied) & orthogonal;
+            }
+            _ => {}
         }
+        attackers &= occupied;
+        from_piece = attacker_piece;
     }
 
     while gain_idx > 1 {
@@ -150,3 +114,55 @@ pub fn see(b: &Board, mov: Move) -> i32 {
 
     gain[0]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uci_io::parse_uci_move;
+
+    fn see_of(fen: &str, uci: &str) -> i32 {
+        let mut b = Board::from_fen(fen).unwrap();
+        let m = parse_uci_move(&mut b, uci).unwrap();
+        see(&b, m)
+    }
+
+    #[test]
+    fn simple_exchanges() {
+        // Pawn takes a knight the enemy pawn defends.
+        assert_eq!(see_of("4k3/8/3p4/4n3/3P4/8/8/4K3 w - - 0 1", "d4e5"), 220);
+        // Rook takes a defended pawn.
+        assert_eq!(see_of("4k3/3p4/4p3/8/8/8/4R3/4K3 w - - 0 1", "e2e6"), -400);
+        // Undefended piece.
+        assert_eq!(see_of("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", "d1d5"), 900);
+    }
+
+    #[test]
+    fn quiet_move_onto_attacked_square() {
+        assert_eq!(see_of("4k3/8/2p5/8/8/8/8/3QK3 w - - 0 1", "d1d5"), -900);
+        assert_eq!(see_of("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", "d1d5"), 0);
+    }
+
+    #[test]
+    fn rook_battery() {
+        // The second rook only joins once the first has left the file.
+        assert_eq!(see_of("4k3/4r3/8/4p3/8/8/4R3/4R2K w - - 0 1", "e2e5"), 100);
+        assert_eq!(see_of("4k3/4r3/8/4p3/8/8/4R3/7K w - - 0 1", "e2e5"), -400);
+    }
+
+    #[test]
+    fn slider_behind_pawn() {
+        // exd5 cxd5 Bxd5: the bishop fires through the pawn's square.
+        assert_eq!(see_of("4k3/8/2p5/3p4/4P3/5B2/8/4K3 w - - 0 1", "e4d5"), 100);
+        // Black's queen behind its rook backs up the recapture.
+        assert_eq!(
+            see_of("3qk3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5"),
+            -400
+        );
+    }
+
+    #[test]
+    fn king_does_not_recapture_into_a_defended_square() {
+        assert_eq!(see_of("8/8/8/4k3/3p4/8/3R4/3RK3 w - - 0 1", "d2d4"), 100);
+        assert_eq!(see_of("8/8/8/4k3/3p4/8/3R4/4K3 w - - 0 1", "d2d4"), -400);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::types::Bitboard;
+
+#[cfg(feature = "portable_simd")]
+use std::simd::{num::SimdUint, u64x4, u64x8};
+
+// File masks, a-file first.
+const FILES: [u64; 8] = [
+    0x0101_0101_0101_0101,
+    0x0202_0202_0202_0202,
+    0x0404_0404_0404_0404,
+    0x0808_0808_0808_0808,
+    0x1010_1010_1010_1010,
+    0x2020_2020_2020_2020,
+    0x4040_4040_4040_4040,
+    0x8080_8080_8080_8080,
+];
+
+/// Most non-pawn, non-king pieces a side can have (eight of them promoted).
+pub const MAX_PIECES: usize = 16;
+
+/// Number of set bits of `bb` on each file.
+#[cfg(feature = "portable_simd")]
+#[inline]
+pub fn file_counts(bb: Bitboard) -> [u32; 8] {
+    let counts = (u64x8::splat(bb) & u64x8::from_array(FILES)).count_ones();
+    counts.to_array().map(|c| c as u32)
+}
+
+#[cfg(not(feature = "portable_simd"))]
+#[inline]
+pub fn file_counts(bb: Bitboard) -> [u32; 8] {
+    FILES.map(|f| (bb & f).count_ones())
+}
+
+/// `popcount(bbs[i] & mask)` for every entry; entries past `bbs.len()` are zero.
+#[cfg(feature = "portable_simd")]
+#[inline]
+pub fn masked_popcounts(bbs: &[Bitboard; MAX_PIECES], mask: Bitboard) -> [u32; MAX_PIECES] {
+    let mut out = [0u32; MAX_PIECES];
+    let mask = u64x4::splat(mask);
+    for (chunk, dst) in bbs.chunks_exact(4).zip(out.chunks_exact_mut(4)) {
+        let counts = (u64x4::from_slice(chunk) & mask).count_ones();
+        for (d, c) in dst.iter_mut().zip(counts.to_array()) {
+            *d = c as u32;
+        }
+    }
+    out
+}
+
+#[cfg(not(feature = "portable_simd"))]
+#[inline]
+pub fn masked_popcounts(bbs: &[Bitboard; MAX_PIECES], mask: Bitboard) -> [u32; MAX_PIECES] {
+    bbs.map(|bb| (bb & mask).count_ones())
+}
@@ -0,0 +1,58 @@
+use crate::opponent::ENGINE_RATING;
+use crate::types::Move;
+use rand::Rng;
+
+pub const MIN_ELO: i64 = 1320;
+pub const MAX_ELO: i64 = ENGINE_RATING as i64;
+pub const DEFAULT_ELO: i64 = 1500;
+
+const MAX_LEVEL: f64 = 20.0;
+// Root moves the weakened engine chooses between, best first.
+const CANDIDATES: usize = 4;
+const PAWN: i32 = 100;
+
+/// Playing strength for `UCI_LimitStrength`: a level from 0 to 20 derived
+/// from `UCI_Elo`, which caps the search depth and how far from the best
+/// move the engine may stray.
+#[derive(Clone, Copy, Debug)]
+pub struct Skill {
+    level: f64,
+}
+
+impl Skill {
+    pub fn from_elo(elo: i64) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        Self {
+            level: (elo - MIN_ELO) as f64 / (MAX_ELO - MIN_ELO) as f64 * MAX_LEVEL,
+        }
+    }
+
+    pub fn max_depth(&self) -> usize {
+        1 + (self.level / 2.0) as usize
+    }
+
+    /// Picks one of the best few root moves of a search run with
+    /// [`SearchLimits::root_scores`](crate::search::SearchLimits::root_scores),
+    /// leaning towards weaker moves the lower the level. `None` when there
+    /// are no scores.
+    pub fn pick_move(&self, root_scores: &[(Move, i32)]) -> Option<Move> {
+        let mut scored = root_scores.to_vec();
+        scored.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        scored.truncate(CANDIDATES);
+
+        let top = scored.first()?.1;
+        let bottom = scored.last()?.1;
+        let delta = (top - bottom).min(PAWN) as f64;
+        let weakness = 120.0 - 2.0 * self.level;
+        let mut rng = rand::thread_rng();
+        scored
+            .into_iter()
+            .map(|(m, score)| {
+                let push = (weakness * (top - score) as f64 + delta * rng.gen_range(0.0..weakness))
+                    / 128.0;
+                (m, score as f64 + push)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(m, _)| m)
+    }
+}
@@ -0,0 +1,83 @@
+use crate::gauntlet::Tally;
+
+/// Sequential probability ratio test between two Elo hypotheses: `elo0`
+/// (the patch is no better than this) and `elo1` (it is at least this good).
+#[derive(Clone, Copy, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    /// False positive and false negative rates.
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SprtResult {
+    AcceptH0,
+    AcceptH1,
+    Continue,
+}
+
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl Sprt {
+    /// Parses `<elo0>,<elo1>`, e.g. `0,5`.
+    pub fn parse(s: &str, alpha: f64, beta: f64) -> Result<Self, String> {
+        let (e0, e1) = s
+            .split_once(',')
+            .ok_or_else(|| format!("sprt: expected <elo0>,<elo1>, got '{s}'"))?;
+        let elo = |v: &str| {
+            v.trim()
+                .parse::<f64>()
+                .map_err(|_| format!("sprt: bad number '{v}' in '{s}'"))
+        };
+        let (elo0, elo1) = (elo(e0)?, elo(e1)?);
+        if elo0 >= elo1 {
+            return Err(format!("sprt: elo0 must be below elo1 in '{s}'"));
+        }
+        Ok(Self {
+            elo0,
+            elo1,
+            alpha,
+            beta,
+        })
+    }
+
+    /// Lower and upper log-likelihood ratio bounds.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    /// Log-likelihood ratio of H1 against H0, using the normal approximation
+    /// to the trinomial win/draw/loss distribution.
+    pub fn llr(&self, t: &Tally) -> f64 {
+        let n = t.games() as f64;
+        if t.wins == 0 || t.losses == 0 {
+            return 0.0;
+        }
+        let p = t.score();
+        let var = (t.wins as f64 * (1.0 - p).powi(2)
+            + t.draws as f64 * (0.5 - p).powi(2)
+            + t.losses as f64 * p.powi(2))
+            / n;
+        let (s0, s1) = (expected_score(self.elo0), expected_score(self.elo1));
+        (s1 - s0) * (2.0 * p - s0 - s1) * n / (2.0 * var)
+    }
+
+    pub fn status(&self, t: &Tally) -> SprtResult {
+        let llr = self.llr(t);
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtResult::AcceptH1
+        } else if llr <= lower {
+            SprtResult::AcceptH0
+        } else {
+            SprtResult::Continue
+        }
+    }
+}
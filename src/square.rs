@@ -0,0 +1,303 @@
+//! Board coordinates. Squares are numbered a1 = 0, b1 = 1, ..., h8 = 63, so
+//! a square's bit in a [`Bitboard`] is `1 << index`.
+
+use crate::types::Bitboard;
+use std::fmt;
+use std::str::FromStr;
+
+/// A file, `a` = 0 through `h` = 7.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct File(u8);
+
+/// A rank, `1` = 0 through `8` = 7.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Rank(u8);
+
+/// One of the 64 squares.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct Square(u8);
+
+impl File {
+    pub const A: File = File(0);
+    pub const B: File = File(1);
+    pub const C: File = File(2);
+    pub const D: File = File(3);
+    pub const E: File = File(4);
+    pub const F: File = File(5);
+    pub const G: File = File(6);
+    pub const H: File = File(7);
+
+    /// `None` unless `index < 8`.
+    #[inline(always)]
+    pub const fn new(index: u8) -> Option<Self> {
+        if index < 8 { Some(Self(index)) } else { None }
+    }
+
+    #[inline(always)]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Every square on the file.
+    #[inline(always)]
+    pub const fn bb(self) -> Bitboard {
+        0x0101_0101_0101_0101 << self.0
+    }
+
+    /// The letter `a`-`h`.
+    #[inline(always)]
+    pub const fn to_char(self) -> char {
+        (b'a' + self.0) as char
+    }
+
+    pub fn from_char(c: char) -> Option<Self> {
+        ('a'..='h').contains(&c).then(|| Self(c as u8 - b'a'))
+    }
+
+    pub fn all() -> impl DoubleEndedIterator<Item = File> {
+        (0..8).map(File)
+    }
+}
+
+impl Rank {
+    pub const FIRST: Rank = Rank(0);
+    pub const SECOND: Rank = Rank(1);
+    pub const THIRD: Rank = Rank(2);
+    pub const FOURTH: Rank = Rank(3);
+    pub const FIFTH: Rank = Rank(4);
+    pub const SIXTH: Rank = Rank(5);
+    pub const SEVENTH: Rank = Rank(6);
+    pub const EIGHTH: Rank = Rank(7);
+
+    /// `None` unless `index < 8`.
+    #[inline(always)]
+    pub const fn new(index: u8) -> Option<Self> {
+        if index < 8 { Some(Self(index)) } else { None }
+    }
+
+    #[inline(always)]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// Every square on the rank.
+    #[inline(always)]
+    pub const fn bb(self) -> Bitboard {
+        0xFF << (8 * self.0)
+    }
+
+    /// The digit `1`-`8`.
+    #[inline(always)]
+    pub const fn to_char(self) -> char {
+        (b'1' + self.0) as char
+    }
+
+    pub fn from_char(c: char) -> Option<Self> {
+        ('1'..='8').contains(&c).then(|| Self(c as u8 - b'1'))
+    }
+
+    pub fn all() -> impl DoubleEndedIterator<Item = Rank> {
+        (0..8).map(Rank)
+    }
+}
+
+impl Square {
+    /// `None` unless `index < 64`.
+    #[inline(always)]
+    pub const fn new(index: u8) -> Option<Self> {
+        if index < 64 { Some(Self(index)) } else { None }
+    }
+
+    /// # Panics
+    /// If `index >= 64`.
+    #[inline(always)]
+    pub const fn from_index(index: usize) -> Self {
+        assert!(index < 64, "square index out of range");
+        Self(index as u8)
+    }
+
+    #[inline(always)]
+    pub const fn at(file: File, rank: Rank) -> Self {
+        Self(rank.0 * 8 + file.0)
+    }
+
+    #[inline(always)]
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    #[inline(always)]
+    pub const fn file(self) -> File {
+        File(self.0 & 7)
+    }
+
+    #[inline(always)]
+    pub const fn rank(self) -> Rank {
+        Rank(self.0 >> 3)
+    }
+
+    /// The square's bit.
+    #[inline(always)]
+    pub const fn bb(self) -> Bitboard {
+        1 << self.0
+    }
+
+    /// The same square seen from the other side of the board (a1 <-> a8).
+    #[inline(always)]
+    pub const fn flip(self) -> Self {
+        Self(self.0 ^ 56)
+    }
+
+    /// The square `files` to the right and `ranks` up, if it is on the board.
+    pub const fn offset(self, files: i8, ranks: i8) -> Option<Self> {
+        let f = (self.0 & 7) as i8 + files;
+        let r = (self.0 >> 3) as i8 + ranks;
+        if f >= 0 && f < 8 && r >= 0 && r < 8 {
+            Some(Self((r * 8 + f) as u8))
+        } else {
+            None
+        }
+    }
+
+    /// a1, b1, ..., h8.
+    pub fn all() -> impl DoubleEndedIterator<Item = Square> + ExactSizeIterator {
+        (0..64).map(Square)
+    }
+
+    /// The lowest square set in `bb`, if any.
+    #[inline(always)]
+    pub fn first(bb: Bitboard) -> Option<Self> {
+        (bb != 0).then(|| Self(bb.trailing_zeros() as u8))
+    }
+}
+
+macro_rules! square_consts {
+    ($($name:ident = $index:expr),* $(,)?) => {
+        impl Square {
+            $(pub const $name: Square = Square($index);)*
+        }
+    };
+}
+
+square_consts! {
+    A1 = 0, B1 = 1, C1 = 2, D1 = 3, E1 = 4, F1 = 5, G1 = 6, H1 = 7,
+    A2 = 8, B2 = 9, C2 = 10, D2 = 11, E2 = 12, F2 = 13, G2 = 14, H2 = 15,
+    A3 = 16, B3 = 17, C3 = 18, D3 = 19, E3 = 20, F3 = 21, G3 = 22, H3 = 23,
+    A4 = 24, B4 = 25, C4 = 26, D4 = 27, E4 = 28, F4 = 29, G4 = 30, H4 = 31,
+    A5 = 32, B5 = 33, C5 = 34, D5 = 35, E5 = 36, F5 = 37, G5 = 38, H5 = 39,
+    A6 = 40, B6 = 41, C6 = 42, D6 = 43, E6 = 44, F6 = 45, G6 = 46, H6 = 47,
+    A7 = 48, B7 = 49, C7 = 50, D7 = 51, E7 = 52, F7 = 53, G7 = 54, H7 = 55,
+    A8 = 56, B8 = 57, C8 = 58, D8 = 59, E8 = 60, F8 = 61, G8 = 62, H8 = 63,
+}
+
+/// The squares set in a bitboard, lowest first.
+#[derive(Clone, Copy)]
+pub struct Squares(pub Bitboard);
+
+impl Iterator for Squares {
+    type Item = Square;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<Square> {
+        let sq = Square::first(self.0)?;
+        self.0 &= self.0 - 1;
+        Some(sq)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.0.count_ones() as usize;
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for Squares {}
+
+impl From<Square> for usize {
+    #[inline(always)]
+    fn from(sq: Square) -> usize {
+        sq.index()
+    }
+}
+
+impl From<Square> for u8 {
+    #[inline(always)]
+    fn from(sq: Square) -> u8 {
+        sq.0
+    }
+}
+
+impl TryFrom<u8> for Square {
+    type Error = SquareError;
+
+    fn try_from(index: u8) -> Result<Self, SquareError> {
+        Square::new(index).ok_or(SquareError)
+    }
+}
+
+impl TryFrom<usize> for Square {
+    type Error = SquareError;
+
+    fn try_from(index: usize) -> Result<Self, SquareError> {
+        u8::try_from(index)
+            .ok()
+            .and_then(Square::new)
+            .ok_or(SquareError)
+    }
+}
+
+impl TryFrom<i32> for Square {
+    type Error = SquareError;
+
+    fn try_from(index: i32) -> Result<Self, SquareError> {
+        u8::try_from(index)
+            .ok()
+            .and_then(Square::new)
+            .ok_or(SquareError)
+    }
+}
+
+/// A square index off the board or text that is not a square name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SquareError;
+
+impl fmt::Display for SquareError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid square")
+    }
+}
+
+impl std::error::Error for SquareError {}
+
+impl FromStr for Square {
+    type Err = SquareError;
+
+    /// Parses a square name such as `e4`.
+    fn from_str(s: &str) -> Result<Self, SquareError> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next(), chars.next()) {
+            (Some(f), Some(r), None) => Ok(Square::at(
+                File::from_char(f).ok_or(SquareError)?,
+                Rank::from_char(r).ok_or(SquareError)?,
+            )),
+            _ => Err(SquareError),
+        }
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file(), self.rank())
+    }
+}
@@ -0,0 +1,208 @@
+use crate::board::Board;
+use crate::engine::Engine;
+use crate::error::ChessError;
+use crate::movelist::MoveList;
+use crate::san::parse_san;
+use crate::search::SearchLimits;
+use crate::types::Move;
+use crate::uci_io::parse_uci_move;
+use std::fmt::Write;
+use std::time::Instant;
+
+/// One EPD test position: the engine passes if it plays one of `best` (when
+/// given) and none of `avoid`.
+pub struct EpdTest {
+    pub id: String,
+    pub board: Board,
+    pub best: Vec<Move>,
+    pub avoid: Vec<Move>,
+}
+
+/// Splits the operations after the position into `(opcode, operands)`,
+/// keeping quoted operands whole.
+fn split_operations(text: &str) -> Vec<(String, Vec<String>)> {
+    let mut ops = Vec::new();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+                if let Some((op, operands)) = words.split_first() {
+                    ops.push((op.clone(), operands.to_vec()));
+                }
+                words.clear();
+            }
+            c if c.is_whitespace() && !quoted => {
+                words.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+            }
+            c => word.push(c),
+        }
+    }
+    ops
+}
+
+impl EpdTest {
+    /// Parses an EPD line. `bm` and `am` moves may be in SAN or UCI notation;
+    /// `line` numbers the position when there is no `id` operation.
+    pub fn parse(text: &str, line: usize) -> Result<Self, String> {
+        let mut fields = Vec::with_capacity(4);
+        let mut rest = text.trim();
+        while fields.len() < 4 && !rest.is_empty() {
+            let (field, tail) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            fields.push(field);
+            rest = tail.trim_start();
+        }
+        if fields.len() < 4 {
+            return Err(format!("line {line}: expected a position and operations"));
+        }
+        let mut board = Board::from_fen(&format!("{} 0 1", fields.join(" ")))
+            .map_err(|e| format!("line {line}: {e}"))?;
+
+        let mut test = EpdTest {
+            id: format!("#{line}"),
+            board: board.clone(),
+            best: Vec::new(),
+            avoid: Vec::new(),
+        };
+        for (op, operands) in split_operations(rest) {
+            let moves = match op.as_str() {
+                "id" => {
+                    test.id = operands.join(" ");
+                    continue;
+                }
+                "bm" => &mut test.best,
+                "am" => &mut test.avoid,
+                _ => continue,
+            };
+            for san in &operands {
+                let m = parse_san(&mut board, san)
+                    .or_else(|| parse_uci_move(&mut board, san))
+                    .ok_or_else(|| format!("line {line}: illegal move {san} in {op}"))?;
+                moves.push(m);
+            }
+        }
+        if test.best.is_empty() && test.avoid.is_empty() {
+            return Err(format!("line {line}: no bm or am operation"));
+        }
+        Ok(test)
+    }
+
+    pub fn passes(&self, m: Option<Move>) -> bool {
+        m.is_some_and(|m| {
+            (self.best.is_empty() || self.best.contains(&m)) && !self.avoid.contains(&m)
+        })
+    }
+
+    /// The expected moves as written in the suite, e.g. `bm Qg6` or `am Bxh7`.
+    pub fn expected(&self) -> String {
+        let mut legal = MoveList::new();
+        self.board.generate_legal_moves(&mut legal);
+        let san = |moves: &[Move]| {
+            moves
+                .iter()
+                .map(|&m| self.board.to_san(m, &legal))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        match (self.best.is_empty(), self.avoid.is_empty()) {
+            (false, true) => format!("bm {}", san(&self.best)),
+            (true, false) => format!("am {}", san(&self.avoid)),
+            _ => format!("bm {} am {}", san(&self.best), san(&self.avoid)),
+        }
+    }
+}
+
+/// Reads an EPD file; blank lines and `#` comments are skipped.
+pub fn load(text: &str) -> Result<Vec<EpdTest>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'))
+        .map(|(i, l)| EpdTest::parse(l.trim(), i + 1))
+        .collect()
+}
+
+pub struct TestOutcome {
+    pub id: String,
+    pub played: Option<Move>,
+    pub passed: bool,
+    pub depth: usize,
+    pub nodes: u64,
+    pub time_ms: u64,
+}
+
+impl TestOutcome {
+    /// Suites name positions `<suite>.<number>` (`WAC.001`,
+    /// `STS(v1.0) Undermine.001`); the part before the last dot groups them
+    /// in the summary.
+    pub fn group(&self) -> &str {
+        self.id
+            .rsplit_once('.')
+            .map_or(&self.id, |(group, _)| group)
+    }
+}
+
+/// Searches every position with a cleared hash table and hands each outcome
+/// to `on_result` as it finishes.
+pub fn run(
+    tests: &[EpdTest],
+    limits: &SearchLimits,
+    hash_mb: usize,
+    threads: usize,
+    mut on_result: impl FnMut(&EpdTest, &TestOutcome),
+) -> Result<Vec<TestOutcome>, ChessError> {
+    let mut engine = Engine::new(hash_mb)?;
+    engine.set_threads(threads);
+    let mut outcomes = Vec::with_capacity(tests.len());
+    for test in tests {
+        engine.new_game();
+        engine.set_position(&test.board.to_fen(), &[])?;
+        let start = Instant::now();
+        let r = engine.search(limits.clone(), None)?;
+        let outcome = TestOutcome {
+            id: test.id.clone(),
+            played: r.best_move,
+            passed: test.passes(r.best_move),
+            depth: r.depth,
+            nodes: r.nodes,
+            time_ms: start.elapsed().as_millis() as u64,
+        };
+        on_result(test, &outcome);
+        outcomes.push(outcome);
+    }
+    Ok(outcomes)
+}
+
+/// Hits per group and overall, with the nodes and time spent.
+pub fn report(outcomes: &[TestOutcome]) -> String {
+    let mut groups: Vec<(&str, usize, usize)> = Vec::new();
+    for o in outcomes {
+        match groups.iter_mut().find(|(g, ..)| *g == o.group()) {
+            Some((_, passed, total)) => {
+                *passed += o.passed as usize;
+                *total += 1;
+            }
+            None => groups.push((o.group(), o.passed as usize, 1)),
+        }
+    }
+    let passed = outcomes.iter().filter(|o| o.passed).count();
+    groups.push(("Total", passed, outcomes.len()));
+
+    let width = groups.iter().map(|(g, ..)| g.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for (group, passed, total) in groups {
+        let pct = 100.0 * passed as f64 / total.max(1) as f64;
+        let _ = writeln!(out, "{group:<width$}  {passed:>5}/{total:<5} {pct:>5.1}%");
+    }
+    let nodes: u64 = outcomes.iter().map(|o| o.nodes).sum();
+    let ms: u64 = outcomes.iter().map(|o| o.time_ms).sum();
+    let _ = writeln!(
+        out,
+        "{nodes} nodes in {:.1}s ({} nps)",
+        ms as f64 / 1000.0,
+        nodes * 1000 / ms.max(1)
+    );
+    out
+}
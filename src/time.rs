@@ -1,38 +1,108 @@
-#[derive(Copy, Clone, Default)]
+use crate::board::Board;
+use crate::types::{Color, Piece};
+
+/// How far past the soft limit an iteration may run before it is abandoned.
+pub const HARD_LIMIT_FACTOR: i64 = 3;
+pub const DEFAULT_MOVE_OVERHEAD_MS: i64 = 50;
+/// Largest share of the clock a single move may take.
+pub const DEFAULT_MAX_TIME_PERCENT: i64 = 20;
+
+/// Moves the clock is split over in the opening; fewer as material comes off
+/// and the game goes on, but never below the minimum.
+const MAX_MOVES_LEFT: i64 = 50;
+const MIN_MOVES_LEFT: i64 = 15;
+/// Move number past which the move count alone stops promising a long game.
+const LONG_GAME_MOVES: i64 = 70;
+/// Non-pawn material of both sides at the start, in pawns.
+const START_MATERIAL: i64 = 62;
+/// Part of the increment kept in reserve, as a divisor; at most a quarter
+/// of the clock.
+const INC_RESERVE_DIVISOR: i64 = 2;
+/// Shortest soft limit, so a nearly empty clock still yields a move.
+const MIN_THINK_MS: i64 = 5;
+
+#[derive(Copy, Clone)]
 pub struct TimeControl {
     pub wtime: i64,
     pub btime: i64,
     pub winc: i64,
     pub binc: i64,
     pub movestogo: i32,
+    /// Kept in reserve for GUI and network lag.
     pub move_overhead_ms: i64,
+    /// Nodes per millisecond of budget; when non-zero the search is limited
+    /// by nodes instead of the clock, so timed games are reproducible.
+    pub nodestime: i64,
+    /// The most of the remaining clock one move may use, in percent.
+    pub max_time_percent: i64,
+}
+
+impl Default for TimeControl {
+    fn default() -> Self {
+        Self {
+            wtime: 0,
+            btime: 0,
+            winc: 0,
+            binc: 0,
+            movestogo: 0,
+            move_overhead_ms: DEFAULT_MOVE_OVERHEAD_MS,
+            nodestime: 0,
+            max_time_percent: DEFAULT_MAX_TIME_PERCENT,
+        }
+    }
 }
 
 impl TimeControl {
-    /// Calculates the optimal and maximum time to think for the current move in milliseconds.
-    pub fn allocation_ms(&self, side_white: bool) -> (i64, i64) {
-        let (time, inc) = if side_white {
+    /// The node budget standing in for `ms` in `nodestime` mode.
+    pub fn nodes_for_ms(&self, ms: u64) -> Option<u64> {
+        (self.nodestime > 0).then(|| ms.saturating_mul(self.nodestime as u64).max(1))
+    }
+
+    /// The soft and hard limit for the side to move in `b`, in milliseconds.
+    /// The search finishes its iteration past the first but never runs past
+    /// the second.
+    ///
+    /// Without `movestogo` the clock is split over the moves the game
+    /// probably has left, and neither limit exceeds `max_time_percent` of
+    /// it. The move overhead and part of the increment are always kept back.
+    pub fn allocation_ms(&self, b: &Board) -> (i64, i64) {
+        let (time, inc) = if b.turn == Color::White {
             (self.wtime, self.winc)
         } else {
             (self.btime, self.binc)
         };
+        let reserve = self.move_overhead_ms + (inc / INC_RESERVE_DIVISOR).min(time / 4);
+        let usable = (time - reserve).max(0);
 
         if self.movestogo > 0 {
             let divisor = (self.movestogo as i64).min(30);
-            let ideal_time = (time / divisor) + (inc * 3 / 4);
-            let safe_time = time - self.move_overhead_ms.max(50);
-            return (ideal_time.min(safe_time), safe_time);
+            let soft_limit = (usable / divisor + inc * 3 / 4).min(usable);
+            return (soft_limit, (soft_limit * HARD_LIMIT_FACTOR).min(usable));
         }
 
-        let moves_remaining = 40;
-        let ideal_time = (time / moves_remaining) + (inc * 3 / 4);
-
-        let max_time = time / 5;
-
-        let hard_limit = time - self.move_overhead_ms.max(50);
-
-        let soft_limit = ideal_time.min(max_time).min(hard_limit).max(5); // Think for at least 5ms
-
+        let cap = usable * self.max_time_percent.clamp(1, 100) / 100;
+        let ideal_time = usable / moves_left(b) + inc * 3 / 4;
+        let soft_limit = ideal_time.min(cap).max(MIN_THINK_MS);
+        let hard_limit = (soft_limit * HARD_LIMIT_FACTOR).min(cap).max(soft_limit);
         (soft_limit, hard_limit)
     }
 }
+
+/// Estimated moves left in the game, from the non-pawn material still on
+/// the board and the move number.
+pub fn moves_left(b: &Board) -> i64 {
+    let count = |pieces: [Piece; 2]| {
+        pieces
+            .iter()
+            .map(|p| b.piece_bb[p.index()].count_ones() as i64)
+            .sum::<i64>()
+    };
+    let material = 3 * count([Piece::WN, Piece::BN])
+        + 3 * count([Piece::WB, Piece::BB])
+        + 5 * count([Piece::WR, Piece::BR])
+        + 9 * count([Piece::WQ, Piece::BQ]);
+    let by_material = MIN_MOVES_LEFT
+        + (MAX_MOVES_LEFT - MIN_MOVES_LEFT) * material.min(START_MATERIAL) / START_MATERIAL;
+    let by_move = LONG_GAME_MOVES - b.fullmove_number as i64;
+    by_material.min(by_move).max(MIN_MOVES_LEFT)
+}
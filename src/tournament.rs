@@ -0,0 +1,309 @@
+//! Tournaments between differently configured copies of the engine, played
+//! in-process: each player is an [`Engine`] with its own threads, hash
+//! table, depth cap and evaluation.
+
+use crate::board::Board;
+use crate::engine::Engine;
+use crate::error::ChessError;
+use crate::eval::{self, EvalMode};
+use crate::gauntlet::{Adjudication, Tally};
+use crate::search::SearchLimits;
+use crate::uci_io::format_uci;
+use std::fmt::Write as _;
+
+pub struct Player {
+    pub name: String,
+    pub threads: usize,
+    pub hash_mb: usize,
+    /// Caps the tournament's depth limit for this player.
+    pub max_depth: Option<usize>,
+    /// The evaluation to search with; the `UseNNUE` setting when `None`.
+    pub eval_mode: Option<EvalMode>,
+}
+
+impl Player {
+    /// Parses `name=a,threads=2,hash=64,depth=12,eval=classical`. Threads
+    /// and hash default to `threads` and `hash_mb`; `eval` takes the
+    /// `UseNNUE` values or `nnue`/`classical`.
+    pub fn parse(spec: &str, threads: usize, hash_mb: usize) -> Result<Self, String> {
+        let mut player = Player {
+            name: String::new(),
+            threads,
+            hash_mb,
+            max_depth: None,
+            eval_mode: None,
+        };
+        for field in spec.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| format!("player: expected key=value, got '{field}'"))?;
+            let number = || {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("player: bad number '{value}' for {key}"))
+            };
+            match key {
+                "name" => player.name = value.to_string(),
+                "threads" => player.threads = number()?.max(1),
+                "hash" => player.hash_mb = number()?.max(1),
+                "depth" => player.max_depth = Some(number()?.max(1)),
+                "eval" => {
+                    let mode = match value {
+                        "nnue" => Some(EvalMode::Nnue),
+                        "classical" => Some(EvalMode::Classical),
+                        _ => EvalMode::parse(value),
+                    };
+                    player.eval_mode =
+                        Some(mode.ok_or_else(|| format!("player: unknown eval '{value}'"))?);
+                }
+                _ => return Err(format!("player: unknown key '{key}'")),
+            }
+        }
+        if player.name.is_empty() {
+            player.name = spec.to_string();
+        }
+        Ok(player)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Every player meets every other.
+    RoundRobin,
+    /// The first player meets each of the others.
+    Gauntlet,
+}
+
+pub struct TournamentConfig {
+    pub players: Vec<Player>,
+    pub format: Format,
+    pub fen: String,
+    /// Games per pairing; the players swap colours after every game.
+    pub rounds: usize,
+    pub limits: SearchLimits,
+    /// Games still running after this many plies are drawn.
+    pub max_plies: usize,
+    pub adjudication: Adjudication,
+}
+
+/// One pairing's results from the first player's point of view.
+pub struct Pairing {
+    pub first: usize,
+    pub second: usize,
+    pub tally: Tally,
+}
+
+/// Applies an [`Adjudication`] when the same engine plays both sides, so
+/// both scores must agree: a win needs `resign_moves` moves each in which
+/// the mover's score favoured the same side by the resign margin.
+pub struct Adjudicator {
+    adj: Adjudication,
+    win_streak: [u32; 2],
+    draw_streak: u32,
+}
+
+impl Adjudicator {
+    pub fn new(adj: Adjudication) -> Self {
+        Self {
+            adj,
+            win_streak: [0; 2],
+            draw_streak: 0,
+        }
+    }
+
+    /// Records the score of the move just played, from White's side, and
+    /// returns White's result if the game is now adjudicated.
+    pub fn update(&mut self, white_score: i32, fullmove: i32) -> Option<f64> {
+        let adj = &self.adj;
+        self.win_streak = match adj.resign_score {
+            Some(limit) if white_score >= limit => [self.win_streak[0] + 1, 0],
+            Some(limit) if white_score <= -limit => [0, self.win_streak[1] + 1],
+            _ => [0, 0],
+        };
+        match adj.draw_score {
+            Some(limit) if white_score.abs() <= limit => self.draw_streak += 1,
+            _ => self.draw_streak = 0,
+        }
+        let resign_plies = 2 * adj.resign_moves.max(1);
+        if self.win_streak[0] >= resign_plies {
+            Some(1.0)
+        } else if self.win_streak[1] >= resign_plies {
+            Some(0.0)
+        } else if self.draw_streak >= 2 * adj.draw_moves.max(1) && fullmove >= adj.draw_after as i32
+        {
+            Some(0.5)
+        } else {
+            None
+        }
+    }
+}
+
+/// The pairings to play, as indices into `players`.
+pub fn pairings(players: usize, format: Format) -> Vec<(usize, usize)> {
+    match format {
+        Format::RoundRobin => (0..players)
+            .flat_map(|i| (i + 1..players).map(move |j| (i, j)))
+            .collect(),
+        Format::Gauntlet => (1..players).map(|j| (0, j)).collect(),
+    }
+}
+
+/// Plays one game and returns White's score and how it ended.
+fn play_game(
+    config: &TournamentConfig,
+    mut engines: [&mut Engine; 2],
+    players: [&Player; 2],
+    default_mode: EvalMode,
+) -> Result<(f64, String), ChessError> {
+    let mut b = Board::from_fen(&config.fen)?;
+    let mut moves: Vec<String> = Vec::new();
+    let mut adjudicator = Adjudicator::new(config.adjudication);
+    for e in engines.iter_mut() {
+        e.new_game();
+    }
+
+    for _ in 0..config.max_plies {
+        let result = b.game_result();
+        if let Some(score) = result.white_score() {
+            return Ok((score, result.reason().into()));
+        }
+
+        let side = b.turn as usize;
+        let player = players[side];
+        let engine = &mut engines[side];
+        let mut limits = config.limits.clone();
+        if let Some(depth) = player.max_depth {
+            limits.max_depth = limits.max_depth.min(depth);
+        }
+        eval::set_eval_mode(player.eval_mode.unwrap_or(default_mode));
+        let played: Vec<&str> = moves.iter().map(String::as_str).collect();
+        engine.set_position(&config.fen, &played)?;
+        let r = engine.search(limits, None)?;
+        let Some(m) = r.best_move else {
+            let score = if side == 0 { 0.0 } else { 1.0 };
+            return Ok((score, format!("{} returned no move", player.name)));
+        };
+        moves.push(format_uci(m));
+        b.make_move(m);
+
+        let white_score = if side == 0 { r.score } else { -r.score };
+        if let Some(score) = adjudicator.update(white_score, b.fullmove_number) {
+            return Ok((score, "adjudicated".into()));
+        }
+    }
+    Ok((0.5, "move limit".into()))
+}
+
+/// Plays every pairing `config.rounds` times, alternating colours, and
+/// hands each result line to `on_game` as it finishes.
+pub fn run(
+    config: &TournamentConfig,
+    mut on_game: impl FnMut(&str),
+) -> Result<Vec<Pairing>, ChessError> {
+    let default_mode = eval::eval_mode();
+    let mut engines = config
+        .players
+        .iter()
+        .map(|p| {
+            let mut e = Engine::new(p.hash_mb)?;
+            e.set_threads(p.threads);
+            Ok(e)
+        })
+        .collect::<Result<Vec<_>, ChessError>>()?;
+
+    let pairs = pairings(config.players.len(), config.format);
+    let total = pairs.len() * config.rounds;
+    let mut results = Vec::with_capacity(pairs.len());
+    let mut game = 0;
+    for (first, second) in pairs {
+        let mut tally = Tally::default();
+        for round in 0..config.rounds {
+            game += 1;
+            let (w, bl) = if round % 2 == 0 {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            // Split the borrow: the two players are always different.
+            let (lo, hi) = engines.split_at_mut(w.max(bl));
+            let (we, be) = if w < bl {
+                (&mut lo[w], &mut hi[0])
+            } else {
+                (&mut hi[0], &mut lo[bl])
+            };
+            let players = [&config.players[w], &config.players[bl]];
+            let (score, why) = play_game(config, [we, be], players, default_mode)?;
+            tally.add(if w == first { score } else { 1.0 - score });
+            on_game(&format!(
+                "game {game}/{total}: {} vs {}: {} ({why})",
+                players[0].name,
+                players[1].name,
+                match score {
+                    s if s > 0.5 => "1-0",
+                    s if s < 0.5 => "0-1",
+                    _ => "1/2-1/2",
+                }
+            ));
+        }
+        results.push(Pairing {
+            first,
+            second,
+            tally,
+        });
+    }
+    eval::set_eval_mode(default_mode);
+    Ok(results)
+}
+
+/// Per-pairing results followed by each player's score against the field,
+/// with Elo differences and their 95% error bars.
+pub fn report(config: &TournamentConfig, results: &[Pairing]) -> String {
+    let mut out = String::new();
+    let header = |out: &mut String, title: &str| {
+        let _ = writeln!(
+            out,
+            "{title:<32} {:>6} {:>5} {:>5} {:>5} {:>7} {:>14}",
+            "Games", "W", "D", "L", "Score", "Elo"
+        );
+    };
+    let row = |out: &mut String, name: &str, t: &Tally| {
+        let (elo, margin) = t.elo();
+        let _ = writeln!(
+            out,
+            "{:<32} {:>6} {:>5} {:>5} {:>5} {:>6.1}% {:>+7.1} ±{:.1}",
+            name,
+            t.games(),
+            t.wins,
+            t.draws,
+            t.losses,
+            t.score() * 100.0,
+            elo,
+            margin
+        );
+    };
+
+    header(&mut out, "Pairing");
+    for p in results {
+        let name = format!(
+            "{} vs {}",
+            config.players[p.first].name, config.players[p.second].name
+        );
+        row(&mut out, &name, &p.tally);
+    }
+    let _ = writeln!(out);
+    header(&mut out, "Player");
+    for (i, player) in config.players.iter().enumerate() {
+        let tallies: Vec<Tally> = results
+            .iter()
+            .filter_map(|p| {
+                if p.first == i {
+                    Some(p.tally)
+                } else {
+                    (p.second == i).then(|| p.tally.reversed())
+                }
+            })
+            .collect();
+        row(&mut out, &player.name, &Tally::combined(&tallies));
+    }
+    out
+}
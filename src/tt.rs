@@ -1,7 +1,17 @@
 use crate::types::{Move, ZKey};
 use num_cpus;
+use std::alloc::Layout;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::ptr::NonNull;
 use std::sync::{Arc, Mutex};
 
+/// Start of a saved table file, followed by the entry count and the entries,
+/// each as little-endian key and data words.
+const FILE_MAGIC: &[u8; 8] = b"CHESSTT\x01";
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 #[repr(u8)]
 pub enum Bound {
@@ -95,6 +105,13 @@ impl TTEntry {
     pub fn is_empty(&self) -> bool {
         self.key == 0
     }
+
+    fn with_age(self, age: u8) -> Self {
+        Self {
+            key: self.key,
+            data: (self.data & !(AGE_MASK << AGE_SHIFT)) | ((age as u64) << AGE_SHIFT),
+        }
+    }
 }
 
 const CLUSTER_SIZE: usize = 4;
@@ -105,28 +122,137 @@ pub struct TTCluster {
     entries: [TTEntry; CLUSTER_SIZE],
 }
 
+/// Tables are aligned to huge pages so the kernel can back them with 2 MiB
+/// pages, which saves TLB misses on probes.
+#[cfg(target_os = "linux")]
+const HUGE_PAGE: usize = 2 * 1024 * 1024;
+
+/// Zero-initialised cluster storage. An all-zero cluster is an empty one,
+/// and on Linux the memory is mapped straight from the OS, so a large table
+/// costs nothing until it is written to rather than being filled up front.
+struct Clusters {
+    ptr: NonNull<TTCluster>,
+    len: usize,
+    /// Start and layout of the whole allocation, which `ptr` lies inside.
+    base: *mut u8,
+    layout: Layout,
+}
+
+// SAFETY: `Clusters` owns its allocation like a `Box<[TTCluster]>`.
+unsafe impl Send for Clusters {}
+unsafe impl Sync for Clusters {}
+
+impl Clusters {
+    /// `None` if the memory can't be had.
+    #[cfg(target_os = "linux")]
+    fn zeroed(len: usize) -> Option<Self> {
+        let size = len.checked_mul(std::mem::size_of::<TTCluster>())?;
+        // Room to round the start up to a huge page.
+        let layout = Layout::from_size_align(size.checked_add(HUGE_PAGE)?, HUGE_PAGE).ok()?;
+        // SAFETY: an anonymous private mapping; it reads as zeroes until written.
+        let base = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                layout.size(),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return None;
+        }
+        let start = (base as usize).next_multiple_of(HUGE_PAGE) as *mut TTCluster;
+        // SAFETY: `start..start + size` lies inside the mapping. The advice
+        // does nothing when transparent huge pages are off.
+        unsafe { libc::madvise(start.cast(), size, libc::MADV_HUGEPAGE) };
+        Some(Self {
+            ptr: NonNull::new(start)?,
+            len,
+            base: base.cast(),
+            layout,
+        })
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn zeroed(len: usize) -> Option<Self> {
+        let layout = Layout::array::<TTCluster>(len).ok()?;
+        // SAFETY: `len` is at least one, so the layout is not zero-sized.
+        let base = unsafe { std::alloc::alloc_zeroed(layout) };
+        Some(Self {
+            ptr: NonNull::new(base.cast::<TTCluster>())?,
+            len,
+            base,
+            layout,
+        })
+    }
+}
+
+impl Deref for Clusters {
+    type Target = [TTCluster];
+
+    fn deref(&self) -> &[TTCluster] {
+        // SAFETY: `len` zero-initialised clusters, and zero is a valid cluster.
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for Clusters {
+    fn deref_mut(&mut self) -> &mut [TTCluster] {
+        // SAFETY: as for `deref`, and `&mut self` makes the access unique.
+        unsafe { std::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for Clusters {
+    fn drop(&mut self) {
+        // SAFETY: allocated in `zeroed` with this base and layout.
+        #[cfg(target_os = "linux")]
+        unsafe {
+            libc::munmap(self.base.cast(), self.layout.size());
+        }
+        #[cfg(not(target_os = "linux"))]
+        unsafe {
+            std::alloc::dealloc(self.base, self.layout);
+        }
+    }
+}
+
 pub struct TransTable {
-    slots: Vec<TTCluster>,
+    slots: Clusters,
     mask: usize,
+    /// Generation of the current search; entries from earlier ones are
+    /// replaced first.
     age: u8,
+    /// Entries written in the current generation, kept up to date by every
+    /// store so `hashfull` needn't scan the table.
+    current: u64,
 }
 
 impl TransTable {
-    fn with_mb(mb: usize) -> Self {
-        let bytes = mb.saturating_mul(1024 * 1024).max(64);
-        let slot_size = std::mem::size_of::<TTCluster>();
-        let slots_count = (bytes / slot_size).max(1).next_power_of_two();
+    /// The largest power-of-two number of clusters that fits in `mb`
+    /// megabytes, so the table never takes more than was asked for. Sizes
+    /// are counted in `u64` since they may pass 4 GB, and capped to what the
+    /// address space can index.
+    fn with_mb(mb: usize) -> Option<Self> {
+        let bytes = (mb as u64).saturating_mul(1024 * 1024);
+        let clusters = (bytes / std::mem::size_of::<TTCluster>() as u64).max(1);
+        let clusters = 1u64 << clusters.ilog2();
+        let slots_count = usize::try_from(clusters).unwrap_or(1 << (usize::BITS - 8));
         let mask = slots_count - 1;
-        Self {
-            slots: vec![TTCluster::default(); slots_count],
+        Some(Self {
+            slots: Clusters::zeroed(slots_count)?,
             mask,
             age: 0,
-        }
+            current: 0,
+        })
     }
 
     #[inline]
     fn tick_age(&mut self) {
         self.age = self.age.wrapping_add(1);
+        self.current = 0;
     }
     #[inline]
     fn idx(&self, key: ZKey) -> usize {
@@ -154,46 +280,52 @@ impl TransTable {
 
     #[inline]
     fn store(&mut self, key: ZKey, depth: i16, score: i32, bound: Bound, best_move: Option<Move>) {
+        self.place(TTEntry::new(key, depth, score, bound, best_move, self.age));
+    }
+
+    /// Writes `new_entry` over the same position's entry if it is at least
+    /// as deep or from an older generation, else into an empty slot, else
+    /// over the shallowest entry of an older generation, and only when the
+    /// whole cluster is from this one over its shallowest entry.
+    #[inline]
+    fn place(&mut self, new_entry: TTEntry) {
+        let age = self.age;
+        let key = new_entry.key;
         let i = self.idx(key);
         let cluster = &mut self.slots[i];
-        let new_entry = TTEntry::new(key, depth, score, bound, best_move, self.age);
 
-        for entry in &mut cluster.entries {
-            if entry.key == key {
-                if self.age == entry.age() || new_entry.depth() >= entry.depth() {
-                    *entry = new_entry;
+        let slot = match cluster.entries.iter().position(|e| e.key == key) {
+            Some(j) => {
+                let entry = cluster.entries[j];
+                if entry.age() == age && new_entry.depth() < entry.depth() {
+                    return;
                 }
-                return;
-            }
-        }
-
-        for entry in &mut cluster.entries {
-            if entry.is_empty() {
-                *entry = new_entry;
-                return;
+                j
             }
-        }
-
-        let mut replace_idx = 0;
-        let mut worst_quality = i32::MAX;
-        for (i, entry) in cluster.entries.iter().enumerate() {
-            let quality = (entry.depth() as i32) * 2 - (self.age.wrapping_sub(entry.age()) as i32);
-            if quality < worst_quality {
-                worst_quality = quality;
-                replace_idx = i;
-            }
-        }
-        cluster.entries[replace_idx] = new_entry;
+            None => cluster
+                .entries
+                .iter()
+                .position(TTEntry::is_empty)
+                .unwrap_or_else(|| {
+                    (0..CLUSTER_SIZE)
+                        .min_by_key(|&j| {
+                            let e = &cluster.entries[j];
+                            (e.age() == age, e.depth())
+                        })
+                        .unwrap_or(0)
+                }),
+        };
+        let old = std::mem::replace(&mut cluster.entries[slot], new_entry);
+        let is_current = |e: &TTEntry| !e.is_empty() && e.age() == age;
+        self.current += u64::from(is_current(&new_entry));
+        self.current -= u64::from(is_current(&old));
     }
 
-    #[inline]
-    fn stats(&self) -> (usize, usize) {
-        let filled = self
-            .slots
+    fn entries(&self) -> impl Iterator<Item = &TTEntry> {
+        self.slots
             .iter()
-            .map(|c| c.entries.iter().filter(|e| !e.is_empty()).count())
-            .sum();
-        (filled, self.slots.len() * CLUSTER_SIZE)
+            .flat_map(|c| c.entries.iter())
+            .filter(|e| !e.is_empty())
     }
 }
 
@@ -201,27 +333,49 @@ impl TransTable {
 pub struct SharedTransTable {
     shards: Vec<Arc<Mutex<TransTable>>>,
     shard_mask: usize,
+    /// Address and index mask of each shard's clusters, so they can be
+    /// prefetched without taking the lock. The cluster vectors are never
+    /// reallocated while the shard lives.
+    cluster_bases: Vec<(usize, usize)>,
 }
 
 impl SharedTransTable {
+    /// Panics if the memory can't be allocated; see [`Self::try_new`].
     pub fn new(size_mb: usize) -> Self {
+        Self::try_new(size_mb).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    pub fn try_new(size_mb: usize) -> Result<Self, String> {
         let shard_count = Self::pick_shard_count();
-        let (per_shard, remainder) = if shard_count == 0 {
-            (size_mb, 0)
-        } else {
-            (size_mb / shard_count, size_mb % shard_count)
-        };
+        let (per_shard, remainder) = size_mb
+            .checked_div(shard_count)
+            .map_or((size_mb, 0), |per| (per, size_mb % shard_count));
         let mut shards = Vec::with_capacity(shard_count.max(1));
         let count = shard_count.max(1);
         for i in 0..count {
-            shards.push(Arc::new(Mutex::new(TransTable::with_mb(
-                (per_shard + if i < remainder { 1 } else { 0 }).max(1),
-            ))));
+            let table = TransTable::with_mb((per_shard + usize::from(i < remainder)).max(1))
+                .ok_or_else(|| format!("cannot allocate a {size_mb} MB hash table"))?;
+            shards.push(Arc::new(Mutex::new(table)));
         }
-        Self {
+        let cluster_bases = shards
+            .iter()
+            .map(|s| {
+                let t = s.lock().unwrap();
+                (t.slots.as_ptr() as usize, t.mask)
+            })
+            .collect();
+        Ok(Self {
             shards,
             shard_mask: count.saturating_sub(1),
-        }
+            cluster_bases,
+        })
+    }
+
+    /// Memory taken by the clusters, which may be less than was asked for
+    /// since each shard holds a power of two of them.
+    pub fn size_mb(&self) -> usize {
+        let clusters: usize = self.cluster_bases.iter().map(|&(_, mask)| mask + 1).sum();
+        clusters * std::mem::size_of::<TTCluster>() / (1024 * 1024)
     }
 
     #[inline]
@@ -241,13 +395,32 @@ impl SharedTransTable {
     }
 
     #[inline]
-    fn shard_for(&self, key: ZKey) -> &Arc<Mutex<TransTable>> {
-        let idx = if self.shards.len().is_power_of_two() {
+    fn shard_position(&self, key: ZKey) -> usize {
+        if self.shards.len().is_power_of_two() {
             self.shard_index(key)
         } else {
             (key as usize) % self.shards.len()
-        };
-        &self.shards[idx]
+        }
+    }
+
+    #[inline]
+    fn shard_for(&self, key: ZKey) -> &Arc<Mutex<TransTable>> {
+        &self.shards[self.shard_position(key)]
+    }
+
+    /// Starts loading the cluster for `key` into cache ahead of a probe.
+    #[inline(always)]
+    pub fn prefetch(&self, key: ZKey) {
+        #[cfg(target_arch = "x86_64")]
+        {
+            use std::arch::x86_64::{_MM_HINT_T0, _mm_prefetch};
+            let (base, mask) = self.cluster_bases[self.shard_position(key)];
+            let addr = base + ((key as usize) & mask) * std::mem::size_of::<TTCluster>();
+            // Only a hint to the CPU; a prefetch never faults.
+            unsafe { _mm_prefetch::<_MM_HINT_T0>(addr as *const i8) };
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = key;
     }
 
     #[inline]
@@ -277,17 +450,189 @@ impl SharedTransTable {
         }
     }
 
+    /// Reallocates the table at `size_mb`, keeping as many entries as fit;
+    /// where they compete for space the deeper ones win. Clones taken before
+    /// the resize keep the old table, so call it while no search is running.
+    /// If the new table can't be allocated the old one is kept.
+    pub fn resize(&mut self, size_mb: usize) -> Result<(), String> {
+        let mut resized = Self::try_new(size_mb)?;
+        {
+            let shards: Vec<_> = self.shards.iter().map(|s| s.lock().unwrap()).collect();
+            let age = shards.first().map_or(0, |s| s.age);
+            for shard in &resized.shards {
+                shard.lock().unwrap().age = age;
+            }
+            let mut entries: Vec<TTEntry> =
+                shards.iter().flat_map(|s| s.entries()).copied().collect();
+            entries.sort_by_key(|e| e.depth());
+            for entry in entries {
+                resized.shard_for(entry.key).lock().unwrap().place(entry);
+            }
+        }
+        std::mem::swap(self, &mut resized);
+        Ok(())
+    }
+
+    /// Writes every filled entry to `path`.
+    pub fn save(&self, path: &Path) -> io::Result<usize> {
+        let shards: Vec<_> = self.shards.iter().map(|s| s.lock().unwrap()).collect();
+        let count: usize = shards.iter().map(|s| s.entries().count()).sum();
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(FILE_MAGIC)?;
+        out.write_all(&(count as u64).to_le_bytes())?;
+        for entry in shards.iter().flat_map(|s| s.entries()) {
+            out.write_all(&entry.key.to_le_bytes())?;
+            out.write_all(&entry.data.to_le_bytes())?;
+        }
+        out.flush()?;
+        Ok(count)
+    }
+
+    /// Adds the entries saved in `path` to the table, which may be of a
+    /// different size than the one saved. They count as belonging to the
+    /// current search so the next one doesn't evict them first. Returns the
+    /// number of entries read.
+    pub fn load(&self, path: &Path) -> io::Result<usize> {
+        let mut input = BufReader::new(File::open(path)?);
+        let mut word = [0u8; 8];
+        input.read_exact(&mut word)?;
+        if &word != FILE_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a saved hash table",
+            ));
+        }
+        input.read_exact(&mut word)?;
+        let count = u64::from_le_bytes(word) as usize;
+        for _ in 0..count {
+            input.read_exact(&mut word)?;
+            let key = ZKey::from_le_bytes(word);
+            input.read_exact(&mut word)?;
+            let data = u64::from_le_bytes(word);
+            if key == 0 {
+                continue;
+            }
+            let mut shard = self.shard_for(key).lock().unwrap();
+            let age = shard.age;
+            shard.place(TTEntry { key, data }.with_age(age));
+        }
+        Ok(count)
+    }
+
+    /// Permille of the table written by the current search, from the
+    /// shards' running counts.
     #[inline]
     pub fn hashfull_permill(&self) -> u32 {
-        let (filled_total, slots_total) = self
+        let (current, slots) = self
             .shards
             .iter()
-            .map(|s| s.lock().unwrap().stats())
+            .map(|s| {
+                let t = s.lock().unwrap();
+                (t.current, (t.slots.len() * CLUSTER_SIZE) as u64)
+            })
             .fold((0, 0), |a, b| (a.0 + b.0, a.1 + b.1));
-        if slots_total == 0 {
-            0
-        } else {
-            ((filled_total * 1000) / slots_total) as u32
+        (current * 1000).checked_div(slots).unwrap_or(0) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::square::Square;
+
+    fn mv(from: usize, to: usize) -> Move {
+        Move::quiet(Square::from_index(from), Square::from_index(to))
+    }
+
+    fn fill(tt: &SharedTransTable) -> Vec<ZKey> {
+        let keys: Vec<ZKey> = (1..=64u64)
+            .map(|i| i.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+            .collect();
+        for (i, &key) in keys.iter().enumerate() {
+            tt.store(
+                key,
+                i as i16 % 20,
+                i as i32 - 32,
+                Bound::Lower,
+                Some(mv(i, 63 - i)),
+            );
+        }
+        keys
+    }
+
+    fn assert_entries(tt: &SharedTransTable, keys: &[ZKey]) {
+        for (i, &key) in keys.iter().enumerate() {
+            let e = tt.probe(key).expect("entry kept");
+            assert_eq!(e.depth(), i as i16 % 20);
+            assert_eq!(e.score(), i as i32 - 32);
+            assert_eq!(e.bound(), Bound::Lower);
+            assert_eq!(e.best_move(), Some(mv(i, 63 - i)));
+        }
+    }
+
+    #[test]
+    fn save_load_round_trip() {
+        let tt = SharedTransTable::new(1);
+        let keys = fill(&tt);
+        let path = std::env::temp_dir().join(format!("chess-tt-{}.bin", std::process::id()));
+        assert_eq!(tt.save(&path).unwrap(), keys.len());
+
+        let loaded = SharedTransTable::new(2);
+        let read = loaded.load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read.unwrap(), keys.len());
+        assert_entries(&loaded, &keys);
+        assert!(loaded.probe(0x1234).is_none());
+    }
+
+    #[test]
+    fn load_rejects_other_files() {
+        let path = std::env::temp_dir().join(format!("chess-tt-bad-{}.bin", std::process::id()));
+        std::fs::write(&path, b"not a table at all").unwrap();
+        let err = SharedTransTable::new(1).load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn resize_keeps_entries() {
+        let mut tt = SharedTransTable::new(1);
+        let keys = fill(&tt);
+        tt.resize(4).unwrap();
+        assert_eq!(tt.size_mb(), 4);
+        assert_entries(&tt, &keys);
+        tt.resize(1).unwrap();
+        assert_entries(&tt, &keys);
+    }
+
+    #[test]
+    fn replacement_keeps_deeper_entries_of_this_search() {
+        let tt = SharedTransTable::new(1);
+        tt.store(42, 10, 7, Bound::Exact, None);
+        tt.store(42, 5, 3, Bound::Upper, None);
+        assert_eq!(tt.probe(42).unwrap().depth(), 10);
+
+        // A shallower entry replaces one left by an earlier search.
+        tt.tick_age();
+        tt.store(42, 5, 3, Bound::Upper, None);
+        let e = tt.probe(42).unwrap();
+        assert_eq!((e.depth(), e.score(), e.bound()), (5, 3, Bound::Upper));
+    }
+
+    #[test]
+    fn hashfull_counts_this_search_only() {
+        let tt = SharedTransTable::new(1);
+        assert_eq!(tt.hashfull_permill(), 0);
+        for key in 1..=20_000u64 {
+            tt.store(key, 1, 0, Bound::Exact, None);
+        }
+        let full = tt.hashfull_permill();
+        assert!(full > 0, "{full}");
+        tt.tick_age();
+        assert_eq!(tt.hashfull_permill(), 0);
+        for key in 1..=20_000u64 {
+            tt.store(key, 1, 0, Bound::Exact, None);
         }
+        assert_eq!(tt.hashfull_permill(), full);
     }
 }
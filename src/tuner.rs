@@ -0,0 +1,253 @@
+use crate::board::Board;
+use crate::eval::{DEFAULT_PARAMS, EvalParams, Score, evaluate_with_params};
+use std::fmt::Write;
+use std::thread;
+
+/// A training position and the result of the game it came from, from
+/// White's point of view (1.0 win, 0.5 draw, 0.0 loss).
+pub struct Sample {
+    pub board: Board,
+    pub result: f64,
+}
+
+/// Parses one `<fen> <result>` line. The result may be given as `[1.0]`,
+/// `1.0`, `"1-0"` or `1-0` (and the draw and loss equivalents).
+pub fn parse_sample(line: &str) -> Result<Sample, String> {
+    let line = line.trim().trim_end_matches(';');
+    let (fen, result) = line
+        .rsplit_once(char::is_whitespace)
+        .ok_or_else(|| format!("expected '<fen> <result>', got '{line}'"))?;
+    let result = match result.trim_matches(|c| matches!(c, '[' | ']' | '"')) {
+        "1-0" | "1.0" | "1" => 1.0,
+        "1/2-1/2" | "0.5" => 0.5,
+        "0-1" | "0.0" | "0" => 0.0,
+        other => return Err(format!("unknown result '{other}'")),
+    };
+    // EPD-style `c9 "1-0";` result opcodes.
+    let fen = fen.trim();
+    let fen = fen.strip_suffix("c9").unwrap_or(fen).trim();
+    let board = Board::from_fen(fen).map_err(|e| e.to_string())?;
+    Ok(Sample { board, result })
+}
+
+/// Parses a training file; blank lines are skipped.
+pub fn parse_samples(text: &str) -> Result<Vec<Sample>, String> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| parse_sample(l).map_err(|e| format!("line {}: {e}", i + 1)))
+        .collect()
+}
+
+/// Calls `f` on every tunable scalar in a fixed order, skipping the
+/// piece-square tables unless `pst` is set.
+fn visit(params: &mut EvalParams, pst: bool, f: &mut impl FnMut(&mut i32)) {
+    let score = |sc: &mut Score, f: &mut dyn FnMut(&mut i32)| {
+        f(&mut sc.mg);
+        f(&mut sc.eg);
+    };
+
+    if pst {
+        for table in params.mg_pst.iter_mut().chain(params.eg_pst.iter_mut()) {
+            table.iter_mut().for_each(&mut *f);
+        }
+    }
+    params.mobility.iter_mut().for_each(|sc| score(sc, f));
+    score(&mut params.doubled_pawn, f);
+    score(&mut params.isolated_pawn, f);
+    params.passed_pawn.iter_mut().for_each(|sc| score(sc, f));
+    params.connected_pawn.iter_mut().for_each(&mut *f);
+    score(&mut params.supported_pawn, f);
+    params.king_attack_weight.iter_mut().for_each(&mut *f);
+    params.king_tropism.iter_mut().for_each(|sc| score(sc, f));
+    params.pawn_shield.iter_mut().for_each(|sc| score(sc, f));
+    score(&mut params.bishop_pair, f);
+    score(&mut params.knight_pawns, f);
+    score(&mut params.rook_pawns, f);
+    score(&mut params.hole, f);
+    score(&mut params.hole_knight_reach, f);
+    score(&mut params.hole_bishop_complex, f);
+    score(&mut params.queen_sortie, f);
+    score(&mut params.bishop_behind_center_pawn, f);
+    score(&mut params.blocked_center_pawn, f);
+    score(&mut params.long_diagonal_bishop, f);
+    score(&mut params.fianchetto, f);
+    score(&mut params.missing_fianchetto_bishop, f);
+}
+
+fn param_count(pst: bool) -> usize {
+    let mut n = 0;
+    visit(&mut DEFAULT_PARAMS.clone(), pst, &mut |_| n += 1);
+    n
+}
+
+fn adjust(params: &mut EvalParams, pst: bool, index: usize, delta: i32) {
+    let mut i = 0;
+    visit(params, pst, &mut |v| {
+        if i == index {
+            *v += delta;
+        }
+        i += 1;
+    });
+}
+
+#[inline]
+fn sigmoid(k: f64, eval: i32) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * eval as f64 / 400.0))
+}
+
+/// Mean squared error between game results and the sigmoid of the eval,
+/// split across all cores.
+pub fn error(samples: &[Sample], params: &EvalParams, k: f64) -> f64 {
+    let threads = num_cpus::get().max(1);
+    let chunk = samples.len().div_ceil(threads).max(1);
+    let total: f64 = thread::scope(|scope| {
+        let handles: Vec<_> = samples
+            .chunks(chunk)
+            .map(|part| {
+                scope.spawn(move || {
+                    part.iter()
+                        .map(|sm| {
+                            let e = sm.result - sigmoid(k, evaluate_with_params(&sm.board, params));
+                            e * e
+                        })
+                        .sum::<f64>()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap_or(0.0)).sum()
+    });
+    total / samples.len().max(1) as f64
+}
+
+/// Finds the sigmoid scaling constant that best fits the current weights.
+pub fn fit_k(samples: &[Sample], params: &EvalParams) -> f64 {
+    let (mut lo, mut hi) = (0.1, 3.0);
+    for _ in 0..30 {
+        let m1 = lo + (hi - lo) / 3.0;
+        let m2 = hi - (hi - lo) / 3.0;
+        if error(samples, params, m1) < error(samples, params, m2) {
+            hi = m2;
+        } else {
+            lo = m1;
+        }
+    }
+    (lo + hi) / 2.0
+}
+
+/// Texel's local search: nudge each weight by ±1 and keep any change that
+/// lowers the error, until a full pass finds nothing or `max_passes` is hit.
+pub fn tune(samples: &[Sample], pst: bool, max_passes: usize) -> EvalParams {
+    let mut params = DEFAULT_PARAMS.clone();
+    let k = fit_k(samples, &params);
+    let mut best = error(samples, &params, k);
+    println!("K = {k:.4}, initial error {best:.6}");
+
+    let count = param_count(pst);
+    for pass in 1..=max_passes {
+        let mut improved = false;
+        for i in 0..count {
+            for delta in [1, -1] {
+                adjust(&mut params, pst, i, delta);
+                let e = error(samples, &params, k);
+                if e < best {
+                    best = e;
+                    improved = true;
+                    break;
+                }
+                adjust(&mut params, pst, i, -delta);
+            }
+        }
+        println!("pass {pass}: error {best:.6}");
+        if !improved {
+            break;
+        }
+    }
+    params
+}
+
+fn write_score(out: &mut String, sc: Score) {
+    let _ = write!(out, "s({}, {})", sc.mg, sc.eg);
+}
+
+fn write_list<T: Copy>(out: &mut String, items: &[T], mut item: impl FnMut(&mut String, T)) {
+    out.push('[');
+    for (i, &x) in items.iter().enumerate() {
+        if i > 0 {
+            out.push_str(", ");
+        }
+        item(out, x);
+    }
+    out.push(']');
+}
+
+/// Renders `params` as a Rust constant that can be pasted over
+/// `DEFAULT_PARAMS` in eval.rs.
+pub fn to_rust(params: &EvalParams) -> String {
+    let mut out = String::from("pub const DEFAULT_PARAMS: EvalParams = EvalParams {\n");
+    for (name, tables) in [("mg_pst", &params.mg_pst), ("eg_pst", &params.eg_pst)] {
+        let _ = writeln!(out, "    {name}: [");
+        for table in tables {
+            out.push_str("        ");
+            write_list(&mut out, table, |o, v| {
+                let _ = write!(o, "{v}");
+            });
+            out.push_str(",\n");
+        }
+        out.push_str("    ],\n");
+    }
+
+    let scores: [(&str, &[Score]); 4] = [
+        ("mobility", &params.mobility),
+        ("passed_pawn", &params.passed_pawn),
+        ("king_tropism", &params.king_tropism),
+        ("pawn_shield", &params.pawn_shield),
+    ];
+    for (name, list) in scores {
+        let _ = write!(out, "    {name}: ");
+        write_list(&mut out, list, write_score);
+        out.push_str(",\n");
+    }
+
+    let ints: [(&str, &[i32]); 2] = [
+        ("connected_pawn", &params.connected_pawn),
+        ("king_attack_weight", &params.king_attack_weight),
+    ];
+    for (name, list) in ints {
+        let _ = write!(out, "    {name}: ");
+        write_list(&mut out, list, |o, v| {
+            let _ = write!(o, "{v}");
+        });
+        out.push_str(",\n");
+    }
+
+    for (name, sc) in [
+        ("doubled_pawn", params.doubled_pawn),
+        ("isolated_pawn", params.isolated_pawn),
+        ("supported_pawn", params.supported_pawn),
+        ("bishop_pair", params.bishop_pair),
+        ("knight_pawns", params.knight_pawns),
+        ("rook_pawns", params.rook_pawns),
+        ("hole", params.hole),
+        ("hole_knight_reach", params.hole_knight_reach),
+        ("hole_bishop_complex", params.hole_bishop_complex),
+        ("queen_sortie", params.queen_sortie),
+        (
+            "bishop_behind_center_pawn",
+            params.bishop_behind_center_pawn,
+        ),
+        ("blocked_center_pawn", params.blocked_center_pawn),
+        ("long_diagonal_bishop", params.long_diagonal_bishop),
+        ("fianchetto", params.fianchetto),
+        (
+            "missing_fianchetto_bishop",
+            params.missing_fianchetto_bishop,
+        ),
+    ] {
+        let _ = write!(out, "    {name}: ");
+        write_score(&mut out, sc);
+        out.push_str(",\n");
+    }
+    out.push_str("};\n");
+    out
+}
@@ -1,3 +1,4 @@
+use crate::square::Square;
 use std::fmt;
 
 pub type Bitboard = u64;
@@ -161,11 +162,12 @@ impl fmt::Display for Piece {
     }
 }
 
-// A move is packed into a u16 for performance.
+// A move is packed into a u16:
 // [ 0- 5]: From Square (6 bits)
 // [ 6-11]: To Square   (6 bits)
 // [12-15]: Flags       (4 bits)
-// const MOVE_FLAG_QUIET: u16 = 0b0000;
+// The all-zero a1a1 "move" doubles as the null move.
+const MOVE_FLAG_QUIET: u16 = 0b0000;
 const MOVE_FLAG_DOUBLE_PUSH: u16 = 0b0001;
 const MOVE_FLAG_KING_CASTLE: u16 = 0b0010;
 const MOVE_FLAG_QUEEN_CASTLE: u16 = 0b0011;
@@ -174,104 +176,205 @@ const MOVE_FLAG_EN_PASSANT: u16 = 0b0101;
 const MOVE_FLAG_PROMOTION: u16 = 0b1000;
 const MOVE_FLAG_PROMO_CAPTURE: u16 = 0b1100;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
-pub struct Move {
-    pub from: u8,
-    pub to: u8,
-    pub capture: bool,
-    pub en_passant: bool,
-    pub double_push: bool,
-    pub castle: bool,
-    pub promotion: Option<PieceKind>,
-}
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default)]
+pub struct Move(u16);
 
 impl Move {
     #[inline(always)]
-    pub fn quiet(from: u8, to: u8) -> Self {
-        Self {
-            from,
-            to,
-            capture: false,
-            en_passant: false,
-            double_push: false,
-            castle: false,
-            promotion: None,
+    const fn pack(from: Square, to: Square, flags: u16) -> Self {
+        Self(from.index() as u16 | (to.index() as u16) << 6 | flags << 12)
+    }
+
+    #[inline(always)]
+    pub const fn quiet(from: Square, to: Square) -> Self {
+        Self::pack(from, to, MOVE_FLAG_QUIET)
+    }
+
+    #[inline(always)]
+    pub const fn capture(from: Square, to: Square) -> Self {
+        Self::pack(from, to, MOVE_FLAG_CAPTURE)
+    }
+
+    #[inline(always)]
+    pub const fn double_push(from: Square, to: Square) -> Self {
+        Self::pack(from, to, MOVE_FLAG_DOUBLE_PUSH)
+    }
+
+    /// An en passant capture landing on the en passant square `to`.
+    #[inline(always)]
+    pub const fn en_passant(from: Square, to: Square) -> Self {
+        Self::pack(from, to, MOVE_FLAG_EN_PASSANT)
+    }
+
+    /// Castling, given as the king's move.
+    #[inline(always)]
+    pub const fn castle(from: Square, to: Square) -> Self {
+        let flags = if to.index() > from.index() {
+            MOVE_FLAG_KING_CASTLE
+        } else {
+            MOVE_FLAG_QUEEN_CASTLE
+        };
+        Self::pack(from, to, flags)
+    }
+
+    /// A promotion to `kind`, which must be a knight, bishop, rook or queen.
+    #[inline(always)]
+    pub const fn promote(from: Square, to: Square, kind: PieceKind, capture: bool) -> Self {
+        let piece = match kind {
+            PieceKind::Knight => 0,
+            PieceKind::Bishop => 1,
+            PieceKind::Rook => 2,
+            _ => 3,
+        };
+        let flags = if capture {
+            MOVE_FLAG_PROMO_CAPTURE
+        } else {
+            MOVE_FLAG_PROMOTION
+        };
+        Self::pack(from, to, flags | piece)
+    }
+
+    #[inline(always)]
+    const fn flags(self) -> u16 {
+        self.0 >> 12
+    }
+
+    #[inline(always)]
+    pub const fn from(self) -> Square {
+        Square::from_index((self.0 & 0x3F) as usize)
+    }
+
+    #[inline(always)]
+    pub const fn to(self) -> Square {
+        Square::from_index((self.0 >> 6 & 0x3F) as usize)
+    }
+
+    /// True for every capture, en passant and capturing promotions included.
+    #[inline(always)]
+    pub const fn is_capture(self) -> bool {
+        self.flags() & MOVE_FLAG_CAPTURE != 0
+    }
+
+    #[inline(always)]
+    pub const fn is_en_passant(self) -> bool {
+        self.flags() == MOVE_FLAG_EN_PASSANT
+    }
+
+    #[inline(always)]
+    pub const fn is_double_push(self) -> bool {
+        self.flags() == MOVE_FLAG_DOUBLE_PUSH
+    }
+
+    #[inline(always)]
+    pub const fn is_castle(self) -> bool {
+        matches!(self.flags(), MOVE_FLAG_KING_CASTLE | MOVE_FLAG_QUEEN_CASTLE)
+    }
+
+    #[inline(always)]
+    pub const fn promotion(self) -> Option<PieceKind> {
+        if self.flags() & MOVE_FLAG_PROMOTION == 0 {
+            return None;
         }
+        Some(match self.flags() & 0b11 {
+            0 => PieceKind::Knight,
+            1 => PieceKind::Bishop,
+            2 => PieceKind::Rook,
+            _ => PieceKind::Queen,
+        })
+    }
+}
+
+impl fmt::Debug for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Move({}{}", self.from(), self.to())?;
+        if let Some(kind) = self.promotion() {
+            write!(f, "={}", kind.to_char_upper())?;
+        }
+        write!(f, ", {:#06b})", self.flags())
     }
 }
 
 impl From<Move> for u16 {
     #[inline(always)]
     fn from(m: Move) -> Self {
-        let from_u16 = m.from as u16;
-        let to_u16 = (m.to as u16) << 6;
-        let mut flags = 0u16;
-
-        if let Some(pk) = m.promotion {
-            flags = if m.capture {
-                MOVE_FLAG_PROMO_CAPTURE
-            } else {
-                MOVE_FLAG_PROMOTION
-            };
-            flags |= match pk {
-                PieceKind::Knight => 0,
-                PieceKind::Bishop => 1,
-                PieceKind::Rook => 2,
-                PieceKind::Queen => 3,
-                _ => 0,
-            };
-        } else if m.en_passant {
-            flags = MOVE_FLAG_EN_PASSANT;
-        } else if m.capture {
-            flags = MOVE_FLAG_CAPTURE;
-        } else if m.castle {
-            flags = if file_of(m.to as i32) > file_of(m.from as i32) {
-                MOVE_FLAG_KING_CASTLE
-            } else {
-                MOVE_FLAG_QUEEN_CASTLE
-            };
-        } else if m.double_push {
-            flags = MOVE_FLAG_DOUBLE_PUSH;
-        }
-
-        from_u16 | to_u16 | (flags << 12)
+        m.0
     }
 }
 
 impl From<u16> for Move {
     #[inline(always)]
     fn from(m: u16) -> Self {
-        if m == 0 {
-            return Move::default();
-        } // Null move
-
-        let from = (m & 0x3F) as u8;
-        let to = ((m >> 6) & 0x3F) as u8;
-        let flags = m >> 12;
-
-        let mut mov = Move::quiet(from, to);
-
-        if flags >= MOVE_FLAG_PROMOTION {
-            mov.promotion = Some(match flags & 0b11 {
-                0 => PieceKind::Knight,
-                1 => PieceKind::Bishop,
-                2 => PieceKind::Rook,
-                _ => PieceKind::Queen,
-            });
-            if (flags & 0b0100) != 0 {
-                mov.capture = true;
-            }
-        } else if flags == MOVE_FLAG_EN_PASSANT {
-            mov.capture = true;
-            mov.en_passant = true;
-        } else if flags == MOVE_FLAG_CAPTURE {
-            mov.capture = true;
-        } else if flags == MOVE_FLAG_KING_CASTLE || flags == MOVE_FLAG_QUEEN_CASTLE {
-            mov.castle = true;
-        } else if flags == MOVE_FLAG_DOUBLE_PUSH {
-            mov.double_push = true;
+        Move(m)
+    }
+}
+
+/// Which moves a generator produces. Noisy moves are captures (including en
+/// passant) and promotions; everything else, castling included, is quiet.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GenKind {
+    All,
+    Noisy,
+    Quiet,
+}
+
+/// Terminal state of a position, checked in order of precedence: a mate
+/// delivered on the 100th halfmove is still a mate.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    Repetition,
+}
+
+/// How a game stands, with the winner or the kind of draw.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum GameResult {
+    Ongoing,
+    WhiteWins,
+    BlackWins,
+    DrawStalemate,
+    DrawFiftyMove,
+    DrawRepetition,
+    DrawMaterial,
+}
+
+impl GameResult {
+    pub fn is_over(self) -> bool {
+        self != GameResult::Ongoing
+    }
+
+    /// 1, ½ or 0 from White's side; `None` while the game goes on.
+    pub fn white_score(self) -> Option<f64> {
+        match self {
+            GameResult::Ongoing => None,
+            GameResult::WhiteWins => Some(1.0),
+            GameResult::BlackWins => Some(0.0),
+            _ => Some(0.5),
+        }
+    }
+
+    /// The PGN result tag: `1-0`, `0-1`, `1/2-1/2` or `*`.
+    pub fn pgn(self) -> &'static str {
+        match self {
+            GameResult::Ongoing => "*",
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            _ => "1/2-1/2",
+        }
+    }
+
+    pub fn reason(self) -> &'static str {
+        match self {
+            GameResult::Ongoing => "game in progress",
+            GameResult::WhiteWins => "White mates",
+            GameResult::BlackWins => "Black mates",
+            GameResult::DrawStalemate => "stalemate",
+            GameResult::DrawFiftyMove => "fifty-move rule",
+            GameResult::DrawRepetition => "threefold repetition",
+            GameResult::DrawMaterial => "insufficient material",
         }
-        mov
     }
 }
 
@@ -281,36 +384,10 @@ pub struct Undo {
     pub old_castle: u8,
     pub old_en_passant_sq: i32,
     pub old_halfmove_clock: i32,
+    pub old_pawn_key: ZKey,
 }
 
 pub const WK_CASTLE: u8 = 1 << 0;
 pub const WQ_CASTLE: u8 = 1 << 1;
 pub const BK_CASTLE: u8 = 1 << 2;
 pub const BQ_CASTLE: u8 = 1 << 3;
-
-#[inline(always)]
-pub fn file_of(sq: i32) -> i32 {
-    sq & 7
-}
-#[inline(always)]
-pub fn rank_of(sq: i32) -> i32 {
-    sq >> 3
-}
-#[inline(always)]
-pub fn in_board(sq: i32) -> bool {
-    (0..64).contains(&sq)
-}
-#[inline(always)]
-pub fn sq_to_str(sq: usize) -> String {
-    let f = (sq % 8) as u8;
-    let r = (sq / 8) as u8;
-    format!("{}{}", (b'a' + f) as char, (b'1' + r) as char)
-}
-#[inline(always)]
-pub fn file_char(sq: usize) -> char {
-    ((sq % 8) as u8 + b'a') as char
-}
-#[inline(always)]
-pub fn rank_char(sq: usize) -> char {
-    ((sq / 8) as u8 + b'1') as char
-}
@@ -1,17 +1,43 @@
+use crate::bench::{BENCH_DEPTH, print_bench};
+use crate::bitbase;
 use crate::board::Board;
-use crate::opening_book::get_book_move;
-use crate::search::best_move_timed;
+use crate::display;
+use crate::error::ChessError;
+use crate::eval;
+use crate::fen;
+use crate::game_db::{self, GameRecorder};
+use crate::nnue;
+use crate::opening_book::{OpeningBook, default_book_path};
+use crate::options::Options;
+use crate::output;
+use crate::perft::divide;
+use crate::search::{SearchLimits, SearchStats, ThreadPool, UciInfoPrinter};
+use crate::skill::Skill;
 use crate::time::TimeControl;
 use crate::tt::SharedTransTable;
-use crate::types::{Color, START_FEN};
-use crate::uci_io::{format_uci, parse_uci_move};
-use num_cpus;
-use std::io::{self, Write};
-use std::sync::Arc;
+use crate::types::{Color, Move, START_FEN};
+use crate::uci_io::{self, format_uci, parse_uci_move};
+use serde_json::json;
+use std::io;
+use std::path::Path;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::thread;
-
-const SEARCH_THREAD_STACK: usize = 32 * 1024 * 1024; // 32 MiB
+use std::time::Instant;
+
+/// Arguments of `go` that end a `searchmoves` list.
+const GO_KEYWORDS: [&str; 10] = [
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "movetime",
+    "infinite",
+    "ponder",
+];
 
 #[inline]
 fn extract_i64(cmd: &str, key: &str) -> Option<i64> {
@@ -25,6 +51,17 @@ fn extract_i64(cmd: &str, key: &str) -> Option<i64> {
     None
 }
 
+/// Moves listed after `searchmoves`, up to the next `go` keyword. Moves
+/// that aren't legal here are ignored.
+fn parse_searchmoves(b: &mut Board, cmd: &str) -> Vec<Move> {
+    cmd.split_whitespace()
+        .skip_while(|t| !t.eq_ignore_ascii_case("searchmoves"))
+        .skip(1)
+        .take_while(|t| !GO_KEYWORDS.iter().any(|k| t.eq_ignore_ascii_case(k)))
+        .filter_map(|t| parse_uci_move(b, t))
+        .collect()
+}
+
 #[inline]
 fn parse_setoption(rest: &str) -> Option<(String, String)> {
     let parts: Vec<&str> = rest.splitn(2, "name").collect();
@@ -40,20 +77,47 @@ fn parse_setoption(rest: &str) -> Option<(String, String)> {
     }
 }
 
-struct PonderState {
+/// The search running in the background, if any. The worker prints its own
+/// `bestmove`, so the input loop stays free to answer `stop` and `isready`.
+struct SearchState {
     handle: Option<std::thread::JoinHandle<()>>,
     stop_signal: Option<Arc<AtomicBool>>,
-    enabled: bool,
+    score: Arc<Mutex<Option<i32>>>,
+    /// Counters of the search once it has finished, for `stats`.
+    stats: Arc<Mutex<Option<SearchStats>>>,
+    /// Ply and side of the searched position, for the game log.
+    searched: Option<(usize, Color)>,
 }
-impl PonderState {
+impl SearchState {
     fn new() -> Self {
         Self {
             handle: None,
             stop_signal: None,
-            enabled: false,
+            score: Arc::new(Mutex::new(None)),
+            stats: Arc::new(Mutex::new(None)),
+            searched: None,
         }
     }
 
+    /// Stops the search and records its score with the game.
+    fn stop_and_record(&mut self, recorder: &mut GameRecorder) {
+        self.stop_and_join();
+        if let Some((ply, side)) = self.searched.take() {
+            let score = self.take_score();
+            recorder.record_search(ply, side, score);
+        }
+    }
+
+    /// Score of the last finished search, if it has not been taken yet.
+    fn take_score(&mut self) -> Option<i32> {
+        self.score.lock().unwrap_or_else(|e| e.into_inner()).take()
+    }
+
+    #[inline]
+    fn is_running(&self) -> bool {
+        self.handle.as_ref().is_some_and(|h| !h.is_finished())
+    }
+
     #[inline]
     fn stop_and_join(&mut self) {
         if let Some(sig) = &self.stop_signal {
@@ -67,140 +131,421 @@ impl PonderState {
 }
 
 fn info<S: AsRef<str>>(s: S) {
-    println!("info string {}", s.as_ref());
-    let _ = io::stdout().flush();
+    if output::is_json() {
+        output::emit(&json!({ "type": "string", "message": s.as_ref() }));
+        return;
+    }
+    uci_io::send(format!("info string {}", s.as_ref()));
+}
+
+/// `bestmove`, or `bestmove 0000` when there is no move to play.
+fn print_bestmove(m: Option<Move>, ponder: Option<Move>) {
+    if output::is_json() {
+        output::emit(&json!({
+            "type": "bestmove",
+            "move": m.map(format_uci),
+            "ponder": ponder.map(format_uci),
+        }));
+        return;
+    }
+    uci_io::send(match (m, ponder) {
+        (Some(m), Some(p)) => format!("bestmove {} ponder {}", format_uci(m), format_uci(p)),
+        (Some(m), None) => format!("bestmove {}", format_uci(m)),
+        (None, _) => "bestmove 0000".to_string(),
+    });
 }
 
+/// Searches, prints `bestmove` and returns the score and counters of the
+/// search. With a `skill` the move played may be weaker than the one found.
 fn search_and_output(
     b: &Board,
     tt: &mut SharedTransTable,
-    time_ms: u64,
-    depth: usize,
+    limits: SearchLimits,
     stop: Arc<AtomicBool>,
-    main_thread: bool,
-) {
-    let (best, _reached_depth, _nodes) =
-        best_move_timed(b, tt, time_ms, depth, Arc::clone(&stop), main_thread);
-
-    if let Some(m) = best {
-        let mut ponder_str = String::new();
+    skill: Option<Skill>,
+    pool: &mut ThreadPool,
+) -> (Option<i32>, SearchStats) {
+    let result = pool.search(b, tt, limits, stop, Some(&mut UciInfoPrinter));
+    let best = match skill {
+        Some(skill) => skill.pick_move(&result.root_scores).or(result.best_move),
+        _ => result.best_move,
+    };
+
+    let ponder = best.and_then(|m| {
         let mut temp_board = b.clone();
         temp_board.make_move(m);
-        if let Some(ponder_move) = tt.probe(temp_board.zobrist).and_then(|e| e.best_move()) {
-            // Fast check: is the ponder move for a piece that can actually move from that square?
-            if temp_board.piece_on[ponder_move.from as usize].color() == Some(temp_board.turn) {
-                ponder_str = format!(" ponder {}", format_uci(ponder_move));
+        // Fast check: is the ponder move for a piece that can actually move from that square?
+        tt.probe(temp_board.zobrist)
+            .and_then(|e| e.best_move())
+            .filter(|p| temp_board.piece_on[p.from().index()].color() == Some(temp_board.turn))
+    });
+    if uci_io::is_debug() {
+        info(result.stats.summary());
+    }
+    print_bestmove(best, ponder);
+    (best.map(|_| result.score), result.stats)
+}
+
+/// Applies a `setoption` and returns the option's name if its value changed.
+fn apply_option(
+    opts: &mut Options,
+    tt: &mut SharedTransTable,
+    name: &str,
+    value: &str,
+) -> Option<&'static str> {
+    let changed = opts.set(name, value);
+    match changed {
+        Ok(Some("Hash")) => {
+            let start = Instant::now();
+            match tt.resize(opts.hash_mb()) {
+                Ok(()) => info(format!(
+                    "hash resized to {} MB in {} ms",
+                    tt.size_mb(),
+                    start.elapsed().as_millis()
+                )),
+                Err(e) => report(ChessError::InvalidOption(format!(
+                    "{e}; keeping the current table"
+                ))),
             }
         }
-        println!("bestmove {}{}", format_uci(m), ponder_str);
+        Ok(Some("Clear Hash")) => tt.clear(),
+        Ok(Some("LogFile")) => {
+            if let Err(e) = uci_io::set_log_file(opts.string("LogFile")) {
+                report(ChessError::Io(e));
+            }
+        }
+        Ok(Some("EvalFile")) => match nnue::load_eval_file(opts.string("EvalFile")) {
+            Ok(arch) => info(format!("loaded {arch} network")),
+            Err(e) => report(ChessError::Nnue(e)),
+        },
+        Ok(Some(
+            "UCI_Opponent" | "Contempt" | "IterationSkip" | "UCI_ShowWDL" | "OutputFormat"
+            | "UseNNUE",
+        )) => {
+            if let Err(e) = opts.sync_search() {
+                report(ChessError::InvalidOption(e));
+            } else if let Ok(Some(opp)) = opts.opponent() {
+                info(format!(
+                    "opponent {:?}, contempt {}",
+                    opp.name,
+                    opp.contempt()
+                ));
+            }
+        }
+        Ok(_) => {}
+        Err(e) => {
+            report(ChessError::InvalidOption(e));
+            return None;
+        }
+    }
+    changed.ok().flatten()
+}
+
+/// Loads the book selected by `OwnBook` and `BookFile`.
+fn load_book(opts: &Options) -> Option<OpeningBook> {
+    if !opts.own_book() {
+        return None;
+    }
+    let Some(path) = opts.book_file().or_else(default_book_path) else {
+        info("Opening book 'moves/book.bin' not found in any standard location.");
+        return None;
+    };
+    match OpeningBook::load(&path) {
+        Ok(book) => {
+            info(format!(
+                "Loaded opening book from: {} ({} entries)",
+                path.display(),
+                book.len()
+            ));
+            Some(book)
+        }
+        Err(e) => {
+            info(format!("could not load book {}: {e}", path.display()));
+            None
+        }
+    }
+}
+
+fn report(e: ChessError) {
+    info(format!("error: {e}"));
+}
+
+fn startpos() -> Result<Board, ChessError> {
+    Board::from_fen(START_FEN)
+}
+
+/// Parses the arguments of a `position` command. Nothing is applied unless
+/// the whole command is valid.
+fn parse_position(rest: &str) -> Result<Board, ChessError> {
+    let parts: Vec<&str> = rest.split_whitespace().collect();
+    let moves_at = parts.iter().position(|s| s.eq_ignore_ascii_case("moves"));
+    let setup = &parts[..moves_at.unwrap_or(parts.len())];
+
+    let mut b = match setup.first() {
+        Some(&"startpos") => startpos()?,
+        Some(&"fen") => fen::parse_fen_strict(&setup[1..].join(" "))?,
+        _ => {
+            return Err(ChessError::Parse(format!(
+                "position: expected startpos or fen, got '{rest}'"
+            )));
+        }
+    };
+
+    if let Some(index) = moves_at {
+        for move_str in parts[index + 1..].iter().copied() {
+            let mv = parse_uci_move(&mut b, move_str)
+                .ok_or_else(|| ChessError::IllegalMove(move_str.to_string()))?;
+            let _ = b.make_move(mv);
+        }
+    }
+    Ok(b)
+}
+
+/// Start FEN and moves of a `position` command that has already been
+/// validated by [`parse_position`].
+fn position_moves(rest: &str) -> (String, Vec<String>) {
+    let (setup, moves) = match rest.split_once("moves") {
+        Some((setup, moves)) => (
+            setup,
+            moves.split_whitespace().map(str::to_string).collect(),
+        ),
+        None => (rest, Vec::new()),
+    };
+    let fen = match setup.trim().strip_prefix("fen") {
+        Some(fen) => fen.trim().to_string(),
+        None => START_FEN.to_string(),
+    };
+    (fen, moves)
+}
+
+/// Describes the limits of a `go` command for the game log.
+fn time_control_label(rest: &str, white: bool) -> String {
+    let (time, inc) = if white {
+        ("wtime", "winc")
     } else {
-        println!("bestmove 0000");
+        ("btime", "binc")
+    };
+    if let Some(ms) = extract_i64(rest, "movetime") {
+        format!("movetime {ms}")
+    } else if let Some(ms) = extract_i64(rest, time) {
+        let inc = extract_i64(rest, inc).unwrap_or(0);
+        format!("{}+{}", ms as f64 / 1000.0, inc as f64 / 1000.0)
+    } else if let Some(d) = extract_i64(rest, "depth") {
+        format!("depth {d}")
+    } else if let Some(n) = extract_i64(rest, "nodes") {
+        format!("nodes {n}")
+    } else {
+        "infinite".to_string()
+    }
+}
+
+/// Appends the finished game to the `GameLog` file, if one is set.
+fn log_game(opts: &Options, recorder: &mut GameRecorder, b: &mut Board) {
+    if let Some(record) = recorder.finish(b, None)
+        && let Some(path) = opts.game_log()
+        && let Err(e) = game_db::append(&path, &record)
+    {
+        report(ChessError::Io(e));
     }
-    let _ = io::stdout().flush();
 }
 
-pub fn run_uci() {
-    let mut b = Board::from_fen(START_FEN).expect("valid startpos");
+/// Whether a `setoption` requires the book to be reloaded. Setting
+/// `BookFile`, even to its current value, always reloads it.
+fn is_book_option(name: &str, changed: Option<&str>) -> bool {
+    name.eq_ignore_ascii_case("BookFile") || changed == Some("OwnBook")
+}
+
+fn spawn_search(
+    b: &Board,
+    tt: &SharedTransTable,
+    limits: SearchLimits,
+    skill: Option<Skill>,
+    threads_count: usize,
+    name: &str,
+    pool: &Arc<Mutex<ThreadPool>>,
+) -> Result<SearchState, ChessError> {
+    let board = b.clone();
+    let pool = Arc::clone(pool);
+    let mut tt_main = tt.clone();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stop_main = Arc::clone(&stop);
+    let score = Arc::new(Mutex::new(None));
+    let score_out = Arc::clone(&score);
+    let stats = Arc::new(Mutex::new(None));
+    let stats_out = Arc::clone(&stats);
+
+    let handle = thread::Builder::new()
+        .name(format!("{name}-main"))
+        .spawn(move || {
+            let mut pool = pool.lock().unwrap_or_else(|e| e.into_inner());
+            pool.set_threads(threads_count);
+            let (result, counters) =
+                search_and_output(&board, &mut tt_main, limits, stop_main, skill, &mut pool);
+            *score_out.lock().unwrap_or_else(|e| e.into_inner()) = result;
+            *stats_out.lock().unwrap_or_else(|e| e.into_inner()) = Some(counters);
+        })
+        .map_err(ChessError::ThreadSpawn)?;
+
+    Ok(SearchState {
+        handle: Some(handle),
+        stop_signal: Some(stop),
+        score,
+        stats,
+        searched: None,
+    })
+}
+
+pub fn run_uci(mut opts: Options) -> Result<(), ChessError> {
+    // Solve the bitbases before the first search needs them.
+    bitbase::init();
+    let mut b = startpos()?;
     let mut tc = TimeControl::default();
 
-    let mut tt_size_mb: usize = 256;
-    let mut tt = SharedTransTable::new(tt_size_mb);
-    let mut threads_count: usize = num_cpus::get().max(1);
-    let mut ponder = PonderState::new();
+    let mut tt = SharedTransTable::new(opts.hash_mb());
+    let mut search = SearchState::new();
+    // Search threads, with move-ordering tables carried from one move of
+    // the game to the next.
+    let pool = Arc::new(Mutex::new(ThreadPool::new(opts.threads())));
+    let mut recorder = GameRecorder::new("uci");
+    // Loaded on the first `go` after start-up or a book option change.
+    let mut book: Option<OpeningBook> = None;
+    let mut book_stale = true;
+    // setoption commands received mid-search, applied once it finishes.
+    let mut pending_options: Vec<(String, String)> = Vec::new();
 
     loop {
         let mut line = String::new();
-        if io::stdin().read_line(&mut line).is_err() {
+        if matches!(io::stdin().read_line(&mut line), Ok(0) | Err(_)) {
+            search.stop_and_record(&mut recorder);
+            log_game(&opts, &mut recorder, &mut b);
             break;
         }
+        uci_io::log_input(&line);
         let cmd = line.trim();
 
+        if !pending_options.is_empty() && !search.is_running() {
+            search.stop_and_join();
+            for (name, value) in pending_options.drain(..) {
+                info(format!("applying deferred option {name}"));
+                let changed = apply_option(&mut opts, &mut tt, &name, &value);
+                book_stale |= is_book_option(&name, changed);
+            }
+        }
+
         if cmd.eq_ignore_ascii_case("uci") {
-            println!("id name chess");
-            println!("id author Will");
-            println!(
-                "option name Hash type spin default {} min 1 max 4096",
-                tt_size_mb
-            );
-            println!("option name Threads type spin default {} min 1 max 128", 1);
-            println!("option name Ponder type check default {}", ponder.enabled);
-            println!("uciok");
-            let _ = io::stdout().flush();
+            uci_io::send("id name chess");
+            uci_io::send("id author Will");
+            for line in opts.uci_lines() {
+                uci_io::send(line);
+            }
+            uci_io::send("uciok");
             continue;
         }
 
         if cmd.eq_ignore_ascii_case("isready") {
-            println!("readyok");
-            let _ = io::stdout().flush();
+            uci_io::send("readyok");
             continue;
         }
 
         if cmd.eq_ignore_ascii_case("ucinewgame") {
-            b = Board::from_fen(START_FEN).unwrap();
-            println!(
-                "info string Polyglot key for startpos: {:x}",
+            search.stop_and_record(&mut recorder);
+            log_game(&opts, &mut recorder, &mut b);
+            b = startpos()?;
+            info(format!(
+                "Polyglot key for startpos: {:x}",
                 crate::polyglot_zobrist::calculate_key(&b)
-            );
+            ));
             tt.clear();
-            ponder.stop_and_join();
-            let _ = io::stdout().flush();
+            search.stop_and_join();
+            pool.lock().unwrap_or_else(|e| e.into_inner()).clear();
             continue;
         }
 
         if let Some(rest) = cmd.strip_prefix("setoption ") {
             if let Some((name, value)) = parse_setoption(rest) {
-                if name.eq_ignore_ascii_case("Hash") {
-                    if let Ok(size) = value.parse::<usize>()
-                        && (1..=4096).contains(&size)
-                        && tt_size_mb != size
-                    {
-                        tt_size_mb = size;
-                        tt = SharedTransTable::new(tt_size_mb);
-                    }
-                } else if name.eq_ignore_ascii_case("Threads") {
-                    if let Ok(n) = value.parse::<usize>()
-                        && (1..=128).contains(&n)
-                    {
-                        threads_count = n;
-                    }
-                } else if name.eq_ignore_ascii_case("Ponder") {
-                    ponder.enabled =
-                        matches!(value.to_ascii_lowercase().as_str(), "true" | "1" | "on");
+                if search.is_running() {
+                    info(format!("search in progress, deferring option {name}"));
+                    pending_options.push((name, value));
+                } else {
+                    let changed = apply_option(&mut opts, &mut tt, &name, &value);
+                    book_stale |= is_book_option(&name, changed);
                 }
             }
             continue;
         }
 
         if let Some(rest) = cmd.strip_prefix("position ") {
-            ponder.stop_and_join();
+            search.stop_and_record(&mut recorder);
+            match parse_position(rest) {
+                Ok(pos) => {
+                    b = pos;
+                    let (fen, moves) = position_moves(rest);
+                    recorder.set_position(fen, moves);
+                }
+                Err(e) => report(e),
+            }
+            continue;
+        }
+
+        if cmd == "d" || cmd.eq_ignore_ascii_case("display") {
+            if output::is_json() {
+                output::emit(&display::describe_json(&b));
+            } else {
+                uci_io::send(display::describe(&b).trim_end());
+            }
+            continue;
+        }
 
-            let parts: Vec<&str> = rest.split_whitespace().collect();
-            let mut moves_start_index: Option<usize> = None;
+        if cmd.eq_ignore_ascii_case("eval") {
+            if output::is_json() {
+                output::emit(&eval::trace_json(&b));
+            } else {
+                uci_io::send(eval::trace(&b).trim_end());
+            }
+            continue;
+        }
 
-            if parts.first() == Some(&"startpos") {
-                b = Board::from_fen(START_FEN).unwrap();
-                if parts.get(1) == Some(&"moves") {
-                    moves_start_index = Some(2);
-                }
-            } else if parts.first() == Some(&"fen") {
-                if let Some(index) = parts.iter().position(|&s| s.eq_ignore_ascii_case("moves")) {
-                    let fen_parts = &parts[1..index];
-                    b = Board::from_fen(&fen_parts.join(" "))
-                        .unwrap_or_else(|_| Board::from_fen(START_FEN).unwrap());
-                    moves_start_index = Some(index + 1);
-                } else {
-                    let fen_parts = &parts[1..];
-                    b = Board::from_fen(&fen_parts.join(" "))
-                        .unwrap_or_else(|_| Board::from_fen(START_FEN).unwrap());
-                }
+        // Non-standard: counters of the last finished search.
+        if cmd.eq_ignore_ascii_case("stats") {
+            let stats = search.stats.lock().unwrap_or_else(|e| e.into_inner());
+            match stats.as_ref() {
+                Some(stats) if output::is_json() => output::emit(&stats.to_json()),
+                Some(stats) => uci_io::send(stats.to_string().trim_end()),
+                None => info("no finished search"),
             }
+            continue;
+        }
 
-            if let Some(start_index) = moves_start_index {
-                for move_str in parts[start_index..].iter().copied() {
-                    if let Some(mv) = parse_uci_move(&mut b, move_str) {
-                        let _ = b.make_move(mv);
-                    }
+        // Non-standard: keep the hash table across engine restarts.
+        if let Some(rest) = cmd.strip_prefix("tt ") {
+            search.stop_and_join();
+            let result = match rest.trim().split_once(' ') {
+                Some(("save", file)) => tt.save(Path::new(file.trim())).map(|n| ("saved", n)),
+                Some(("load", file)) => tt.load(Path::new(file.trim())).map(|n| ("loaded", n)),
+                _ => {
+                    info("usage: tt save <file> | tt load <file>");
+                    continue;
                 }
+            };
+            match result {
+                Ok((verb, n)) => info(format!("{verb} {n} hash entries")),
+                Err(e) => report(ChessError::Io(e)),
+            }
+            continue;
+        }
+
+        if let Some(rest) = cmd.strip_prefix("bench") {
+            search.stop_and_join();
+            print_bench(rest.trim().parse().unwrap_or(BENCH_DEPTH));
+            continue;
+        }
+
+        let mut tokens = cmd.split_whitespace();
+        if tokens.next() == Some("debug") {
+            match tokens.collect::<Vec<_>>()[..] {
+                ["on"] | [] => uci_io::set_debug(true),
+                ["off"] => uci_io::set_debug(false),
+                _ => info("usage: debug on | debug off"),
             }
             continue;
         }
@@ -210,23 +555,39 @@ pub fn run_uci() {
         }
 
         if cmd.eq_ignore_ascii_case("stop") {
-            ponder.stop_and_join();
-            if let Some(best) = tt.probe(b.zobrist).and_then(|e| e.best_move()) {
-                println!("bestmove {}", format_uci(best));
-            } else {
-                println!("bestmove 0000");
+            search.stop_and_join();
+            continue;
+        }
+
+        if let Some(rest) = cmd.strip_prefix("go perft") {
+            search.stop_and_join();
+            match rest.trim().parse::<usize>() {
+                Ok(depth) => divide(&mut b, depth),
+                Err(_) => info("usage: go perft <depth>"),
             }
-            let _ = io::stdout().flush();
             continue;
         }
 
         if let Some(rest) = cmd.strip_prefix("go") {
             info(format!("FEN before go: {}", b.to_fen()));
-            ponder.stop_and_join();
-
-            if let Some(book_uci) = get_book_move(&b) {
-                println!("bestmove {}", book_uci);
-                let _ = io::stdout().flush();
+            search.stop_and_record(&mut recorder);
+            recorder.set_time_control(time_control_label(rest, b.turn == Color::White));
+            let opponent = opts.string("UCI_Opponent");
+            recorder.set_opponent((!opponent.is_empty()).then(|| opponent.to_string()));
+
+            if book_stale {
+                book = load_book(&opts);
+                book_stale = false;
+            }
+            let ply = (b.fullmove_number as usize).saturating_sub(1) * 2 + b.turn as usize;
+            if ply < opts.book_depth()
+                && let Some(m) = book
+                    .as_mut()
+                    .and_then(|bk| bk.pick_move(&b, opts.book_variety()))
+            {
+                recorder.record_search(recorder.ply(), b.turn, None);
+                info("Playing book move.");
+                print_bestmove(Some(m), None);
                 continue;
             }
 
@@ -237,125 +598,84 @@ pub fn run_uci() {
                 .split_whitespace()
                 .any(|t| t.eq_ignore_ascii_case("infinite"));
 
+            let skill = opts.skill();
             let depth = extract_i64(rest, "depth").map_or(128, |d| d.max(1) as usize);
-            let helper_depth = depth.min(128);
+            let depth = skill.map_or(depth, |s| depth.min(s.max_depth()));
+            let nodes = extract_i64(rest, "nodes").map_or(u64::MAX, |n| n.max(1) as u64);
 
             tc.wtime = extract_i64(rest, "wtime").unwrap_or(0);
             tc.btime = extract_i64(rest, "btime").unwrap_or(0);
             tc.winc = extract_i64(rest, "winc").unwrap_or(0);
             tc.binc = extract_i64(rest, "binc").unwrap_or(0);
             tc.movestogo = extract_i64(rest, "movestogo").unwrap_or(0) as i32;
-
-            let time_to_use = if is_ponder || is_infinite {
-                u64::MAX / 4
+            tc.move_overhead_ms = opts.move_overhead_ms();
+            tc.nodestime = opts.nodestime();
+            tc.max_time_percent = opts.max_time_percent();
+
+            // If pondering isn’t enabled, a `go ponder` is searched like a normal move.
+            let is_ponder = is_ponder && opts.ponder();
+            let white = b.turn == Color::White;
+            let has_clock = extract_i64(rest, if white { "wtime" } else { "btime" }).is_some();
+            // Without a clock or movetime only `depth`/`nodes` (or `stop`) end
+            // the search.
+            let budget = if is_ponder || is_infinite {
+                None
             } else if let Some(movetime) = extract_i64(rest, "movetime") {
-                movetime.max(0) as u64
+                Some((movetime.max(0) as u64, movetime.max(0) as u64))
+            } else if has_clock {
+                let (soft, hard) = tc.allocation_ms(&b);
+                Some((soft.max(0) as u64, hard.max(0) as u64))
             } else {
-                tc.allocation_ms(b.turn == Color::White).0.max(0) as u64
+                None
+            };
+            // In `nodestime` mode the soft budget becomes a node limit.
+            let (soft_ms, hard_ms, nodes) = match budget {
+                Some((soft, hard)) => match tc.nodes_for_ms(soft) {
+                    Some(n) => (u64::MAX / 4, u64::MAX / 4, nodes.min(n)),
+                    None => (soft, hard, nodes),
+                },
+                None => (u64::MAX / 4, u64::MAX / 4, nodes),
+            };
+            let limits = SearchLimits {
+                time_ms: soft_ms,
+                hard_time_ms: hard_ms,
+                max_depth: depth,
+                nodes,
+                root_moves: parse_searchmoves(&mut b, rest),
+                root_scores: skill.is_some(),
+                config: opts.search_config().unwrap_or_default(),
             };
 
-            if is_ponder {
-                // If pondering isn’t enabled, fall through to normal search.
-                if ponder.enabled {
-                    let board_clone = b.clone();
-                    let mut tt_for_thread = tt.clone();
-                    let stop = Arc::new(AtomicBool::new(false));
-                    let stop_clone = Arc::clone(&stop);
-
-                    let builder = thread::Builder::new()
-                        .name("ponder-main".into())
-                        .stack_size(SEARCH_THREAD_STACK);
-                    let handle = builder
-                        .spawn(move || {
-                            let mut helpers = Vec::new();
-                            let threads_count = num_cpus::get().max(1);
-                            for i in 0..threads_count.saturating_sub(1) {
-                                let board_h = board_clone.clone();
-                                let tt_h = tt_for_thread.clone();
-                                let stop_h = Arc::clone(&stop_clone);
-                                let name = format!("ponder-helper-{}", i);
-                                let _ = thread::Builder::new()
-                                    .name(name)
-                                    .stack_size(SEARCH_THREAD_STACK)
-                                    .spawn(move || {
-                                        let mut tt_loc = tt_h;
-                                        let _ = best_move_timed(
-                                            &board_h,
-                                            &mut tt_loc,
-                                            u64::MAX / 4,
-                                            helper_depth,
-                                            stop_h,
-                                            false,
-                                        );
-                                    })
-                                    .map(|jh| helpers.push(jh));
-                            }
-
-                            search_and_output(
-                                &board_clone,
-                                &mut tt_for_thread,
-                                time_to_use,
-                                depth, // main ponder thread uses requested depth
-                                stop_clone,
-                                true,
-                            );
-                            for h in helpers {
-                                let _ = h.join();
-                            }
-                        })
-                        .expect("spawn ponder");
-
-                    ponder.handle = Some(handle);
-                    ponder.stop_signal = Some(stop);
-                    continue;
-                }
-            }
-
-            let stop_signal = Arc::new(AtomicBool::new(false));
-
-            let mut helpers = vec![];
-            for i in 0..threads_count.saturating_sub(1) {
-                let board_clone = b.clone();
-                let tt_clone = tt.clone();
-                let stop_clone = Arc::clone(&stop_signal);
-                let name = format!("helper-{}", i);
-                let _ = thread::Builder::new()
-                    .name(name)
-                    .stack_size(SEARCH_THREAD_STACK)
-                    .spawn(move || {
-                        let mut tt_local = tt_clone;
-                        let _ = best_move_timed(
-                            &board_clone,
-                            &mut tt_local,
-                            u64::MAX / 4,
-                            helper_depth,
-                            stop_clone,
-                            false,
-                        );
-                    })
-                    .map(|jh| helpers.push(jh));
+            if uci_io::is_debug() {
+                info(match budget {
+                    Some((soft, hard)) => format!("time budget {soft} ms, hard limit {hard} ms"),
+                    None => "no time limit".to_string(),
+                });
             }
 
-            // Main search
-            search_and_output(
-                &b,
-                &mut tt,
-                time_to_use,
-                depth,
-                Arc::clone(&stop_signal),
-                true,
-            );
-
-            stop_signal.store(true, Ordering::Relaxed);
-            for h in helpers {
-                let _ = h.join();
+            let name = if is_ponder { "ponder" } else { "search" };
+            match spawn_search(&b, &tt, limits, skill, opts.threads(), name, &pool) {
+                Ok(state) => {
+                    search = state;
+                    search.searched = Some((recorder.ply(), b.turn));
+                }
+                Err(e) => {
+                    report(e);
+                    print_bestmove(None, None);
+                }
             }
             continue;
         }
 
         if cmd.eq_ignore_ascii_case("quit") {
-            ponder.stop_and_join();
+            search.stop_and_record(&mut recorder);
+            log_game(&opts, &mut recorder, &mut b);
             break;
         }
+
+        if uci_io::is_debug() && !cmd.is_empty() {
+            info(format!("unknown command: {cmd}"));
+        }
     }
+    Ok(())
 }
@@ -1,22 +1,76 @@
 use crate::board::Board;
+use crate::movelist::MoveList;
+use crate::square::Square;
 use crate::types::{Move, PieceKind};
+use std::fmt::Display;
+use std::fs::{File, OpenOptions};
+use std::io::{self, LineWriter, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
 
-pub fn parse_uci_move(b: &mut Board, s: &str) -> Option<Move> {
-    let bytes = s.as_bytes();
-    if bytes.len() < 4 {
-        return None;
+static LOG: Mutex<Option<LineWriter<File>>> = Mutex::new(None);
+static START: OnceLock<Instant> = OnceLock::new();
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+/// Starts mirroring UCI traffic to `path`, appending to it. An empty path
+/// closes the log.
+pub fn set_log_file(path: &str) -> io::Result<()> {
+    let file = if path.is_empty() {
+        None
+    } else {
+        Some(LineWriter::new(
+            OpenOptions::new().create(true).append(true).open(path)?,
+        ))
+    };
+    START.get_or_init(Instant::now);
+    *LOG.lock().unwrap_or_else(|e| e.into_inner()) = file;
+    Ok(())
+}
+
+/// Writes `line` to the log, if one is open, prefixed by the seconds since
+/// the first log was opened and `<<` for input or `>>` for output.
+fn log(direction: &str, line: &str) {
+    let mut log = LOG.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(file) = log.as_mut() {
+        let t = START.get_or_init(Instant::now).elapsed();
+        let _ = writeln!(file, "[{:>9.3}] {direction} {line}", t.as_secs_f64());
     }
+}
 
-    let f_file = (bytes[0] as char).to_ascii_lowercase() as u8 - b'a';
-    let f_rank = (bytes[1] as char) as u8 - b'1';
-    let t_file = (bytes[2] as char).to_ascii_lowercase() as u8 - b'a';
-    let t_rank = (bytes[3] as char) as u8 - b'1';
-    if f_file > 7 || f_rank > 7 || t_file > 7 || t_rank > 7 {
-        return None;
+/// Logs a command received from the GUI.
+pub fn log_input(line: &str) {
+    log("<<", line.trim_end());
+}
+
+/// Prints `line` to stdout, flushes and logs it.
+pub fn send(line: impl Display) {
+    let line = line.to_string();
+    let mut out = io::stdout().lock();
+    let _ = writeln!(out, "{line}");
+    let _ = out.flush();
+    drop(out);
+    for l in line.lines() {
+        log(">>", l);
     }
+}
+
+/// Set by the `debug on` command: the engine then sends extra `info string`
+/// lines.
+pub fn set_debug(on: bool) {
+    DEBUG.store(on, Ordering::Relaxed);
+}
 
-    let from = f_rank * 8 + f_file;
-    let to = t_rank * 8 + t_file;
+pub fn is_debug() -> bool {
+    DEBUG.load(Ordering::Relaxed)
+}
+
+pub fn parse_uci_move(b: &mut Board, s: &str) -> Option<Move> {
+    let bytes = s.as_bytes();
+    let square =
+        |range: std::ops::Range<usize>| s.get(range)?.to_ascii_lowercase().parse::<Square>().ok();
+    let from = square(0..2)?;
+    let to = square(2..4)?;
 
     let promo = if bytes.len() >= 5 {
         match (bytes[4] as char).to_ascii_lowercase() {
@@ -30,21 +84,17 @@ pub fn parse_uci_move(b: &mut Board, s: &str) -> Option<Move> {
         None
     };
 
-    let mut moves = Vec::new();
+    let mut moves = MoveList::new();
     b.generate_legal_moves(&mut moves);
     moves
         .into_iter()
-        .find(|m| m.from == from && m.to == to && m.promotion == promo)
+        .find(|m| m.from() == from && m.to() == to && m.promotion() == promo)
 }
 
 pub fn format_uci(m: Move) -> String {
-    let ff = (m.from % 8) + b'a';
-    let fr = (m.from / 8) + b'1';
-    let tf = (m.to % 8) + b'a';
-    let tr = (m.to / 8) + b'1';
-    let mut s = format!("{}{}{}{}", ff as char, fr as char, tf as char, tr as char);
+    let mut s = format!("{}{}", m.from(), m.to());
 
-    if let Some(pk) = m.promotion {
+    if let Some(pk) = m.promotion() {
         s.push(match pk {
             PieceKind::Queen => 'q',
             PieceKind::Rook => 'r',
@@ -3,10 +3,12 @@ use crate::types::Piece;
 // Include the pre-computed keys
 include!(concat!(env!("OUT_DIR"), "/generated_zobrist.rs"));
 
-// A single, global, pre-computed instance of the Zobrist keys
+// A single, global, pre-computed instance of the Zobrist keys. Boards only
+// carry their current key and always hash through this table.
 pub static ZOB: &Zobrist = &ZOBRIST_KEYS;
 
-#[derive(Clone)]
+// Deliberately not `Clone`: a copy of the 13×64 key table has no business
+// living inside a `Board`.
 pub struct Zobrist {
     pub piece: [[ZKey; 64]; 13],
     pub castle: [ZKey; 16],
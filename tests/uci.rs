@@ -0,0 +1,45 @@
+//! Drives the `chess uci` binary over stdin/stdout.
+
+use chess::board::Board;
+use chess::types::START_FEN;
+use chess::uci_io::parse_uci_move;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+/// Sends `commands` and returns the move of the first `bestmove` line.
+fn bestmove(commands: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_chess"))
+        .arg("uci")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("engine starts");
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "{commands}").unwrap();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    let line = stdout
+        .lines()
+        .map_while(Result::ok)
+        .find(|l| l.starts_with("bestmove"))
+        .expect("engine answers go with bestmove");
+    writeln!(stdin, "quit").unwrap();
+    let _ = child.wait();
+    line.split_whitespace().nth(1).unwrap().to_string()
+}
+
+#[test]
+fn go_nodes_1_returns_a_legal_move() {
+    let m = bestmove("position startpos\ngo nodes 1");
+    let mut b = Board::from_fen(START_FEN).unwrap();
+    assert!(parse_uci_move(&mut b, &m).is_some(), "{m}");
+}
+
+#[test]
+fn go_movetime_0_returns_a_legal_move() {
+    let m = bestmove("position startpos moves e2e4\ngo movetime 0");
+    let mut b = Board::from_fen(START_FEN).unwrap();
+    let e4 = parse_uci_move(&mut b, "e2e4").unwrap();
+    b.make_move(e4);
+    assert!(parse_uci_move(&mut b, &m).is_some(), "{m}");
+}